@@ -0,0 +1,110 @@
+//! How long a frame takes to draw, per view and terminal size. Run with
+//! `cargo bench --features bench`, and compare against a run from before
+//! a layout change. Adding `-- --profile-time 10` runs each one for ten
+//! seconds without the statistics, for a profiler like `perf` to watch.
+
+use std::hint::black_box;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use opomodoro::bench;
+use opomodoro::clock::MockClock;
+use opomodoro::engine::{BreakDebt, Engine};
+use opomodoro::history::Targets;
+use opomodoro::template::Messages;
+use opomodoro::theme::ThemeChoice;
+use opomodoro::{App, Config, Countdown, FontChoice, HourFormat, ViewMode};
+
+/// Terminal sizes from a split pane up to a full screen.
+const SIZES: [(u16, u16); 3] = [(40, 12), (80, 24), (200, 60)];
+
+fn config(view: ViewMode) -> Config {
+    Config {
+        work_time: Duration::from_secs(25 * 60),
+        break_time: Duration::from_secs(5 * 60),
+        cycles: 4,
+        long_break: None,
+        late: false,
+        warm_up: None,
+        cool_down: None,
+        font: FontChoice::Auto,
+        view,
+        countdown: Countdown::Clock,
+        theme: ThemeChoice::Tomato,
+        no_color: false,
+        volume: 100,
+        speak: false,
+        notify: false,
+        visual_bell: false,
+        history: None,
+        state: None,
+        tasks: None,
+        notes: false,
+        snooze: Duration::from_secs(5 * 60),
+        max_snoozes: 2,
+        idle: None,
+        work_warning: None,
+        break_warning: None,
+        hard_break: None,
+        get_ready: None,
+        break_debt: BreakDebt::Off,
+        pause_budget: None,
+        strict: false,
+        block_commands: None,
+        ambient: None,
+        targets: Targets::default(),
+        task: Some("Write the report".to_string()),
+        clock: HourFormat::H24,
+        sidebar: false,
+        mascot: true,
+        animations: false,
+        spinner: false,
+        low_power: false,
+        end_of_day: None,
+        manual_advance: None,
+        messages: Messages::default(),
+        chain: Vec::new(),
+        single: None,
+        intervals: None,
+    }
+}
+
+fn render(c: &mut Criterion) {
+    let running = AtomicBool::new(true);
+    let mut group = c.benchmark_group("render");
+    for view in [ViewMode::Digits, ViewMode::Ring, ViewMode::Minimal] {
+        let config = config(view);
+        let engine = Engine::with_clock(&config, MockClock::new());
+        let app = App::with_engine(config, engine, &running);
+        for (width, height) in SIZES {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            let id = BenchmarkId::new(format!("{view:?}"), format!("{width}x{height}"));
+            group.bench_function(id, |b| {
+                b.iter(|| {
+                    // Drawing the same frame twice would only diff it.
+                    terminal.clear().unwrap();
+                    terminal.draw(|frame| bench::render(frame, &app)).unwrap();
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn big_time_lines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("big_time_lines");
+    for text in ["25:00", "1:25:00", "25m", "100%"] {
+        for (width, height) in SIZES {
+            let id = BenchmarkId::new(text, format!("{width}x{height}"));
+            group.bench_function(id, |b| {
+                b.iter(|| bench::big_time_lines(FontChoice::Auto, black_box(text), width, height))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, render, big_time_lines);
+criterion_main!(benches);
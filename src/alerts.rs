@@ -0,0 +1,236 @@
+use std::env;
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Most times a chime rings the bell.
+const MAX_RINGS: usize = 3;
+/// Time between the rings of a chime.
+const RING_GAP: Duration = Duration::from_millis(200);
+
+/// How an alert is sounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    /// The terminal bell, `\x07`.
+    Bell,
+    /// The Windows system sound. Many Windows terminals drop the bell.
+    #[cfg(windows)]
+    MessageBeep,
+}
+
+impl Backend {
+    /// Pick a backend for where we are running. Over SSH the sound has to
+    /// come from the client's terminal, so that always gets the bell.
+    fn detect() -> Backend {
+        if env::var_os("SSH_CONNECTION").is_some() {
+            return Backend::Bell;
+        }
+        #[cfg(windows)]
+        return Backend::MessageBeep;
+        #[cfg(not(windows))]
+        return Backend::Bell;
+    }
+}
+
+/// How announcements are spoken: the platform's text-to-speech command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speech {
+    /// `say`, built into macOS.
+    #[cfg(target_os = "macos")]
+    Say,
+    /// `espeak-ng`, or the older `espeak` it replaced.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    Espeak(&'static str),
+    /// `spd-say`, handing the text to speech-dispatcher.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    SpdSay,
+    /// The SAPI voices, through PowerShell.
+    #[cfg(windows)]
+    Sapi,
+}
+
+impl Speech {
+    /// The speech command available here, if any. Like the bell, speech
+    /// over SSH would come out of the wrong machine, so there is none.
+    pub fn detect() -> Option<Speech> {
+        if env::var_os("SSH_CONNECTION").is_some() {
+            return None;
+        }
+        #[cfg(target_os = "macos")]
+        return Some(Speech::Say);
+        #[cfg(all(unix, not(target_os = "macos")))]
+        return match ["espeak-ng", "espeak", "spd-say"].into_iter().find(|name| on_path(name)) {
+            Some("spd-say") => Some(Speech::SpdSay),
+            Some(name) => Some(Speech::Espeak(name)),
+            None => None,
+        };
+        #[cfg(windows)]
+        return Some(Speech::Sapi);
+    }
+
+    /// The command speaking `text` at `volume`, 1 to 100.
+    fn command(self, text: &str, volume: u8) -> Command {
+        match self {
+            #[cfg(target_os = "macos")]
+            Speech::Say => {
+                // `say` has no volume flag, but reads this embedded command.
+                let mut command = Command::new("say");
+                command.arg(format!("[[volm {:.2}]] {text}", f32::from(volume) / 100.0));
+                command
+            }
+            #[cfg(all(unix, not(target_os = "macos")))]
+            Speech::Espeak(name) => {
+                let mut command = Command::new(name);
+                command.args(["-a", &volume.to_string(), text]);
+                command
+            }
+            #[cfg(all(unix, not(target_os = "macos")))]
+            Speech::SpdSay => {
+                // From -100 to 100, with 0 the default.
+                let volume = i32::from(volume) * 2 - 100;
+                let mut command = Command::new("spd-say");
+                command.args(["-i", &volume.to_string(), text]);
+                command
+            }
+            #[cfg(windows)]
+            Speech::Sapi => {
+                let script = format!(
+                    "Add-Type -AssemblyName System.Speech; \
+                     $voice = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+                     $voice.Volume = {volume}; $voice.Speak('{}')",
+                    text.replace('\'', "''"),
+                );
+                let mut command = Command::new("powershell");
+                command.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+                command
+            }
+        }
+    }
+}
+
+/// `duration` the way it reads aloud: `25 minutes`, or `40 seconds`
+/// under a minute.
+pub fn spoken(duration: Duration) -> String {
+    let secs = duration.as_secs_f64().round() as u64;
+    let (n, unit) = if secs < 60 { (secs, "second") } else { ((secs + 30) / 60, "minute") };
+    format!("{n} {unit}{}", if n == 1 { "" } else { "s" })
+}
+
+/// Whether an executable called `name` is on the `PATH`.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn on_path(name: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(name).is_file()))
+}
+
+/// Audible alerts fired when a phase ends.
+#[derive(Debug)]
+pub struct Alerts {
+    volume: u8,
+    muted: bool,
+    backend: Backend,
+    speech: Option<Speech>,
+    /// The announcement being spoken, if one may still be.
+    speaking: Option<Child>,
+}
+
+impl Alerts {
+    pub fn new(volume: u8, speech: Option<Speech>) -> Alerts {
+        let backend = Backend::detect();
+        debug!(?backend, ?speech, "alert backend");
+        Alerts { volume: volume.min(100), muted: false, backend, speech, speaking: None }
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    /// Set the volume, as a percentage up to 100.
+    pub fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+    }
+
+    /// True when alerts are muted at runtime or the volume is zero.
+    pub fn is_silent(&self) -> bool {
+        self.muted || self.volume == 0
+    }
+
+    /// Sound the alert, chime `n` of a few that tell phases apart: the bell
+    /// rung `n + 1` times, up to three, or a different system sound for
+    /// each on Windows. Neither backend has a volume of its own, so any
+    /// non-zero volume sounds it.
+    pub fn chime(&self, n: usize) {
+        if self.is_silent() {
+            debug!(muted = self.muted, volume = self.volume, "bell skipped");
+            return;
+        }
+        match self.backend {
+            Backend::Bell => {
+                for ring in 0..=n.min(MAX_RINGS - 1) {
+                    if ring > 0 {
+                        thread::sleep(RING_GAP);
+                    }
+                    ring_bell();
+                }
+            }
+            #[cfg(windows)]
+            Backend::MessageBeep => {
+                if !message_beep(n) {
+                    warn!("MessageBeep failed, falling back to the terminal bell");
+                    ring_bell();
+                }
+            }
+        }
+    }
+
+    /// Speak `text`, if announcements are on, cutting short whatever was
+    /// still being said. Returns straight away.
+    pub fn say(&mut self, text: &str) {
+        let Some(speech) = self.speech.filter(|_| !self.is_silent()) else {
+            return;
+        };
+        if let Some(mut child) = self.speaking.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        let mut command = speech.command(text, self.volume);
+        debug!(?command, "speaking");
+        let spawned = command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn();
+        match spawned {
+            Ok(child) => self.speaking = Some(child),
+            Err(e) => warn!("could not speak: {e}"),
+        }
+    }
+}
+
+impl Drop for Alerts {
+    /// Reap the last announcement, letting it finish.
+    fn drop(&mut self) {
+        if let Some(mut child) = self.speaking.take() {
+            let _ = child.wait();
+        }
+    }
+}
+
+fn ring_bell() {
+    print!("\x07");
+    if let Err(e) = io::stdout().flush() {
+        warn!("could not ring the bell: {e}");
+    }
+}
+
+/// Play system sound `n`, going round the ones there are.
+#[cfg(windows)]
+fn message_beep(n: usize) -> bool {
+    use windows_sys::Win32::System::Diagnostics::Debug::MessageBeep;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{MB_ICONASTERISK, MB_ICONEXCLAMATION, MB_ICONHAND, MB_OK};
+    let sounds = [MB_ICONASTERISK, MB_ICONEXCLAMATION, MB_ICONHAND, MB_OK];
+    // SAFETY: MessageBeep takes no pointers; it only queues a system sound.
+    unsafe { MessageBeep(sounds[n % sounds.len()]) != 0 }
+}
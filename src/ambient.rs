@@ -0,0 +1,160 @@
+//! Background sound played through work phases, made up on the fly so
+//! there are no audio files to ship. Playing it needs the `ambient` cargo
+//! feature; other builds turn it down with an error.
+
+use std::io;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// How far one press of `+` or `-` moves the volume.
+pub const VOLUME_STEP: u8 = 10;
+
+/// Sounds to work to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AmbientSound {
+    /// Even hiss across every pitch.
+    White,
+    /// Low rumble with drops pattering over it.
+    Rain,
+}
+
+/// Plays an [`AmbientSound`] while work is running and holds it otherwise.
+/// Dropping it stops the sound and closes the audio device.
+pub struct Ambient {
+    volume: u8,
+    #[cfg(feature = "ambient")]
+    sink: rodio::Sink,
+    // Declared after the sink so it is closed last.
+    #[cfg(feature = "ambient")]
+    _stream: rodio::OutputStream,
+}
+
+impl std::fmt::Debug for Ambient {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Ambient").field("volume", &self.volume).finish_non_exhaustive()
+    }
+}
+
+impl Ambient {
+    /// Open the default audio device and queue `sound` on it, held until
+    /// [`set`](Ambient::set) starts it.
+    #[cfg(feature = "ambient")]
+    pub fn open(sound: AmbientSound, volume: u8) -> io::Result<Ambient> {
+        let mut stream = rodio::OutputStreamBuilder::open_default_stream()
+            .map_err(|e| io::Error::other(format!("could not open the audio device: {e}")))?;
+        // It would print to a terminal the TUI is drawing on.
+        stream.log_on_drop(false);
+        let sink = rodio::Sink::connect_new(stream.mixer());
+        sink.pause();
+        sink.append(Noise::new(sound));
+        let mut ambient = Ambient { volume: 0, sink, _stream: stream };
+        ambient.set_volume(volume);
+        debug!(?sound, volume, "ambient sound ready");
+        Ok(ambient)
+    }
+
+    #[cfg(not(feature = "ambient"))]
+    pub fn open(sound: AmbientSound, _volume: u8) -> io::Result<Ambient> {
+        debug!(?sound, "no ambient sound in this build");
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this build cannot play sound; drop ambient or build with the ambient feature",
+        ))
+    }
+
+    /// Play while `working`, hold the sound otherwise.
+    pub fn set(&mut self, working: bool) {
+        #[cfg(feature = "ambient")]
+        match (working, self.sink.is_paused()) {
+            (true, true) => self.sink.play(),
+            (false, false) => self.sink.pause(),
+            _ => {}
+        }
+        #[cfg(not(feature = "ambient"))]
+        let _ = working;
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    /// Set the volume, as a percentage up to 100.
+    pub fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+        #[cfg(feature = "ambient")]
+        self.sink.set_volume(f32::from(self.volume) / 100.0);
+    }
+}
+
+/// Endless noise shaped like an [`AmbientSound`].
+#[cfg(feature = "ambient")]
+struct Noise {
+    sound: AmbientSound,
+    /// State of the xorshift generator the noise comes from.
+    seed: u32,
+    /// The noise with its highs filtered out, for the rumble of rain.
+    low: f32,
+    /// What is left ringing of the last drop.
+    drop: f32,
+}
+
+#[cfg(feature = "ambient")]
+impl Noise {
+    const RATE: u32 = 44_100;
+
+    fn new(sound: AmbientSound) -> Noise {
+        Noise { sound, seed: 0x9e37_79b9, low: 0.0, drop: 0.0 }
+    }
+
+    /// Uniform in -1..1.
+    fn white(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+#[cfg(feature = "ambient")]
+impl Iterator for Noise {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let white = self.white();
+        let sample = match self.sound {
+            AmbientSound::White => white * 0.25,
+            AmbientSound::Rain => {
+                self.low += (white - self.low) * 0.04;
+                // Every few thousand samples a drop lands, and dies away
+                // within a few milliseconds.
+                if self.seed.is_multiple_of(3000) {
+                    self.drop = 0.6;
+                }
+                self.drop *= 0.995;
+                self.low * 1.5 + white * self.drop * 0.3
+            }
+        };
+        Some(sample)
+    }
+}
+
+#[cfg(feature = "ambient")]
+impl rodio::Source for Noise {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> rodio::ChannelCount {
+        1
+    }
+
+    fn sample_rate(&self) -> rodio::SampleRate {
+        Noise::RATE
+    }
+
+    fn total_duration(&self) -> Option<std::time::Duration> {
+        None
+    }
+}
@@ -0,0 +1,124 @@
+//! Recurring start times for `opomodoro daemon --recurring`, written like
+//! `weekdays 09:00` or `mon,wed 13:30`.
+
+use std::fmt;
+use std::str::FromStr;
+use chrono::{DateTime, Datelike, Days, Local, NaiveTime, Weekday};
+
+const WEEK: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// A time of day to start a session on some days of the week.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot {
+    /// Indexed from Monday.
+    days: [bool; 7],
+    time: NaiveTime,
+}
+
+impl Slot {
+    /// The first time after `after` the slot comes round, looking a week
+    /// ahead. A time that falls in a DST gap that day is skipped.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        (0..=7).find_map(|n| {
+            let day = after.date_naive().checked_add_days(Days::new(n))?;
+            if !self.days[day.weekday().num_days_from_monday() as usize] {
+                return None;
+            }
+            day.and_time(self.time).and_local_timezone(Local).earliest().filter(|at| *at > after)
+        })
+    }
+}
+
+/// Parse days like `weekdays`, `mon-fri` or `tue,thu`.
+fn parse_days(text: &str) -> Result<[bool; 7], String> {
+    let mut days = [false; 7];
+    let day = |name: &str| {
+        name.parse::<Weekday>()
+            .map(|day| day.num_days_from_monday() as usize)
+            .map_err(|_| format!("unknown day {name:?}"))
+    };
+    for part in text.split(',') {
+        match part.trim().to_ascii_lowercase().as_str() {
+            "daily" => days = [true; 7],
+            "weekdays" => days[..5].fill(true),
+            "weekends" => days[5..].fill(true),
+            part => match part.split_once('-') {
+                Some((from, to)) => {
+                    let (from, to) = (day(from)?, day(to)?);
+                    // A range may wrap round the week, as in `fri-mon`.
+                    let mut n = from;
+                    loop {
+                        days[n] = true;
+                        if n == to {
+                            break;
+                        }
+                        n = (n + 1) % 7;
+                    }
+                }
+                None => days[day(part)?] = true,
+            },
+        }
+    }
+    Ok(days)
+}
+
+impl FromStr for Slot {
+    type Err = String;
+
+    /// `[DAYS] HH:MM`, every day when the days are left out.
+    fn from_str(text: &str) -> Result<Slot, String> {
+        let text = text.trim();
+        let (days, time) = match text.rsplit_once(' ') {
+            Some((days, time)) => (parse_days(days)?, time),
+            None => ([true; 7], text),
+        };
+        let time = NaiveTime::parse_from_str(time, "%H:%M")
+            .map_err(|_| format!("expected a time like 09:00 in {text:?}"))?;
+        Ok(Slot { days, time })
+    }
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let days = match self.days {
+            [true, true, true, true, true, true, true] => "daily".to_string(),
+            [true, true, true, true, true, false, false] => "weekdays".to_string(),
+            [false, false, false, false, false, true, true] => "weekends".to_string(),
+            days => {
+                let on = WEEK.iter().filter(|day| days[day.num_days_from_monday() as usize]);
+                on.map(|day| day.to_string().to_lowercase()).collect::<Vec<_>>().join(",")
+            }
+        };
+        write!(f, "{days} {}", self.time.format("%H:%M"))
+    }
+}
+
+/// Every slot sessions start at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    slots: Vec<Slot>,
+}
+
+impl Schedule {
+    pub fn parse(entries: &[String]) -> Result<Schedule, String> {
+        let slots = entries.iter().map(|entry| entry.parse()).collect::<Result<Vec<Slot>, _>>()?;
+        Ok(Schedule { slots })
+    }
+
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    /// The next start after `after`, of whichever slot comes first.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        self.slots.iter().filter_map(|slot| slot.next_after(after)).min()
+    }
+}
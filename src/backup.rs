@@ -0,0 +1,144 @@
+//! `opomodoro backup`: the config, history and session state in one tar
+//! file, and back again. A manifest goes first in the archive, tagged with
+//! [`FORMAT`], so a later version that lays its files out differently can
+//! tell an old backup apart and upgrade it as it restores.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use crate::error::{self, OpomodoroError};
+use crate::history::HistoryBackend;
+use crate::settings::Settings;
+use crate::state::StateFile;
+
+/// The layout of the archive: bumped when it changes, so restoring can
+/// tell which layout a backup has.
+pub const FORMAT: u32 = 1;
+
+/// Name of the manifest in the archive.
+const MANIFEST: &str = "manifest.json";
+
+/// What is in a backup, and what made it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+    /// The opomodoro version that wrote the backup.
+    pub version: String,
+    pub created: DateTime<Local>,
+    pub files: Vec<String>,
+}
+
+/// A file a backup may hold: its name in the archive, and where it lives.
+pub type BackupFile = (&'static str, PathBuf);
+
+/// The files backed up, where the platform has a place for them.
+pub fn default_files() -> Vec<BackupFile> {
+    let files = [
+        ("config.toml", Settings::default_path()),
+        ("history.jsonl", HistoryBackend::Jsonl.default_path()),
+        ("history.db", HistoryBackend::Sqlite.default_path()),
+        ("state.json", StateFile::default_path()),
+    ];
+    files.into_iter().filter_map(|(name, path)| Some((name, path?))).collect()
+}
+
+/// Write those of `files` that exist to a tar archive at `out`, gzipped if
+/// its name ends in `.gz`. Returns the manifest written.
+pub fn create(out: &Path, files: &[BackupFile]) -> error::Result<Manifest> {
+    let present: Vec<_> = files.iter().filter(|(_, path)| path.is_file()).collect();
+    let manifest = Manifest {
+        format: FORMAT,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        created: Local::now(),
+        files: present.iter().map(|(name, _)| name.to_string()).collect(),
+    };
+    let file = File::create(out).map_err(|e| {
+        io::Error::new(e.kind(), format!("could not write {}: {e}", out.display()))
+    })?;
+    if out.extension().is_some_and(|ext| ext == "gz") {
+        let gz = write_tar(GzEncoder::new(file, Compression::default()), &manifest, &present)?;
+        gz.finish()?;
+    } else {
+        write_tar(file, &manifest, &present)?.flush()?;
+    }
+    Ok(manifest)
+}
+
+fn write_tar<W: Write>(out: W, manifest: &Manifest, files: &[&BackupFile]) -> io::Result<W> {
+    let mut tar = tar::Builder::new(out);
+    let json = serde_json::to_vec_pretty(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(json.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created.timestamp().max(0) as u64);
+    tar.append_data(&mut header, MANIFEST, json.as_slice())?;
+    for (name, path) in files {
+        tar.append_path_with_name(path, name)?;
+    }
+    tar.into_inner()
+}
+
+/// Put the files of the backup at `archive` back in place among `files`.
+/// Unless `overwrite` is set, nothing is restored if any of them is
+/// already there. Returns the backup's manifest.
+pub fn restore(archive: &Path, files: &[BackupFile], overwrite: bool) -> error::Result<Manifest> {
+    let invalid = |message: String| OpomodoroError::parse(format!("backup {}", archive.display()), message);
+    let mut file = BufReader::new(File::open(archive)?);
+    let mut magic = [0; 2];
+    let gzipped = file.read_exact(&mut magic).is_ok() && magic == [0x1f, 0x8b];
+    file.rewind()?;
+    let input: Box<dyn Read> = if gzipped { Box::new(GzDecoder::new(file)) } else { Box::new(file) };
+    let mut tar = tar::Archive::new(input);
+    let mut entries = tar.entries()?;
+
+    let first = entries.next().transpose().map_err(|e| invalid(e.to_string()))?;
+    let manifest: Manifest = match first {
+        Some(entry) if entry.path()?.as_os_str() == MANIFEST => {
+            serde_json::from_reader(entry).map_err(|e| invalid(e.to_string()))?
+        }
+        _ => return Err(invalid(format!("no {MANIFEST} at the start; not an opomodoro backup?"))),
+    };
+    if manifest.format > FORMAT {
+        return Err(invalid(format!(
+            "made by opomodoro {} in backup format {}, but this one reads up to format {FORMAT}; \
+             upgrade to restore it",
+            manifest.version, manifest.format,
+        )));
+    }
+    let target = |name: &str| files.iter().find(|(known, _)| *known == name).map(|(_, path)| path);
+    if !overwrite {
+        let existing: Vec<_> = manifest
+            .files
+            .iter()
+            .filter_map(|name| target(name))
+            .filter(|path| path.exists())
+            .map(|path| path.display().to_string())
+            .collect();
+        if !existing.is_empty() {
+            let message = format!("{} already there; pass --force to replace", existing.join(", "));
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, message).into());
+        }
+    }
+    for entry in entries {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let Some(path) = target(&name) else {
+            tracing::warn!(%name, "skipping a file this version does not know");
+            continue;
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write next to the file and move it over, so a failure halfway
+        // leaves the old one whole.
+        let partial = path.with_extension("restoring");
+        io::copy(&mut entry, &mut File::create(&partial)?)?;
+        fs::rename(&partial, path)?;
+    }
+    Ok(manifest)
+}
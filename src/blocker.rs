@@ -0,0 +1,79 @@
+//! Commands run as work starts and stops, to block distracting sites or
+//! apps, e.g. through a helper that edits `/etc/hosts`.
+//!
+//! Whatever was blocked is unblocked again however the session ends: when
+//! the [`Blocker`] is dropped, also while unwinding from a panic, or by
+//! [`release`] on the way out of a forced exit.
+
+use std::io;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use tracing::{debug, info};
+
+/// The unblock command while something is blocked. Taking it out is what
+/// runs it, so it runs once however many ways the session ends.
+static BLOCKED: Mutex<Option<String>> = Mutex::new(None);
+
+/// Runs the block command when work starts and the unblock command when
+/// it stops.
+#[derive(Debug)]
+pub struct Blocker {
+    block: String,
+    unblock: String,
+}
+
+impl Blocker {
+    pub fn new(block: String, unblock: String) -> Blocker {
+        Blocker { block, unblock }
+    }
+
+    /// Block while `working`, unblock otherwise, running a command only
+    /// when that changes.
+    pub fn set(&mut self, working: bool) -> io::Result<()> {
+        let mut blocked = BLOCKED.lock().unwrap_or_else(|e| e.into_inner());
+        match (working, blocked.is_some()) {
+            (true, false) => {
+                info!("blocking");
+                // Unblock even if the command failed halfway through.
+                *blocked = Some(self.unblock.clone());
+                run(&self.block)
+            }
+            (false, true) => {
+                info!("unblocking");
+                blocked.take().map_or(Ok(()), |unblock| run(&unblock))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for Blocker {
+    fn drop(&mut self) {
+        release();
+    }
+}
+
+/// Unblock, if anything is blocked. For exits that skip destructors, and
+/// safe to call from anywhere at any time.
+pub fn release() {
+    let unblock = BLOCKED.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(unblock) = unblock {
+        let _ = run(&unblock);
+    }
+}
+
+/// Run `command` through the shell and wait for it, keeping what it
+/// prints off the TUI.
+fn run(command: &str) -> io::Result<()> {
+    debug!(command, "running");
+    #[cfg(unix)]
+    let out = Command::new("sh").args(["-c", command]).stdin(Stdio::null()).output()?;
+    #[cfg(windows)]
+    let out = Command::new("cmd").args(["/C", command]).stdin(Stdio::null()).output()?;
+    if out.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    let reason = stderr.lines().next().map_or(out.status.to_string(), str::to_string);
+    Err(io::Error::other(format!("`{command}` failed: {reason}")))
+}
@@ -0,0 +1,90 @@
+//! A heatmap of focus time per day, the way code hosts chart contributions,
+//! for `opomodoro stats --calendar`.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use chrono::{Datelike, Days, NaiveDate};
+use crossterm::style::Stylize;
+use ratatui::backend::IntoCrossterm;
+use crate::history::{Entry, format_focus};
+use crate::theme::Theme;
+
+/// Weeks the calendar covers, this one included.
+pub const WEEKS: u64 = 12;
+
+/// Cells for no focus and for each quarter of the busiest day.
+const LEVELS: [&str; 5] = ["·", "░", "▒", "▓", "█"];
+
+const WEEKDAYS: [&str; 7] = ["Mon", "", "Wed", "", "Fri", "", ""];
+
+/// The calendar of the [`WEEKS`] weeks up to `today`, a column per week
+/// from Monday, drawn in `theme`'s colors.
+pub fn render(entries: &[Entry], today: NaiveDate, theme: &Theme) -> String {
+    let start = today.week(chrono::Weekday::Mon).first_day() - Days::new(7 * (WEEKS - 1));
+    let mut focus: HashMap<NaiveDate, Duration> = HashMap::new();
+    for entry in entries.iter().filter(|entry| entry.is_work()) {
+        let day = entry.started.date_naive();
+        if (start..=today).contains(&day) {
+            *focus.entry(day).or_default() += Duration::from_secs(entry.elapsed_secs);
+        }
+    }
+    let busiest = focus.values().max().copied().unwrap_or_default();
+    let level = |day: NaiveDate| {
+        let time = focus.get(&day).copied().unwrap_or_default();
+        if time.is_zero() {
+            return 0;
+        }
+        1 + (3 * time.as_secs() / busiest.as_secs().max(1)) as usize
+    };
+    let cell = |level: usize| {
+        let glyph = LEVELS[level].repeat(2);
+        let color = if level == 0 { theme.track } else { theme.progress };
+        glyph.with(color.into_crossterm()).to_string()
+    };
+
+    let mut out = String::new();
+    out.push_str(&month_labels(start));
+    for (weekday, label) in WEEKDAYS.iter().enumerate() {
+        out.push_str(&format!("{label:<4}"));
+        for week in 0..WEEKS {
+            let day = start + Days::new(week * 7 + weekday as u64);
+            if day > today {
+                break;
+            }
+            out.push_str(&cell(level(day)));
+        }
+        out.push('\n');
+    }
+    let legend: Vec<_> = (0..LEVELS.len()).map(cell).collect();
+    out.push_str(&format!("\n    Less {} More\n", legend.join(" ")));
+    let total: Duration = focus.values().sum();
+    if total.is_zero() {
+        out.push_str(&format!("    No focus time in the last {WEEKS} weeks\n"));
+    } else {
+        out.push_str(&format!(
+            "    {} focused in the last {WEEKS} weeks, {} on the busiest day\n",
+            format_focus(total),
+            format_focus(busiest),
+        ));
+    }
+    out
+}
+
+/// A row naming each month above the week it starts in.
+fn month_labels(start: NaiveDate) -> String {
+    let mut row = " ".repeat(4);
+    let mut month = None;
+    for week in 0..WEEKS {
+        let monday = start + Days::new(week * 7);
+        let column = 4 + 2 * week as usize;
+        // A month that only has the first week would crowd out the next.
+        let crowded = week == 0 && (monday + Days::new(7)).month() != monday.month();
+        if month != Some(monday.month()) && !crowded && row.chars().count() <= column {
+            row.push_str(&" ".repeat(column - row.chars().count()));
+            row.push_str(&monday.format("%b").to_string());
+            month = Some(monday.month());
+        }
+    }
+    row.push('\n');
+    row
+}
@@ -0,0 +1,44 @@
+//! Sessions queued back to back, like a morning block, lunch and an
+//! afternoon block, run one after another by the same timer.
+
+use std::time::Duration;
+
+/// One session in a chain: cycles of work and breaks, or a single break
+/// when it has no work, like lunch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub name: Option<String>,
+    /// None for a block that is only a break.
+    pub work_time: Option<Duration>,
+    pub break_time: Duration,
+    pub cycles: u32,
+}
+
+impl Block {
+    pub fn is_break(&self) -> bool {
+        self.work_time.is_none()
+    }
+
+    /// What to call the block on screen: its name, or else its schedule,
+    /// e.g. `50m/10m x2`.
+    pub fn label(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let time = |duration: Duration| humantime::format_duration(duration).to_string();
+        match self.work_time {
+            Some(work) => format!("{}/{} x{}", time(work), time(self.break_time), self.cycles),
+            None => format!("{} break", time(self.break_time)),
+        }
+    }
+
+    /// Time the whole block takes if every phase runs its course, with the
+    /// break after the last work phase when `late`.
+    pub fn duration(&self, late: bool) -> Duration {
+        let Some(work) = self.work_time else {
+            return self.break_time;
+        };
+        let breaks = if late { self.cycles } else { self.cycles.saturating_sub(1) };
+        work * self.cycles + self.break_time * breaks
+    }
+}
@@ -0,0 +1,157 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// A moment on the monotonic clock: `std`'s everywhere but in the browser,
+/// where `std` has no clock to read and [`web::Instant`] takes its place.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub use std::time::Instant;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use web::Instant;
+
+/// Where the engine gets the current time from.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The real monotonic clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to, for tests and simulations.
+///
+/// Clones share the same time, so a test can keep one handle and give
+/// another to the engine:
+///
+/// ```
+/// use std::time::Duration;
+/// use opomodoro::clock::{Clock, MockClock};
+///
+/// let clock = MockClock::new();
+/// let handle = clock.clone();
+/// let start = clock.now();
+/// handle.advance(Duration::from_secs(90));
+/// assert_eq!(clock.now() - start, Duration::from_secs(90));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    start: Instant,
+    /// Nanoseconds since `start`.
+    offset: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    pub fn new() -> MockClock {
+        MockClock { start: Instant::now(), offset: Arc::new(AtomicU64::new(0)) }
+    }
+
+    /// Move the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        self.offset.fetch_add(by.as_nanos() as u64, Ordering::SeqCst);
+    }
+
+    /// Time the clock has been advanced by since it was created.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.offset.load(Ordering::SeqCst))
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> MockClock {
+        MockClock::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+}
+
+/// Time in the browser, for wasm32-unknown-unknown.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub mod web {
+    use std::ops::{Add, AddAssign, Sub, SubAssign};
+    use std::time::Duration;
+
+    /// Time since the page loaded, read from `performance.now()`, or since
+    /// the epoch from `Date.now()` where there is no window, as in a
+    /// worker. It does what the engine needs of `std::time::Instant`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Instant(Duration);
+
+    impl Instant {
+        pub fn now() -> Instant {
+            let millis = web_sys::window()
+                .and_then(|window| window.performance())
+                .map_or_else(js_sys::Date::now, |performance| performance.now());
+            Instant(Duration::from_secs_f64(millis.max(0.0) / 1000.0))
+        }
+
+        pub fn duration_since(&self, earlier: Instant) -> Duration {
+            self.saturating_duration_since(earlier)
+        }
+
+        pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+            self.0.checked_sub(earlier.0)
+        }
+
+        pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+            self.0.saturating_sub(earlier.0)
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Instant::now().saturating_duration_since(*self)
+        }
+
+        pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+            self.0.checked_add(duration).map(Instant)
+        }
+
+        pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+            self.0.checked_sub(duration).map(Instant)
+        }
+    }
+
+    impl Add<Duration> for Instant {
+        type Output = Instant;
+
+        fn add(self, duration: Duration) -> Instant {
+            Instant(self.0 + duration)
+        }
+    }
+
+    impl AddAssign<Duration> for Instant {
+        fn add_assign(&mut self, duration: Duration) {
+            self.0 += duration;
+        }
+    }
+
+    impl Sub<Duration> for Instant {
+        type Output = Instant;
+
+        fn sub(self, duration: Duration) -> Instant {
+            Instant(self.0 - duration)
+        }
+    }
+
+    impl SubAssign<Duration> for Instant {
+        fn sub_assign(&mut self, duration: Duration) {
+            self.0 -= duration;
+        }
+    }
+
+    impl Sub for Instant {
+        type Output = Duration;
+
+        fn sub(self, earlier: Instant) -> Duration {
+            self.duration_since(earlier)
+        }
+    }
+}
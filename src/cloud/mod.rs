@@ -0,0 +1,133 @@
+//! `opomodoro sync`: the history kept in step between machines through
+//! storage of the user's own, like a WebDAV share, rather than a service
+//! run by anyone else.
+//!
+//! The history goes back and forth in deltas. Each time a machine syncs it
+//! pushes the entries it recorded since the last time as a delta of its
+//! own, which is never written again, and pulls the deltas of the other
+//! machines it has not seen yet. With no delta written twice, two machines
+//! syncing at once cannot undo each other.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use crate::history::{self, Entry, HistoryStore, host_name};
+use crate::profile;
+
+#[cfg(feature = "webdav")]
+pub mod webdav;
+
+/// Entries pushed together by one machine.
+#[derive(Debug, Clone)]
+pub struct Delta {
+    /// Unique among the deltas of every machine, and safe in a URL or a
+    /// file name: the machine's name and when the delta was made.
+    pub id: String,
+    pub entries: Vec<Entry>,
+}
+
+impl Delta {
+    /// A delta of `entries` from this machine.
+    pub fn new(entries: Vec<Entry>) -> Delta {
+        let host: String = host_name()
+            .chars()
+            .map(|ch| if ch.is_ascii_alphanumeric() || ch == '-' { ch } else { '_' })
+            .collect();
+        Delta { id: format!("{host}-{}", Local::now().format("%Y%m%dT%H%M%S%3f")), entries }
+    }
+}
+
+/// Storage the deltas of every machine are kept in.
+pub trait SyncBackend: fmt::Debug {
+    /// Where the deltas are, for messages.
+    fn location(&self) -> &str;
+
+    /// Store `delta` for the other machines to pull.
+    fn push(&self, delta: &Delta) -> io::Result<()>;
+
+    /// Every delta stored but those in `seen`.
+    fn pull(&self, seen: &BTreeSet<String>) -> io::Result<Vec<Delta>>;
+}
+
+/// What this machine remembers of syncing, kept in `sync.json`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SyncState {
+    /// When the last sync went through. No session runs while one does,
+    /// so the entries recorded since all start after it.
+    #[serde(default)]
+    pub last_sync: Option<DateTime<Local>>,
+    /// The deltas pushed or pulled so far.
+    #[serde(default)]
+    pub seen: BTreeSet<String>,
+}
+
+impl SyncState {
+    /// `sync.json` in the [profile's](crate::profile) data folder, if the
+    /// platform has a data dir.
+    pub fn default_path() -> Option<PathBuf> {
+        profile::data_dir().map(|dir| dir.join("sync.json"))
+    }
+
+    /// The state at `path`; a machine that never synced has a blank one.
+    pub fn load(path: &Path) -> io::Result<SyncState> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(SyncState::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(tmp, path)
+    }
+}
+
+/// How a sync went.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Synced {
+    /// Entries from the other machines added to the history.
+    pub pulled: usize,
+    /// Entries of this machine's pushed.
+    pub pushed: usize,
+}
+
+/// Pull the other machines' new deltas into `store`, then push this
+/// machine's new entries; call it with no session running. `state` only
+/// takes in what went through, so a sync that fails halfway picks up from
+/// there the next time, and entries pulled twice are only added once.
+pub fn sync(
+    store: &dyn HistoryStore,
+    backend: &dyn SyncBackend,
+    state: &mut SyncState,
+) -> io::Result<Synced> {
+    let now = Local::now();
+    let mut entries = store.load()?;
+    let mut pulled = Vec::new();
+    for delta in backend.pull(&state.seen)? {
+        let new = history::missing(&entries, delta.entries);
+        store.insert(&new)?;
+        entries.extend(new.iter().cloned());
+        pulled.extend(new);
+        state.seen.insert(delta.id);
+    }
+    let since = state.last_sync;
+    let recorded = entries.iter().filter(|entry| since.is_none_or(|since| entry.started > since));
+    let own = history::missing(&pulled, recorded.cloned().collect());
+    let pushed = own.len();
+    if !own.is_empty() {
+        let delta = Delta::new(own);
+        backend.push(&delta)?;
+        state.seen.insert(delta.id);
+    }
+    state.last_sync = Some(now);
+    Ok(Synced { pulled: pulled.len(), pushed })
+}
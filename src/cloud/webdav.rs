@@ -0,0 +1,129 @@
+//! Deltas kept on a WebDAV share, like a Nextcloud folder, as one
+//! `<id>.jsonl` file each.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::time::Duration;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use tracing::info;
+use ureq::http::{Request, Response, StatusCode};
+use ureq::{Agent, Body};
+use crate::history;
+use super::{Delta, SyncBackend};
+
+#[derive(Debug)]
+pub struct WebDav {
+    /// The folder's URL, ending in `/`.
+    url: String,
+    auth: Option<String>,
+    agent: Agent,
+}
+
+fn failed(e: ureq::Error) -> io::Error {
+    io::Error::other(format!("webdav: {e}"))
+}
+
+impl WebDav {
+    /// Keep the deltas in the folder at `url`, signing in with `user` and
+    /// `password` if given.
+    pub fn new(url: &str, user: Option<&str>, password: Option<&str>) -> WebDav {
+        let auth = user.map(|user| {
+            format!("Basic {}", BASE64.encode(format!("{user}:{}", password.unwrap_or_default())))
+        });
+        let agent = Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(30)))
+            .http_status_as_error(false)
+            .allow_non_standard_methods(true)
+            .build()
+            .into();
+        WebDav { url: format!("{}/", url.trim_end_matches('/')), auth, agent }
+    }
+
+    /// Send a request with `method` to `url`, failing on anything but
+    /// success.
+    fn send(
+        &self,
+        method: &str,
+        url: &str,
+        depth: Option<&str>,
+        body: Vec<u8>,
+    ) -> io::Result<Response<Body>> {
+        let mut request = Request::builder().method(method).uri(url);
+        if let Some(auth) = &self.auth {
+            request = request.header("Authorization", auth);
+        }
+        if let Some(depth) = depth {
+            request = request.header("Depth", depth);
+        }
+        let request = request.body(body).map_err(|e| io::Error::other(format!("webdav: {e}")))?;
+        let response = self.agent.run(request).map_err(failed)?;
+        let status = response.status();
+        if !status.is_success() {
+            let kind = match status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => io::ErrorKind::PermissionDenied,
+                StatusCode::NOT_FOUND | StatusCode::CONFLICT => io::ErrorKind::NotFound,
+                _ => io::ErrorKind::Other,
+            };
+            return Err(io::Error::new(kind, format!("webdav: {method} {url}: {status}")));
+        }
+        Ok(response)
+    }
+}
+
+fn read(response: Response<Body>) -> io::Result<String> {
+    response.into_body().read_to_string().map_err(failed)
+}
+
+impl SyncBackend for WebDav {
+    fn location(&self) -> &str {
+        &self.url
+    }
+
+    /// Upload the delta, making the folder first if it is not there yet.
+    fn push(&self, delta: &Delta) -> io::Result<()> {
+        let url = format!("{}{}.jsonl", self.url, delta.id);
+        let body = history::to_jsonl(&delta.entries)?.into_bytes();
+        match self.send("PUT", &url, None, body.clone()) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.send("MKCOL", &self.url, None, Vec::new())?;
+                info!(url = %self.url, "made the sync folder");
+                self.send("PUT", &url, None, body)?;
+            }
+            result => {
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    fn pull(&self, seen: &BTreeSet<String>) -> io::Result<Vec<Delta>> {
+        let listing = match self.send("PROPFIND", &self.url, Some("1"), Vec::new()) {
+            Ok(response) => read(response)?,
+            // Nothing pushed yet.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut deltas = Vec::new();
+        for id in delta_ids(&listing) {
+            if seen.contains(id) {
+                continue;
+            }
+            let url = format!("{}{id}.jsonl", self.url);
+            let text = read(self.send("GET", &url, None, Vec::new())?)?;
+            deltas.push(Delta { id: id.to_string(), entries: history::from_jsonl(&text)? });
+        }
+        Ok(deltas)
+    }
+}
+
+/// The ids of the deltas in a PROPFIND listing: the names of the `.jsonl`
+/// files in its `href`s, whatever the namespace prefix.
+fn delta_ids(listing: &str) -> BTreeSet<&str> {
+    listing
+        .split("href>")
+        .filter_map(|part| part.split('<').next())
+        .filter_map(|href| href.trim().rsplit('/').next()?.strip_suffix(".jsonl"))
+        .filter(|id| !id.is_empty())
+        .collect()
+}
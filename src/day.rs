@@ -0,0 +1,54 @@
+//! End-of-day mode: once the day's session has run to its last cycle, no
+//! new one starts until tomorrow, short of `start --override`. When the
+//! day was called done is kept in a file of its own, so any instance, the
+//! daemon's included, goes by it.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use crate::profile;
+
+#[derive(Debug)]
+pub struct DayFile {
+    path: PathBuf,
+}
+
+impl DayFile {
+    pub fn new(path: PathBuf) -> DayFile {
+        DayFile { path }
+    }
+
+    /// `day_done` in the [profile's](crate::profile) data folder, if the
+    /// platform has a data dir.
+    pub fn default_path() -> Option<PathBuf> {
+        profile::data_dir().map(|dir| dir.join("day_done"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Call the day done at `at`.
+    pub fn close(&self, at: DateTime<Local>) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, at.to_rfc3339())
+    }
+
+    /// When today was called done, if it was. A day called done before
+    /// today, or a file that makes no sense, holds nothing back.
+    pub fn closed_today(&self) -> io::Result<Option<DateTime<Local>>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let Ok(at) = DateTime::parse_from_rfc3339(text.trim()) else {
+            return Ok(None);
+        };
+        let at = at.with_timezone(&Local);
+        Ok((at.date_naive() == Local::now().date_naive()).then_some(at))
+    }
+}
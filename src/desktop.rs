@@ -0,0 +1,40 @@
+//! Desktop notifications, for when nobody is watching a terminal, like a
+//! session the daemon starts on its own.
+
+use std::io;
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+/// Show `title` and `body` as a desktop notification.
+pub fn notify(title: &str, body: &str) -> io::Result<()> {
+    let mut command = command(title, body)?;
+    debug!(?command, "notifying");
+    let status = command.stdout(Stdio::null()).stderr(Stdio::null()).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{:?} exited with {status}", command.get_program())))
+    }
+}
+
+/// `notify-send`, as every freedesktop notification daemon answers it.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn command(title: &str, body: &str) -> io::Result<Command> {
+    let mut command = Command::new("notify-send");
+    command.args(["--app-name", "opomodoro", title, body]);
+    Ok(command)
+}
+
+#[cfg(target_os = "macos")]
+fn command(title: &str, body: &str) -> io::Result<Command> {
+    let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+    let script = format!("display notification {} with title {}", quote(body), quote(title));
+    let mut command = Command::new("osascript");
+    command.args(["-e", &script]);
+    Ok(command)
+}
+
+#[cfg(windows)]
+fn command(_title: &str, _body: &str) -> io::Result<Command> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "no desktop notifications on Windows yet"))
+}
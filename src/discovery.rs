@@ -0,0 +1,85 @@
+//! Finding shared timers on the local network over mDNS, so joining does
+//! not take an IP address.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use tracing::debug;
+use crate::history::host_name;
+
+/// The DNS-SD service type shared timers advertise.
+const SERVICE: &str = "_opomodoro._tcp.local.";
+
+/// A timer shared on the local network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedTimer {
+    /// Who shares it, as `user@host`.
+    pub name: String,
+    pub addr: SocketAddr,
+}
+
+/// A shared timer being announced on the network, until dropped.
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+}
+
+impl fmt::Debug for Advertisement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Advertisement").finish_non_exhaustive()
+    }
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        // Let browsers know it is gone instead of waiting for the TTL.
+        let _ = self.daemon.shutdown();
+    }
+}
+
+fn failed(e: mdns_sd::Error) -> io::Error {
+    io::Error::other(format!("mdns: {e}"))
+}
+
+/// Announce the timer shared on `addr`. An unspecified address like
+/// `0.0.0.0` is announced with every address of the machine.
+pub fn advertise(addr: SocketAddr) -> io::Result<Advertisement> {
+    let daemon = ServiceDaemon::new().map_err(failed)?;
+    let host = host_name();
+    let user = env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or_default();
+    let name = if user.is_empty() { host.clone() } else { format!("{user}@{host}") };
+    let ip = if addr.ip().is_unspecified() { String::new() } else { addr.ip().to_string() };
+    let mut info = ServiceInfo::new(SERVICE, &name, &format!("{host}.local."), ip, addr.port(), None)
+        .map_err(failed)?;
+    if addr.ip().is_unspecified() {
+        info = info.enable_addr_auto();
+    }
+    daemon.register(info).map_err(failed)?;
+    Ok(Advertisement { daemon })
+}
+
+/// The timers that answer within `wait`, by name.
+pub fn browse(wait: Duration) -> io::Result<Vec<SharedTimer>> {
+    let daemon = ServiceDaemon::new().map_err(failed)?;
+    let events = daemon.browse(SERVICE).map_err(failed)?;
+    let deadline = Instant::now() + wait;
+    let mut found = BTreeMap::new();
+    while let Ok(event) = events.recv_deadline(deadline) {
+        let ServiceEvent::ServiceResolved(info) = event else {
+            continue;
+        };
+        let name = info.get_fullname().trim_end_matches(SERVICE).trim_end_matches('.').to_string();
+        // Prefer IPv4, which every member of the team can reach.
+        let mut addrs: Vec<_> = info.get_addresses().iter().copied().collect();
+        addrs.sort_by_key(|ip| !ip.is_ipv4());
+        if let Some(&ip) = addrs.first() {
+            debug!(%name, %ip, port = info.get_port(), "found a shared timer");
+            found.insert(name.clone(), SharedTimer { name, addr: SocketAddr::new(ip, info.get_port()) });
+        }
+    }
+    let _ = daemon.shutdown();
+    Ok(found.into_values().collect())
+}
@@ -0,0 +1,96 @@
+//! The engine run as a task of an async host, like a chat bot or a server,
+//! with no thread of its own: [`Engine::run_async`] takes [`Command`]s on
+//! an mpsc channel and sends what happens on a broadcast channel, for any
+//! number of listeners.
+//!
+//! ```no_run
+//! # async fn host() {
+//! use opomodoro::{Config, embed::Command, engine::Engine};
+//! use tokio::sync::{broadcast, mpsc};
+//!
+//! let config = Config::builder().work_mins(50).break_mins(10).build().unwrap();
+//! let (commands, inbox) = mpsc::channel(16);
+//! let (events, _) = broadcast::channel(16);
+//! let mut listener = events.subscribe();
+//! tokio::spawn(Engine::new(&config).run_async(inbox, events));
+//! commands.send(Command::Toggle).await.unwrap();
+//! while let Ok(event) = listener.recv().await {
+//!     println!("{event:?}");
+//! }
+//! # }
+//! ```
+
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use crate::clock::Clock;
+use crate::engine::{Engine, Event, SkipTarget};
+
+/// The longest the engine sleeps between two looks at the clock, so a pause
+/// budget running out is noticed in time.
+const MAX_SLEEP: Duration = Duration::from_secs(1);
+
+/// Something the host asks the running engine to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Toggle,
+    /// End the current phase and move on as if it had run out.
+    Skip,
+    SkipTo(SkipTarget),
+    GotoCycle(u32),
+    Snooze,
+    /// Take back a skip made moments ago.
+    UndoSkip,
+    /// Run the work phases and breaks still to start for these lengths.
+    SetLengths { work: Duration, break_time: Duration },
+    /// End the session early.
+    Quit,
+}
+
+impl<C: Clock> Engine<C> {
+    /// Run the session to its end, following `commands` and sending every
+    /// event on `events`; with no one listening the events are dropped.
+    /// Dropping every sender of `commands` quits the session. The engine
+    /// comes back finished, for a last look at it.
+    pub async fn run_async(
+        mut self,
+        mut commands: mpsc::Receiver<Command>,
+        events: broadcast::Sender<Event>,
+    ) -> Engine<C> {
+        while !self.is_finished() {
+            // Wake up just after the phase runs out, with a little slack so
+            // the clock is sure to be past it.
+            let wait = if self.is_paused() { MAX_SLEEP } else { self.remaining().min(MAX_SLEEP) };
+            let wait = wait + Duration::from_millis(5);
+            let done = tokio::select! {
+                command = commands.recv() => match command {
+                    Some(command) => self.obey(command),
+                    None => self.quit(),
+                },
+                () = tokio::time::sleep(wait) => Vec::new(),
+            };
+            for event in done.into_iter().chain(self.tick()) {
+                let _ = events.send(event);
+            }
+        }
+        self
+    }
+
+    fn obey(&mut self, command: Command) -> Vec<Event> {
+        match command {
+            Command::Toggle => {
+                self.toggle_pause();
+                Vec::new()
+            }
+            Command::Skip => self.skip(),
+            Command::SkipTo(target) => self.skip_to(target),
+            Command::GotoCycle(cycle) => self.goto_cycle(cycle),
+            Command::Snooze => self.snooze(),
+            Command::UndoSkip => self.undo_skip(),
+            Command::SetLengths { work, break_time } => {
+                self.set_lengths(work, break_time);
+                Vec::new()
+            }
+            Command::Quit => self.quit(),
+        }
+    }
+}
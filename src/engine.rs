@@ -0,0 +1,1093 @@
+use std::fmt;
+use std::time::Duration;
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use crate::Config;
+use crate::chain::Block;
+use crate::clock::{Clock, Instant, SystemClock};
+use crate::history::{Interruption, Outcome};
+use crate::intervals::Intervals;
+
+/// How long after a work phase ends a snooze is still accepted.
+pub const GRACE_WINDOW: Duration = Duration::from_secs(30);
+
+/// How long after a skip it can still be taken back.
+pub const UNDO_WINDOW: Duration = Duration::from_secs(10);
+
+/// What a phase is for. It serializes as its name, so the history, the
+/// state file and the control socket all write it the same way.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum PhaseKind {
+    Work,
+    ShortBreak,
+    /// A longer break, every few cycles.
+    LongBreak,
+    /// Planning, before the first work phase.
+    WarmUp,
+    /// Review, after the last phase of the session.
+    CoolDown,
+    /// A phase of the library user's own, by name, e.g. `Standup`.
+    Custom(String),
+}
+
+impl PhaseKind {
+    /// What the phase is called on screen and in the history. Short breaks
+    /// are just breaks, as they always were.
+    pub fn name(&self) -> &str {
+        match self {
+            PhaseKind::Work => "Work",
+            PhaseKind::ShortBreak => "Break",
+            PhaseKind::LongBreak => "Long break",
+            PhaseKind::WarmUp => "Warm-up",
+            PhaseKind::CoolDown => "Cool-down",
+            PhaseKind::Custom(name) => name,
+        }
+    }
+}
+
+/// The kind [`PhaseKind::name`] calls `name`; any other name is a custom one.
+impl From<&str> for PhaseKind {
+    fn from(name: &str) -> PhaseKind {
+        match name {
+            "Work" => PhaseKind::Work,
+            "Break" => PhaseKind::ShortBreak,
+            "Long break" => PhaseKind::LongBreak,
+            "Warm-up" => PhaseKind::WarmUp,
+            "Cool-down" => PhaseKind::CoolDown,
+            name => PhaseKind::Custom(name.to_string()),
+        }
+    }
+}
+
+impl From<String> for PhaseKind {
+    fn from(name: String) -> PhaseKind {
+        PhaseKind::from(name.as_str())
+    }
+}
+
+impl From<PhaseKind> for String {
+    fn from(kind: PhaseKind) -> String {
+        match kind {
+            PhaseKind::Custom(name) => name,
+            kind => kind.name().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for PhaseKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Phase {
+    pub kind: PhaseKind,
+    pub duration: Duration,
+}
+
+impl Phase {
+    pub fn build(kind: PhaseKind, duration: Duration) -> Phase {
+        Phase { kind, duration }
+    }
+
+    pub fn is_work(&self) -> bool {
+        self.kind == PhaseKind::Work
+    }
+
+    /// A short break or a long one.
+    pub fn is_break(&self) -> bool {
+        matches!(self.kind, PhaseKind::ShortBreak | PhaseKind::LongBreak)
+    }
+}
+
+/// Whether the phase's time is running out, or on hold. It serializes as
+/// the time left, a running timer's as of the system clock's now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "TimerStateRepr", into = "TimerStateRepr")]
+pub enum TimerState {
+    Running { end: Instant },
+    Paused { remaining: Duration },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TimerStateRepr {
+    Running { remaining: Duration },
+    Paused { remaining: Duration },
+}
+
+impl From<TimerState> for TimerStateRepr {
+    fn from(state: TimerState) -> TimerStateRepr {
+        match state {
+            TimerState::Running { end } => {
+                TimerStateRepr::Running { remaining: end.saturating_duration_since(Instant::now()) }
+            }
+            TimerState::Paused { remaining } => TimerStateRepr::Paused { remaining },
+        }
+    }
+}
+
+impl From<TimerStateRepr> for TimerState {
+    fn from(repr: TimerStateRepr) -> TimerState {
+        match repr {
+            TimerStateRepr::Running { remaining } => TimerState::Running { end: Instant::now() + remaining },
+            TimerStateRepr::Paused { remaining } => TimerState::Paused { remaining },
+        }
+    }
+}
+
+impl TimerState {
+    pub fn toggle_pause(&mut self, now: Instant) {
+        *self = match *self {
+            TimerState::Running { end } => {
+                let remaining = end.saturating_duration_since(now);
+                TimerState::Paused { remaining }
+            }
+            TimerState::Paused { remaining } => {
+                TimerState::Running { end: now + remaining }
+            }
+        };
+    }
+
+    /// Time left as of `now`.
+    pub fn remaining(&self, now: Instant) -> Duration {
+        match *self {
+            TimerState::Running { end } => end.saturating_duration_since(now),
+            TimerState::Paused { remaining } => remaining,
+        }
+    }
+}
+
+/// The engine as it was before a skip, for taking the skip back.
+#[derive(Debug, Clone)]
+struct Undo {
+    at: Instant,
+    cycle: u32,
+    interval: usize,
+    phase: Phase,
+    timer_state: TimerState,
+    /// Whether the phase was on hold before it ever ran, rather than paused.
+    held: bool,
+    snoozes: u32,
+    paused_for: Duration,
+    budget_spent: bool,
+    break_debt: Duration,
+}
+
+/// What to do about break time skipped over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BreakDebt {
+    /// Let it go.
+    #[default]
+    Off,
+    /// Keep count, and show the debt.
+    Show,
+    /// Add the debt to the next break, which pays it off.
+    Repay,
+}
+
+/// What happens to a work phase paused for longer than the pause budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverPauseBudget {
+    /// Say so, and let the phase go on.
+    #[default]
+    Warn,
+    /// End the phase there and then, recorded as incomplete.
+    Fail,
+}
+
+/// Where a skip from the skip menu should land.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipTarget {
+    /// End the work phase and start this cycle's break.
+    Break,
+    /// Skip ahead to the next work phase, dropping any break in between;
+    /// in an interval session, to the next round.
+    NextWork,
+    /// Skip whatever is left of the cycles and end the session.
+    RestOfCycle,
+}
+
+/// Something the engine did that the frontend may want to react to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    PhaseEnded {
+        phase: Phase,
+        cycle: u32,
+        elapsed: Duration,
+        outcome: Outcome,
+        /// The phase was a snoozed extension of a finished work phase.
+        extension: bool,
+    },
+    /// The break that just started was turned back into work.
+    Snoozed,
+    /// The last skip was taken back; the phase it ended goes on.
+    SkipUndone,
+    /// The work phase has been paused for longer than the pause budget;
+    /// if that `failed` it, it ends right after.
+    PauseBudgetSpent { budget: Duration, failed: bool },
+}
+
+/// What a frontend needs from a session, whether the [`Engine`] runs in
+/// this process or somewhere else.
+pub trait Timer {
+    fn now(&self) -> Instant;
+    fn phase(&self) -> Phase;
+    fn cycle(&self) -> u32;
+    fn num_cycles(&self) -> u32;
+    fn work_time(&self) -> Duration;
+    fn break_time(&self) -> Duration;
+    fn late(&self) -> bool;
+    fn is_paused(&self) -> bool;
+    fn is_finished(&self) -> bool;
+    fn remaining(&self) -> Duration;
+    fn grace_left(&self) -> Option<Duration>;
+    fn undo_left(&self) -> Option<Duration>;
+    fn get_ready_left(&self) -> Option<Duration>;
+    /// Break time skipped and not yet made up for.
+    fn break_debt(&self) -> Duration;
+    /// Length of the cool-down still to come after the last phase, if any.
+    fn cool_down(&self) -> Option<Duration>;
+
+    /// The long break's length and how many cycles apart it comes, if the
+    /// session takes one.
+    fn long_break(&self) -> Option<(Duration, u32)> {
+        None
+    }
+
+    /// The break that follows the work phase of `cycle`.
+    fn break_after(&self, cycle: u32) -> Phase {
+        match self.long_break() {
+            Some((length, every)) if cycle.is_multiple_of(every) => Phase::build(PhaseKind::LongBreak, length),
+            _ => Phase::build(PhaseKind::ShortBreak, self.break_time()),
+        }
+    }
+    fn toggle_pause(&mut self);
+    fn tick(&mut self) -> Vec<Event>;
+    fn skip(&mut self) -> Vec<Event>;
+    fn skip_to(&mut self, target: SkipTarget) -> Vec<Event>;
+    fn goto_cycle(&mut self, cycle: u32) -> Vec<Event>;
+    fn snooze(&mut self) -> Vec<Event>;
+    fn undo_skip(&mut self) -> Vec<Event>;
+    fn quit(&mut self) -> Vec<Event>;
+
+    /// Correct the time left in the current phase. Sessions running
+    /// elsewhere keep their own time and ignore it.
+    fn set_remaining(&mut self, _remaining: Duration) {}
+
+    /// Go on with `block` once the session is finished. Sessions running
+    /// elsewhere chain their own blocks and ignore it.
+    fn start_block(&mut self, _block: &Block) {}
+
+    /// Run the phases still to start for `work` and `break_time`. Returns
+    /// false when the session runs elsewhere and keeps its own lengths.
+    fn set_lengths(&mut self, _work: Duration, _break_time: Duration) -> bool {
+        false
+    }
+
+    /// Run `cycles` cycles in all; likewise false when that is not up to us.
+    fn set_cycles(&mut self, _cycles: u32) -> bool {
+        false
+    }
+
+    /// The phases of an interval session still to come after this one, in
+    /// order; None for any other session, or one running elsewhere.
+    fn intervals_to_come(&self) -> Option<Vec<Phase>> {
+        None
+    }
+
+    /// Hand `interruption` to whoever records the history. Returns false
+    /// when the frontend should keep it with the phase itself.
+    fn record_interruption(&mut self, _interruption: &Interruption) -> bool {
+        false
+    }
+
+    /// Wall-clock time the current phase runs out, unless it is on hold.
+    fn phase_ends_at(&self) -> Option<DateTime<Local>> {
+        if self.is_paused() || self.is_finished() {
+            return None;
+        }
+        Some(Local::now() + self.remaining() + self.get_ready_left().unwrap_or_default())
+    }
+
+    /// The phase that follows this one, if the session goes on after it.
+    fn next_phase(&self) -> Option<Phase> {
+        if self.is_finished() {
+            return None;
+        }
+        if let Some(intervals) = self.intervals_to_come() {
+            return intervals.into_iter().next();
+        }
+        let cool_down = || self.cool_down().map(|cool_down| Phase::build(PhaseKind::CoolDown, cool_down));
+        let more = self.cycle() < self.num_cycles();
+        let work = || Some(Phase::build(PhaseKind::Work, self.work_time()));
+        match self.phase().kind {
+            PhaseKind::WarmUp => work(),
+            PhaseKind::Work if more || self.late() => Some(self.break_after(self.cycle())),
+            PhaseKind::ShortBreak | PhaseKind::LongBreak if more => work(),
+            PhaseKind::Work | PhaseKind::ShortBreak | PhaseKind::LongBreak => cool_down(),
+            // Custom phases are their owner's business.
+            PhaseKind::CoolDown | PhaseKind::Custom(_) => None,
+        }
+    }
+
+    /// Time the rest of the session takes if every phase runs its course:
+    /// what is left of this phase plus all the phases still to come.
+    fn session_remaining(&self) -> Duration {
+        if self.is_finished() {
+            return Duration::ZERO;
+        }
+        let (cycle, cycles) = (self.cycle(), self.num_cycles());
+        let mut rest = self.remaining();
+        if let Some(intervals) = self.intervals_to_come() {
+            return rest + intervals.iter().map(|phase| phase.duration).sum::<Duration>();
+        }
+        let phase = self.phase();
+        match phase.kind {
+            PhaseKind::CoolDown | PhaseKind::Custom(_) => return rest,
+            // The warm-up comes before the work phase of its cycle.
+            PhaseKind::WarmUp => rest += self.work_time(),
+            PhaseKind::Work | PhaseKind::ShortBreak | PhaseKind::LongBreak => {}
+        }
+        let has_break = |cycle: u32| cycle < cycles || self.late();
+        if !phase.is_break() && has_break(cycle) {
+            rest += self.break_after(cycle).duration;
+        }
+        for later in cycle + 1..=cycles {
+            rest += self.work_time();
+            if has_break(later) {
+                rest += self.break_after(later).duration;
+            }
+        }
+        rest + self.cool_down().unwrap_or_default()
+    }
+
+    /// Wall-clock time the session would end, pushed back while paused.
+    fn session_ends_at(&self) -> Option<DateTime<Local>> {
+        (!self.is_finished()).then(|| Local::now() + self.session_remaining())
+    }
+}
+
+/// The cycle/phase state machine, free of any terminal handling.
+/// Time comes from `C`, so tests can drive it with a
+/// [`MockClock`](crate::clock::MockClock).
+#[derive(Debug)]
+pub struct Engine<C: Clock = SystemClock> {
+    clock: C,
+    current_cycle: u32,
+    num_cycles: u32,
+    work_time: Duration,
+    break_time: Duration,
+    /// Count down this long before each work phase after the first.
+    get_ready: Option<Duration>,
+    long_break: Option<(Duration, u32)>,
+    /// Run a cool-down this long once the last block is over.
+    cool_down: Option<Duration>,
+    /// Blocks of the chain still to start after this one.
+    blocks_left: usize,
+    /// The set run round after round in place of work and breaks, if any.
+    intervals: Option<Intervals>,
+    /// Where the phase on now is in its round of `intervals`.
+    interval: usize,
+    break_debt_policy: BreakDebt,
+    break_debt: Duration,
+    late: bool,
+    /// Start each phase after one runs out on hold, for the user to start.
+    manual_advance: bool,
+    phase: Phase,
+    timer_state: TimerState,
+    snooze: Duration,
+    max_snoozes: u32,
+    snoozes: u32,
+    grace_until: Option<Instant>,
+    undo: Option<Undo>,
+    finished: bool,
+    pause_budget: Option<(Duration, OverPauseBudget)>,
+    /// Time the phase spent paused before the pause it is in, if any.
+    paused_for: Duration,
+    /// When the pause the phase is in started, if it counts.
+    paused_since: Option<Instant>,
+    /// Whether the phase went over the pause budget.
+    budget_spent: bool,
+}
+
+impl Engine {
+    pub fn new(config: &Config) -> Engine {
+        Engine::with_clock(config, SystemClock)
+    }
+}
+
+impl<C: Clock> Engine<C> {
+    /// An engine for `config`, running its first block if it has a chain,
+    /// after the warm-up if there is one; or only its single phase or its
+    /// intervals, if it has those.
+    pub fn with_clock(config: &Config, clock: C) -> Engine<C> {
+        let now = clock.now();
+        let mut engine = Engine {
+            clock,
+            current_cycle: 1,
+            num_cycles: config.cycles,
+            work_time: config.work_time,
+            break_time: config.break_time,
+            get_ready: config.get_ready,
+            long_break: config.long_break,
+            cool_down: config.cool_down,
+            blocks_left: 0,
+            intervals: None,
+            interval: 0,
+            break_debt_policy: config.break_debt,
+            break_debt: Duration::ZERO,
+            late: config.late,
+            manual_advance: config.manual_advance.is_some(),
+            phase: Phase::build(PhaseKind::Work, config.work_time),
+            timer_state: TimerState::Running { end: now + config.work_time },
+            snooze: config.snooze,
+            max_snoozes: config.max_snoozes,
+            snoozes: 0,
+            grace_until: None,
+            undo: None,
+            finished: false,
+            pause_budget: config.pause_budget,
+            paused_for: Duration::ZERO,
+            paused_since: None,
+            budget_spent: false,
+        };
+        let intervals = config.single.clone().map(Intervals::single).or_else(|| config.intervals.clone());
+        if let Some(intervals) = intervals {
+            // A cycle to a round, with nothing after the last, so whatever
+            // the phases' kinds, the session is over when they are.
+            engine.num_cycles = intervals.rounds;
+            engine.late = false;
+            engine.cool_down = None;
+            engine.intervals = Some(intervals);
+            engine.start_interval(0, now);
+            return engine;
+        }
+        if let Some(first) = config.chain.first() {
+            engine.start_block(first);
+            engine.blocks_left = config.chain.len() - 1;
+        }
+        // A chain that opens with a break has nothing to plan for yet.
+        if let Some(warm_up) = config.warm_up.filter(|_| engine.phase.is_work()) {
+            engine.phase = Phase::build(PhaseKind::WarmUp, warm_up);
+            engine.timer_state = TimerState::Running { end: now + warm_up };
+        }
+        engine
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase.clone()
+    }
+
+    pub fn timer_state(&self) -> TimerState {
+        self.timer_state
+    }
+
+    pub fn cycle(&self) -> u32 {
+        self.current_cycle
+    }
+
+    pub fn num_cycles(&self) -> u32 {
+        self.num_cycles
+    }
+
+    pub fn work_time(&self) -> Duration {
+        self.work_time
+    }
+
+    pub fn break_time(&self) -> Duration {
+        self.break_time
+    }
+
+    pub fn late(&self) -> bool {
+        self.late
+    }
+
+    pub fn is_paused(&self) -> bool {
+        matches!(self.timer_state, TimerState::Paused { .. })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Time left in the phase; while getting ready for work, all of it.
+    pub fn remaining(&self) -> Duration {
+        self.timer_state.remaining(self.now()).min(self.phase.duration)
+    }
+
+    /// Time left in the countdown before the work phase starts, if one is
+    /// on: the pre-phase, kept as the head of the phase's own timer, so
+    /// pausing or skipping it works as it does for the phase.
+    pub fn get_ready_left(&self) -> Option<Duration> {
+        let left = self.timer_state.remaining(self.now()).saturating_sub(self.phase.duration);
+        (!left.is_zero()).then_some(left)
+    }
+
+    /// Time left to snooze the break that just started, if a snooze is allowed.
+    pub fn grace_left(&self) -> Option<Duration> {
+        let now = self.now();
+        self.grace_until
+            .filter(|_| self.snoozes < self.max_snoozes)
+            .map(|until| until.saturating_duration_since(now))
+            .filter(|left| !left.is_zero())
+    }
+
+    /// Time left to take back the last skip, if there was one.
+    pub fn undo_left(&self) -> Option<Duration> {
+        let now = self.now();
+        self.undo
+            .as_ref()
+            .map(|undo| (undo.at + UNDO_WINDOW).saturating_duration_since(now))
+            .filter(|left| !left.is_zero())
+    }
+
+    pub fn break_debt(&self) -> Duration {
+        self.break_debt
+    }
+
+    pub fn long_break(&self) -> Option<(Duration, u32)> {
+        self.long_break
+    }
+
+    /// Length of the cool-down, unless blocks of the chain are still to
+    /// come, or it is already on or over.
+    pub fn cool_down(&self) -> Option<Duration> {
+        let over = self.finished || self.phase.kind == PhaseKind::CoolDown;
+        self.cool_down.filter(|_| self.blocks_left == 0 && !over)
+    }
+
+    /// Time the current phase has spent paused. A phase held before it
+    /// ever ran, like the first one while waiting to start, has none.
+    pub fn paused_time(&self) -> Duration {
+        let now = self.now();
+        self.paused_for + self.paused_since.map_or(Duration::ZERO, |since| now.saturating_duration_since(since))
+    }
+
+    pub fn toggle_pause(&mut self) {
+        let now = self.now();
+        match self.paused_since.take() {
+            Some(since) => self.paused_for += now.saturating_duration_since(since),
+            // A phase held before it ever ran is waiting, not paused.
+            None if !self.is_paused() && self.remaining() < self.phase.duration => {
+                self.paused_since = Some(now);
+            }
+            None => {}
+        }
+        self.timer_state.toggle_pause(now);
+    }
+
+    /// Change the time left in the current phase, to at most all of it,
+    /// keeping it running or paused.
+    pub fn set_remaining(&mut self, remaining: Duration) {
+        let now = self.now();
+        let remaining = remaining.min(self.phase.duration);
+        self.timer_state = match self.timer_state {
+            TimerState::Running { .. } => TimerState::Running { end: now + remaining },
+            TimerState::Paused { .. } => TimerState::Paused { remaining },
+        };
+    }
+
+    /// Run work and break phases for `work` and `break_time` from the next
+    /// one to start; the phase on now keeps its length and its time left.
+    pub fn set_lengths(&mut self, work: Duration, break_time: Duration) {
+        self.work_time = work;
+        self.break_time = break_time;
+    }
+
+    /// Run `cycles` cycles in all, but never fewer than the one on now.
+    pub fn set_cycles(&mut self, cycles: u32) {
+        self.num_cycles = cycles.max(self.current_cycle);
+        if let Some(set) = &mut self.intervals {
+            set.rounds = self.num_cycles;
+        }
+    }
+
+    /// Complete the current phase if its time has run out, or fail a work
+    /// phase paused for too long if the pause budget says to.
+    pub fn tick(&mut self) -> Vec<Event> {
+        let now = self.now();
+        if let Some(events) = self.check_pause_budget(now) {
+            return events;
+        }
+        if self.finished
+            || self.is_paused()
+            || !self.timer_state.remaining(now).is_zero() {
+            return Vec::new();
+        }
+        let event = self.end_phase(Outcome::Completed, now);
+        self.undo = None;
+        let worked = self.phase.is_work() && self.intervals.is_none();
+        self.advance(now);
+        self.hold(now);
+        if worked && !self.finished && self.max_snoozes > 0 {
+            self.grace_until = Some(now + GRACE_WINDOW);
+        }
+        vec![event]
+    }
+
+    pub fn skip(&mut self) -> Vec<Event> {
+        let now = self.now();
+        if self.finished {
+            return Vec::new();
+        }
+        let undo = self.undo_point(now);
+        let event = self.end_phase(Outcome::Skipped, now);
+        self.advance(now);
+        self.undo = Some(undo);
+        vec![event]
+    }
+
+    pub fn skip_to(&mut self, target: SkipTarget) -> Vec<Event> {
+        let now = self.now();
+        if self.finished {
+            return Vec::new();
+        }
+        let was_work = self.phase.is_work();
+        let undo = self.undo_point(now);
+        let events = match target {
+            SkipTarget::Break => {
+                if !was_work || self.intervals.is_some() {
+                    return Vec::new();
+                }
+                let event = self.end_phase(Outcome::Skipped, now);
+                self.start_break(now);
+                vec![event]
+            }
+            SkipTarget::NextWork => {
+                let event = self.end_phase(Outcome::Skipped, now);
+                if self.phase.kind == PhaseKind::WarmUp {
+                    self.run_work(now);
+                } else if self.current_cycle == self.num_cycles {
+                    self.wind_down(now);
+                } else if self.intervals.is_some() {
+                    self.current_cycle += 1;
+                    self.start_interval(0, now);
+                } else {
+                    if was_work {
+                        // The break in between is dropped whole.
+                        self.owe_break(self.break_time);
+                    }
+                    self.start_work(now);
+                }
+                vec![event]
+            }
+            SkipTarget::RestOfCycle => {
+                let event = self.end_phase(Outcome::Skipped, now);
+                self.wind_down(now);
+                vec![event]
+            }
+        };
+        self.undo = Some(undo);
+        events
+    }
+
+    /// Continue a saved session at `cycle`, in a phase of `kind` with
+    /// `remaining` time left.
+    pub fn restore(&mut self, cycle: u32, kind: PhaseKind, remaining: Duration) {
+        let now = self.now();
+        self.current_cycle = cycle.clamp(1, self.num_cycles);
+        let interval = self.intervals.as_ref().and_then(|set| {
+            let index = set.phases.iter().position(|phase| phase.kind == kind)?;
+            Some((index, set.phases[index].duration))
+        });
+        if let Some((index, duration)) = interval {
+            self.interval = index;
+            self.phase = Phase::build(kind, duration);
+            self.timer_state = TimerState::Running { end: now + remaining.min(duration) };
+            self.reset_pauses();
+            return;
+        }
+        let duration = match kind {
+            PhaseKind::Work => self.work_time,
+            PhaseKind::ShortBreak => self.break_time,
+            PhaseKind::LongBreak => self.long_break.map_or(remaining, |(length, _)| length),
+            // Their full lengths are not kept; what is left will do.
+            PhaseKind::WarmUp | PhaseKind::CoolDown | PhaseKind::Custom(_) => remaining,
+        };
+        self.phase = Phase::build(kind, duration);
+        let remaining = remaining.min(self.phase.duration);
+        self.timer_state = TimerState::Running { end: now + remaining };
+        self.reset_pauses();
+    }
+
+    /// Start `block` from its first cycle, in place of the session that
+    /// just finished. A block with no work is a single break.
+    pub fn start_block(&mut self, block: &Block) {
+        let now = self.now();
+        self.current_cycle = 1;
+        self.num_cycles = if block.is_break() { 1 } else { block.cycles };
+        self.blocks_left = self.blocks_left.saturating_sub(1);
+        self.break_time = block.break_time;
+        self.snoozes = 0;
+        self.grace_until = None;
+        self.undo = None;
+        self.finished = false;
+        self.reset_pauses();
+        match block.work_time {
+            Some(work) => {
+                self.work_time = work;
+                self.run_work(now);
+            }
+            None => self.start_break(now),
+        }
+    }
+
+    /// Abandon the current phase and start the work phase of `cycle`.
+    pub fn goto_cycle(&mut self, cycle: u32) -> Vec<Event> {
+        let now = self.now();
+        if self.finished || !(1..=self.num_cycles).contains(&cycle) {
+            return Vec::new();
+        }
+        let undo = self.undo_point(now);
+        let event = self.end_phase(Outcome::Skipped, now);
+        if self.intervals.is_some() {
+            self.current_cycle = cycle;
+            self.start_interval(0, now);
+        } else {
+            self.current_cycle = cycle - 1;
+            self.start_work(now);
+        }
+        self.undo = Some(undo);
+        vec![event]
+    }
+
+    /// End the session early, reporting the interrupted phase.
+    pub fn quit(&mut self) -> Vec<Event> {
+        let now = self.now();
+        if self.finished {
+            return Vec::new();
+        }
+        let event = self.end_phase(Outcome::Quit, now);
+        self.undo = None;
+        self.finished = true;
+        vec![event]
+    }
+
+    /// Go back to the phase the last skip ended, with the time it had left,
+    /// as long as that was under [`UNDO_WINDOW`] ago.
+    pub fn undo_skip(&mut self) -> Vec<Event> {
+        let now = self.now();
+        if self.undo_left().is_none() {
+            return Vec::new();
+        }
+        let Some(undo) = self.undo.take() else {
+            return Vec::new();
+        };
+        self.current_cycle = undo.cycle;
+        self.interval = undo.interval;
+        self.phase = undo.phase;
+        self.snoozes = undo.snoozes;
+        self.timer_state = match undo.timer_state {
+            // Time spent since the skip does not count against the phase.
+            TimerState::Running { end } => TimerState::Running { end: now + end.saturating_duration_since(undo.at) },
+            paused => paused,
+        };
+        self.paused_for = undo.paused_for;
+        let paused = matches!(undo.timer_state, TimerState::Paused { .. }) && !undo.held;
+        self.paused_since = paused.then_some(now);
+        self.budget_spent = undo.budget_spent;
+        self.break_debt = undo.break_debt;
+        self.grace_until = None;
+        self.finished = false;
+        vec![Event::SkipUndone]
+    }
+
+    /// Turn the break that just started back into `snooze` more minutes of work.
+    pub fn snooze(&mut self) -> Vec<Event> {
+        let now = self.now();
+        if self.grace_left().is_none() {
+            return Vec::new();
+        }
+        self.snoozes += 1;
+        self.grace_until = None;
+        self.undo = None;
+        self.phase = Phase::build(PhaseKind::Work, self.snooze);
+        self.timer_state = TimerState::Running { end: now + self.snooze };
+        self.reset_pauses();
+        vec![Event::Snoozed]
+    }
+
+    /// Report the work phase going over the pause budget, the first time
+    /// it does, and end it if that fails it.
+    fn check_pause_budget(&mut self, now: Instant) -> Option<Vec<Event>> {
+        let (budget, over) = self.pause_budget?;
+        if self.finished || self.budget_spent || !self.phase.is_work() || self.paused_time() <= budget {
+            return None;
+        }
+        self.budget_spent = true;
+        let failed = over == OverPauseBudget::Fail;
+        let mut events = vec![Event::PauseBudgetSpent { budget, failed }];
+        if failed {
+            events.push(self.end_phase(Outcome::Incomplete, now));
+            self.undo = None;
+            self.advance(now);
+            self.hold(now);
+        }
+        Some(events)
+    }
+
+    /// Keep the phase that just started from running until it is started
+    /// by hand, in manual-advance mode.
+    fn hold(&mut self, now: Instant) {
+        if self.manual_advance && !self.finished {
+            // Held before it ever ran, so the wait is not pause time.
+            self.timer_state = TimerState::Paused { remaining: self.timer_state.remaining(now) };
+        }
+    }
+
+    fn undo_point(&self, now: Instant) -> Undo {
+        Undo {
+            at: now,
+            cycle: self.current_cycle,
+            interval: self.interval,
+            phase: self.phase.clone(),
+            timer_state: self.timer_state,
+            held: self.is_paused() && self.paused_since.is_none(),
+            snoozes: self.snoozes,
+            // A pause going on counts up to the skip.
+            paused_for: self.paused_time(),
+            budget_spent: self.budget_spent,
+            break_debt: self.break_debt,
+        }
+    }
+
+    /// Add `skipped` break time to the debt, if it is kept.
+    fn owe_break(&mut self, skipped: Duration) {
+        if self.break_debt_policy != BreakDebt::Off {
+            self.break_debt += skipped;
+        }
+    }
+
+    /// Start the count of pause time over, for a new phase.
+    fn reset_pauses(&mut self) {
+        self.paused_for = Duration::ZERO;
+        self.paused_since = None;
+        self.budget_spent = false;
+    }
+
+    fn end_phase(&mut self, outcome: Outcome, now: Instant) -> Event {
+        self.grace_until = None;
+        if outcome == Outcome::Skipped && self.phase.is_break() {
+            self.owe_break(self.timer_state.remaining(now).min(self.phase.duration));
+        }
+        Event::PhaseEnded {
+            phase: self.phase.clone(),
+            cycle: self.current_cycle,
+            elapsed: self.phase.duration.saturating_sub(self.timer_state.remaining(now)),
+            outcome,
+            extension: self.phase.is_work() && self.snoozes > 0,
+        }
+    }
+
+    fn advance(&mut self, now: Instant) {
+        let last = self.current_cycle == self.num_cycles;
+        if let Some(count) = self.intervals.as_ref().map(|set| set.phases.len()) {
+            if self.interval + 1 < count {
+                self.start_interval(self.interval + 1, now);
+            } else if !last {
+                self.current_cycle += 1;
+                self.start_interval(0, now);
+            } else {
+                self.finished = true;
+            }
+            return;
+        }
+        match self.phase.kind {
+            PhaseKind::WarmUp => self.run_work(now),
+            PhaseKind::Work if last && !self.late => self.wind_down(now),
+            PhaseKind::Work => self.start_break(now),
+            PhaseKind::ShortBreak | PhaseKind::LongBreak if last => self.wind_down(now),
+            PhaseKind::ShortBreak | PhaseKind::LongBreak => self.start_work(now),
+            PhaseKind::CoolDown | PhaseKind::Custom(_) => self.finished = true,
+        }
+    }
+
+    /// End the session, after the cool-down if it has one still to run.
+    fn wind_down(&mut self, now: Instant) {
+        match self.cool_down() {
+            Some(cool_down) => {
+                self.phase = Phase::build(PhaseKind::CoolDown, cool_down);
+                self.timer_state = TimerState::Running { end: now + cool_down };
+                self.reset_pauses();
+            }
+            None => self.finished = true,
+        }
+    }
+
+    fn start_break(&mut self, now: Instant) {
+        let mut phase = self.break_after(self.current_cycle);
+        if self.break_debt_policy == BreakDebt::Repay {
+            phase.duration += std::mem::take(&mut self.break_debt);
+        }
+        self.timer_state = TimerState::Running { end: now + phase.duration };
+        self.phase = phase;
+        self.reset_pauses();
+    }
+
+    /// Start the work phase of the next cycle, after the get-ready
+    /// countdown if there is one.
+    fn start_work(&mut self, now: Instant) {
+        self.phase = Phase::build(PhaseKind::Work, self.work_time);
+        let countdown = self.get_ready.unwrap_or_default();
+        self.timer_state = TimerState::Running { end: now + countdown + self.work_time };
+        self.current_cycle += 1;
+        self.snoozes = 0;
+        self.reset_pauses();
+    }
+
+    /// Start the phase at `index` in this round of the intervals.
+    fn start_interval(&mut self, index: usize, now: Instant) {
+        let Some(phase) = self.intervals.as_ref().and_then(|set| set.phases.get(index)) else {
+            return;
+        };
+        self.interval = index;
+        self.timer_state = TimerState::Running { end: now + phase.duration };
+        self.phase = phase.clone();
+        self.reset_pauses();
+    }
+
+    /// Start the work phase of this cycle straight away, as the first of a
+    /// session or block.
+    fn run_work(&mut self, now: Instant) {
+        self.phase = Phase::build(PhaseKind::Work, self.work_time);
+        self.timer_state = TimerState::Running { end: now + self.work_time };
+        self.snoozes = 0;
+        self.reset_pauses();
+    }
+}
+
+impl<C: Clock> Timer for Engine<C> {
+    fn now(&self) -> Instant {
+        Engine::now(self)
+    }
+
+    fn phase(&self) -> Phase {
+        Engine::phase(self)
+    }
+
+    fn cycle(&self) -> u32 {
+        Engine::cycle(self)
+    }
+
+    fn num_cycles(&self) -> u32 {
+        Engine::num_cycles(self)
+    }
+
+    fn work_time(&self) -> Duration {
+        Engine::work_time(self)
+    }
+
+    fn break_time(&self) -> Duration {
+        Engine::break_time(self)
+    }
+
+    fn late(&self) -> bool {
+        Engine::late(self)
+    }
+
+    fn is_paused(&self) -> bool {
+        Engine::is_paused(self)
+    }
+
+    fn is_finished(&self) -> bool {
+        Engine::is_finished(self)
+    }
+
+    fn remaining(&self) -> Duration {
+        Engine::remaining(self)
+    }
+
+    fn grace_left(&self) -> Option<Duration> {
+        Engine::grace_left(self)
+    }
+
+    fn undo_left(&self) -> Option<Duration> {
+        Engine::undo_left(self)
+    }
+
+    fn get_ready_left(&self) -> Option<Duration> {
+        Engine::get_ready_left(self)
+    }
+
+    fn break_debt(&self) -> Duration {
+        Engine::break_debt(self)
+    }
+
+    fn cool_down(&self) -> Option<Duration> {
+        Engine::cool_down(self)
+    }
+
+    fn long_break(&self) -> Option<(Duration, u32)> {
+        Engine::long_break(self)
+    }
+
+    fn toggle_pause(&mut self) {
+        Engine::toggle_pause(self)
+    }
+
+    fn set_remaining(&mut self, remaining: Duration) {
+        Engine::set_remaining(self, remaining)
+    }
+
+    fn start_block(&mut self, block: &Block) {
+        Engine::start_block(self, block)
+    }
+
+    fn set_lengths(&mut self, work: Duration, break_time: Duration) -> bool {
+        Engine::set_lengths(self, work, break_time);
+        true
+    }
+
+    fn set_cycles(&mut self, cycles: u32) -> bool {
+        Engine::set_cycles(self, cycles);
+        true
+    }
+
+    fn intervals_to_come(&self) -> Option<Vec<Phase>> {
+        let set = self.intervals.as_ref()?;
+        Some(set.after(self.current_cycle, self.interval).cloned().collect())
+    }
+
+    fn tick(&mut self) -> Vec<Event> {
+        Engine::tick(self)
+    }
+
+    fn skip(&mut self) -> Vec<Event> {
+        Engine::skip(self)
+    }
+
+    fn skip_to(&mut self, target: SkipTarget) -> Vec<Event> {
+        Engine::skip_to(self, target)
+    }
+
+    fn goto_cycle(&mut self, cycle: u32) -> Vec<Event> {
+        Engine::goto_cycle(self, cycle)
+    }
+
+    fn snooze(&mut self) -> Vec<Event> {
+        Engine::snooze(self)
+    }
+
+    fn undo_skip(&mut self) -> Vec<Event> {
+        Engine::undo_skip(self)
+    }
+
+    fn quit(&mut self) -> Vec<Event> {
+        Engine::quit(self)
+    }
+}
@@ -0,0 +1,89 @@
+use std::io;
+use std::time::Duration;
+use chrono::{DateTime, Local};
+use thiserror::Error;
+use crate::{MAX_CYCLES, MAX_GET_READY, MAX_PHASE};
+
+/// Everything that can go wrong in opomodoro.
+#[derive(Debug, Error)]
+pub enum OpomodoroError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("issue parsing {what}: {message}")]
+    Parse { what: String, message: String },
+    #[error("invalid configuration: {0}")]
+    Config(#[from] ConfigError),
+    #[error("terminal error: {0}")]
+    Terminal(io::Error),
+    #[error("{name} integration failed: {message}")]
+    Integration { name: &'static str, message: String },
+    #[error(
+        "another timer is running{}",
+        pid.map(|pid| format!(" (pid {pid})")).unwrap_or_default()
+    )]
+    AlreadyRunning { pid: Option<u32> },
+    #[error("you called it a day at {}; start again tomorrow, or pass --override", at.format("%H:%M"))]
+    DayDone { at: DateTime<Local> },
+}
+
+impl OpomodoroError {
+    pub fn parse(what: impl Into<String>, message: impl ToString) -> OpomodoroError {
+        OpomodoroError::Parse { what: what.into(), message: message.to_string() }
+    }
+
+    /// A failure of any ratatui backend, the real terminal or a test one.
+    pub fn terminal(e: impl std::error::Error + Send + Sync + 'static) -> OpomodoroError {
+        OpomodoroError::Terminal(io::Error::other(e))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, OpomodoroError>;
+
+/// Why a [`Config`](crate::Config) was rejected by
+/// [`Config::validate`](crate::Config::validate).
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConfigError {
+    /// A duration that must be positive was zero.
+    #[error("{0} must be longer than zero")]
+    ZeroDuration(&'static str),
+    /// A duration was longer than [`MAX_PHASE`].
+    #[error(
+        "{0} of {len} is longer than the {max}h maximum",
+        len = humantime::format_duration(*.1),
+        max = MAX_PHASE.as_secs() / 3600
+    )]
+    TooLong(&'static str, Duration),
+    /// The get-ready countdown was longer than [`MAX_GET_READY`].
+    #[error(
+        "get ready countdown of {len} is longer than the {max}s maximum",
+        len = humantime::format_duration(*.0),
+        max = MAX_GET_READY.as_secs()
+    )]
+    LongCountdown(Duration),
+    #[error("cycles must be at least 1")]
+    NoCycles,
+    #[error("long breaks must come every 1 cycle or more")]
+    NoLongBreakCycles,
+    #[error("{0} cycles is more than the maximum of {MAX_CYCLES}")]
+    TooManyCycles(u32),
+    #[error("an interval set needs at least one interval")]
+    NoIntervals,
+}
+
+/// Why a file could not be brought up to the format this version reads.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SchemaError {
+    /// Written by a newer opomodoro; reading it could lose what it added.
+    #[error("{what} is in format {found}, from a newer opomodoro; this one reads up to format {current}")]
+    Newer { what: String, found: u32, current: u32 },
+    /// A migration gave up on the data.
+    #[error("could not upgrade {what} from format {from}: {message}")]
+    Migration { what: String, from: u32, message: String },
+}
+
+/// The stores work in `io::Result`, like the files they read.
+impl From<SchemaError> for io::Error {
+    fn from(e: SchemaError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
@@ -0,0 +1,154 @@
+use crate::FontChoice;
+
+#[derive(Debug)]
+pub struct Font {
+    pub height: u16,
+    digits: [&'static [&'static str]; 10],
+    colon: &'static [&'static str],
+    /// `m`, for the time left in minutes.
+    minutes: &'static [&'static str],
+    /// `%`, for the share of the phase left.
+    percent: &'static [&'static str],
+}
+
+impl Font {
+    fn glyph(&self, ch: char) -> &'static [&'static str] {
+        match ch {
+            '0'..='9' => self.digits[(ch as u8 - b'0') as usize],
+            ':' => self.colon,
+            'm' => self.minutes,
+            '%' => self.percent,
+            _ => &[],
+        }
+    }
+
+    /// Width in cells of `text` rendered in this font, including glyph spacing.
+    pub fn width(&self, text: &str) -> u16 {
+        let glyphs: u16 = text
+            .chars()
+            .map(|ch| self.glyph(ch).first().map_or(0, |row| row.chars().count() as u16))
+            .sum();
+        glyphs + text.chars().count().saturating_sub(1) as u16
+    }
+
+    /// Render `text` as `height` rows of block characters.
+    pub fn render(&self, text: &str) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut rows: Vec<String> = Vec::with_capacity(self.height as usize);
+        for row in 0..self.height as usize {
+            let mut line = String::new();
+            for (i, ch) in chars.iter().enumerate() {
+                if let Some(part) = self.glyph(*ch).get(row) {
+                    line.push_str(part);
+                }
+                if i + 1 < chars.len() {
+                    line.push(' '); // spacing between glyphs
+                }
+            }
+            rows.push(line);
+        }
+        rows
+    }
+}
+
+pub const LARGE: Font = Font {
+    height: 7,
+    digits: [
+        // 0
+        &["███████", "██   ██", "██   ██", "██   ██", "██   ██", "██   ██", "███████"],
+        // 1
+        &["   ██  ", "  ███  ", " ████  ", "   ██  ", "   ██  ", "   ██  ", " ██████"],
+        // 2
+        &["███████", "     ██", "     ██", "███████", "██     ", "██     ", "███████"],
+        // 3
+        &["███████", "     ██", "     ██", "███████", "     ██", "     ██", "███████"],
+        // 4
+        &["██   ██", "██   ██", "██   ██", "███████", "     ██", "     ██", "     ██"],
+        // 5
+        &["███████", "██     ", "██     ", "███████", "     ██", "     ██", "███████"],
+        // 6
+        &["███████", "██     ", "██     ", "███████", "██   ██", "██   ██", "███████"],
+        // 7
+        &["███████", "     ██", "    ██ ", "   ██  ", "  ██   ", "  ██   ", "  ██   "],
+        // 8
+        &["███████", "██   ██", "██   ██", "███████", "██   ██", "██   ██", "███████"],
+        // 9
+        &["███████", "██   ██", "██   ██", "███████", "     ██", "     ██", "███████"],
+    ],
+    colon: &["  ", "  ", "██", "  ", "██", "  ", "  "],
+    minutes: &["       ", "       ", "███████", "██ █ ██", "██ █ ██", "██ █ ██", "██ █ ██"],
+    percent: &["██   ██", "██  ██ ", "   ██  ", "  ██   ", " ██    ", "██   ██", "     ██"],
+};
+
+pub const MEDIUM: Font = Font {
+    height: 5,
+    digits: [
+        // 0
+        &["█████", "█   █", "█   █", "█   █", "█████"],
+        // 1
+        &["  █  ", " ██  ", "  █  ", "  █  ", "█████"],
+        // 2
+        &["█████", "    █", "█████", "█    ", "█████"],
+        // 3
+        &["█████", "    █", "█████", "    █", "█████"],
+        // 4
+        &["█   █", "█   █", "█████", "    █", "    █"],
+        // 5
+        &["█████", "█    ", "█████", "    █", "█████"],
+        // 6
+        &["█████", "█    ", "█████", "█   █", "█████"],
+        // 7
+        &["█████", "    █", "   █ ", "  █  ", "  █  "],
+        // 8
+        &["█████", "█   █", "█████", "█   █", "█████"],
+        // 9
+        &["█████", "█   █", "█████", "    █", "█████"],
+    ],
+    colon: &["  ", "██", "  ", "██", "  "],
+    minutes: &["     ", "     ", "█████", "█ █ █", "█ █ █"],
+    percent: &["█   █", "   █ ", "  █  ", " █   ", "█   █"],
+};
+
+pub const SMALL: Font = Font {
+    height: 3,
+    digits: [
+        // 0
+        &["█▀█", "█ █", "▀▀▀"],
+        // 1
+        &["▀█ ", " █ ", "▀▀▀"],
+        // 2
+        &["▀▀█", "█▀▀", "▀▀▀"],
+        // 3
+        &["▀▀█", " ▀█", "▀▀▀"],
+        // 4
+        &["█ █", "▀▀█", "  ▀"],
+        // 5
+        &["█▀▀", "▀▀█", "▀▀▀"],
+        // 6
+        &["█▀▀", "█▀█", "▀▀▀"],
+        // 7
+        &["▀▀█", "  █", "  ▀"],
+        // 8
+        &["█▀█", "█▀█", "▀▀▀"],
+        // 9
+        &["█▀█", "▀▀█", "▀▀▀"],
+    ],
+    colon: &["▄", " ", "▀"],
+    minutes: &["     ", "█▀█▀█", "▀ ▀ ▀"],
+    percent: &["▀ █", "▄▀ ", "▀ ▀"],
+};
+
+/// All fonts, largest first.
+const FONTS: [&Font; 3] = [&LARGE, &MEDIUM, &SMALL];
+
+/// Resolve `choice` to a font that fits `text` within `width` x `height`.
+/// Returns `None` when even the chosen (or smallest) font does not fit.
+pub fn pick(choice: FontChoice, text: &str, width: u16, height: u16) -> Option<&'static Font> {
+    let fits = |font: &Font| font.height <= height && font.width(text) <= width;
+    match choice {
+        FontChoice::Auto => FONTS.into_iter().find(|font| fits(font)),
+        FontChoice::Large => Some(&LARGE).filter(|font| fits(font)),
+        FontChoice::Medium => Some(&MEDIUM).filter(|font| fits(font)),
+        FontChoice::Small => Some(&SMALL).filter(|font| fits(font)),
+    }
+}
@@ -0,0 +1,587 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeZone};
+use clap::ValueEnum;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use crate::engine::PhaseKind;
+use crate::error::SchemaError;
+use crate::profile;
+use crate::schema::Schema;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod sync;
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteHistory;
+pub use sync::{SyncedHistory, host_name};
+
+/// The format of the history's entries, in either backend. See
+/// [`crate::schema`] for how to change it.
+pub const SCHEMA: Schema = Schema::new("history", &[]);
+
+/// How a phase came to an end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Completed,
+    Skipped,
+    Quit,
+    /// Failed for being paused longer than the pause budget allows.
+    Incomplete,
+    /// Paused or interrupted in strict mode, so not a pomodoro.
+    Voided,
+}
+
+/// Where an interruption came from, in pomodoro technique terms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InterruptionSource {
+    Internal,
+    External,
+}
+
+impl InterruptionSource {
+    pub fn toggle(self) -> InterruptionSource {
+        match self {
+            InterruptionSource::Internal => InterruptionSource::External,
+            InterruptionSource::External => InterruptionSource::Internal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interruption {
+    pub at: DateTime<Local>,
+    pub source: InterruptionSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// One finished phase, as stored in the history file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub started: DateTime<Local>,
+    pub kind: PhaseKind,
+    pub cycle: u32,
+    pub planned_secs: u64,
+    pub elapsed_secs: u64,
+    pub outcome: Outcome,
+    /// Extra work, e.g. from snoozing a break or time away counted as work,
+    /// rather than a pomodoro of its own.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub extension: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interruptions: Vec<Interruption>,
+    /// Times the phase was paused by hand; older entries have none.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub pauses: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    /// Work on a planned task that had already used up its estimate.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub over_budget: bool,
+}
+
+impl Entry {
+    pub fn is_work(&self) -> bool {
+        self.kind == PhaseKind::Work
+    }
+
+    /// A short break or a long one.
+    pub fn is_break(&self) -> bool {
+        matches!(self.kind, PhaseKind::ShortBreak | PhaseKind::LongBreak)
+    }
+
+    /// The `#tags` on the entry's task.
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.task.as_deref().into_iter().flat_map(tags)
+    }
+}
+
+fn is_zero(count: &u32) -> bool {
+    *count == 0
+}
+
+/// The `#tags` in a task label, e.g. `#writing` in `Draft intro #writing`.
+pub fn tags(label: &str) -> impl Iterator<Item = &str> {
+    label.split_whitespace().filter(|word| is_tag(word))
+}
+
+/// A task label without its tags.
+pub fn untagged(label: &str) -> String {
+    label.split_whitespace().filter(|word| !is_tag(word)).collect::<Vec<_>>().join(" ")
+}
+
+fn is_tag(word: &str) -> bool {
+    word.len() > 1 && word.starts_with('#')
+}
+
+/// Where finished phases are kept, so reports can read them back.
+pub trait HistoryStore: fmt::Debug + Send {
+    /// The file behind the store, for messages.
+    fn path(&self) -> &Path;
+
+    fn append(&self, entry: &Entry) -> io::Result<()>;
+
+    /// Every entry, oldest first.
+    fn load(&self) -> io::Result<Vec<Entry>>;
+
+    /// The entries started at `since` or later, oldest first.
+    fn load_since(&self, since: DateTime<Local>) -> io::Result<Vec<Entry>> {
+        let mut entries = self.load()?;
+        entries.retain(|entry| entry.started >= since);
+        Ok(entries)
+    }
+
+    /// Delete the entries started before `before`, handing them back
+    /// oldest first.
+    fn remove_before(&self, before: DateTime<Local>) -> io::Result<Vec<Entry>>;
+
+    /// Add entries from another history, which may be older than the ones
+    /// here, in their place among them.
+    fn insert(&self, entries: &[Entry]) -> io::Result<()> {
+        entries.iter().try_for_each(|entry| self.append(entry))
+    }
+}
+
+/// `entries` oldest first, each phase once. Entries that start at the same
+/// instant are one phase, found in two copies of a history.
+pub fn dedup(mut entries: Vec<Entry>) -> Vec<Entry> {
+    entries.sort_by(|a, b| (a.started, &a.kind).cmp(&(b.started, &b.kind)));
+    entries.dedup_by(|a, b| a.started == b.started && a.kind == b.kind);
+    entries
+}
+
+/// The entries in `other` that `entries` do not have.
+pub fn missing(entries: &[Entry], other: Vec<Entry>) -> Vec<Entry> {
+    let mut seen: Vec<_> = entries.iter().map(|entry| (entry.started, &entry.kind)).collect();
+    seen.sort();
+    let is_new = |entry: &Entry| seen.binary_search(&(entry.started, &entry.kind)).is_err();
+    dedup(other.into_iter().filter(is_new).collect())
+}
+
+/// Add `entries` to the archive at `path` as JSON lines, compressed with
+/// gzip if the name ends in `.gz`. Gzip streams can follow one another, so
+/// pruning again adds to the same archive.
+pub fn archive(path: &Path, entries: &[Entry]) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        let mut out = GzEncoder::new(file, Compression::default());
+        write_lines(&mut out, entries)?;
+        out.finish()?;
+    } else {
+        let mut out = BufWriter::new(file);
+        write_lines(&mut out, entries)?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+fn write_lines(out: &mut impl Write, entries: &[Entry]) -> io::Result<()> {
+    for entry in entries {
+        writeln!(out, "{}", serde_json::to_string(entry)?)?;
+    }
+    Ok(())
+}
+
+/// How the history is stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryBackend {
+    /// A JSON line per phase, easy to read and to process with other tools.
+    #[default]
+    Jsonl,
+    /// An SQLite database, quicker to query once the history grows large.
+    Sqlite,
+}
+
+impl HistoryBackend {
+    /// `history.jsonl` or `history.db` in the [profile's](crate::profile)
+    /// data folder, if the platform has a data dir.
+    pub fn default_path(self) -> Option<PathBuf> {
+        let name = match self {
+            HistoryBackend::Jsonl => "history.jsonl",
+            HistoryBackend::Sqlite => "history.db",
+        };
+        profile::data_dir().map(|dir| dir.join(name))
+    }
+
+    /// The backend for the file at `path`, going by its extension.
+    fn of(path: &Path) -> HistoryBackend {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("db" | "sqlite" | "sqlite3") => HistoryBackend::Sqlite,
+            _ => HistoryBackend::Jsonl,
+        }
+    }
+}
+
+/// The store for the history at `path`: a synced folder for a directory,
+/// an SQLite database for a `.db` file, JSON Lines otherwise.
+pub fn open(path: PathBuf) -> io::Result<Box<dyn HistoryStore>> {
+    if path.is_dir() {
+        return Ok(Box::new(SyncedHistory::new(path)));
+    }
+    match HistoryBackend::of(&path) {
+        HistoryBackend::Jsonl => Ok(Box::new(History::new(path))),
+        #[cfg(feature = "sqlite")]
+        HistoryBackend::Sqlite => Ok(Box::new(SqliteHistory::open(path)?)),
+        #[cfg(not(feature = "sqlite"))]
+        HistoryBackend::Sqlite => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this build cannot read SQLite history; set history_backend to jsonl or build with the sqlite feature",
+        )),
+    }
+}
+
+/// Append-only JSON Lines history of finished phases.
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new(path: PathBuf) -> History {
+        History { path }
+    }
+
+    /// `<data dir>/opomodoro/history.jsonl`, if the platform has a data dir.
+    pub fn default_path() -> Option<PathBuf> {
+        HistoryBackend::Jsonl.default_path()
+    }
+}
+
+impl HistoryStore for History {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Add the entry, first bringing a file in an older format up to date.
+    fn append(&self, entry: &Entry) -> io::Result<()> {
+        let version = self.version()?;
+        SCHEMA.check(version)?;
+        if version < SCHEMA.version() {
+            self.rewrite(|_| false, &[])?;
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", header())?;
+        }
+        let line = serde_json::to_string(entry)?;
+        writeln!(file, "{line}")
+    }
+
+    /// Read every entry, skipping lines that fail to parse.
+    fn load(&self) -> io::Result<Vec<Entry>> {
+        match self.read()? {
+            Some(text) => from_jsonl(&text),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn remove_before(&self, before: DateTime<Local>) -> io::Result<Vec<Entry>> {
+        self.rewrite(|entry| entry.started < before, &[])
+    }
+
+    fn insert(&self, entries: &[Entry]) -> io::Result<()> {
+        if !self.path.exists() {
+            entries.iter().try_for_each(|entry| self.append(entry))
+        } else {
+            self.rewrite(|_| false, entries).map(drop)
+        }
+    }
+}
+
+impl History {
+    fn read(&self) -> io::Result<Option<String>> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => Ok(Some(text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The format of the file, going by its first line; a file yet to be
+    /// written will be in the current one.
+    fn version(&self) -> io::Result<u32> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(SCHEMA.version()),
+            Err(e) => return Err(e),
+        };
+        let mut first = String::new();
+        BufReader::new(file).read_line(&mut first)?;
+        if first.is_empty() {
+            return Ok(SCHEMA.version());
+        }
+        Ok(split_header(&first).0)
+    }
+
+    /// Rewrite the file in the current format without the entries `remove`
+    /// picks, handing those back oldest first, and with `add` in order among
+    /// the rest. Lines that fail to parse are kept as they are, after the
+    /// line before them.
+    fn rewrite(&self, remove: impl Fn(&Entry) -> bool, add: &[Entry]) -> io::Result<Vec<Entry>> {
+        let Some(text) = self.read()? else {
+            return Ok(Vec::new());
+        };
+        let (version, lines) = split_header(&text);
+        SCHEMA.check(version)?;
+        let mut removed = Vec::new();
+        let mut kept = Vec::new();
+        let mut at = None;
+        for line in lines.lines() {
+            match parse(line, version)? {
+                Some(entry) if remove(&entry) => removed.push(entry),
+                Some(entry) if version < SCHEMA.version() => {
+                    at = Some(entry.started);
+                    kept.push((at, serde_json::to_string(&entry)?));
+                }
+                entry => {
+                    at = entry.map(|entry| entry.started).or(at);
+                    kept.push((at, line.to_string()));
+                }
+            }
+        }
+        if removed.is_empty() && add.is_empty() && version == SCHEMA.version() {
+            return Ok(removed);
+        }
+        for entry in add {
+            kept.push((Some(entry.started), serde_json::to_string(entry)?));
+        }
+        if !add.is_empty() {
+            kept.sort_by_key(|(at, _)| *at);
+        }
+        let mut out = format!("{}\n", header());
+        for (_, line) in kept {
+            out.push_str(&line);
+            out.push('\n');
+        }
+        // Swap the new file in whole, so a crash cannot leave half a history.
+        let temp = self.path.with_extension("jsonl.tmp");
+        fs::write(&temp, out)?;
+        fs::rename(&temp, &self.path)?;
+        if version < SCHEMA.version() {
+            info!(from = version, to = SCHEMA.version(), path = %self.path.display(), "upgraded history");
+        }
+        Ok(removed)
+    }
+}
+
+/// The first line of a JSON Lines history, naming the format of the
+/// entries after it. Files from before there was one start with an entry.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    history_format: u32,
+}
+
+fn header() -> String {
+    serde_json::json!(Header { history_format: SCHEMA.version() }).to_string()
+}
+
+/// `entries` the way a JSON Lines history file has them, header and all.
+pub fn to_jsonl(entries: &[Entry]) -> io::Result<String> {
+    let mut text = format!("{}\n", header());
+    for entry in entries {
+        text.push_str(&serde_json::to_string(entry)?);
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// The entries in the text of a JSON Lines history, skipping lines that
+/// fail to parse.
+pub fn from_jsonl(text: &str) -> io::Result<Vec<Entry>> {
+    let (version, lines) = split_header(text);
+    SCHEMA.check(version)?;
+    Ok(lines.lines().filter_map(|line| parse(line, version).ok().flatten()).collect())
+}
+
+/// The format of a JSON Lines history, and the lines after its header.
+fn split_header(text: &str) -> (u32, &str) {
+    let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+    match serde_json::from_str::<Header>(first) {
+        Ok(header) => (header.history_format, rest),
+        Err(_) => (1, text),
+    }
+}
+
+/// The entry on a line of a history in format `version`, or `None` for a
+/// line that is not one.
+fn parse(line: &str, version: u32) -> Result<Option<Entry>, SchemaError> {
+    if version == SCHEMA.version() {
+        return Ok(serde_json::from_str(line).ok());
+    }
+    let Ok(mut value) = serde_json::from_str(line) else {
+        return Ok(None);
+    };
+    SCHEMA.upgrade(&mut value, version)?;
+    Ok(serde_json::from_value(value).ok())
+}
+
+/// Totals over a set of history entries.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Summary {
+    pub pomodoros: u32,
+    /// Pomodoros spent on tasks past their estimate.
+    pub over_budget: u32,
+    /// Work phases voided in strict mode.
+    pub voided: u32,
+    pub focus: Duration,
+    pub internal_interruptions: u32,
+    pub external_interruptions: u32,
+}
+
+impl Summary {
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> Summary {
+        let mut summary = Summary::default();
+        for entry in entries.into_iter().filter(|e| e.is_work()) {
+            if entry.outcome == Outcome::Completed && !entry.extension {
+                summary.pomodoros += 1;
+                if entry.over_budget {
+                    summary.over_budget += 1;
+                }
+            }
+            if entry.outcome == Outcome::Voided {
+                summary.voided += 1;
+            }
+            summary.focus += Duration::from_secs(entry.elapsed_secs);
+            for interruption in &entry.interruptions {
+                match interruption.source {
+                    InterruptionSource::Internal => summary.internal_interruptions += 1,
+                    InterruptionSource::External => summary.external_interruptions += 1,
+                }
+            }
+        }
+        summary
+    }
+
+    pub fn interruptions(&self) -> u32 {
+        self.internal_interruptions + self.external_interruptions
+    }
+}
+
+/// What a report groups the history by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// Each `#tag` on the task; work on a task with several counts for each.
+    Tag,
+    Task,
+    Day,
+}
+
+/// The order of the groups in a report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SortBy {
+    /// Most focus time first.
+    #[default]
+    Focus,
+    /// Most pomodoros first.
+    Pomodoros,
+    /// Alphabetically, or oldest first for days.
+    Name,
+}
+
+/// Totals for each tag, task or day in `entries`, in `sort` order.
+pub fn group(entries: &[Entry], by: GroupBy, sort: SortBy) -> Vec<(String, Summary)> {
+    let mut groups: BTreeMap<String, Vec<&Entry>> = BTreeMap::new();
+    for entry in entries.iter().filter(|entry| entry.is_work()) {
+        let keys = match by {
+            GroupBy::Tag => {
+                // `#Writing` and `#writing` are one tag, counted once per entry.
+                let mut tags: Vec<_> = entry.tags().map(str::to_lowercase).collect();
+                tags.sort();
+                tags.dedup();
+                if tags.is_empty() { vec!["(untagged)".to_string()] } else { tags }
+            }
+            GroupBy::Task => {
+                let task = entry.task.as_deref().map(untagged).filter(|task| !task.is_empty());
+                vec![task.unwrap_or_else(|| "(no task)".to_string())]
+            }
+            GroupBy::Day => vec![entry.started.format("%Y-%m-%d %a").to_string()],
+        };
+        for key in keys {
+            groups.entry(key).or_default().push(entry);
+        }
+    }
+    let mut rows: Vec<_> =
+        groups.into_iter().map(|(key, entries)| (key, Summary::from_entries(entries))).collect();
+    match sort {
+        SortBy::Focus => rows.sort_by_key(|(_, summary)| Reverse(summary.focus)),
+        SortBy::Pomodoros => rows.sort_by_key(|(_, summary)| Reverse(summary.pomodoros)),
+        SortBy::Name => {}
+    }
+    rows
+}
+
+/// Focus time to aim for, on top of the session's cycle count.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Targets {
+    pub daily: Option<Duration>,
+    pub weekly: Option<Duration>,
+}
+
+impl Targets {
+    pub fn is_empty(&self) -> bool {
+        self.daily.is_none() && self.weekly.is_none()
+    }
+}
+
+/// Focus time today and this week, which starts on Monday.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Focus {
+    pub today: Duration,
+    pub week: Duration,
+}
+
+impl Focus {
+    /// The focus time in `entries` as of `now`.
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a Entry>, now: DateTime<Local>) -> Focus {
+        let mut focus = Focus::default();
+        for entry in entries.into_iter().filter(|e| e.is_work()) {
+            focus.add(entry.started, Duration::from_secs(entry.elapsed_secs), now);
+        }
+        focus
+    }
+
+    /// Count `time` worked at `at` if it falls today or this week.
+    pub fn add(&mut self, at: DateTime<Local>, time: Duration, now: DateTime<Local>) {
+        if at.iso_week() != now.iso_week() {
+            return;
+        }
+        self.week += time;
+        if at.date_naive() == now.date_naive() {
+            self.today += time;
+        }
+    }
+}
+
+/// When `date` starts in local time, for [`HistoryStore::load_since`].
+pub fn start_of(date: NaiveDate) -> DateTime<Local> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    // Where a clock change skips midnight, the UTC reading is close enough.
+    Local.from_local_datetime(&midnight).earliest().unwrap_or_else(|| Local.from_utc_datetime(&midnight))
+}
+
+/// Focus time the way reports show it: `2h10m`, `45m`.
+pub fn format_focus(time: Duration) -> String {
+    let minutes = time.as_secs() / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h{m:02}m"),
+    }
+}
@@ -0,0 +1,160 @@
+//! History in an SQLite database, for when the JSON Lines file grows too
+//! large to read in full for every report.
+//!
+//! Each entry is kept as its JSON next to its start time, which is indexed
+//! so that reports over a day or a week only read those entries. The
+//! database's `user_version` is the [`SCHEMA`] version the entries are in,
+//! or 0 for a database not set up yet.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use chrono::{DateTime, Local};
+use rusqlite::{Connection, Transaction, params};
+use serde_json::Value;
+use tracing::info;
+use super::{Entry, History, HistoryStore, SCHEMA};
+
+/// The history in an SQLite database.
+pub struct SqliteHistory {
+    path: PathBuf,
+    connection: Mutex<Connection>,
+}
+
+impl fmt::Debug for SqliteHistory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SqliteHistory").field("path", &self.path).finish_non_exhaustive()
+    }
+}
+
+fn failed(e: rusqlite::Error) -> io::Error {
+    io::Error::other(format!("sqlite: {e}"))
+}
+
+impl SqliteHistory {
+    /// Open the database at `path`, creating it if need be. A new database
+    /// starts with the entries of `history.jsonl` next to it, if there is one.
+    pub fn open(path: PathBuf) -> io::Result<SqliteHistory> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut connection = Connection::open(&path).map_err(failed)?;
+        let version: u32 =
+            connection.pragma_query_value(None, "user_version", |row| row.get(0)).map_err(failed)?;
+        SCHEMA.check(version)?;
+        if version == 0 {
+            let jsonl = History::new(path.with_file_name("history.jsonl"));
+            let entries = jsonl.load()?;
+            let tx = connection.transaction().map_err(failed)?;
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS entries (
+                    id INTEGER PRIMARY KEY,
+                    started INTEGER NOT NULL,
+                    entry TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS entries_started ON entries (started);",
+            )
+            .map_err(failed)?;
+            for entry in &entries {
+                insert(&tx, entry)?;
+            }
+            tx.pragma_update(None, "user_version", SCHEMA.version()).map_err(failed)?;
+            tx.commit().map_err(failed)?;
+            if !entries.is_empty() {
+                info!(count = entries.len(), from = %jsonl.path().display(), "imported history");
+            }
+        } else if version < SCHEMA.version() {
+            let tx = connection.transaction().map_err(failed)?;
+            upgrade(&tx, version)?;
+            tx.pragma_update(None, "user_version", SCHEMA.version()).map_err(failed)?;
+            tx.commit().map_err(failed)?;
+            info!(from = version, to = SCHEMA.version(), path = %path.display(), "upgraded history");
+        }
+        Ok(SqliteHistory { path, connection: Mutex::new(connection) })
+    }
+
+    fn query(&self, since: Option<DateTime<Local>>) -> io::Result<Vec<Entry>> {
+        let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let mut statement = connection
+            .prepare_cached("SELECT entry FROM entries WHERE started >= ?1 ORDER BY started, id")
+            .map_err(failed)?;
+        let since = since.map_or(i64::MIN, |since| since.timestamp());
+        let rows = statement.query_map([since], |row| row.get::<_, String>(0)).map_err(failed)?;
+        let mut entries = Vec::new();
+        for json in rows {
+            // Like the JSON Lines file, skip entries that fail to parse.
+            if let Ok(entry) = serde_json::from_str(&json.map_err(failed)?) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Bring every entry written in format `from` up to date. Entries that are
+/// not JSON are left for reading to skip.
+fn upgrade(tx: &Transaction, from: u32) -> io::Result<()> {
+    let rows = {
+        let mut statement = tx.prepare("SELECT id, entry FROM entries").map_err(failed)?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)));
+        rows.map_err(failed)?.collect::<Result<Vec<_>, _>>().map_err(failed)?
+    };
+    for (id, json) in rows {
+        let Ok(mut value) = serde_json::from_str::<Value>(&json) else {
+            continue;
+        };
+        SCHEMA.upgrade(&mut value, from)?;
+        tx.execute("UPDATE entries SET entry = ?1 WHERE id = ?2", params![value.to_string(), id])
+            .map_err(failed)?;
+    }
+    Ok(())
+}
+
+fn insert(connection: &Connection, entry: &Entry) -> io::Result<()> {
+    let json = serde_json::to_string(entry)?;
+    connection
+        .execute("INSERT INTO entries (started, entry) VALUES (?1, ?2)", params![entry.started.timestamp(), json])
+        .map_err(failed)?;
+    Ok(())
+}
+
+impl HistoryStore for SqliteHistory {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn append(&self, entry: &Entry) -> io::Result<()> {
+        insert(&self.connection.lock().unwrap_or_else(|e| e.into_inner()), entry)
+    }
+
+    fn load(&self) -> io::Result<Vec<Entry>> {
+        self.query(None)
+    }
+
+    fn load_since(&self, since: DateTime<Local>) -> io::Result<Vec<Entry>> {
+        self.query(Some(since))
+    }
+
+    fn remove_before(&self, before: DateTime<Local>) -> io::Result<Vec<Entry>> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let tx = connection.transaction().map_err(failed)?;
+        let mut removed = Vec::new();
+        {
+            let mut statement = tx
+                .prepare("SELECT entry FROM entries WHERE started < ?1 ORDER BY started, id")
+                .map_err(failed)?;
+            let rows = statement.query_map([before.timestamp()], |row| row.get::<_, String>(0)).map_err(failed)?;
+            for json in rows {
+                if let Ok(entry) = serde_json::from_str(&json.map_err(failed)?) {
+                    removed.push(entry);
+                }
+            }
+        }
+        tx.execute("DELETE FROM entries WHERE started < ?1", [before.timestamp()]).map_err(failed)?;
+        tx.commit().map_err(failed)?;
+        // Hand the space back, or the file stays as large as before.
+        connection.execute_batch("VACUUM").map_err(failed)?;
+        Ok(removed)
+    }
+}
@@ -0,0 +1,87 @@
+//! History in a folder synced between machines by something like Dropbox
+//! or Syncthing.
+//!
+//! Each machine appends to a file of its own, `history-<host>.jsonl`, so
+//! no two machines write to one file and the sync has no edits to choose
+//! between. Reading takes every `.jsonl` file in the folder, any conflicted
+//! copies the sync made included, and keeps each entry once.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local};
+use super::{Entry, History, HistoryStore, dedup};
+
+/// The history in a synced folder.
+#[derive(Debug)]
+pub struct SyncedHistory {
+    dir: PathBuf,
+    /// The file this machine writes to.
+    own: History,
+}
+
+impl SyncedHistory {
+    pub fn new(dir: PathBuf) -> SyncedHistory {
+        let own = History::new(dir.join(format!("history-{}.jsonl", host_name())));
+        SyncedHistory { dir, own }
+    }
+
+    /// The history files in the folder, this machine's and the others'.
+    /// Hidden files, like those Syncthing writes as it syncs, are left out.
+    fn files(&self) -> io::Result<Vec<History>> {
+        let mut paths = Vec::new();
+        for item in fs::read_dir(&self.dir)? {
+            let path = item?.path();
+            let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if !hidden && path.extension().is_some_and(|ext| ext == "jsonl") && path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(paths.into_iter().map(History::new).collect())
+    }
+}
+
+impl HistoryStore for SyncedHistory {
+    fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    fn append(&self, entry: &Entry) -> io::Result<()> {
+        self.own.append(entry)
+    }
+
+    fn load(&self) -> io::Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for file in self.files()? {
+            entries.extend(file.load()?);
+        }
+        Ok(dedup(entries))
+    }
+
+    /// Remove the old entries from every file, the other machines' too;
+    /// should one of them append meanwhile, the sync keeps both versions
+    /// and reading puts them back together.
+    fn remove_before(&self, before: DateTime<Local>) -> io::Result<Vec<Entry>> {
+        let mut removed = Vec::new();
+        for file in self.files()? {
+            removed.extend(file.remove_before(before)?);
+        }
+        Ok(dedup(removed))
+    }
+
+    fn insert(&self, entries: &[Entry]) -> io::Result<()> {
+        self.own.insert(entries)
+    }
+}
+
+/// This machine's name, without any domain.
+pub fn host_name() -> String {
+    let name = env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .unwrap_or_default();
+    let name = name.trim().split('.').next().unwrap_or_default();
+    if name.is_empty() { "opomodoro".to_string() } else { name.to_string() }
+}
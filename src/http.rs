@@ -0,0 +1,191 @@
+//! A small REST API for a running session, for browser widgets and stream
+//! deck buttons:
+//!
+//! ```text
+//! GET  /status    the timer, as JSON
+//! POST /pause     pause, then report the timer
+//! POST /resume    unpause, then report the timer
+//! POST /skip      end the phase, then report the timer
+//! GET  /history   today's history entries
+//! GET  /events    server-sent events with the timer, every second and
+//!                 whenever it changes
+//! ```
+//!
+//! Requests go through the control socket's queue, so the session answers
+//! them the same way it answers `opomodoro pause` and friends.
+//!
+//! Any page can read the timer, but only the API's own origin or a client
+//! sending no `Origin` at all, like a stream deck's or curl, can change it:
+//! otherwise any site open in the browser could pause or skip it.
+
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::thread;
+use chrono::Local;
+use serde::Serialize;
+use tiny_http::{Header, Method, Response, Server};
+use tracing::{debug, warn};
+use crate::engine::PhaseKind;
+use crate::history::{self, HistoryStore};
+use crate::ipc::{Request, SessionHandle, Snapshot};
+
+/// The timer as reported by `GET /status`, in plain seconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Status {
+    pub phase: PhaseKind,
+    pub cycle: u32,
+    pub num_cycles: u32,
+    pub remaining_secs: u64,
+    pub phase_secs: u64,
+    pub paused: bool,
+    pub finished: bool,
+    /// Phases completed so far.
+    pub completed: usize,
+}
+
+impl From<Snapshot> for Status {
+    fn from(snapshot: Snapshot) -> Status {
+        Status {
+            phase: snapshot.phase,
+            cycle: snapshot.cycle,
+            num_cycles: snapshot.num_cycles,
+            remaining_secs: snapshot.remaining.as_secs(),
+            phase_secs: snapshot.phase_duration.as_secs(),
+            paused: snapshot.paused,
+            finished: snapshot.finished,
+            completed: snapshot.completed,
+        }
+    }
+}
+
+/// Serve the API on `addr` from a thread of its own, passing requests on
+/// to `session`. History is read from `history`, if the session keeps one.
+pub fn serve(addr: SocketAddr, session: SessionHandle, history: Option<Box<dyn HistoryStore>>) -> io::Result<()> {
+    let server = Server::http(addr).map_err(io::Error::other)?;
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            debug!(method = %request.method(), url = request.url(), "http request");
+            if request.method() == &Method::Get && request.url().split('?').next() == Some("/events") {
+                let session = session.clone();
+                thread::spawn(move || stream_events(request.into_writer(), &session));
+                continue;
+            }
+            let method = request.method();
+            let path = request.url().split('?').next().unwrap_or_default();
+            let (status, body) = if method == &Method::Post && is_foreign(&request) {
+                error(403, "the timer can't be changed from another origin")
+            } else {
+                route(method, request.url(), &session, history.as_deref())
+            };
+            let mut response = Response::from_string(body)
+                .with_status_code(status)
+                .with_header(header("Content-Type", "application/json"));
+            if method == &Method::Get && path == "/status" {
+                // Widgets run on pages served from anywhere.
+                response.add_header(header("Access-Control-Allow-Origin", "*"));
+            }
+            if let Err(e) = request.respond(response) {
+                warn!("http response failed: {e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Whether `request` comes from a page on some origin other than the API's
+/// own, going by its `Origin` and `Host` headers.
+fn is_foreign(request: &tiny_http::Request) -> bool {
+    let value = |name: &'static str| {
+        let header = request.headers().iter().find(|header| header.field.equiv(name))?;
+        Some(header.value.as_str())
+    };
+    match (value("Origin"), value("Host")) {
+        (None, _) => false,
+        (Some(origin), Some(host)) => origin != format!("http://{host}"),
+        (Some(_), None) => true,
+    }
+}
+
+/// Status code and JSON body for a request.
+fn route(
+    method: &Method,
+    url: &str,
+    session: &SessionHandle,
+    history: Option<&dyn HistoryStore>,
+) -> (u16, String) {
+    let path = url.split('?').next().unwrap_or(url);
+    let request = match (method, path) {
+        (Method::Get, "/status") => Request::Snapshot,
+        (Method::Post, "/pause") => Request::Pause,
+        (Method::Post, "/resume") => Request::Unpause,
+        (Method::Post, "/skip") => Request::Skip,
+        (Method::Get, "/history") => return today(history),
+        (_, "/status" | "/pause" | "/resume" | "/skip" | "/history" | "/events") => {
+            return error(405, "method not allowed");
+        }
+        _ => return error(404, "not found"),
+    };
+    match session.ask(request) {
+        Some(snapshot) => json(&Status::from(snapshot)),
+        None => error(503, "the session has ended"),
+    }
+}
+
+/// Write the session's state to `writer` as server-sent events until the
+/// session ends or the client goes away. The response is written by hand:
+/// tiny_http buffers chunked bodies, which would hold events back.
+fn stream_events(mut writer: Box<dyn Write + Send>, session: &SessionHandle) {
+    let updates = session.watch();
+    let head = "HTTP/1.1 200 OK\r\n\
+        Content-Type: text/event-stream\r\n\
+        Cache-Control: no-cache\r\n\
+        Access-Control-Allow-Origin: *\r\n\
+        Connection: close\r\n\r\n";
+    if writer.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+    // Start with the state as it is, rather than up to a second from now.
+    let first = session.ask(Request::Snapshot);
+    for snapshot in first.into_iter().chain(updates) {
+        let Ok(data) = serde_json::to_string(&Status::from(snapshot)) else {
+            return;
+        };
+        let sent = write!(writer, "event: status\ndata: {data}\n\n").and_then(|()| writer.flush());
+        if sent.is_err() {
+            debug!("event stream client went away");
+            return;
+        }
+    }
+}
+
+fn today(history: Option<&dyn HistoryStore>) -> (u16, String) {
+    let Some(history) = history else {
+        return json(&Vec::<()>::new());
+    };
+    let today = Local::now().date_naive();
+    match history.load_since(history::start_of(today)) {
+        Ok(entries) => {
+            let entries: Vec<_> = entries
+                .into_iter()
+                .filter(|entry| entry.started.date_naive() == today)
+                .collect();
+            json(&entries)
+        }
+        Err(e) => error(500, &format!("could not read {}: {e}", history.path().display())),
+    }
+}
+
+fn json(value: &impl Serialize) -> (u16, String) {
+    match serde_json::to_string(value) {
+        Ok(body) => (200, body),
+        Err(e) => error(500, &e.to_string()),
+    }
+}
+
+fn error(status: u16, message: &str) -> (u16, String) {
+    (status, serde_json::json!({ "error": message }).to_string())
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("header names and values are ASCII")
+}
@@ -0,0 +1,140 @@
+//! How long the user has left the keyboard and mouse alone, so a work
+//! phase can pause itself when they walk away.
+
+use std::env;
+#[cfg(not(windows))]
+use std::process::Command;
+use std::time::Duration;
+use tracing::debug;
+
+/// Where the idle time comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    /// GNOME's idle monitor, which also works under Wayland.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    Mutter,
+    /// The freedesktop screensaver interface, as KDE implements it.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    ScreenSaver,
+    /// `xprintidle`, for other X11 desktops.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    Xprintidle,
+    /// The HID system's idle time, as `ioreg` reports it.
+    #[cfg(target_os = "macos")]
+    IoKit,
+    #[cfg(windows)]
+    LastInput,
+}
+
+/// Every backend that might work here, the most specific first.
+const BACKENDS: &[Backend] = &[
+    #[cfg(all(unix, not(target_os = "macos")))]
+    Backend::Mutter,
+    #[cfg(all(unix, not(target_os = "macos")))]
+    Backend::ScreenSaver,
+    #[cfg(all(unix, not(target_os = "macos")))]
+    Backend::Xprintidle,
+    #[cfg(target_os = "macos")]
+    Backend::IoKit,
+    #[cfg(windows)]
+    Backend::LastInput,
+];
+
+/// Reads the user's idle time from the desktop.
+#[derive(Debug)]
+pub struct IdleMonitor {
+    backend: Backend,
+}
+
+impl IdleMonitor {
+    /// The first backend that answers here, if any. Over SSH the desktop
+    /// is someone else's, so there is none.
+    pub fn detect() -> Option<IdleMonitor> {
+        if env::var_os("SSH_CONNECTION").is_some() {
+            return None;
+        }
+        let backend = BACKENDS.iter().copied().find(|backend| backend.idle_time().is_some())?;
+        debug!(?backend, "idle backend");
+        Some(IdleMonitor { backend })
+    }
+
+    /// Time since the last key press or mouse move anywhere on the desktop.
+    pub fn idle_time(&self) -> Option<Duration> {
+        self.backend.idle_time()
+    }
+}
+
+impl Backend {
+    fn idle_time(self) -> Option<Duration> {
+        match self {
+            #[cfg(all(unix, not(target_os = "macos")))]
+            Backend::Mutter => gdbus(
+                "org.gnome.Mutter.IdleMonitor",
+                "/org/gnome/Mutter/IdleMonitor/Core",
+                "org.gnome.Mutter.IdleMonitor.GetIdletime",
+            ),
+            #[cfg(all(unix, not(target_os = "macos")))]
+            Backend::ScreenSaver => gdbus(
+                "org.freedesktop.ScreenSaver",
+                "/org/freedesktop/ScreenSaver",
+                "org.freedesktop.ScreenSaver.GetSessionIdleTime",
+            ),
+            #[cfg(all(unix, not(target_os = "macos")))]
+            Backend::Xprintidle => {
+                env::var_os("DISPLAY")?;
+                let millis = output(&mut Command::new("xprintidle"))?.trim().parse().ok()?;
+                Some(Duration::from_millis(millis))
+            }
+            #[cfg(target_os = "macos")]
+            Backend::IoKit => {
+                let out = output(Command::new("ioreg").args(["-c", "IOHIDSystem", "-d", "4"]))?;
+                let line = out.lines().find(|line| line.contains("\"HIDIdleTime\""))?;
+                let nanos = line.rsplit(' ').next()?.trim().parse().ok()?;
+                Some(Duration::from_nanos(nanos))
+            }
+            #[cfg(windows)]
+            Backend::LastInput => last_input(),
+        }
+    }
+}
+
+/// Run `command`, returning what it printed if it succeeded.
+#[cfg(not(windows))]
+fn output(command: &mut Command) -> Option<String> {
+    let out = command.output().ok().filter(|out| out.status.success())?;
+    String::from_utf8(out.stdout).ok()
+}
+
+/// Call a D-Bus method on the session bus that answers in milliseconds,
+/// e.g. `(uint64 5120,)`.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn gdbus(dest: &str, object: &str, method: &str) -> Option<Duration> {
+    env::var_os("DBUS_SESSION_BUS_ADDRESS")?;
+    let out = output(Command::new("gdbus").args([
+        "call",
+        "--session",
+        "--dest",
+        dest,
+        "--object-path",
+        object,
+        "--method",
+        method,
+    ]))?;
+    let millis = out.trim().trim_matches(['(', ')', ',']).split(' ').next_back()?.parse().ok()?;
+    Some(Duration::from_millis(millis))
+}
+
+#[cfg(windows)]
+fn last_input() -> Option<Duration> {
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+    let mut info = LASTINPUTINFO { cbSize: size_of::<LASTINPUTINFO>() as u32, dwTime: 0 };
+    // SAFETY: `info` is a LASTINPUTINFO with `cbSize` set, as the call expects.
+    if unsafe { GetLastInputInfo(&mut info) } == 0 {
+        return None;
+    }
+    // SAFETY: GetTickCount takes nothing and cannot fail.
+    let now = unsafe { GetTickCount() };
+    // Both are milliseconds since boot that wrap after 49 days.
+    Some(Duration::from_millis(now.wrapping_sub(info.dwTime).into()))
+}
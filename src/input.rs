@@ -0,0 +1,130 @@
+//! Where the app's keys come from: the terminal, or a script played back
+//! with its timing, for tests, debugging and demos.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+
+/// A source of terminal events for the app to act on.
+pub trait InputSource: fmt::Debug {
+    /// The next event, waiting up to `timeout` for one.
+    fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>>;
+}
+
+/// Keys and resizes from the terminal itself.
+#[derive(Debug, Default)]
+pub struct TerminalInput;
+
+impl InputSource for TerminalInput {
+    fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if event::poll(timeout)? {
+            return event::read().map(Some);
+        }
+        Ok(None)
+    }
+}
+
+/// Key presses from a script, each sent once its time since the first read
+/// has come. Every line of the script is a time, then the keys to press:
+///
+/// ```text
+/// # Skip to the break after two seconds, then quit
+/// 2s s enter
+/// 1m30s q
+/// ```
+///
+/// Keys are single characters, or one of `enter`, `esc`, `tab`, `backspace`,
+/// `space`, `up`, `down`, `left` and `right`, optionally after `ctrl-`.
+#[derive(Debug)]
+pub struct ReplayInput {
+    steps: VecDeque<(Duration, KeyEvent)>,
+    started: Option<Instant>,
+    /// Where input comes from once the script is done; nowhere by default.
+    rest: Option<Box<dyn InputSource>>,
+}
+
+impl ReplayInput {
+    /// Hand over to `rest` once the script is done, e.g. to the keyboard.
+    pub fn then(mut self, rest: impl InputSource + 'static) -> ReplayInput {
+        self.rest = Some(Box::new(rest));
+        self
+    }
+}
+
+impl FromStr for ReplayInput {
+    type Err = String;
+
+    fn from_str(script: &str) -> Result<ReplayInput, String> {
+        let mut steps = VecDeque::new();
+        for (number, line) in script.lines().enumerate() {
+            let mut words = line.split_whitespace();
+            let Some(time) = words.next().filter(|word| !word.starts_with('#')) else {
+                continue;
+            };
+            let at = humantime::parse_duration(time)
+                .map_err(|e| format!("line {}: bad time {time:?}: {e}", number + 1))?;
+            if steps.back().is_some_and(|(last, _)| at < *last) {
+                return Err(format!("line {}: {time} is earlier than the line before", number + 1));
+            }
+            for word in words {
+                let key = key(word).map_err(|e| format!("line {}: {e}", number + 1))?;
+                steps.push_back((at, key));
+            }
+        }
+        Ok(ReplayInput { steps, started: None, rest: None })
+    }
+}
+
+impl InputSource for ReplayInput {
+    fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let Some(&(at, key)) = self.steps.front() else {
+            return match &mut self.rest {
+                Some(rest) => rest.next(timeout),
+                None => {
+                    thread::sleep(timeout);
+                    Ok(None)
+                }
+            };
+        };
+        let wait = (started + at).saturating_duration_since(Instant::now());
+        if wait > timeout {
+            thread::sleep(timeout);
+            return Ok(None);
+        }
+        thread::sleep(wait);
+        self.steps.pop_front();
+        Ok(Some(Event::Key(key)))
+    }
+}
+
+/// The key a script names with `word`.
+fn key(word: &str) -> Result<KeyEvent, String> {
+    let (modifiers, name) = match word.strip_prefix("ctrl-") {
+        Some(name) => (KeyModifiers::CONTROL, name),
+        None => (KeyModifiers::NONE, word),
+    };
+    let code = match name {
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "space" => KeyCode::Char(' '),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => KeyCode::Char(ch),
+                _ => return Err(format!("unknown key {word:?}")),
+            }
+        }
+    };
+    Ok(KeyEvent::new(code, modifiers))
+}
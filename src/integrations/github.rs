@@ -0,0 +1,148 @@
+//! GitHub issues as task labels, with an optional comment summing up the
+//! focus time once the session ends.
+
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ureq::Agent;
+use crate::error::{self, OpomodoroError};
+use crate::history::Summary;
+use super::{DeliveryError, Integration};
+
+/// `owner/repo#123`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssueRef {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+}
+
+impl IssueRef {
+    pub fn parse(value: &str) -> error::Result<IssueRef> {
+        let invalid = || OpomodoroError::parse(
+            "GitHub issue",
+            format!("expected owner/repo#123, got {value:?}"),
+        );
+        let (repo, number) = value.split_once('#').ok_or_else(invalid)?;
+        let (owner, repo) = repo.split_once('/').ok_or_else(invalid)?;
+        if owner.is_empty() || repo.is_empty() {
+            return Err(invalid());
+        }
+        Ok(IssueRef {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            number: number.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    title: String,
+}
+
+/// A comment waiting in the outbox.
+#[derive(Debug, Serialize, Deserialize)]
+struct Comment {
+    issue: IssueRef,
+    body: String,
+}
+
+#[derive(Debug)]
+pub struct GitHub {
+    issue: Option<IssueRef>,
+    api: String,
+    token: Option<String>,
+    comment: bool,
+    agent: Agent,
+}
+
+impl GitHub {
+    /// Work on `issue`, if given; without one this only delivers comments
+    /// queued by earlier sessions. Commenting needs a `token`; reading
+    /// public issues does not.
+    pub fn new(issue: Option<IssueRef>, api: &str, token: Option<String>, comment: bool) -> GitHub {
+        let agent = Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(10)))
+            .http_status_as_error(false)
+            .build()
+            .into();
+        GitHub { issue, api: api.trim_end_matches('/').to_string(), token, comment, agent }
+    }
+
+    fn url(&self, issue: &IssueRef, path: &str) -> String {
+        let IssueRef { owner, repo, number } = issue;
+        format!("{}/repos/{owner}/{repo}/issues/{number}{path}", self.api)
+    }
+
+    fn request<B>(&self, request: ureq::RequestBuilder<B>) -> ureq::RequestBuilder<B> {
+        let request = request
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", concat!("opomodoro/", env!("CARGO_PKG_VERSION")));
+        match &self.token {
+            Some(token) => request.header("Authorization", format!("Bearer {token}")),
+            None => request,
+        }
+    }
+}
+
+fn failed(message: impl ToString) -> OpomodoroError {
+    OpomodoroError::Integration { name: "github", message: message.to_string() }
+}
+
+impl Integration for GitHub {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn task_label(&mut self) -> error::Result<Option<String>> {
+        let Some(issue) = &self.issue else {
+            return Ok(None);
+        };
+        let url = self.url(issue, "");
+        let mut response = self.request(self.agent.get(&url)).call().map_err(failed)?;
+        if !response.status().is_success() {
+            return Err(failed(format!("{url}: {}", response.status())));
+        }
+        let found: Issue = response.body_mut().read_json().map_err(failed)?;
+        Ok(Some(format!("#{} {}", issue.number, found.title)))
+    }
+
+    fn session_ended(&mut self, summary: &Summary) -> error::Result<Vec<Value>> {
+        let Some(issue) = self.issue.clone().filter(|_| self.comment) else {
+            return Ok(Vec::new());
+        };
+        if summary.focus.is_zero() {
+            return Ok(Vec::new());
+        }
+        if self.token.is_none() {
+            return Err(failed("commenting needs github_token in the config"));
+        }
+        let body = format!(
+            "Focused on this for {}m over {} pomodoro(s), with {} interruption(s).",
+            summary.focus.as_secs() / 60,
+            summary.pomodoros,
+            summary.interruptions(),
+        );
+        let comment = serde_json::to_value(Comment { issue, body }).map_err(failed)?;
+        Ok(vec![comment])
+    }
+
+    fn deliver(&mut self, payload: &Value) -> Result<(), DeliveryError> {
+        let comment = Comment::deserialize(payload)
+            .map_err(|e| DeliveryError::Rejected(format!("bad queued comment: {e}")))?;
+        let url = self.url(&comment.issue, "/comments");
+        let response = self
+            .request(self.agent.post(&url))
+            .send_json(serde_json::json!({ "body": comment.body }))
+            .map_err(|e| DeliveryError::Retry(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else if status.is_server_error() || status.as_u16() == 429 {
+            Err(DeliveryError::Retry(format!("{url}: {status}")))
+        } else {
+            Err(DeliveryError::Rejected(format!("{url}: {status}")))
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Jira worklogs for work phases whose task names an issue, like
+//! `PROJ-42 Fix the login form`.
+
+use std::time::Duration;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tracing::{debug, info};
+use ureq::Agent;
+use crate::error::{self, OpomodoroError};
+use crate::history::{Entry, Outcome};
+use super::{DeliveryError, Integration};
+
+/// Jira rejects worklogs shorter than a minute.
+const MIN_WORKLOG: u64 = 60;
+
+/// A worklog, as queued in the outbox.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Worklog {
+    issue: String,
+    started: String,
+    seconds: u64,
+}
+
+#[derive(Debug)]
+pub struct Jira {
+    url: String,
+    auth: String,
+    agent: Agent,
+}
+
+impl Jira {
+    /// Log work on the Jira at `url`. With an `email` the token is a Jira
+    /// Cloud API token, otherwise a Data Center personal access token.
+    pub fn new(url: &str, email: Option<&str>, token: &str) -> Jira {
+        let auth = match email {
+            Some(email) => format!("Basic {}", BASE64.encode(format!("{email}:{token}"))),
+            None => format!("Bearer {token}"),
+        };
+        let agent = Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(10)))
+            .http_status_as_error(false)
+            .build()
+            .into();
+        Jira { url: url.trim_end_matches('/').to_string(), auth, agent }
+    }
+
+    fn send(&self, worklog: &Worklog) -> Result<(), DeliveryError> {
+        let url = format!("{}/rest/api/2/issue/{}/worklog", self.url, worklog.issue);
+        let response = self
+            .agent
+            .post(&url)
+            .header("Authorization", &self.auth)
+            .send_json(json!({
+                "started": worklog.started,
+                "timeSpentSeconds": worklog.seconds,
+            }))
+            .map_err(|e| DeliveryError::Retry(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            info!(issue = %worklog.issue, seconds = worklog.seconds, "logged work in jira");
+            Ok(())
+        } else if status.is_server_error() || status.as_u16() == 429 {
+            Err(DeliveryError::Retry(format!("{url}: {status}")))
+        } else {
+            Err(DeliveryError::Rejected(format!("{url}: {status}")))
+        }
+    }
+}
+
+/// The first Jira issue key in `text`, like `PROJ-42`.
+pub fn find_key(text: &str) -> Option<&str> {
+    text.split(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '-' || ch == '_'))
+        .find(|word| {
+            let Some((project, number)) = word.split_once('-') else {
+                return false;
+            };
+            project.starts_with(|ch: char| ch.is_ascii_uppercase())
+                && project.chars().all(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit() || ch == '_')
+                && !number.is_empty()
+                && number.chars().all(|ch| ch.is_ascii_digit())
+        })
+}
+
+impl Integration for Jira {
+    fn name(&self) -> &'static str {
+        "jira"
+    }
+
+    fn phase_ended(&mut self, entry: &Entry) -> error::Result<Vec<Value>> {
+        if !entry.is_work() || entry.outcome != Outcome::Completed {
+            return Ok(Vec::new());
+        }
+        let Some(issue) = entry.task.as_deref().and_then(find_key) else {
+            return Ok(Vec::new());
+        };
+        if entry.elapsed_secs < MIN_WORKLOG {
+            debug!(issue, seconds = entry.elapsed_secs, "work phase too short for a jira worklog");
+            return Ok(Vec::new());
+        }
+        let worklog = Worklog {
+            issue: issue.to_string(),
+            started: entry.started.format("%Y-%m-%dT%H:%M:%S%.3f%z").to_string(),
+            seconds: entry.elapsed_secs,
+        };
+        let worklog = serde_json::to_value(worklog).map_err(|e| {
+            OpomodoroError::Integration { name: "jira", message: e.to_string() }
+        })?;
+        Ok(vec![worklog])
+    }
+
+    fn deliver(&mut self, payload: &Value) -> Result<(), DeliveryError> {
+        let worklog = Worklog::deserialize(payload)
+            .map_err(|e| DeliveryError::Rejected(format!("bad queued worklog: {e}")))?;
+        self.send(&worklog)
+    }
+}
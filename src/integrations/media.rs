@@ -0,0 +1,159 @@
+//! Media players paused or started as phases begin: MPRIS players over
+//! D-Bus on Linux, Spotify through AppleScript on macOS.
+//!
+//! There is nothing to retry here; music that starts a minute late is
+//! worse than none, so a failed action is dropped, not queued.
+
+use std::fmt;
+use std::io;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::debug;
+use crate::engine::Phase;
+use crate::error;
+use super::{DeliveryError, Integration};
+
+/// What to do with the media player as a phase starts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaAction {
+    Play,
+    Pause,
+    /// Play this URI, like a Spotify playlist.
+    Open(String),
+}
+
+impl FromStr for MediaAction {
+    type Err = String;
+
+    /// `play`, `pause`, or a URI to play, e.g. `spotify:playlist:…`.
+    fn from_str(text: &str) -> Result<MediaAction, String> {
+        match text.trim() {
+            "play" => Ok(MediaAction::Play),
+            "pause" => Ok(MediaAction::Pause),
+            uri if uri.contains(':') => Ok(MediaAction::Open(uri.to_string())),
+            other => Err(format!("expected play, pause or a URI to play, not {other:?}")),
+        }
+    }
+}
+
+impl fmt::Display for MediaAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MediaAction::Play => f.write_str("play"),
+            MediaAction::Pause => f.write_str("pause"),
+            MediaAction::Open(uri) => f.write_str(uri),
+        }
+    }
+}
+
+/// Controls the media player as work and breaks start.
+#[derive(Debug)]
+pub struct Media {
+    on_work: Option<MediaAction>,
+    on_break: Option<MediaAction>,
+}
+
+impl Media {
+    pub fn new(on_work: Option<MediaAction>, on_break: Option<MediaAction>) -> Media {
+        Media { on_work, on_break }
+    }
+}
+
+impl Integration for Media {
+    fn name(&self) -> &'static str {
+        "media"
+    }
+
+    fn phase_started(&mut self, phase: &Phase) -> error::Result<Vec<Value>> {
+        let action = if phase.is_work() { &self.on_work } else { &self.on_break };
+        Ok(action.iter().map(|action| serde_json::json!(action)).collect())
+    }
+
+    fn deliver(&mut self, payload: &Value) -> Result<(), DeliveryError> {
+        let action = MediaAction::deserialize(payload)
+            .map_err(|e| DeliveryError::Rejected(format!("bad queued action: {e}")))?;
+        control(&action).map_err(|e| DeliveryError::Rejected(format!("could not {action}: {e}")))
+    }
+}
+
+/// Run `command`, returning what it printed.
+#[cfg(unix)]
+fn output(command: &mut Command) -> io::Result<String> {
+    debug!(?command, "controlling media");
+    let out = command.stdin(Stdio::null()).output()?;
+    if out.status.success() {
+        return Ok(String::from_utf8_lossy(&out.stdout).into_owned());
+    }
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    let reason = stderr.lines().next().map_or(out.status.to_string(), str::to_string);
+    Err(io::Error::other(format!("{:?} failed: {reason}", command.get_program())))
+}
+
+/// Every MPRIS player on the session bus, through `gdbus`.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn players() -> io::Result<Vec<String>> {
+    let names = output(Command::new("gdbus").args([
+        "call",
+        "--session",
+        "--dest",
+        "org.freedesktop.DBus",
+        "--object-path",
+        "/org/freedesktop/DBus",
+        "--method",
+        "org.freedesktop.DBus.ListNames",
+    ]))?;
+    // The reply reads like `(['org.freedesktop.DBus', ':1.7', ...],)`.
+    Ok(names
+        .split('\'')
+        .filter(|name| name.starts_with("org.mpris.MediaPlayer2."))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Pause every player, or play in the first one found.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn control(action: &MediaAction) -> io::Result<()> {
+    let players = players()?;
+    let call = |player: &str, method: &str, args: &[&str]| {
+        let method = format!("org.mpris.MediaPlayer2.Player.{method}");
+        let mut command = Command::new("gdbus");
+        command.args(["call", "--session", "--dest", player]);
+        command.args(["--object-path", "/org/mpris/MediaPlayer2", "--method", &method]);
+        output(command.args(args)).map(drop)
+    };
+    // With no player running there is nothing to pause, which is fine.
+    if let MediaAction::Pause = action {
+        return players.iter().try_for_each(|player| call(player, "Pause", &[]));
+    }
+    let Some(first) = players.first() else {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no MPRIS media player running"));
+    };
+    match action {
+        MediaAction::Open(uri) => call(first, "OpenUri", &[uri]),
+        _ => call(first, "Play", &[]),
+    }
+}
+
+/// Tell Spotify, if it is running.
+#[cfg(target_os = "macos")]
+fn control(action: &MediaAction) -> io::Result<()> {
+    let command = match action {
+        MediaAction::Play => "play".to_string(),
+        MediaAction::Pause => "pause".to_string(),
+        MediaAction::Open(uri) => {
+            format!("play track \"{}\"", uri.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+    };
+    let script = format!(
+        "if application \"Spotify\" is running then tell application \"Spotify\" to {command}"
+    );
+    output(Command::new("osascript").args(["-e", &script])).map(drop)
+}
+
+#[cfg(windows)]
+fn control(_action: &MediaAction) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "no media control on Windows yet"))
+}
@@ -0,0 +1,177 @@
+//! Services a session reports to. Those reached over the network live
+//! behind cargo features so a default build stays free of network code.
+//!
+//! Integrations do not talk to their service from the hooks directly: the
+//! hooks hand back payloads, which go through a file-backed [`Outbox`] and
+//! are retried with backoff until [`Integration::deliver`] gets them through.
+
+use std::fmt;
+use chrono::Local;
+use serde_json::Value;
+use crate::engine::Phase;
+use crate::error::{self, OpomodoroError};
+use crate::history::{Entry, Summary};
+
+#[cfg(feature = "github")]
+pub mod github;
+#[cfg(feature = "jira")]
+pub mod jira;
+pub mod media;
+pub mod outbox;
+
+pub use outbox::Outbox;
+use outbox::Pending;
+
+/// Why a payload did not get through.
+#[derive(Debug)]
+pub enum DeliveryError {
+    /// The service could not be reached or had trouble; try again later.
+    Retry(String),
+    /// The service turned it down and will keep doing so.
+    Rejected(String),
+}
+
+/// Hooks a service can implement; all but [`deliver`](Integration::deliver)
+/// default to doing nothing.
+pub trait Integration: fmt::Debug {
+    /// Short name, used in errors, logs and the outbox.
+    fn name(&self) -> &'static str;
+
+    /// Label for the session's work phases, looked up before it starts.
+    fn task_label(&mut self) -> error::Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Payloads to deliver now that a phase is running for the first time.
+    fn phase_started(&mut self, _phase: &Phase) -> error::Result<Vec<Value>> {
+        Ok(Vec::new())
+    }
+
+    /// Payloads to deliver now that a phase was recorded in the history.
+    fn phase_ended(&mut self, _entry: &Entry) -> error::Result<Vec<Value>> {
+        Ok(Vec::new())
+    }
+
+    /// Payloads to deliver now that the session is over.
+    fn session_ended(&mut self, _summary: &Summary) -> error::Result<Vec<Value>> {
+        Ok(Vec::new())
+    }
+
+    /// Send one payload from the hooks above, maybe from an earlier run.
+    fn deliver(&mut self, payload: &Value) -> Result<(), DeliveryError>;
+}
+
+/// The session's integrations and the outbox their payloads wait in.
+#[derive(Debug, Default)]
+pub struct Integrations {
+    list: Vec<Box<dyn Integration>>,
+    outbox: Option<Outbox>,
+}
+
+impl Integrations {
+    /// Integrations queueing undelivered payloads in `outbox`; without one,
+    /// payloads that fail to send are dropped.
+    pub fn new(outbox: Option<Outbox>) -> Integrations {
+        Integrations { list: Vec::new(), outbox }
+    }
+
+    pub fn push(&mut self, integration: Box<dyn Integration>) {
+        self.list.push(integration);
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut dyn Integration> {
+        self.list.iter_mut().map(|integration| integration.as_mut() as &mut dyn Integration)
+    }
+
+    /// Report a phase that just started. Returns the problems hit on the way.
+    pub fn phase_started(&mut self, phase: &Phase) -> Vec<OpomodoroError> {
+        self.publish(|integration| integration.phase_started(phase))
+    }
+
+    /// Report a recorded phase. Returns the problems hit on the way.
+    pub fn phase_ended(&mut self, entry: &Entry) -> Vec<OpomodoroError> {
+        self.publish(|integration| integration.phase_ended(entry))
+    }
+
+    /// Report the end of the session. Returns the problems hit on the way.
+    pub fn session_ended(&mut self, summary: &Summary) -> Vec<OpomodoroError> {
+        self.publish(|integration| integration.session_ended(summary))
+    }
+
+    /// Send what earlier runs left in the outbox, backing off or not.
+    pub fn flush(&mut self) -> Vec<OpomodoroError> {
+        self.send(Vec::new(), true)
+    }
+
+    fn publish(
+        &mut self,
+        mut hook: impl FnMut(&mut dyn Integration) -> error::Result<Vec<Value>>,
+    ) -> Vec<OpomodoroError> {
+        if self.list.is_empty() {
+            return Vec::new();
+        }
+        let now = Local::now();
+        let mut problems = Vec::new();
+        let mut new = Vec::new();
+        for integration in &mut self.list {
+            match hook(integration.as_mut()) {
+                Ok(payloads) => new.extend(
+                    payloads.into_iter().map(|payload| Pending::new(integration.name(), payload, now)),
+                ),
+                Err(e) => problems.push(e),
+            }
+        }
+        problems.extend(self.send(new, false));
+        problems
+    }
+
+    /// Deliver `new` and whatever in the outbox is due (or everything, with
+    /// `all`), putting back what is still undelivered.
+    fn send(&mut self, new: Vec<Pending>, all: bool) -> Vec<OpomodoroError> {
+        let mut problems = Vec::new();
+        let mut pending = match self.outbox.as_ref().map(Outbox::load).transpose() {
+            Ok(queued) => queued.unwrap_or_default(),
+            Err(e) => {
+                problems.push(e.into());
+                Vec::new()
+            }
+        };
+        if pending.is_empty() && new.is_empty() {
+            return problems;
+        }
+        pending.extend(new);
+        let now = Local::now();
+        let mut kept = Vec::new();
+        for mut item in pending {
+            // Leave payloads for integrations not set up this run in the
+            // outbox, as well as those still backing off.
+            let Some(integration) = self.list.iter_mut().find(|i| i.name() == item.integration)
+            else {
+                kept.push(item);
+                continue;
+            };
+            if !all && item.next_attempt > now {
+                kept.push(item);
+                continue;
+            }
+            let name = integration.name();
+            match integration.deliver(&item.payload) {
+                Ok(()) => {}
+                Err(DeliveryError::Retry(message)) => {
+                    item.retry_later(now);
+                    let message = format!("{message} (will retry)");
+                    problems.push(OpomodoroError::Integration { name, message });
+                    kept.push(item);
+                }
+                Err(DeliveryError::Rejected(message)) => {
+                    problems.push(OpomodoroError::Integration { name, message });
+                }
+            }
+        }
+        if let Some(outbox) = &self.outbox
+            && let Err(e) = outbox.save(&kept) {
+            problems.push(e.into());
+        }
+        problems
+    }
+}
@@ -0,0 +1,92 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local, TimeDelta};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::profile;
+
+/// First wait before retrying a payload; it doubles with every failure.
+const FIRST_RETRY: TimeDelta = TimeDelta::seconds(30);
+/// Longest wait between retries.
+const MAX_RETRY: TimeDelta = TimeDelta::hours(1);
+
+/// A payload waiting for its integration to deliver it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pending {
+    pub integration: String,
+    pub payload: Value,
+    #[serde(default)]
+    pub attempts: u32,
+    pub next_attempt: DateTime<Local>,
+}
+
+impl Pending {
+    pub fn new(integration: &str, payload: Value, now: DateTime<Local>) -> Pending {
+        Pending { integration: integration.to_string(), payload, attempts: 0, next_attempt: now }
+    }
+
+    /// Back off after a failed attempt at `now`.
+    pub fn retry_later(&mut self, now: DateTime<Local>) {
+        self.attempts += 1;
+        let wait = FIRST_RETRY * 2i32.saturating_pow(self.attempts.saturating_sub(1).min(16));
+        self.next_attempt = now + wait.min(MAX_RETRY);
+    }
+}
+
+/// JSON Lines file of undelivered integration payloads, so nothing is lost
+/// while offline.
+#[derive(Debug)]
+pub struct Outbox {
+    path: PathBuf,
+}
+
+impl Outbox {
+    pub fn new(path: PathBuf) -> Outbox {
+        Outbox { path }
+    }
+
+    /// `outbox.jsonl` in the [profile's](crate::profile) data folder, if
+    /// the platform has a data dir.
+    pub fn default_path() -> Option<PathBuf> {
+        profile::data_dir().map(|dir| dir.join("outbox.jsonl"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn load(&self) -> io::Result<Vec<Pending>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(io::Error::from))
+            .collect()
+    }
+
+    /// Replace the outbox with `pending`, removing the file once it is empty.
+    pub fn save(&self, pending: &[Pending]) -> io::Result<()> {
+        if pending.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut text = String::new();
+        for item in pending {
+            text.push_str(&serde_json::to_string(item)?);
+            text.push('\n');
+        }
+        // Write then rename so a crash never leaves half an outbox.
+        let tmp = self.path.with_extension("jsonl.tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(tmp, &self.path)
+    }
+}
@@ -0,0 +1,76 @@
+//! Interval training: a set of labelled phases, like 45 seconds on and 15
+//! off, run round after round with nothing else around them. Each label
+//! has a chime of its own, so the change can be heard without looking.
+
+use std::str::FromStr;
+use std::time::Duration;
+use crate::engine::{Phase, PhaseKind};
+
+/// The phases of one round, and how many rounds to run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Intervals {
+    pub phases: Vec<Phase>,
+    pub rounds: u32,
+}
+
+impl Intervals {
+    /// One round of `phase` alone, a one-off countdown.
+    pub fn single(phase: Phase) -> Intervals {
+        Intervals { phases: vec![phase], rounds: 1 }
+    }
+
+    /// Time all the rounds take if every phase runs its course.
+    pub fn duration(&self) -> Duration {
+        self.phases.iter().map(|phase| phase.duration).sum::<Duration>() * self.rounds
+    }
+
+    /// The phases still to come after the one at `index` in `round`,
+    /// counting rounds from 1.
+    pub fn after(&self, round: u32, index: usize) -> impl Iterator<Item = &Phase> {
+        let later = (round..self.rounds).flat_map(|_| self.phases.iter());
+        self.phases.iter().skip(index + 1).chain(later)
+    }
+
+    /// Which chime marks the start of a phase of `kind`: its label's place
+    /// among the set's labels, each counted once.
+    pub fn chime(&self, kind: &PhaseKind) -> Option<usize> {
+        let mut labels: Vec<&PhaseKind> = Vec::new();
+        for phase in &self.phases {
+            if !labels.contains(&&phase.kind) {
+                labels.push(&phase.kind);
+            }
+        }
+        labels.iter().position(|label| *label == kind)
+    }
+}
+
+impl FromStr for Intervals {
+    type Err = String;
+
+    /// `[LABEL=]LENGTH/[LABEL=]LENGTH... [xROUNDS]`, e.g. `45s/15s x10` or
+    /// `squats=40s/rest=20s/plank=40s/rest=20s x3`. Left unlabelled, the
+    /// first phase is `On` and the others `Off`; left out, one round.
+    fn from_str(text: &str) -> Result<Intervals, String> {
+        let (set, rounds) = match text.rsplit_once('x') {
+            Some((set, rounds)) if !rounds.contains(['/', '=']) => {
+                let rounds = rounds.trim().parse().map_err(|e| format!("bad rounds {rounds:?}: {e}"))?;
+                (set, rounds)
+            }
+            _ => (text, 1),
+        };
+        let mut phases = Vec::new();
+        for (index, interval) in set.split('/').map(str::trim).enumerate() {
+            let (label, length) = match interval.split_once('=') {
+                Some((label, length)) => (label.trim(), length.trim()),
+                None if index == 0 => ("On", interval),
+                None => ("Off", interval),
+            };
+            if label.is_empty() || length.is_empty() {
+                return Err(format!("expected [LABEL=]LENGTH, e.g. 45s or rest=15s, got {interval:?}"));
+            }
+            let length = humantime::parse_duration(length).map_err(|e| format!("bad length {length:?}: {e}"))?;
+            phases.push(Phase::build(PhaseKind::from(label), length));
+        }
+        Ok(Intervals { phases, rounds })
+    }
+}
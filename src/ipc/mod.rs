@@ -0,0 +1,493 @@
+//! Control socket of a running session: a JSON request per line, answered
+//! by a JSON [`Snapshot`] per line. It is a Unix socket, or a named pipe
+//! on Windows.
+
+#[cfg(windows)]
+mod pipe;
+
+use std::fmt;
+#[cfg(unix)]
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener as Listener, UnixStream as Stream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use crate::engine::{Event, Phase, PhaseKind, SkipTarget, Timer};
+use crate::history::{Interruption, InterruptionSource, Outcome};
+#[cfg(windows)]
+use pipe::{PipeListener as Listener, PipeStream as Stream};
+
+/// How long a client waits for an answer before giving up on the session.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Something a client asks the session to do.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    /// Change nothing, just report the state.
+    Snapshot,
+    Toggle,
+    Pause,
+    /// Continue a paused timer.
+    Unpause,
+    /// End the current phase and move on as if it had run out.
+    Skip,
+    SkipTo { target: SkipTarget },
+    /// Run the work phases or breaks still to start for this long; the
+    /// phase on now keeps its length.
+    SetLengths { work: Option<Duration>, break_time: Option<Duration> },
+    GotoCycle { cycle: u32 },
+    Snooze,
+    /// Take back a skip made moments ago.
+    UndoSkip,
+    Interrupt { source: InterruptionSource, note: Option<String> },
+}
+
+/// The session as seen after a request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub phase: PhaseKind,
+    pub phase_duration: Duration,
+    pub cycle: u32,
+    pub num_cycles: u32,
+    pub remaining: Duration,
+    pub paused: bool,
+    pub finished: bool,
+    pub late: bool,
+    pub work_time: Duration,
+    pub break_time: Duration,
+    pub grace_left: Option<Duration>,
+    #[serde(default)]
+    pub undo_left: Option<Duration>,
+    #[serde(default)]
+    pub get_ready_left: Option<Duration>,
+    #[serde(default)]
+    pub break_debt: Duration,
+    #[serde(default)]
+    pub cool_down: Option<Duration>,
+    #[serde(default)]
+    pub long_break: Option<(Duration, u32)>,
+    /// Phases completed so far, so clients can tell when one ends.
+    pub completed: usize,
+}
+
+impl Snapshot {
+    /// Whether `other` shows the same phase in the same state, however much
+    /// time has passed between the two.
+    pub fn same_state(&self, other: &Snapshot) -> bool {
+        (&self.phase, self.cycle, self.num_cycles, self.paused, self.finished, self.completed)
+            == (&other.phase, other.cycle, other.num_cycles, other.paused, other.finished, other.completed)
+    }
+}
+
+/// `<runtime dir>/opomodoro/control.sock`, falling back to the data dir on
+/// platforms without a runtime dir.
+#[cfg(unix)]
+pub fn default_path() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .or_else(dirs::data_dir)
+        .map(|dir| dir.join("opomodoro").join("control.sock"))
+}
+
+/// `\\.\pipe\opomodoro-<user>`: pipes live in a namespace of their own,
+/// shared by every user of the machine.
+#[cfg(windows)]
+pub fn default_path() -> Option<PathBuf> {
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    Some(PathBuf::from(format!(r"\\.\pipe\opomodoro-{user}")))
+}
+
+/// A request waiting for the session to answer it.
+#[derive(Debug)]
+pub struct Pending {
+    pub request: Request,
+    reply: Sender<Snapshot>,
+}
+
+impl Pending {
+    /// `request`, with the receiver its answer will arrive on.
+    fn new(request: Request) -> (Pending, Receiver<Snapshot>) {
+        let (reply, answer) = mpsc::channel();
+        (Pending { request, reply }, answer)
+    }
+
+    pub fn reply(self, snapshot: Snapshot) {
+        // The client may have hung up already; nothing to do about it.
+        let _ = self.reply.send(snapshot);
+    }
+}
+
+/// Clients waiting for snapshots as the session goes.
+type Watchers = Arc<Mutex<Vec<Sender<Snapshot>>>>;
+
+/// A way into the session for frontends living in the same process, like
+/// the HTTP API.
+#[derive(Debug, Clone)]
+pub struct SessionHandle {
+    requests: Sender<Pending>,
+    watchers: Watchers,
+}
+
+impl SessionHandle {
+    /// Send `request` and wait for the answer; `None` once the session has
+    /// ended.
+    pub fn ask(&self, request: Request) -> Option<Snapshot> {
+        let (pending, answer) = Pending::new(request);
+        self.requests.send(pending).ok()?;
+        answer.recv().ok()
+    }
+
+    /// Snapshots published by the session, every second and whenever its
+    /// state changes, until it ends.
+    pub fn watch(&self) -> Receiver<Snapshot> {
+        let (sender, receiver) = mpsc::channel();
+        self.watchers.lock().unwrap_or_else(|e| e.into_inner()).push(sender);
+        receiver
+    }
+}
+
+/// The session's end of the control socket. Each client is served on its
+/// own thread; requests are queued for the session loop to pick up.
+#[derive(Debug)]
+pub struct ControlServer {
+    #[cfg_attr(windows, allow(dead_code))]
+    path: PathBuf,
+    sender: Sender<Pending>,
+    requests: Receiver<Pending>,
+    watchers: Watchers,
+}
+
+impl ControlServer {
+    /// Listen on `path`, replacing a socket left behind by a dead session.
+    /// Callers hold the instance lock, so a live one cannot be there. A
+    /// named pipe goes away with the process, so there is none to replace.
+    pub fn bind(path: &Path) -> io::Result<ControlServer> {
+        #[cfg(unix)]
+        {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        let listener = Listener::bind(path)?;
+        let (sender, requests) = mpsc::channel();
+        let clients = sender.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let sender = clients.clone();
+                        thread::spawn(move || serve_client(stream, sender));
+                    }
+                    Err(e) => warn!("control socket accept failed: {e}"),
+                }
+            }
+        });
+        let watchers = Watchers::default();
+        Ok(ControlServer { path: path.to_path_buf(), sender, requests, watchers })
+    }
+
+    /// A handle for frontends in this process to reach the session with.
+    pub fn handle(&self) -> SessionHandle {
+        SessionHandle { requests: self.sender.clone(), watchers: self.watchers.clone() }
+    }
+
+    /// Whether anyone is [watching](SessionHandle::watch) the session.
+    pub fn is_watched(&self) -> bool {
+        !self.watchers.lock().unwrap_or_else(|e| e.into_inner()).is_empty()
+    }
+
+    /// Hand `snapshot` to the watchers, forgetting those that went away.
+    pub fn publish(&self, snapshot: &Snapshot) {
+        let mut watchers = self.watchers.lock().unwrap_or_else(|e| e.into_inner());
+        watchers.retain(|watcher| watcher.send(snapshot.clone()).is_ok());
+    }
+
+    /// Wait up to `timeout` for the next request.
+    pub fn next(&self, timeout: Duration) -> Option<Pending> {
+        self.requests.recv_timeout(timeout).ok()
+    }
+
+    /// The next request, if one is already waiting.
+    pub fn try_next(&self) -> Option<Pending> {
+        self.requests.try_recv().ok()
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        // Let watchers see the session is over.
+        self.watchers.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        #[cfg(unix)]
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn serve_client(stream: Stream, sender: Sender<Pending>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let request = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                debug!("bad control request {line:?}: {e}");
+                return;
+            }
+        };
+        let (pending, answer) = Pending::new(request);
+        if sender.send(pending).is_err() {
+            return;
+        }
+        let Ok(snapshot) = answer.recv() else {
+            return;
+        };
+        let Ok(mut json) = serde_json::to_string(&snapshot) else {
+            return;
+        };
+        json.push('\n');
+        if writer.write_all(json.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+/// A connection to a session that answers requests with snapshots.
+pub trait Link: fmt::Debug {
+    fn send(&mut self, request: &Request) -> io::Result<Snapshot>;
+}
+
+/// A client's end of the control socket.
+#[derive(Debug)]
+pub struct ControlClient {
+    reader: BufReader<Stream>,
+    writer: Stream,
+}
+
+impl ControlClient {
+    pub fn connect(path: &Path) -> io::Result<ControlClient> {
+        let writer = Stream::connect(path)?;
+        writer.set_read_timeout(Some(REPLY_TIMEOUT))?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(ControlClient { reader, writer })
+    }
+
+}
+
+impl Link for ControlClient {
+    fn send(&mut self, request: &Request) -> io::Result<Snapshot> {
+        let mut json = serde_json::to_string(request)?;
+        json.push('\n');
+        self.writer.write_all(json.as_bytes())?;
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+/// A session running in another process, driven over its control socket
+/// or followed over some other [`Link`]. Quitting detaches and leaves that
+/// session running.
+#[derive(Debug)]
+pub struct RemoteEngine<L: Link = ControlClient> {
+    client: L,
+    snapshot: Snapshot,
+    fetched: Instant,
+    detached: bool,
+}
+
+impl RemoteEngine {
+    pub fn connect(path: &Path) -> io::Result<RemoteEngine> {
+        RemoteEngine::with_link(ControlClient::connect(path)?)
+    }
+}
+
+impl<L: Link> RemoteEngine<L> {
+    pub fn with_link(mut client: L) -> io::Result<RemoteEngine<L>> {
+        let snapshot = client.send(&Request::Snapshot)?;
+        Ok(RemoteEngine { client, snapshot, fetched: Instant::now(), detached: false })
+    }
+
+    /// Send `request`, returning an ended phase if one was seen.
+    fn send(&mut self, request: Request) -> Vec<Event> {
+        if self.detached {
+            return Vec::new();
+        }
+        let snapshot = match self.client.send(&request) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!("lost the session: {e}");
+                self.detached = true;
+                return Vec::new();
+            }
+        };
+        let previous = std::mem::replace(&mut self.snapshot, snapshot);
+        self.fetched = Instant::now();
+        if self.snapshot.completed > previous.completed {
+            vec![Event::PhaseEnded {
+                phase: phase_of(&previous),
+                cycle: previous.cycle,
+                elapsed: previous.phase_duration,
+                outcome: Outcome::Completed,
+                extension: false,
+            }]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Time since the snapshot was taken, which a running timer has used up.
+    fn since_fetch(&self) -> Duration {
+        if self.snapshot.paused {
+            Duration::ZERO
+        } else {
+            self.fetched.elapsed()
+        }
+    }
+}
+
+fn phase_of(snapshot: &Snapshot) -> Phase {
+    Phase::build(snapshot.phase.clone(), snapshot.phase_duration)
+}
+
+impl<L: Link> Timer for RemoteEngine<L> {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn phase(&self) -> Phase {
+        phase_of(&self.snapshot)
+    }
+
+    fn cycle(&self) -> u32 {
+        self.snapshot.cycle
+    }
+
+    fn num_cycles(&self) -> u32 {
+        self.snapshot.num_cycles
+    }
+
+    fn work_time(&self) -> Duration {
+        self.snapshot.work_time
+    }
+
+    fn break_time(&self) -> Duration {
+        self.snapshot.break_time
+    }
+
+    fn late(&self) -> bool {
+        self.snapshot.late
+    }
+
+    fn is_paused(&self) -> bool {
+        self.snapshot.paused
+    }
+
+    fn is_finished(&self) -> bool {
+        self.detached || self.snapshot.finished
+    }
+
+    fn remaining(&self) -> Duration {
+        let left = self.snapshot.remaining + self.snapshot.get_ready_left.unwrap_or_default();
+        left.saturating_sub(self.since_fetch()).min(self.snapshot.phase_duration)
+    }
+
+    fn grace_left(&self) -> Option<Duration> {
+        self.snapshot
+            .grace_left
+            .map(|left| left.saturating_sub(self.fetched.elapsed()))
+            .filter(|left| !left.is_zero())
+    }
+
+    fn undo_left(&self) -> Option<Duration> {
+        self.snapshot
+            .undo_left
+            .map(|left| left.saturating_sub(self.fetched.elapsed()))
+            .filter(|left| !left.is_zero())
+    }
+
+    fn break_debt(&self) -> Duration {
+        self.snapshot.break_debt
+    }
+
+    fn cool_down(&self) -> Option<Duration> {
+        self.snapshot.cool_down
+    }
+
+    fn long_break(&self) -> Option<(Duration, u32)> {
+        self.snapshot.long_break
+    }
+
+    fn get_ready_left(&self) -> Option<Duration> {
+        self.snapshot
+            .get_ready_left
+            .map(|left| left.saturating_sub(self.since_fetch()))
+            .filter(|left| !left.is_zero())
+    }
+
+    fn toggle_pause(&mut self) {
+        self.send(Request::Toggle);
+    }
+
+    fn tick(&mut self) -> Vec<Event> {
+        self.send(Request::Snapshot)
+    }
+
+    fn skip(&mut self) -> Vec<Event> {
+        self.send(Request::Skip);
+        Vec::new()
+    }
+
+    fn skip_to(&mut self, target: SkipTarget) -> Vec<Event> {
+        self.send(Request::SkipTo { target });
+        // The session records skips itself; nothing ended on this side.
+        Vec::new()
+    }
+
+    fn goto_cycle(&mut self, cycle: u32) -> Vec<Event> {
+        self.send(Request::GotoCycle { cycle });
+        Vec::new()
+    }
+
+    fn snooze(&mut self) -> Vec<Event> {
+        self.send(Request::Snooze);
+        Vec::new()
+    }
+
+    fn undo_skip(&mut self) -> Vec<Event> {
+        self.send(Request::UndoSkip);
+        Vec::new()
+    }
+
+    fn quit(&mut self) -> Vec<Event> {
+        self.detached = true;
+        Vec::new()
+    }
+
+    fn set_lengths(&mut self, work: Duration, break_time: Duration) -> bool {
+        self.send(Request::SetLengths { work: Some(work), break_time: Some(break_time) });
+        true
+    }
+
+    fn record_interruption(&mut self, interruption: &Interruption) -> bool {
+        self.send(Request::Interrupt {
+            source: interruption.source,
+            note: interruption.note.clone(),
+        });
+        true
+    }
+}
@@ -0,0 +1,148 @@
+//! Named pipes, the control socket on Windows. Once connected, either end
+//! is a plain file to read and write. The server makes a new instance of
+//! the pipe for each client, the way a socket accepts a connection.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::iter;
+use std::os::windows::ffi::OsStrExt;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+use std::path::Path;
+use std::ptr;
+use std::sync::Mutex;
+use std::time::Duration;
+use windows_sys::Win32::Foundation::{ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE};
+use windows_sys::Win32::Storage::FileSystem::{
+    FILE_FLAG_FIRST_PIPE_INSTANCE, FILE_FLAGS_AND_ATTRIBUTES, PIPE_ACCESS_DUPLEX,
+};
+use windows_sys::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+    PIPE_UNLIMITED_INSTANCES, PIPE_WAIT, WaitNamedPipeW,
+};
+
+/// Bytes the pipe buffers each way; a request or a snapshot is far less.
+const BUFFER: u32 = 4096;
+
+/// How long a client waits for a free instance while the server is busy
+/// making the next one.
+const BUSY_WAIT_MS: u32 = 2000;
+
+/// The server's end of a named pipe, waiting for clients.
+#[derive(Debug)]
+pub struct PipeListener {
+    /// The pipe's name as UTF-16, ending in a NUL.
+    name: Vec<u16>,
+    /// The instance made by `bind`, until a client takes it.
+    first: Mutex<Option<OwnedHandle>>,
+}
+
+impl PipeListener {
+    /// Make the pipe at `path`, like `\\.\pipe\opomodoro`. Fails if another
+    /// process has made it already.
+    pub fn bind(path: &Path) -> io::Result<PipeListener> {
+        let name = wide(path);
+        let first = create(&name, FILE_FLAG_FIRST_PIPE_INSTANCE)?;
+        Ok(PipeListener { name, first: Mutex::new(Some(first)) })
+    }
+
+    /// Wait for the next client to connect.
+    pub fn accept(&self) -> io::Result<PipeStream> {
+        let first = self.first.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let handle = match first {
+            Some(handle) => handle,
+            None => create(&self.name, 0)?,
+        };
+        // SAFETY: `handle` is a pipe instance of ours, waited on without
+        // overlapped I/O, so the null OVERLAPPED is allowed.
+        if unsafe { ConnectNamedPipe(handle.as_raw_handle(), ptr::null_mut()) } == 0 {
+            let e = io::Error::last_os_error();
+            // A client that came between making the instance and waiting
+            // for one is connected all the same.
+            if e.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32) {
+                return Err(e);
+            }
+        }
+        Ok(PipeStream(File::from(handle)))
+    }
+
+    /// The clients as they connect, like `UnixListener::incoming`.
+    pub fn incoming(&self) -> impl Iterator<Item = io::Result<PipeStream>> + '_ {
+        iter::repeat_with(|| self.accept())
+    }
+}
+
+/// Either end of a connected pipe.
+#[derive(Debug)]
+pub struct PipeStream(File);
+
+impl PipeStream {
+    /// Connect to the pipe at `path`, waiting a little for a free instance
+    /// if the server is between two clients.
+    pub fn connect(path: &Path) -> io::Result<PipeStream> {
+        let open = || OpenOptions::new().read(true).write(true).open(path);
+        match open() {
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY as i32) => {
+                // SAFETY: the name is a NUL-terminated UTF-16 string that
+                // outlives the call.
+                unsafe { WaitNamedPipeW(wide(path).as_ptr(), BUSY_WAIT_MS) };
+                open().map(PipeStream)
+            }
+            file => file.map(PipeStream),
+        }
+    }
+
+    /// Pipes opened for blocking I/O cannot time out reads, so this does
+    /// nothing; it is here for the calls a socket takes.
+    pub fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn try_clone(&self) -> io::Result<PipeStream> {
+        self.0.try_clone().map(PipeStream)
+    }
+}
+
+impl Read for PipeStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for PipeStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// A new instance of the pipe called `name`, with `flags` on top of the
+/// usual ones.
+fn create(name: &[u16], flags: FILE_FLAGS_AND_ATTRIBUTES) -> io::Result<OwnedHandle> {
+    // SAFETY: `name` is a NUL-terminated UTF-16 string that outlives the
+    // call, and no security attributes means the default ones.
+    let handle = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            PIPE_ACCESS_DUPLEX | flags,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            BUFFER,
+            BUFFER,
+            0,
+            ptr::null(),
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: the handle was just made, and nothing else owns it.
+    Ok(unsafe { OwnedHandle::from_raw_handle(handle) })
+}
+
+/// `path` as a NUL-terminated UTF-16 string, for the Win32 calls.
+fn wide(path: &Path) -> Vec<u16> {
+    path.as_os_str().encode_wide().chain(iter::once(0)).collect()
+}
@@ -1,265 +1,2380 @@
+#[cfg(feature = "terminal")]
+mod alerts;
+pub mod ambient;
+pub mod autostart;
+pub mod backup;
+#[cfg(feature = "plain")]
+pub mod blocker;
+#[cfg(feature = "terminal")]
+pub mod calendar;
+pub mod chain;
+pub mod clock;
+pub mod cloud;
+pub mod day;
+#[cfg(feature = "plain")]
+pub mod desktop;
+#[cfg(feature = "tokio")]
+pub mod embed;
+#[cfg(all(unix, feature = "mdns"))]
+pub mod discovery;
+pub mod engine;
+pub mod error;
+#[cfg(feature = "terminal")]
+mod font;
+pub mod history;
+#[cfg(feature = "terminal")]
+mod idle;
+#[cfg(feature = "terminal")]
+pub mod input;
+#[cfg(all(unix, feature = "http"))]
+pub mod http;
+#[cfg(feature = "plain")]
+pub mod integrations;
+pub mod intervals;
+#[cfg(any(unix, windows))]
+pub mod ipc;
+#[cfg(feature = "plain")]
+pub mod lock;
+#[cfg(feature = "terminal")]
+mod mascot;
+#[cfg(unix)]
+pub mod net;
+#[cfg(feature = "terminal")]
+pub mod onboarding;
+#[cfg(feature = "terminal")]
+mod options;
+#[cfg(all(feature = "http", feature = "terminal"))]
+pub mod overlay;
+#[cfg(feature = "plain")]
+pub mod plain;
+pub mod planner;
+pub mod profile;
+#[cfg(feature = "terminal")]
+mod prompt;
+#[cfg(feature = "terminal")]
+pub mod record;
+pub mod schedule;
+pub mod schema;
+pub mod score;
+pub mod service;
+pub mod settings;
+pub mod simulate;
+pub mod state;
+pub mod suggest;
+#[cfg(unix)]
+pub mod systemd;
+pub mod template;
+#[cfg(feature = "terminal")]
+pub mod terminal;
+pub mod theme;
+pub mod todo;
+#[cfg(feature = "terminal")]
 mod ui;
+mod view;
+mod wallclock;
+use std::path::PathBuf;
+use std::time::Duration;
+#[cfg(feature = "terminal")]
 use std::thread;
-use std::io::{self, Write};
-use std::time::{Duration, Instant};
+#[cfg(feature = "terminal")]
+use std::time::{Instant, SystemTime};
+#[cfg(feature = "terminal")]
 use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "terminal")]
+use chrono::{DateTime, Datelike, Local};
+#[cfg(feature = "terminal")]
 use crossterm::event::{
     KeyCode,
+    KeyEvent,
     KeyModifiers, 
-    read, 
-    poll,
 };
-use ratatui::{DefaultTerminal, Frame};
+#[cfg(feature = "terminal")]
+use ratatui::{Frame, Terminal};
+#[cfg(feature = "terminal")]
+use ratatui::backend::Backend;
+#[cfg(feature = "terminal")]
+use ratatui::layout::Size;
+#[cfg(feature = "terminal")]
+use tracing::{debug, info, warn};
+#[cfg(feature = "terminal")]
+use alerts::{Alerts, Speech, spoken};
+use template::Messages;
+#[cfg(feature = "terminal")]
+use template::Vars;
+use ambient::AmbientSound;
+#[cfg(feature = "terminal")]
+use ambient::{Ambient, VOLUME_STEP};
+#[cfg(feature = "terminal")]
+use blocker::Blocker;
+use chain::Block;
+#[cfg(feature = "terminal")]
+use day::DayFile;
+use engine::{BreakDebt, OverPauseBudget, Phase};
+use intervals::Intervals;
+#[cfg(feature = "terminal")]
+use engine::{Engine, Event, PhaseKind, SkipTarget, Timer};
+use history::Targets;
+#[cfg(feature = "terminal")]
+use history::{Entry, Focus, HistoryStore, Interruption, InterruptionSource, Outcome, Summary};
+#[cfg(feature = "terminal")]
+use idle::IdleMonitor;
+#[cfg(feature = "terminal")]
+use input::{InputSource, TerminalInput};
+#[cfg(feature = "terminal")]
+use integrations::Integrations;
+#[cfg(feature = "terminal")]
+use options::Field;
+#[cfg(feature = "terminal")]
+use planner::{Plan, Task, TaskFile};
+#[cfg(feature = "terminal")]
+use prompt::{InputResult, TextInput};
+#[cfg(feature = "terminal")]
+use score::FocusScore;
+#[cfg(feature = "terminal")]
+use state::{SessionState, StateFile};
+#[cfg(feature = "terminal")]
+use theme::Theme;
+use theme::ThemeChoice;
 
-#[derive(Debug)]
-struct Phase<'a> {
-    kind: &'a str,
-    duration: Duration,
-}
-
-impl<'a> Phase<'a> {
-   fn build(
-    kind: &'a str,
-    duration: Duration,
-    ) -> Phase<'a> {
-        Phase { kind, duration }
-    }
-}
+pub use error::{ConfigError, OpomodoroError};
+pub use view::{Countdown, FontChoice, ViewMode};
+#[cfg(feature = "bench")]
+pub use ui::bench;
+pub use wallclock::HourFormat;
 
 #[derive(Debug)]
 pub struct Config {
     pub work_time: Duration,
     pub break_time: Duration,
     pub cycles: u32,
+    /// Take a break this long in place of the usual one after every so
+    /// many cycles, if set.
+    pub long_break: Option<(Duration, u32)>,
     pub late: bool,
+    /// Plan for this long before the first work phase, if set.
+    pub warm_up: Option<Duration>,
+    /// Review for this long after the last phase, if set.
+    pub cool_down: Option<Duration>,
+    pub font: FontChoice,
+    pub view: ViewMode,
+    pub countdown: Countdown,
+    pub theme: ThemeChoice,
+    /// Draw without color, as `NO_COLOR` asks; what the theme's track
+    /// color would tell apart is dimmed instead.
+    pub no_color: bool,
+    pub volume: u8,
+    /// Speak phase changes out loud, as well as ringing the bell.
+    pub speak: bool,
+    /// Show a desktop notification as each phase runs out.
+    pub notify: bool,
+    /// Flash the screen whenever an alert sounds.
+    pub visual_bell: bool,
+    pub history: Option<PathBuf>,
+    pub state: Option<PathBuf>,
+    pub tasks: Option<PathBuf>,
+    pub notes: bool,
+    pub snooze: Duration,
+    pub max_snoozes: u32,
+    /// Pause work phases after this long without input, if set.
+    pub idle: Option<Duration>,
+    /// Warn this long before a work phase ends, if set.
+    pub work_warning: Option<Duration>,
+    /// Warn this long before a break ends, if set.
+    pub break_warning: Option<Duration>,
+    /// Fill the screen during breaks, holding off skips for this long, if set.
+    pub hard_break: Option<Duration>,
+    /// Count down this long before each work phase that follows a break.
+    pub get_ready: Option<Duration>,
+    /// Whether to keep count of break time skipped, and make it up later.
+    pub break_debt: BreakDebt,
+    /// Most time a work phase may spend paused, and what going over does.
+    pub pause_budget: Option<(Duration, OverPauseBudget)>,
+    /// Void work phases that are paused or interrupted, rather than count
+    /// them as pomodoros.
+    pub strict: bool,
+    /// Shell commands to run as work starts and stops: block, then unblock.
+    pub block_commands: Option<(String, String)>,
+    /// Sound to play through work phases, and its volume.
+    pub ambient: Option<(AmbientSound, u8)>,
+    /// Focus time to aim for per day and week, shown as gauges.
+    pub targets: Targets,
+    pub task: Option<String>,
+    pub clock: HourFormat,
+    pub sidebar: bool,
+    /// Show the tomato mascot next to the timer.
+    pub mascot: bool,
+    /// Play a short splash when the phase changes.
+    pub animations: bool,
+    /// Spin a braille dot by the phase while paused, and show it still
+    /// while running, to tell a live timer at a glance.
+    pub spinner: bool,
+    /// Wake and draw about once a second, with no splash, to save battery.
+    pub low_power: bool,
+    /// Celebrate once the last cycle ends and call the day done in this
+    /// file, holding off new sessions until tomorrow, if set.
+    pub end_of_day: Option<PathBuf>,
+    /// Hold each phase after one runs out until a key is pressed, sounding
+    /// the alert again this often, up to this many more times, if set.
+    pub manual_advance: Option<(Duration, u32)>,
+    /// What banners and announcements say.
+    pub messages: Messages,
+    /// Every block of a chained run in order, the first included; empty
+    /// for a single session.
+    pub chain: Vec<Block>,
+    /// Run just this phase, with no cycles, blocks, warm-up or cool-down
+    /// around it, if set: a one-off countdown.
+    pub single: Option<Phase>,
+    /// Run rounds of these phases in place of work and breaks, if set,
+    /// e.g. 45 seconds on and 15 off, ten times.
+    pub intervals: Option<Intervals>,
 }
 
-#[derive(Debug, Clone, Copy)]
-enum TimerState {
-    Running { end: Instant },
-    Paused { remaining: Duration },
+/// Longest phase (or snooze) a config may ask for.
+pub const MAX_PHASE: Duration = Duration::from_secs(24 * 60 * 60);
+/// Longest get-ready countdown before a work phase.
+pub const MAX_GET_READY: Duration = Duration::from_secs(60);
+/// Most cycles a single session may have.
+pub const MAX_CYCLES: u32 = 100;
+
+/// Reject a phase (or snooze) length of zero or over [`MAX_PHASE`];
+/// `what` names it in the error.
+pub fn check_length(what: &'static str, duration: Duration) -> Result<(), ConfigError> {
+    if duration.is_zero() {
+        return Err(ConfigError::ZeroDuration(what));
+    }
+    if duration > MAX_PHASE {
+        return Err(ConfigError::TooLong(what, duration));
+    }
+    Ok(())
 }
 
-impl TimerState {
-        fn toggle_pause(&mut self, now: Instant) {
-            *self = match *self {
-                TimerState::Running { end } => {
-                    let remaining = end.saturating_duration_since(now);
-                    TimerState::Paused { remaining }
-                }
-                TimerState::Paused { remaining } => {
-                    TimerState::Running { end: now + remaining }
-                }
-            };
-        }
+/// Seconds as MM:SS, or H:MM:SS from an hour up.
+pub fn format_time(total_secs: u64) -> String {
+    let (hh, mm, ss) = (total_secs / 3600, total_secs / 60 % 60, total_secs % 60);
+    if hh > 0 {
+        format!("{hh}:{mm:02}:{ss:02}")
+    } else {
+        format!("{mm:02}:{ss:02}")
+    }
+}
 
-        fn remaining(&self, now: Instant) -> Duration {
-            match *self {
-                TimerState::Running { end } => end.saturating_duration_since(now),
-                TimerState::Paused { remaining } => remaining,
+impl Config {
+    /// A builder starting from the defaults, for putting a config together
+    /// without going through every field.
+    ///
+    /// ```
+    /// let config = opomodoro::Config::builder().work_mins(50).break_mins(10).cycles(3).build()?;
+    /// assert_eq!(config.cycles, 3);
+    /// # Ok::<(), opomodoro::ConfigError>(())
+    /// ```
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    /// Reject configs that would run a degenerate or absurd timer.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut durations = vec![("work time", self.work_time), ("break time", self.break_time)];
+        if self.max_snoozes > 0 {
+            durations.push(("snooze", self.snooze));
+        }
+        if let Some((long_break, every)) = self.long_break {
+            if every == 0 {
+                return Err(ConfigError::NoLongBreakCycles);
             }
+            durations.push(("long break", long_break));
+        }
+        durations.extend(self.warm_up.map(|warm_up| ("warm-up", warm_up)));
+        durations.extend(self.cool_down.map(|cool_down| ("cool-down", cool_down)));
+        durations.extend(self.manual_advance.map(|(every, _)| ("alert repeat", every)));
+        durations.extend(self.single.as_ref().map(|phase| ("timer", phase.duration)));
+        if let Some(intervals) = &self.intervals {
+            if intervals.phases.is_empty() {
+                return Err(ConfigError::NoIntervals);
+            }
+            durations.extend(intervals.phases.iter().map(|phase| ("interval", phase.duration)));
+        }
+        for block in &self.chain {
+            durations.extend(block.work_time.map(|work| ("work time", work)));
+            durations.push(("break time", block.break_time));
+        }
+        let optional = [
+            ("daily target", self.targets.daily),
+            ("weekly target", self.targets.weekly),
+            ("work warning", self.work_warning),
+            ("break warning", self.break_warning),
+            ("get ready countdown", self.get_ready),
+        ];
+        for (what, duration) in optional {
+            if duration.is_some_and(|duration| duration.is_zero()) {
+                return Err(ConfigError::ZeroDuration(what));
+            }
+        }
+        for (what, duration) in durations {
+            check_length(what, duration)?;
+        }
+        if let Some(countdown) = self.get_ready.filter(|&countdown| countdown > MAX_GET_READY) {
+            return Err(ConfigError::LongCountdown(countdown));
+        }
+        let blocks = self.chain.iter().filter(|block| !block.is_break());
+        let rounds = self.intervals.as_ref().map(|intervals| intervals.rounds);
+        for cycles in std::iter::once(self.cycles).chain(blocks.map(|block| block.cycles)).chain(rounds) {
+            match cycles {
+                0 => return Err(ConfigError::NoCycles),
+                n if n > MAX_CYCLES => return Err(ConfigError::TooManyCycles(n)),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Puts a [`Config`] together a piece at a time; see [`Config::builder`].
+/// Whatever is not set keeps the default the config file has, and
+/// nothing is kept on disk: no history, state or task files.
+#[derive(Debug)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> ConfigBuilder {
+        let minutes = |mins: u64| Duration::from_secs(mins * 60);
+        ConfigBuilder {
+            config: Config {
+                work_time: minutes(25),
+                break_time: minutes(5),
+                cycles: settings::DEFAULT_CYCLES,
+                long_break: None,
+                late: false,
+                warm_up: None,
+                cool_down: None,
+                font: FontChoice::Auto,
+                view: ViewMode::Digits,
+                countdown: Countdown::Clock,
+                theme: ThemeChoice::default(),
+                no_color: false,
+                volume: settings::DEFAULT_VOLUME,
+                speak: false,
+                notify: false,
+                visual_bell: false,
+                history: None,
+                state: None,
+                tasks: None,
+                notes: false,
+                snooze: minutes(5),
+                max_snoozes: settings::DEFAULT_MAX_SNOOZES,
+                idle: None,
+                work_warning: None,
+                break_warning: None,
+                hard_break: None,
+                get_ready: None,
+                break_debt: BreakDebt::default(),
+                pause_budget: None,
+                strict: false,
+                block_commands: None,
+                ambient: None,
+                targets: Targets::default(),
+                task: None,
+                clock: HourFormat::Auto,
+                sidebar: false,
+                mascot: false,
+                animations: true,
+                spinner: true,
+                low_power: false,
+                end_of_day: None,
+                manual_advance: None,
+                messages: Messages::default(),
+                chain: Vec::new(),
+                single: None,
+                intervals: None,
+            },
+        }
+    }
+}
+
+impl ConfigBuilder {
+    pub fn work(mut self, work: Duration) -> ConfigBuilder {
+        self.config.work_time = work;
+        self
+    }
+
+    pub fn work_mins(self, mins: u64) -> ConfigBuilder {
+        self.work(Duration::from_secs(mins * 60))
+    }
+
+    pub fn break_time(mut self, break_time: Duration) -> ConfigBuilder {
+        self.config.break_time = break_time;
+        self
+    }
+
+    pub fn break_mins(self, mins: u64) -> ConfigBuilder {
+        self.break_time(Duration::from_secs(mins * 60))
+    }
+
+    pub fn cycles(mut self, cycles: u32) -> ConfigBuilder {
+        self.config.cycles = cycles;
+        self
+    }
+
+    /// Take a break of `mins` minutes in place of the usual one after every
+    /// `every` cycles, e.g. `long_break(15, 4)`.
+    pub fn long_break(mut self, mins: u64, every: u32) -> ConfigBuilder {
+        self.config.long_break = Some((Duration::from_secs(mins * 60), every));
+        self
+    }
+
+    /// Also take the break after the last work phase.
+    pub fn late(mut self, late: bool) -> ConfigBuilder {
+        self.config.late = late;
+        self
+    }
+
+    pub fn warm_up(mut self, warm_up: Duration) -> ConfigBuilder {
+        self.config.warm_up = Some(warm_up);
+        self
+    }
+
+    pub fn cool_down(mut self, cool_down: Duration) -> ConfigBuilder {
+        self.config.cool_down = Some(cool_down);
+        self
+    }
+
+    /// Keep the history in the file at `path`.
+    pub fn history(mut self, path: PathBuf) -> ConfigBuilder {
+        self.config.history = Some(path);
+        self
+    }
+
+    pub fn task(mut self, task: impl Into<String>) -> ConfigBuilder {
+        self.config.task = Some(task.into());
+        self
+    }
+
+    /// Run `phase` alone, e.g. a 37-minute `Custom("Standup prep")`.
+    pub fn single(mut self, phase: Phase) -> ConfigBuilder {
+        self.config.single = Some(phase);
+        self
+    }
+
+    /// Run rounds of `intervals` in place of work and breaks.
+    pub fn intervals(mut self, intervals: Intervals) -> ConfigBuilder {
+        self.config.intervals = Some(intervals);
+        self
+    }
+
+    /// The config, if it passes [`Config::validate`].
+    pub fn build(self) -> Result<Config, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
     }
 }
 
+#[cfg(feature = "terminal")]
 #[derive(Debug)]
 enum Action {
     Toggle,
     Skip,
+    SkipPhase,
+    SkipTo(SkipTarget),
+    /// New work and break lengths, for the phases still to start.
+    SetLengths(Option<Duration>, Option<Duration>),
+    StepLength { work: bool, longer: bool },
+    GotoPrompt,
+    GotoCycle(u32),
+    Snooze,
+    UndoSkip,
+    CycleView,
+    CycleCountdown,
+    ToggleMute,
+    AmbientLouder,
+    AmbientQuieter,
+    Interrupt,
+    LogInterruption(InterruptionSource, Option<String>),
+    /// Back from being away, with what the time away was.
+    Returned(Spent),
+    SaveNote(Option<String>),
+    AddTask(Task),
+    StartPlan,
+    /// Start a scheduled session without waiting for its time.
+    StartNow,
+    NextTask,
+    PreviousTask,
+    ToggleSidebar,
+    OpenSettings,
+    /// Step a setting up, or down.
+    AdjustOption(Field, bool),
+    /// Set a setting to what was typed in for it.
+    SetOption(Field, String),
+    SaveOptions,
+    ShowHelp,
+    CloseOverlay,
     Quit,
     None,
 }
 
+#[cfg(feature = "terminal")]
+#[derive(Debug)]
+enum AppScreen {
+    Timer,
+    Interruption { source: InterruptionSource, note: TextInput },
+    Note(TextInput),
+    SkipMenu { selected: usize },
+    GotoCycle(TextInput),
+    Plan(TextInput),
+    /// Back from being away: ask what the time was.
+    Away,
+    /// Counting down to a scheduled start.
+    Waiting(DateTime<Local>),
+    /// The keys and what they do.
+    Help,
+    /// The settings, with the line the cursor is on, a new value being
+    /// typed in, and how the last change went.
+    Settings { selected: usize, editing: Option<TextInput>, message: Option<String> },
+    /// The day is done: the summary, with confetti falling since then.
+    DayDone(Instant),
+}
+
+/// Entries of the skip menu, in display order.
+#[cfg(feature = "terminal")]
+const SKIP_CHOICES: [(SkipTarget, &str); 3] = [
+    (SkipTarget::Break, "Skip to break"),
+    (SkipTarget::NextWork, "Skip to next work"),
+    (SkipTarget::RestOfCycle, "Skip rest of cycle"),
+];
+
+/// What the screen shows that changes without any input: the frame is
+/// drawn again only when this does, or after a key or command.
+#[cfg(feature = "terminal")]
+#[derive(Debug, Clone, PartialEq)]
+struct Look {
+    size: Size,
+    screen: std::mem::Discriminant<AppScreen>,
+    remaining: u64,
+    phase: (usize, u32, PhaseKind),
+    paused: bool,
+    finished: bool,
+    /// The wall clock, to the minute on the timer and to the second on the
+    /// screens that count it.
+    clock: i64,
+    /// Whether a banner is up, and which way round it blinks.
+    banner: Option<bool>,
+    /// Seconds left to get ready for work, while counting down.
+    get_ready: Option<u64>,
+    warnings: usize,
+    /// Whether the visual bell is flashing, and which way round.
+    flash: Option<bool>,
+    spinner: Option<char>,
+}
+
+/// A heading, and the keys under it with what they do.
+#[cfg(feature = "terminal")]
+type KeyGroup = (&'static str, &'static [(&'static str, &'static str)]);
+
+/// The timer screen's keys by what they are for, as the help lists them.
+/// There is no remapping yet, so these are the keys `handle_key` matches.
+#[cfg(feature = "terminal")]
+const KEY_HELP: [KeyGroup; 5] = [
+    ("Timer", &[
+        ("p", "pause or resume"),
+        ("s", "skip ahead"),
+        ("g", "go to a cycle"),
+        ("z", "snooze the break"),
+        ("u", "undo a skip"),
+        ("[ ]", "shorter, longer work"),
+        ("{ }", "shorter, longer breaks"),
+        ("i", "log an interruption"),
+    ]),
+    ("Tasks", &[("n ↓", "next task"), ("↑", "previous task"), ("t", "show the task list")]),
+    ("Display", &[
+        ("v", "change the view"),
+        ("f", "change the countdown"),
+        ("o", "settings"),
+        ("?", "this help"),
+    ]),
+    ("Sound", &[("m", "mute alerts"), ("+ -", "ambient sound up, down")]),
+    ("Session", &[("q ^C", "quit")]),
+];
+
+/// How long a banner flashes across the timer.
+#[cfg(feature = "terminal")]
+const BANNER: Duration = Duration::from_secs(10);
+
+/// How long the splash between two phases plays.
+#[cfg(feature = "terminal")]
+const TRANSITION: Duration = Duration::from_millis(1200);
+
+/// How long the visual bell flashes the screen, inverted and back every
+/// [`FLASH_FRAME`].
+#[cfg(feature = "terminal")]
+const FLASH: Duration = Duration::from_millis(600);
+#[cfg(feature = "terminal")]
+const FLASH_FRAME: Duration = Duration::from_millis(150);
+
+/// The spinner's turns while paused, one a [`SPIN_FRAME`], which is the
+/// loop's usual tick, and what stands in for it while running.
+#[cfg(feature = "terminal")]
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+#[cfg(feature = "terminal")]
+const SPINNER_STILL: char = '⠿';
+#[cfg(feature = "terminal")]
+const SPIN_FRAME: Duration = Duration::from_millis(100);
+
+/// Longest the loop sleeps in low-power mode. Over 30s of a running release
+/// build on Linux, drawing every wakeup took 128ms of CPU time, drawing only
+/// what changed 45ms, and waking once a second on top of that 19ms. The
+/// cost is that control commands may take up to a second to act.
+#[cfg(feature = "terminal")]
+const LOW_POWER_TICK: Duration = Duration::from_secs(1);
+
+/// How often the idle time is checked.
+#[cfg(feature = "terminal")]
+const IDLE_POLL: Duration = Duration::from_secs(2);
+
+/// A gap in time longer than this between two turns of the loop means the
+/// machine slept or the process was stopped.
+#[cfg(feature = "terminal")]
+const TIME_JUMP: Duration = Duration::from_secs(60);
+
+/// A phase paused because the user walked away or the machine slept.
+#[cfg(feature = "terminal")]
+#[derive(Debug)]
+struct Away {
+    /// When input stopped, or the clock jumped from.
+    since: DateTime<Local>,
+    /// Idle time at the last check, until the user is back.
+    idle: Option<Duration>,
+}
+
+/// A phase held, in manual-advance mode, until the user is there to start it.
+#[cfg(feature = "terminal")]
+#[derive(Debug)]
+struct Held {
+    /// The phase that ran out, for the alert to be about.
+    ended: Phase,
+    /// When the alert last sounded.
+    alerted: Instant,
+    /// Times it has sounded again.
+    repeats: u32,
+}
+
+/// What the time away turned out to be.
+#[cfg(feature = "terminal")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Spent {
+    Work,
+    Break,
+    /// Neither; leave it out of the history.
+    Nothing,
+}
+
+/// Where the loop last was, to notice the clock jumping.
+#[cfg(feature = "terminal")]
+#[derive(Debug, Clone)]
+struct Stamp {
+    at: Instant,
+    wall: SystemTime,
+    cycle: u32,
+    phase: Phase,
+    remaining: Duration,
+}
+
+#[cfg(feature = "terminal")]
 #[derive(Debug, PartialEq)]
 enum EndState {
     None,
-    Completed,
-    Skipped,
-    Erred,
     Quit,
 }
 
 
+/// The frontend of a session: input, overlays, history and the state file
+/// around a [`Timer`], which is a local [`Engine`] unless attached elsewhere.
+#[cfg(feature = "terminal")]
 #[derive(Debug)]
-pub struct App<'a> {
-    current_cycle: u32,
-    num_cycles: u32,
-    work_time: Duration,
-    break_time: Duration,
-    phase: Phase<'a>,
-    timer_state: TimerState,
+pub struct App<'a, T: Timer = Engine> {
+    engine: T,
     end_state: EndState, 
     running: &'a AtomicBool,
     remaining: Duration,
-    late: bool,
+    font: FontChoice,
+    view: ViewMode,
+    countdown: Countdown,
+    alerts: Alerts,
+    screen: AppScreen,
+    history: Option<Box<dyn HistoryStore>>,
+    phase_started: DateTime<Local>,
+    interruptions: Vec<Interruption>,
+    /// Times the phase on now was paused by hand.
+    pauses: u32,
+    strict: bool,
+    /// The work phase on now was paused or interrupted in strict mode, and
+    /// no longer counts.
+    voided: bool,
+    session: Vec<Entry>,
+    warnings: Vec<String>,
+    notes: bool,
+    pending_note: Option<Entry>,
+    /// A skipped phase, held back while the skip can still be undone.
+    skipped: Option<Entry>,
+    task: Option<String>,
+    plan: Option<Plan>,
+    task_file: Option<TaskFile>,
+    sidebar: bool,
+    mascot: bool,
+    animations: bool,
+    low_power: bool,
+    /// The blocks of a chained run, and which of them is on.
+    chain: Vec<Block>,
+    block: usize,
+    /// The interval set the session runs, for the chime each phase starts with.
+    intervals: Option<Intervals>,
+    /// When the phase on screen last changed, for the splash.
+    transition: Option<Instant>,
+    visual_bell: bool,
+    /// When the visual bell last went off.
+    flash: Option<Instant>,
+    spinner: bool,
+    /// What the spinner counts its turns from.
+    spun_from: Instant,
+    /// The cycle and phase drawn last.
+    shown_phase: Option<(u32, PhaseKind)>,
+    /// What the last frame showed, and whether input since calls for
+    /// another.
+    drawn: Option<Look>,
+    redraw: bool,
+    /// The block, cycle and phase last reported to the integrations as
+    /// started.
+    started_phase: Option<(usize, u32, PhaseKind)>,
+    targets: Targets,
+    /// Work this week from before the session: when, and how long.
+    past_focus: Vec<(DateTime<Local>, Duration)>,
+    /// When a banner was raised, and its message.
+    banner: Option<(Instant, String)>,
+    /// Warn this long before a work phase or a break ends.
+    work_warning: Option<Duration>,
+    break_warning: Option<Duration>,
+    /// Whether the phase has yet to cross its warning time.
+    warning_armed: bool,
+    messages: Messages,
+    /// Breaks take the whole screen and can't be skipped this early on.
+    hard_break: Option<Duration>,
+    /// Blocks distractions while work is running; only a local engine has one.
+    blocker: Option<Blocker>,
+    /// Plays while work is running; likewise only for a local engine.
+    ambient: Option<Ambient>,
+    /// Pauses work phases after the user has been idle this long.
+    idle: Option<(IdleMonitor, Duration)>,
+    idle_checked: Option<Instant>,
+    /// Ask about clock jumps; only a local engine can be corrected.
+    watch_clock: bool,
+    stamp: Option<Stamp>,
+    away: Option<Away>,
+    /// Only watching: the keys that would change the session do nothing.
+    spectator: bool,
+    integrations: Integrations,
+    input: Box<dyn InputSource>,
+    clock: HourFormat,
+    theme: Theme,
+    no_color: bool,
+    /// Show a desktop notification as each phase runs out.
+    notify_desktop: bool,
+    state_file: Option<StateFile>,
+    /// Where to call the day done once the session runs its course.
+    day_file: Option<DayFile>,
+    /// How often to sound the alert again for a held phase, and how many
+    /// times, in manual-advance mode.
+    manual_advance: Option<(Duration, u32)>,
+    held: Option<Held>,
+    last_saved: Option<Instant>,
+    saved_for_resume: bool,
+    #[cfg(any(unix, windows))]
+    control: Option<ipc::ControlServer>,
+    /// When watchers were last sent a snapshot, and which.
+    #[cfg(any(unix, windows))]
+    published: Option<(Instant, ipc::Snapshot)>,
+    /// The service manager to tell how the daemon is doing.
+    #[cfg(unix)]
+    supervisor: Option<systemd::Notifier>,
 }
 
+#[cfg(feature = "terminal")]
 impl<'a> App<'a> {
-    pub fn run (&mut self, 
-        terminal: &mut DefaultTerminal
-    ) -> io::Result<()> {
+    pub fn new (
+    config: Config,
+    running: &'a AtomicBool,
+    ) -> App<'a> {
+        let engine = Engine::new(&config);
+        let blocker = config.block_commands.clone().map(|(block, unblock)| Blocker::new(block, unblock));
+        let ambient = config.ambient;
+        let mut app = App::with_engine(config, engine, running);
+        app.remaining = app.engine.remaining();
+        app.watch_clock = true;
+        app.blocker = blocker;
+        if let Some((sound, volume)) = ambient {
+            match Ambient::open(sound, volume) {
+                Ok(ambient) => app.ambient = Some(ambient),
+                Err(e) => app.warn(format!("No ambient sound: {e}")),
+            }
+        }
+        app
+    }
+
+    /// Pick up a session that was quit early where it left off.
+    pub fn resume(&mut self, state: &SessionState) {
+        self.engine.restore(state.cycle, state.phase.clone(), state.remaining());
+        self.remaining = self.engine.remaining();
+        info!(cycle = state.cycle, phase = %state.phase, "resumed session");
+    }
+}
+
+#[cfg(feature = "terminal")]
+impl<'a, T: Timer> App<'a, T> {
+    pub fn run<B: Backend> (&mut self, 
+        terminal: &mut Terminal<B>
+    ) -> error::Result<()>
+    where
+        B::Error: Send + Sync + 'static,
+    {
+        let mut failure = None;
          {
             while self.end_state != EndState::Quit {
+                let now = self.engine.now();
                 if ! self.running.load(Ordering::Relaxed) {
-                    self.end_state = EndState::Quit;
+                    info!("shutting down on signal");
+                    self.shutdown();
                     break;
                 }
-                let now = Instant::now();
-                self.remaining = self.timer_state.remaining(now);
-                let action = self.handle_input();
-                self.apply_action(action, now);
-                self.update(now);
-                terminal.draw(|frame| self.draw(frame))?;
+                self.remaining = self.engine.remaining();
+                self.sync_state(now);
+                let action = match self.handle_input() {
+                    Ok(action) => action,
+                    Err(e) => {
+                        warn!("input failed: {e}");
+                        self.shutdown();
+                        failure = Some(e);
+                        break;
+                    }
+                };
+                if !matches!(action, Action::None) {
+                    debug!(?action, "input");
+                    // Reflect the action in the state file right away.
+                    self.last_saved = None;
+                }
+                self.apply_action(action);
+                #[cfg(any(unix, windows))]
+                while let Some(pending) = self.control.as_ref().and_then(|c| c.try_next()) {
+                    self.answer(pending);
+                    self.redraw = true;
+                }
+                self.check_away(now);
+                self.update();
+                self.stamp();
+                #[cfg(any(unix, windows))]
+                self.publish(now);
+                self.watch_phase(now);
+                let look = self.look(terminal.size().map_err(OpomodoroError::terminal)?);
+                let moving = self.transition().is_some() || self.celebrating();
+                if self.redraw || moving || self.drawn.as_ref() != Some(&look) {
+                    terminal
+                        .draw(|frame| ui::render(frame, self))
+                        .map_err(OpomodoroError::terminal)?;
+                    self.drawn = Some(look);
+                    self.redraw = false;
+                }
             };
         }
-        Ok(())
+        self.finish();
+        failure.map_or(Ok(()), Err)
     }
 
-    pub fn new (
+    /// Notice the user walking away or the machine sleeping, and ask what
+    /// the time in between was once they are back.
+    fn check_away(&mut self, now: Instant) {
+        self.check_idle(now);
+        self.check_clock();
+        let Some(away) = &self.away else {
+            return;
+        };
+        if !self.engine.is_paused() {
+            // Skipped, quit or unpaused by hand in the meantime.
+            self.away = None;
+        } else if away.idle.is_none() && matches!(self.screen, AppScreen::Timer) {
+            self.screen = AppScreen::Away;
+        }
+    }
+
+    /// Pause a running work phase once the user has been idle for the
+    /// configured time, giving that time back.
+    fn check_idle(&mut self, now: Instant) {
+        let Some((monitor, after)) = &self.idle else {
+            return;
+        };
+        if self.idle_checked.is_some_and(|at| now.duration_since(at) < IDLE_POLL) {
+            return;
+        }
+        self.idle_checked = Some(now);
+        let Some(idle) = monitor.idle_time() else {
+            return;
+        };
+        match &mut self.away {
+            None => {
+                let working = self.engine.phase().is_work() && !self.engine.is_paused();
+                if !working || idle < *after || !matches!(self.screen, AppScreen::Timer) {
+                    return;
+                }
+                self.engine.toggle_pause();
+                self.engine.set_remaining(self.engine.remaining() + idle);
+                let since = Local::now() - idle;
+                info!(%since, "idle, pausing");
+                self.away = Some(Away { since, idle: Some(idle) });
+            }
+            Some(Away { idle: Some(last), .. }) if idle >= *last => *last = idle,
+            // Any input since the last check means they are back.
+            Some(away) => away.idle = None,
+        }
+    }
+
+    /// Pause a running phase the clock jumped ahead in, taking back the
+    /// time the timer counted meanwhile.
+    fn check_clock(&mut self) {
+        let Some(stamp) = self.stamp.take() else {
+            return;
+        };
+        let engine = &self.engine;
+        if !self.watch_clock || engine.is_paused() || engine.is_finished() {
+            return;
+        }
+        let counted = engine.now().saturating_duration_since(stamp.at);
+        // The monotonic clock stops while the machine sleeps; the wall clock
+        // does not.
+        let gone = SystemTime::now().duration_since(stamp.wall).unwrap_or_default().max(counted);
+        if gone < TIME_JUMP {
+            return;
+        }
+        self.engine.toggle_pause();
+        if (stamp.cycle, stamp.phase) == (self.engine.cycle(), self.engine.phase()) {
+            self.engine.set_remaining(stamp.remaining);
+        }
+        let since = Local::now() - gone;
+        info!(%since, "clock jumped, pausing");
+        self.away = Some(Away { since, idle: None });
+    }
+
+    /// Start the splash when the phase on screen changes.
+    fn watch_phase(&mut self, now: Instant) {
+        let phase = (self.engine.cycle(), self.engine.phase().kind);
+        let changed = self.shown_phase.replace(phase.clone()).is_some_and(|shown| shown != phase);
+        if changed && self.animations && !self.engine.is_finished() {
+            self.transition = Some(now);
+        }
+    }
+
+    /// What a frame drawn now would show, as far as it changes by itself.
+    fn look(&self, size: Size) -> Look {
+        let engine = &self.engine;
+        let wall = Local::now();
+        let clock = match self.screen {
+            AppScreen::Timer => wall.timestamp() / 60,
+            _ => wall.timestamp(),
+        };
+        Look {
+            size,
+            screen: std::mem::discriminant(&self.screen),
+            remaining: self.remaining.as_secs(),
+            phase: (self.block, engine.cycle(), engine.phase().kind),
+            paused: engine.is_paused(),
+            finished: engine.is_finished(),
+            clock,
+            banner: self.banner().map(|_| !self.low_power && wall.timestamp_subsec_millis() < 500),
+            get_ready: engine.get_ready_left().map(|left| left.as_secs()),
+            warnings: self.warnings.len(),
+            flash: self.flashing(),
+            spinner: self.spinner(),
+        }
+    }
+
+    /// Whether confetti is falling, which moves on every frame.
+    fn celebrating(&self) -> bool {
+        self.animations && matches!(self.screen, AppScreen::DayDone(_))
+    }
+
+    /// How far along the splash is, from 0 to 1, while it plays.
+    fn transition(&self) -> Option<f64> {
+        let elapsed = self.engine.now().saturating_duration_since(self.transition?);
+        (elapsed < TRANSITION).then(|| elapsed.as_secs_f64() / TRANSITION.as_secs_f64())
+    }
+
+    /// Remember where the loop is, for [`App::check_clock`].
+    fn stamp(&mut self) {
+        let engine = &self.engine;
+        self.stamp = Some(Stamp {
+            at: engine.now(),
+            wall: SystemTime::now(),
+            cycle: engine.cycle(),
+            phase: engine.phase(),
+            remaining: engine.remaining(),
+        });
+    }
+
+    /// Take requests from other processes on `server` while running.
+    #[cfg(any(unix, windows))]
+    pub fn listen(&mut self, server: ipc::ControlServer) {
+        self.control = Some(server);
+    }
+
+    /// Keep `notifier`'s watchdog fed while serving, and say when the
+    /// daemon stops.
+    #[cfg(unix)]
+    pub fn supervise(&mut self, notifier: systemd::Notifier) {
+        self.supervisor = Some(notifier);
+    }
+
+    /// Run the session without a terminal, taking actions from control
+    /// socket clients instead of the keyboard.
+    #[cfg(any(unix, windows))]
+    pub fn serve(&mut self, server: ipc::ControlServer) {
+        self.listen(server);
+        while self.end_state != EndState::Quit {
+            if ! self.running.load(Ordering::Relaxed) {
+                info!("shutting down on signal");
+                #[cfg(unix)]
+                if let Some(supervisor) = &self.supervisor {
+                    supervisor.stopping();
+                }
+                self.shutdown();
+                break;
+            }
+            let now = self.engine.now();
+            #[cfg(unix)]
+            if let Some(supervisor) = &mut self.supervisor {
+                supervisor.keep_alive(now);
+            }
+            self.remaining = self.engine.remaining();
+            self.sync_state(now);
+            let pending = self.control.as_ref().and_then(|c| c.next(Duration::from_millis(100)));
+            if let Some(pending) = pending {
+                self.answer(pending);
+            }
+            self.update();
+            // Nobody is looking to celebrate; the day is done all the same.
+            if matches!(self.screen, AppScreen::DayDone(_)) {
+                self.end_state = EndState::Quit;
+            }
+            self.publish(now);
+        }
+        self.finish();
+    }
+
+    /// Apply a control socket request and reply with the resulting state.
+    #[cfg(any(unix, windows))]
+    fn answer(&mut self, pending: ipc::Pending) {
+        use ipc::Request;
+        debug!(request = ?pending.request, "control request");
+        let paused = self.engine.is_paused();
+        let action = match pending.request.clone() {
+            Request::Snapshot => Action::None,
+            Request::Toggle => Action::Toggle,
+            Request::Pause if !paused => Action::Toggle,
+            Request::Unpause if paused => Action::Toggle,
+            Request::Pause | Request::Unpause => Action::None,
+            Request::Skip => Action::SkipPhase,
+            Request::SkipTo { target } => Action::SkipTo(target),
+            Request::SetLengths { work, break_time } => Action::SetLengths(work, break_time),
+            Request::GotoCycle { cycle } => Action::GotoCycle(cycle),
+            Request::Snooze => Action::Snooze,
+            Request::UndoSkip => Action::UndoSkip,
+            Request::Interrupt { source, note } => Action::LogInterruption(source, note),
+        };
+        if !matches!(action, Action::None) {
+            self.last_saved = None;
+        }
+        self.apply_action(action);
+        self.update();
+        self.remaining = self.engine.remaining();
+        pending.reply(self.snapshot());
+    }
+
+    /// Send watchers the session's state every second, and right away when
+    /// it changes.
+    #[cfg(any(unix, windows))]
+    fn publish(&mut self, now: Instant) {
+        let Some(control) = self.control.as_ref().filter(|c| c.is_watched()) else {
+            return;
+        };
+        let snapshot = self.snapshot();
+        let due = self.published.as_ref().is_none_or(|(at, last)| {
+            now.duration_since(*at) >= Duration::from_secs(1) || !last.same_state(&snapshot)
+        });
+        if due {
+            control.publish(&snapshot);
+            self.published = Some((now, snapshot));
+        }
+    }
+
+    /// The session as reported to control socket clients.
+    #[cfg(any(unix, windows))]
+    fn snapshot(&self) -> ipc::Snapshot {
+        let engine = &self.engine;
+        let phase = engine.phase();
+        ipc::Snapshot {
+            phase: phase.kind,
+            phase_duration: phase.duration,
+            cycle: engine.cycle(),
+            num_cycles: engine.num_cycles(),
+            remaining: engine.remaining(),
+            paused: engine.is_paused(),
+            finished: engine.is_finished(),
+            late: engine.late(),
+            work_time: engine.work_time(),
+            break_time: engine.break_time(),
+            grace_left: engine.grace_left(),
+            undo_left: engine.undo_left(),
+            get_ready_left: engine.get_ready_left(),
+            break_debt: engine.break_debt(),
+            cool_down: engine.cool_down(),
+            long_break: engine.long_break(),
+            completed: self
+                .session
+                .iter()
+                .filter(|entry| entry.outcome == Outcome::Completed)
+                .count(),
+        }
+    }
+
+    /// Flush what is left once the session loop ends.
+    fn finish(&mut self) {
+        if let Some(blocker) = &mut self.blocker
+            && let Err(e) = blocker.set(false) {
+            self.warn(format!("Could not unblock: {e}"));
+        }
+        // Stop the sound and let go of the audio device.
+        self.ambient = None;
+        if let Some(entry) = self.pending_note.take() {
+            self.save_entry(entry);
+        }
+        if let Some(entry) = self.skipped.take() {
+            self.save_entry(entry);
+        }
+        #[cfg(any(unix, windows))]
+        self.publish(self.engine.now());
+        let summary = self.summary();
+        let problems = self.integrations.session_ended(&summary);
+        self.notify(problems);
+        if !self.saved_for_resume
+            && let Some(file) = &self.state_file
+            && let Err(e) = file.clear() {
+            let message = format!(
+                "Could not remove session state {}: {e}",
+                file.path().display()
+            );
+            self.warn(message);
+        }
+    }
+
+    /// An app around any [`Timer`], e.g. a session in another process.
+    pub fn with_engine (
     config: Config,
+    engine: T,
     running: &'a AtomicBool,
-    ) -> App<'a> {
-        let current_cycle: u32 = 1;
-        let num_cycles = config.cycles;
-        let work_time = config.work_time;
-        let break_time = config.break_time;
-        let phase = Phase::build("Work", work_time);
-        let timer_state = TimerState::Running 
-            { end: Instant::now() + work_time  };
+    ) -> App<'a, T> {
         let end_state = EndState::None;
-        let late = config.late;
-        let remaining = work_time;
-        App {
-            current_cycle, 
-            num_cycles, 
-            work_time, 
-            break_time,
-            phase,
-            timer_state,
+        let font = config.font;
+        let view = config.view;
+        let speech = config.speak.then(Speech::detect).flatten();
+        let no_speech = config.speak && speech.is_none();
+        let alerts = Alerts::new(config.volume, speech);
+        let remaining = config.work_time;
+        let spun_from = engine.now();
+        let mut app = App {
+            engine,
             end_state,
             running,
             remaining,
-            late, 
+            font,
+            view,
+            countdown: config.countdown,
+            alerts,
+            screen: AppScreen::Timer,
+            history: None,
+            phase_started: Local::now(),
+            interruptions: Vec::new(),
+            pauses: 0,
+            strict: config.strict,
+            voided: false,
+            session: Vec::new(),
+            warnings: Vec::new(),
+            notes: config.notes,
+            pending_note: None,
+            skipped: None,
+            task: config.task,
+            plan: None,
+            task_file: config.tasks.map(TaskFile::new),
+            sidebar: config.sidebar,
+            mascot: config.mascot,
+            animations: config.animations && !config.low_power,
+            low_power: config.low_power,
+            chain: config.chain,
+            block: 0,
+            intervals: config.intervals,
+            transition: None,
+            visual_bell: config.visual_bell,
+            flash: None,
+            spinner: config.spinner,
+            spun_from,
+            drawn: None,
+            redraw: true,
+            shown_phase: None,
+            started_phase: None,
+            targets: config.targets,
+            past_focus: Vec::new(),
+            banner: None,
+            work_warning: config.work_warning,
+            break_warning: config.break_warning,
+            warning_armed: false,
+            messages: config.messages,
+            hard_break: config.hard_break,
+            blocker: None,
+            ambient: None,
+            idle: None,
+            idle_checked: None,
+            watch_clock: false,
+            stamp: None,
+            away: None,
+            spectator: false,
+            integrations: Integrations::default(),
+            input: Box::new(TerminalInput),
+            clock: config.clock.resolve(),
+            theme: config.theme.theme(),
+            no_color: config.no_color,
+            notify_desktop: config.notify,
+            state_file: config.state.map(StateFile::new),
+            day_file: config.end_of_day.map(DayFile::new),
+            manual_advance: config.manual_advance,
+            held: None,
+            last_saved: None,
+            saved_for_resume: false,
+            #[cfg(any(unix, windows))]
+            control: None,
+            #[cfg(any(unix, windows))]
+            published: None,
+            #[cfg(unix)]
+            supervisor: None,
+        };
+        if no_speech {
+            app.warn("Nothing to speak with: install espeak-ng, and run locally rather than over SSH".to_string());
+        }
+        if let Some(path) = config.history {
+            match history::open(path.clone()) {
+                Ok(store) => app.history = Some(store),
+                Err(e) => app.warn(format!("Could not open history at {}: {e}", path.display())),
+            }
+        }
+        app.load_plan();
+        app.load_focus();
+        if let Some(after) = config.idle {
+            match IdleMonitor::detect() {
+                Some(monitor) => app.idle = Some((monitor, after)),
+                None => app.warn(
+                    "Could not tell when you are idle here; work phases will not pause by themselves"
+                        .to_string(),
+                ),
+            }
         }
+        app
+    }
+
+    /// Keep a problem for after the TUI closes, and log it right away.
+    fn warn(&mut self, message: String) {
+        warn!("{message}");
+        self.warnings.push(message);
     }
 
-    fn draw(&self, frame: &mut Frame) {
+    /// Pick up the tasks planned earlier today.
+    fn load_plan(&mut self) {
+        let Some(file) = &self.task_file else {
+            return;
+        };
+        match file.load_today() {
+            Ok(plan) => self.plan = plan,
+            Err(e) => {
+                let message = format!("Could not read tasks from {}: {e}", file.path().display());
+                self.warn(message);
+            }
+        }
+    }
+
+    /// Pick up this week's focus time from the history, for the targets.
+    fn load_focus(&mut self) {
+        let Some(history) = self.history.as_ref().filter(|_| !self.targets.is_empty()) else {
+            return;
+        };
+        let now = Local::now();
+        let monday = now.date_naive().week(chrono::Weekday::Mon).first_day();
+        match history.load_since(history::start_of(monday)) {
+            Ok(entries) => {
+                self.past_focus = entries
+                    .iter()
+                    .filter(|entry| entry.is_work() && entry.started.iso_week() == now.iso_week())
+                    .map(|entry| (entry.started, Duration::from_secs(entry.elapsed_secs)))
+                    .collect();
+            }
+            Err(e) => {
+                let message = format!("Could not read history from {}: {e}", history.path().display());
+                self.warn(message);
+            }
+        }
+    }
+
+    /// The focus targets and progress towards them, if there are targets and
+    /// a history to count from.
+    fn focus_targets(&self) -> Option<(Targets, Focus)> {
+        (!self.targets.is_empty() && self.history.is_some()).then(|| (self.targets, self.focus()))
+    }
+
+    /// Focus time today and this week, this session's included as it goes.
+    fn focus(&self) -> Focus {
+        let now = Local::now();
+        let mut focus = Focus::default();
+        for &(at, time) in &self.past_focus {
+            focus.add(at, time, now);
+        }
+        let held = self.pending_note.iter().chain(&self.skipped);
+        for entry in self.session.iter().chain(held).filter(|e| e.is_work()) {
+            focus.add(entry.started, Duration::from_secs(entry.elapsed_secs), now);
+        }
+        let phase = self.engine.phase();
+        if phase.is_work() && !self.engine.is_finished() {
+            focus.add(self.phase_started, phase.duration.saturating_sub(self.remaining), now);
+        }
+        focus
+    }
+
+    fn save_plan(&mut self) {
+        let (Some(file), Some(plan)) = (&self.task_file, &self.plan) else {
+            return;
+        };
+        if let Err(e) = file.save(plan) {
+            let message = format!("Could not write tasks to {}: {e}", file.path().display());
+            self.warn(message);
+            self.task_file = None;
+        }
+    }
+
+    fn save_state(&mut self, active: bool) {
+        let Some(file) = &self.state_file else {
+            return;
+        };
+        let engine = &self.engine;
+        let state = SessionState {
+            updated: Local::now(),
+            active,
+            phase: engine.phase().kind,
+            cycle: engine.cycle(),
+            num_cycles: engine.num_cycles(),
+            remaining_secs: self.remaining.as_secs(),
+            paused: engine.is_paused(),
+            work_secs: engine.work_time().as_secs(),
+            break_secs: engine.break_time().as_secs(),
+            late: engine.late(),
+        };
+        if let Err(e) = file.save(&state) {
+            // Give up on the state file rather than failing every tick.
+            let message = format!(
+                "Could not write session state to {}: {e}",
+                file.path().display()
+            );
+            self.warn(message);
+            self.state_file = None;
+        }
+    }
+
+    /// Keep the running session's state file roughly a second fresh.
+    fn sync_state(&mut self, now: Instant) {
+        if self.last_saved.is_none_or(|at| now.duration_since(at) >= Duration::from_secs(1)) {
+            self.save_state(true);
+            self.last_saved = Some(now);
+        }
+    }
+
+    fn save_for_resume(&mut self) {
+        self.save_state(false);
+        self.saved_for_resume = true;
+    }
+
+    /// Totals for the phases finished during this run.
+    pub fn summary(&self) -> Summary {
+        Summary::from_entries(&self.session)
+    }
+
+    /// How focused this run was, by the phases finished during it.
+    pub fn focus_score(&self) -> FocusScore {
+        FocusScore::from_entries(&self.session)
+    }
+
+    /// Show the session without offering any way to change it, e.g. on a
+    /// projector.
+    pub fn spectate(&mut self) {
+        self.spectator = true;
+    }
+
+    /// Open the planning screen, holding the timer until the plan is done.
+    pub fn plan_session(&mut self) {
+        if !self.engine.is_paused() {
+            self.engine.toggle_pause();
+        }
+        self.plan.get_or_insert_default();
+        self.screen = AppScreen::Plan(TextInput::default());
+    }
+
+    /// Hold the timer on a countdown screen until `at`, then start.
+    pub fn start_at(&mut self, at: DateTime<Local>) {
+        if !self.engine.is_paused() {
+            self.engine.toggle_pause();
+        }
+        self.screen = AppScreen::Waiting(at);
+    }
+
+    /// Start the session once its scheduled time has come.
+    fn check_start(&mut self) {
+        if let AppScreen::Waiting(at) = self.screen
+            && Local::now() >= at {
+            info!("scheduled start");
+            self.ring(0);
+            self.apply_action(Action::StartNow);
+        }
+    }
+
+    /// Work through `plan`'s tasks, in place of any planned earlier.
+    pub fn set_plan(&mut self, plan: Plan) {
+        self.plan = (!plan.is_empty()).then_some(plan);
+        self.save_plan();
+    }
+
+    /// Report the session's phases and summary to `integrations`.
+    pub fn set_integrations(&mut self, integrations: Integrations) {
+        self.integrations = integrations;
+    }
+
+    /// Take keys from `input` rather than the terminal, e.g. a
+    /// [`ReplayInput`](input::ReplayInput).
+    pub fn set_input(&mut self, input: impl InputSource + 'static) {
+        self.input = Box::new(input);
+    }
+
+    /// The tasks planned for the session, with the pomodoros they took.
+    pub fn plan(&self) -> Option<&Plan> {
+        self.plan.as_ref()
+    }
+
+    /// Label for the work phase: the planned task, or the configured one.
+    fn current_task(&self) -> Option<&str> {
+        match self.plan.as_ref().and_then(Plan::current) {
+            Some(task) => Some(&task.name),
+            None => self.task.as_deref(),
+        }
+    }
+
+    /// Work now goes to a planned task that has used up its estimate.
+    fn over_budget(&self) -> bool {
+        self.plan.as_ref().and_then(Plan::current).is_some_and(Task::is_done)
+    }
+
+    /// In strict mode, void the work phase on now for being `why`, once it
+    /// has started: it runs on, but is recorded as voided.
+    fn void(&mut self, why: &str) {
+        let phase = self.engine.phase();
+        let started = self.engine.remaining() < phase.duration;
+        if !self.strict || self.voided || !phase.is_work() || !started {
+            return;
+        }
+        self.voided = true;
+        let message = format!("Pomodoro voided: {why}");
+        info!("{message}");
+        self.ring(0);
+        self.banner = Some((self.engine.now(), message));
+    }
+
+    /// The banner to flash across the timer, if it is still showing.
+    fn banner(&self) -> Option<&str> {
+        let (at, message) = self.banner.as_ref()?;
+        let shown = self.engine.now().saturating_duration_since(*at) < BANNER;
+        shown.then_some(message.as_str())
+    }
+
+    /// Whether a work phase is running, neither paused nor over.
+    fn is_working(&self) -> bool {
+        let engine = &self.engine;
+        engine.phase().is_work() && !engine.is_paused() && !engine.is_finished()
+    }
+
+    /// Play the ambient sound while work is running, and only then.
+    fn sync_ambient(&mut self) {
+        let working = self.is_working();
+        if let Some(ambient) = &mut self.ambient {
+            ambient.set(working);
+        }
+    }
+
+    /// The ambient sound's volume, if one is playing this session.
+    fn ambient_volume(&self) -> Option<u8> {
+        self.ambient.as_ref().map(Ambient::volume)
+    }
+
+    /// Block distractions while work is running, and only then.
+    fn sync_blocker(&mut self) {
+        let working = self.is_working();
+        if let Some(blocker) = &mut self.blocker
+            && let Err(e) = blocker.set(working) {
+            self.warn(format!("Could not {}: {e}", if working { "block" } else { "unblock" }));
+        }
+    }
+
+    /// Tell the integrations about each phase once, as soon as it runs.
+    fn check_phase_started(&mut self) {
+        let engine = &self.engine;
+        if engine.is_paused() || engine.is_finished() {
+            return;
+        }
+        let phase = engine.phase();
+        let started = Some((self.block, engine.cycle(), phase.kind.clone()));
+        if std::mem::replace(&mut self.started_phase, started.clone()) != started {
+            let problems = self.integrations.phase_started(&phase);
+            self.notify(problems);
+            let announcement = self.messages.phase_start.render(&self.template_vars(None, true));
+            self.alerts.say(&announcement);
+        }
+    }
+
+    /// Values for the message templates as things stand, or as `ended`
+    /// gives way to the phase on now. Durations are written out in words
+    /// for `speech` and kept short for the screen.
+    fn template_vars(&self, ended: Option<Phase>, speech: bool) -> Vars {
+        let time = |duration: Duration| {
+            let duration = Duration::from_secs(duration.as_secs_f64().round() as u64);
+            if speech { spoken(duration) } else { humantime::format_duration(duration).to_string() }
+        };
+        let engine = &self.engine;
+        let (phase, remaining, next) = match ended {
+            Some(ended) => {
+                let now = (!engine.is_finished()).then(|| engine.phase());
+                (ended, Duration::ZERO, now)
+            }
+            None => (engine.phase(), engine.remaining(), engine.next_phase()),
+        };
+        let mut vars = Vars::default();
+        vars.set("phase", phase.kind)
+            .set("duration", time(phase.duration))
+            .set("remaining", time(remaining))
+            .set("cycle", engine.cycle())
+            .set("cycles", engine.num_cycles());
+        if let Some(next) = next {
+            vars.set("next_phase", next.kind).set("next_duration", time(next.duration));
+        }
+        if let Some(task) = self.current_task() {
+            vars.set("task", task);
+        }
+        if let Some((_, block)) = self.current_block() {
+            vars.set("block", block.label());
+        }
+        vars
+    }
+
+    /// Whether the break on now takes the whole screen.
+    fn in_hard_break(&self) -> bool {
+        self.hard_break.is_some() && self.engine.phase().is_break() && !self.engine.is_finished()
+    }
+
+    /// Time left before the break on now may be skipped, in a hard break.
+    fn hard_break_left(&self) -> Option<Duration> {
+        if !self.in_hard_break() {
+            return None;
+        }
+        let elapsed = self.engine.phase().duration.saturating_sub(self.engine.remaining());
+        self.hard_break?.checked_sub(elapsed).filter(|left| !left.is_zero())
+    }
+
+    /// Ring and raise a banner once the phase gets within its warning time
+    /// of the end. Only a phase seen with more time left than that is
+    /// armed, so starting or resuming inside the window stays quiet.
+    fn check_warning(&mut self) {
+        let phase = self.engine.phase();
+        let warning = match phase.kind {
+            PhaseKind::Work => self.work_warning,
+            PhaseKind::ShortBreak | PhaseKind::LongBreak => self.break_warning,
+            PhaseKind::WarmUp | PhaseKind::CoolDown | PhaseKind::Custom(_) => None,
+        };
+        let Some(warning) = warning.filter(|_| !self.engine.is_finished()) else {
+            return;
+        };
+        let remaining = self.engine.remaining();
+        if remaining > warning {
+            self.warning_armed = true;
+        } else if std::mem::take(&mut self.warning_armed) && !remaining.is_zero() {
+            let template = if phase.is_work() {
+                &self.messages.work_warning
+            } else {
+                &self.messages.break_warning
+            };
+            let message = template.render(&self.template_vars(None, false));
+            info!("{message}");
+            self.ring(0);
+            self.banner = Some((self.engine.now(), message));
+        }
+    }
+
+    /// Non-fatal problems hit while the TUI was active, for printing afterwards.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Act on `key` as though it were pressed at the terminal.
+    pub fn press(&mut self, key: KeyEvent) {
+        let action = self.handle_key(key);
+        self.apply_action(action);
+    }
+
+    /// Catch up with the engine and draw a frame, as the run loop does, e.g.
+    /// onto a ratatui `TestBackend`.
+    pub fn draw(&mut self, frame: &mut Frame) {
+        self.remaining = self.engine.remaining();
+        self.watch_phase(self.engine.now());
         ui::render(frame, self);
     }
 
-    fn handle_input(&mut self) -> Action {
-        match poll(Duration::from_millis(100)) {
-            Ok(true) => {
-                let read_event = match read() {
-                    Ok(ev) => ev,
-                    Err(e) => {
-                        eprintln!("Error reading event: {e}");
-                        self.end_state = EndState::Erred;
-                        return Action::Quit;
+    fn handle_events(&mut self, events: Vec<Event>) {
+        for event in events {
+            debug!(?event, "engine event");
+            match event {
+                Event::PhaseEnded { phase, cycle, elapsed, outcome, extension } => {
+                    self.record_phase(phase.clone(), cycle, elapsed, outcome, extension);
+                    let next = self.engine.phase();
+                    let usual = self.engine.break_after(self.engine.cycle()).duration;
+                    if next.is_break() && next.duration > usual {
+                        let extra = (next.duration - usual).as_secs_f64().round();
+                        let extra = humantime::format_duration(Duration::from_secs(extra as u64));
+                        let message = format!("Break extended by {extra} to make up for skipped ones");
+                        info!("{message}");
+                        self.banner = Some((self.engine.now(), message));
                     }
-                };
-
-                if let Some(key) = read_event.as_key_press_event() {
-                    match key.code {
-                        KeyCode::Char('p') => {
-                            return Action::Toggle;
-                        }
-                        KeyCode::Char('s') => {
-                            return Action::Skip;
-                        }
-                        KeyCode::Char('q') => {
-                            return Action::Quit;
-                        }
-                        KeyCode::Char('c') => {
-                            if key.modifiers.contains(KeyModifiers::CONTROL) {
-                                return Action::Quit; 
-                            }
-                            return Action::None;   
+                    if outcome != Outcome::Quit {
+                        thread::sleep(Duration::from_millis(300));
+                    }
+                    if outcome == Outcome::Completed {
+                        self.alert_phase_end(&phase);
+                        if let Some(template) = &self.messages.phase_end {
+                            let message = template.render(&self.template_vars(Some(phase.clone()), false));
+                            self.banner = Some((self.engine.now(), message));
                         }
-                        _ => {
-                            return Action::None;
+                        let held = self.manual_advance.is_some() && self.engine.is_paused();
+                        if held && !self.engine.is_finished() {
+                            info!(phase = %next.kind, "holding the next phase");
+                            self.held = Some(Held { ended: phase, alerted: self.engine.now(), repeats: 0 });
                         }
                     }
                 }
-                Action::None
+                Event::Snoozed => {
+                    self.phase_started = Local::now();
+                }
+                Event::SkipUndone => {
+                    // Pick the skipped phase up where it was, interruptions
+                    // and all.
+                    if let Some(entry) = self.skipped.take() {
+                        self.phase_started = entry.started;
+                        let since = std::mem::take(&mut self.interruptions);
+                        self.interruptions = entry.interruptions;
+                        self.interruptions.extend(since);
+                        self.pauses += entry.pauses;
+                        self.voided = entry.outcome == Outcome::Voided;
+                    }
+                }
+                Event::PauseBudgetSpent { budget, failed } => {
+                    let budget = humantime::format_duration(budget);
+                    let message = if failed {
+                        format!("Paused over the {budget} budget; pomodoro failed")
+                    } else {
+                        format!("Paused over the {budget} budget for this pomodoro")
+                    };
+                    info!("{message}");
+                    self.ring(0);
+                    self.banner = Some((self.engine.now(), message));
+                }
+            }
+        }
+    }
+
+    /// Sound chime `chime`, 0 for the plain bell, and flash the screen if
+    /// the visual bell is on.
+    fn ring(&mut self, chime: usize) {
+        self.alerts.chime(chime);
+        if self.visual_bell {
+            self.flash = Some(self.engine.now());
+        }
+    }
+
+    /// Whether the screen is drawn inverted now, while the visual bell
+    /// flashes it on and off; None once it is done.
+    fn flashing(&self) -> Option<bool> {
+        let elapsed = self.engine.now().saturating_duration_since(self.flash?);
+        (elapsed < FLASH).then(|| (elapsed.as_millis() / FLASH_FRAME.as_millis()).is_multiple_of(2))
+    }
+
+    /// The spinner by the phase, turning while paused; None if it is off
+    /// or the session is over.
+    pub(crate) fn spinner(&self) -> Option<char> {
+        let engine = &self.engine;
+        if !self.spinner || engine.is_finished() {
+            return None;
+        }
+        if !engine.is_paused() {
+            return Some(SPINNER_STILL);
+        }
+        let turns = engine.now().saturating_duration_since(self.spun_from).as_millis() / SPIN_FRAME.as_millis();
+        Some(SPINNER[turns as usize % SPINNER.len()])
+    }
+
+    /// Sound the alert for `phase` running out, and say so on the desktop
+    /// if asked to.
+    fn alert_phase_end(&mut self, phase: &Phase) {
+        let next = self.engine.phase();
+        let intervals = self.intervals.as_ref().filter(|_| !self.engine.is_finished());
+        self.ring(intervals.and_then(|set| set.chime(&next.kind)).unwrap_or(0));
+        if self.notify_desktop {
+            let title = format!("{} over", phase.kind);
+            let next = if self.engine.is_finished() {
+                &self.messages.session_end
+            } else {
+                &self.messages.phase_start
+            };
+            let body = next.render(&self.template_vars(None, false));
+            // notify-send can be slow to answer; keep it off the loop.
+            thread::spawn(move || {
+                if let Err(e) = desktop::notify(&title, &body) {
+                    warn!("could not notify: {e}");
+                }
+            });
+        }
+    }
+
+    /// Sound the alert again for a held phase when it is time to, or let
+    /// the phase go if it was started some other way, e.g. over the socket.
+    fn repeat_alert(&mut self) {
+        let Some(held) = &self.held else {
+            return;
+        };
+        let now = self.engine.now();
+        if !self.engine.is_paused() {
+            self.held = None;
+            return;
+        }
+        let Some((every, max)) = self.manual_advance else {
+            return;
+        };
+        if held.repeats < max && now.saturating_duration_since(held.alerted) >= every {
+            let ended = held.ended.clone();
+            self.alert_phase_end(&ended);
+            if let Some(held) = &mut self.held {
+                held.alerted = now;
+                held.repeats += 1;
+            }
+        }
+    }
+
+    /// Start the held phase, now that a key shows someone is there.
+    fn advance(&mut self) {
+        if self.held.take().is_some() && self.engine.is_paused() {
+            info!("advancing to the held phase");
+            self.engine.toggle_pause();
+        }
+    }
+
+    fn record_phase(
+        &mut self,
+        phase: Phase,
+        cycle: u32,
+        elapsed: Duration,
+        outcome: Outcome,
+        extension: bool,
+    ) {
+        // Skipped or run to the end, a voided pomodoro is void all the same.
+        let outcome = match outcome {
+            Outcome::Completed | Outcome::Skipped | Outcome::Incomplete if self.voided => Outcome::Voided,
+            outcome => outcome,
+        };
+        self.voided = false;
+        let entry = Entry {
+            started: self.phase_started,
+            kind: phase.kind.clone(),
+            cycle,
+            planned_secs: phase.duration.as_secs(),
+            elapsed_secs: elapsed.as_secs(),
+            outcome,
+            extension,
+            interruptions: std::mem::take(&mut self.interruptions),
+            pauses: std::mem::take(&mut self.pauses),
+            note: None,
+            task: if phase.is_work() { self.current_task().map(str::to_string) } else { None },
+            over_budget: phase.is_work() && self.over_budget(),
+        };
+        if outcome == Outcome::Completed
+            && phase.is_work()
+            && !extension
+            && let Some(plan) = &mut self.plan {
+            if let Some(task) = plan.current().filter(|task| task.is_done()) {
+                let message = format!(
+                    "Over budget: {} has taken {} of {} pomodoros",
+                    task.name,
+                    task.actual + 1,
+                    task.estimate,
+                );
+                info!("{message}");
+                self.banner = Some((self.engine.now(), message));
+            }
+            plan.record_pomodoro();
+            self.save_plan();
+        }
+        self.phase_started = Local::now();
+
+        // Hold skipped phases back while the skip can be undone, and
+        // finished work phases until the note prompt is answered.
+        if outcome == Outcome::Skipped {
+            if let Some(previous) = self.skipped.replace(entry) {
+                self.save_entry(previous);
+            }
+        } else if self.notes && outcome == Outcome::Completed && entry.is_work() {
+            if let Some(previous) = self.pending_note.replace(entry) {
+                self.save_entry(previous);
+            }
+            self.screen = AppScreen::Note(TextInput::default());
+        } else {
+            self.save_entry(entry);
+        }
+    }
+
+    fn save_entry(&mut self, entry: Entry) {
+        if let Some(history) = &self.history
+            && let Err(e) = history.append(&entry) {
+            let message = format!(
+                "Could not write history to {}: {e}",
+                history.path().display()
+            );
+            self.warn(message);
+        }
+        let problems = self.integrations.phase_ended(&entry);
+        self.notify(problems);
+        self.session.push(entry);
+    }
+
+    /// Keep the integrations' problems as warnings.
+    fn notify(&mut self, problems: Vec<OpomodoroError>) {
+        for problem in problems {
+            self.warn(problem.to_string());
+        }
+    }
+
+
+    /// How long until the countdown shows another second, for low-power
+    /// mode to sleep through. Nothing on screen moves faster than that,
+    /// except what low-power mode turns off.
+    fn until_next_second(&self) -> Duration {
+        let engine = &self.engine;
+        if engine.is_paused() || engine.is_finished() {
+            return LOW_POWER_TICK;
+        }
+        // Just past the turn of the second, so the new one is what shows.
+        let left = engine.get_ready_left().unwrap_or_else(|| engine.remaining());
+        let into_second = Duration::from_nanos(u64::from(left.subsec_nanos()));
+        (into_second + Duration::from_millis(5)).min(LOW_POWER_TICK)
+    }
+
+    fn handle_input(&mut self) -> error::Result<Action> {
+        // Only the countdown changes while waiting, once a second.
+        let timeout = match self.screen {
+            AppScreen::Waiting(_) => {
+                let into_second = Local::now().timestamp_subsec_millis().min(999);
+                Duration::from_millis(u64::from(1000 - into_second))
+            }
+            _ if self.flashing().is_some() => FLASH_FRAME / 3,
+            _ if self.low_power => self.until_next_second(),
+            _ => Duration::from_millis(100),
+        };
+        match self.input.next(timeout) {
+            Ok(Some(read_event)) => {
+                self.redraw = true;
+
+                if let Some(key) = read_event.as_key_press_event() {
+                    return Ok(self.handle_key(key));
+                }
+                Ok(Action::None)
             }
-            Ok(false) => {
-                Action::None
+            Ok(None) => {
+                Ok(Action::None)
             } 
-            Err(e) => {
-                eprintln!("Error polling events: {e}");
-                self.end_state = EndState::Erred;
-                Action::Quit
+            Err(e) => Err(OpomodoroError::Terminal(e)),
+        }
+    }
+
+    /// What pressing `key` on the current screen does.
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let on_timer = matches!(self.screen, AppScreen::Timer);
+        let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+        // Quitting comes first, so a held phase is left held.
+        if ctrl_c || (on_timer && key.code == KeyCode::Char('q')) {
+            return Action::Quit;
+        }
+        // Any other key starts a held phase; on the timer, that is all it does.
+        if self.held.is_some() {
+            self.advance();
+            if matches!(self.screen, AppScreen::Timer) {
+                return Action::None;
+            }
+        }
+        if !matches!(self.screen, AppScreen::Timer) {
+            return self.handle_overlay_key(key);
+        }
+        if self.spectator && !matches!(key.code, KeyCode::Char('v' | 'f' | 'm' | '?')) {
+            return Action::None;
+        }
+        match key.code {
+            KeyCode::Char('p') => Action::Toggle,
+            KeyCode::Char('s') => Action::Skip,
+            KeyCode::Char('g') => Action::GotoPrompt,
+            KeyCode::Char('z') => Action::Snooze,
+            KeyCode::Char('u') => Action::UndoSkip,
+            KeyCode::Char('[') => Action::StepLength { work: true, longer: false },
+            KeyCode::Char(']') => Action::StepLength { work: true, longer: true },
+            KeyCode::Char('{') => Action::StepLength { work: false, longer: false },
+            KeyCode::Char('}') => Action::StepLength { work: false, longer: true },
+            KeyCode::Char('v') => Action::CycleView,
+            KeyCode::Char('f') => Action::CycleCountdown,
+            KeyCode::Char('m') => Action::ToggleMute,
+            KeyCode::Char('+' | '=') => Action::AmbientLouder,
+            KeyCode::Char('-') => Action::AmbientQuieter,
+            KeyCode::Char('i') => Action::Interrupt,
+            KeyCode::Char('n') | KeyCode::Down => Action::NextTask,
+            KeyCode::Up => Action::PreviousTask,
+            KeyCode::Char('t') => Action::ToggleSidebar,
+            KeyCode::Char('o') => Action::OpenSettings,
+            KeyCode::Char('?') => Action::ShowHelp,
+            _ => Action::None,
+        }
+    }
+
+    fn handle_overlay_key(&mut self, key: KeyEvent) -> Action {
+        match &mut self.screen {
+            AppScreen::Timer => Action::None,
+            AppScreen::Interruption { source, note } => {
+                if key.code == KeyCode::Tab {
+                    *source = source.toggle();
+                    return Action::None;
+                }
+                match note.handle_key(key) {
+                    InputResult::Editing => Action::None,
+                    InputResult::Submit => Action::LogInterruption(*source, note.take()),
+                    InputResult::Cancel => Action::CloseOverlay,
+                }
+            }
+            AppScreen::SkipMenu { selected } => match key.code {
+                KeyCode::Up | KeyCode::Char('k') => {
+                    *selected = selected.checked_sub(1).unwrap_or(SKIP_CHOICES.len() - 1);
+                    Action::None
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    *selected = (*selected + 1) % SKIP_CHOICES.len();
+                    Action::None
+                }
+                KeyCode::Char(ch @ '1'..='3') => {
+                    Action::SkipTo(SKIP_CHOICES[(ch as u8 - b'1') as usize].0)
+                }
+                KeyCode::Enter => Action::SkipTo(SKIP_CHOICES[*selected].0),
+                KeyCode::Esc | KeyCode::Char('s') => Action::CloseOverlay,
+                _ => Action::None,
+            },
+            AppScreen::GotoCycle(input) => {
+                if let KeyCode::Char(ch) = key.code
+                    && !ch.is_ascii_digit() {
+                    return Action::None;
+                }
+                match input.handle_key(key) {
+                    InputResult::Editing => Action::None,
+                    InputResult::Submit => match input.take().and_then(|n| n.parse().ok()) {
+                        Some(cycle) => Action::GotoCycle(cycle),
+                        None => Action::CloseOverlay,
+                    },
+                    InputResult::Cancel => Action::CloseOverlay,
+                }
+            }
+            AppScreen::Plan(input) => match input.handle_key(key) {
+                InputResult::Editing => Action::None,
+                InputResult::Submit => match input.take().and_then(|line| Task::parse(&line)) {
+                    Some(task) => Action::AddTask(task),
+                    // An empty line ends the plan.
+                    None => Action::StartPlan,
+                },
+                InputResult::Cancel => Action::StartPlan,
+            },
+            AppScreen::Away => match key.code {
+                KeyCode::Char('w') => Action::Returned(Spent::Work),
+                KeyCode::Char('b') => Action::Returned(Spent::Break),
+                KeyCode::Char('d') | KeyCode::Esc => Action::Returned(Spent::Nothing),
+                _ => Action::None,
+            },
+            AppScreen::Note(note) => match note.handle_key(key) {
+                InputResult::Editing => Action::None,
+                InputResult::Submit => Action::SaveNote(note.take()),
+                // Esc skips the note but still saves the phase.
+                InputResult::Cancel => Action::SaveNote(None),
+            },
+            AppScreen::Waiting(_) => match key.code {
+                KeyCode::Enter => Action::StartNow,
+                KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+                _ => Action::None,
+            },
+            AppScreen::Help => match key.code {
+                KeyCode::Esc | KeyCode::Char('?') => Action::CloseOverlay,
+                _ => Action::None,
+            },
+            AppScreen::DayDone(_) => match key.code {
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => Action::Quit,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Action::Quit,
+                _ => Action::None,
+            },
+            AppScreen::Settings { selected, editing, message } => {
+                let field = options::FIELDS[*selected];
+                if let Some(input) = editing.as_mut() {
+                    let text = match input.handle_key(key) {
+                        InputResult::Editing => return Action::None,
+                        InputResult::Submit => input.take(),
+                        InputResult::Cancel => None,
+                    };
+                    *editing = None;
+                    return text.map_or(Action::None, |text| Action::SetOption(field, text));
+                }
+                *message = None;
+                match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        *selected = selected.checked_sub(1).unwrap_or(options::FIELDS.len() - 1);
+                        Action::None
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        *selected = (*selected + 1) % options::FIELDS.len();
+                        Action::None
+                    }
+                    KeyCode::Left | KeyCode::Char('h') => Action::AdjustOption(field, false),
+                    KeyCode::Right | KeyCode::Char('l') => Action::AdjustOption(field, true),
+                    KeyCode::Enter | KeyCode::Char(' ') if field.is_typed() => {
+                        *editing = Some(TextInput::default());
+                        Action::None
+                    }
+                    KeyCode::Enter | KeyCode::Char(' ') => Action::AdjustOption(field, true),
+                    KeyCode::Char('w') => Action::SaveOptions,
+                    KeyCode::Esc | KeyCode::Char('o') => Action::CloseOverlay,
+                    _ => Action::None,
+                }
             }
         }
     }
 
-    fn apply_action(&mut self, action: Action, now: Instant) {
+    fn apply_action(&mut self, action: Action) {
+        // A hard break holds off whatever would cut it short, from the
+        // keyboard or the control socket alike.
+        let cuts_short = matches!(
+            action,
+            Action::Skip
+                | Action::SkipPhase
+                | Action::SkipTo(_)
+                | Action::GotoPrompt
+                | Action::GotoCycle(_)
+                | Action::Snooze
+        );
+        if cuts_short && self.hard_break_left().is_some() {
+            debug!(?action, "held off by the hard break");
+            return;
+        }
         match action {
             Action::Toggle => {
-                self.timer_state.toggle_pause(now);    
+                self.engine.toggle_pause();
+                if self.engine.is_paused() {
+                    self.pauses += 1;
+                    self.void("paused");
+                }
             }
             Action::Skip => {
-                self.end_state = EndState::Skipped;
+                self.screen = AppScreen::SkipMenu { selected: 0 };
+            }
+            Action::SkipPhase => {
+                let events = self.engine.skip();
+                self.handle_events(events);
+            }
+            Action::SkipTo(target) => {
+                self.screen = AppScreen::Timer;
+                let events = self.engine.skip_to(target);
+                self.handle_events(events);
+            }
+            Action::SetLengths(work, break_time) => {
+                let result = self.set_lengths(work, break_time);
+                self.lengths_changed(result);
+            }
+            Action::StepLength { work, longer } => {
+                let result = self.step_length(work, longer);
+                self.lengths_changed(result);
+            }
+            Action::GotoPrompt => {
+                self.screen = AppScreen::GotoCycle(TextInput::default());
+            }
+            Action::GotoCycle(cycle) => {
+                self.screen = AppScreen::Timer;
+                let events = self.engine.goto_cycle(cycle);
+                self.handle_events(events);
+            }
+            Action::Snooze => {
+                let events = self.engine.snooze();
+                self.handle_events(events);
+            }
+            Action::UndoSkip => {
+                let events = self.engine.undo_skip();
+                self.handle_events(events);
+            }
+            Action::CycleView => {
+                self.view = self.view.next();
+            }
+            Action::CycleCountdown => {
+                self.countdown = self.countdown.next();
+            }
+            Action::ToggleMute => {
+                self.alerts.toggle_mute();
+            }
+            Action::AmbientLouder | Action::AmbientQuieter => {
+                let Some(ambient) = &mut self.ambient else {
+                    return;
+                };
+                let volume = match action {
+                    Action::AmbientLouder => ambient.volume().saturating_add(VOLUME_STEP),
+                    _ => ambient.volume().saturating_sub(VOLUME_STEP),
+                };
+                ambient.set_volume(volume);
+                let message = format!("Ambient sound at {}%", ambient.volume());
+                self.banner = Some((self.engine.now(), message));
+            }
+            // Interruptions only make sense against a work phase.
+            Action::Interrupt if self.engine.phase().is_work() => {
+                self.screen = AppScreen::Interruption {
+                    source: InterruptionSource::External,
+                    note: TextInput::default(),
+                };
+            }
+            Action::LogInterruption(source, note) => {
+                let interruption = Interruption { at: Local::now(), source, note };
+                if !self.engine.record_interruption(&interruption) {
+                    self.interruptions.push(interruption);
+                }
+                self.screen = AppScreen::Timer;
+                self.void("interrupted");
+            }
+            Action::Returned(spent) => {
+                self.screen = AppScreen::Timer;
+                if let Some(away) = self.away.take() {
+                    self.account_away(away, spent);
+                }
+                if self.engine.is_paused() {
+                    self.engine.toggle_pause();
+                }
+            }
+            Action::SaveNote(note) => {
+                if let Some(mut entry) = self.pending_note.take() {
+                    entry.note = note;
+                    self.save_entry(entry);
+                }
+                self.screen = AppScreen::Timer;
+            }
+            Action::AddTask(task) => {
+                if let Some(plan) = &mut self.plan {
+                    plan.push(task);
+                }
+            }
+            Action::StartPlan => {
+                if self.plan.as_ref().is_some_and(Plan::is_empty) {
+                    self.plan = None;
+                }
+                self.save_plan();
+                self.screen = AppScreen::Timer;
+                if self.engine.is_paused() {
+                    self.engine.toggle_pause();
+                }
+                self.phase_started = Local::now();
+            }
+            Action::StartNow => {
+                self.screen = AppScreen::Timer;
+                if self.engine.is_paused() {
+                    self.engine.toggle_pause();
+                }
+                self.phase_started = Local::now();
+            }
+            Action::NextTask => {
+                if let Some(plan) = &mut self.plan {
+                    plan.advance();
+                    self.save_plan();
+                }
+            }
+            Action::PreviousTask => {
+                if let Some(plan) = &mut self.plan {
+                    plan.back();
+                    self.save_plan();
+                }
+            }
+            Action::ToggleSidebar => {
+                self.sidebar = !self.sidebar;
+            }
+            Action::OpenSettings => {
+                self.screen = AppScreen::Settings { selected: 0, editing: None, message: None };
+            }
+            Action::AdjustOption(field, up) => {
+                let result = self.adjust_option(field, up);
+                self.option_changed(field, result);
+            }
+            Action::SetOption(field, text) => {
+                let result = self.set_option(field, &text);
+                self.option_changed(field, result);
+            }
+            Action::SaveOptions => {
+                let message = match self.save_options() {
+                    Ok(path) => format!("Saved to {}", path.display()),
+                    Err(e) => format!("Could not save: {e}"),
+                };
+                self.settings_message(message);
+            }
+            Action::ShowHelp => {
+                self.screen = AppScreen::Help;
+            }
+            Action::CloseOverlay => {
+                self.screen = AppScreen::Timer;
             }
             Action::Quit => {
-                self.running.store(false, Ordering::Relaxed);
-                self.end_state = EndState::Quit;
+                self.shutdown();
             }
             _ => {},    
         }
     } 
 
-    fn update(&mut self, now: Instant) {
-        if matches!(self.timer_state, TimerState::Running { .. }) 
-            && self.remaining == Duration::ZERO {
-            self.end_state = EndState::Completed;
-        }
-        match self.end_state {
-            EndState::Completed => {
-                thread::sleep(Duration::from_millis(300));
-                print!("\x07");
-                io::stdout().flush().unwrap();
-                if self.phase.kind == "Work" {
-                    if self.current_cycle == self.num_cycles && ! self.late {
-                        self.end_state = EndState::Quit;
-                    } else {
-                        self.end_state = EndState::None;
-                        self.phase = Phase { kind: "Break", duration: self.break_time };
-                        self.timer_state = TimerState::Running { end: now + self.break_time };
-                    }
-                } else {
-                    if self.current_cycle == self.num_cycles {
-                        self.end_state = EndState::Quit;
-                    } else {
-                        self.end_state = EndState::None;
-                        self.phase = Phase { kind: "Work", duration: self.work_time };
-                        self.timer_state = TimerState::Running { end: now + self.work_time };
-                        self.current_cycle += 1;
-                    }
-                }
+    /// Put the time away where the user says it belongs: into the current
+    /// phase if it is of that kind, or else into an entry of its own. Work
+    /// left for anything else counts as an interruption.
+    fn account_away(&mut self, away: Away, spent: Spent) {
+        let gone = (Local::now() - away.since).to_std().unwrap_or_default();
+        info!(?spent, away = ?gone, "back");
+        let working = self.engine.phase().is_work();
+        if (spent == Spent::Work) == working && spent != Spent::Nothing {
+            self.engine.set_remaining(self.engine.remaining().saturating_sub(gone));
+            return;
+        }
+        if working {
+            let note = format!("Away for {} min", gone.as_secs().div_ceil(60));
+            let interruption = Interruption {
+                at: away.since,
+                source: InterruptionSource::Internal,
+                note: Some(note),
+            };
+            if !self.engine.record_interruption(&interruption) {
+                self.interruptions.push(interruption);
             }
-            EndState::Skipped => {
-                thread::sleep(Duration::from_millis(300));
-                if self.phase.kind == "Work" {
-                    if self.current_cycle == self.num_cycles && ! self.late {
-                        self.end_state = EndState::Quit;
-                    } else {
-                        self.end_state = EndState::None;
-                        self.phase = Phase { kind: "Break", duration: self.break_time };
-                        self.timer_state = TimerState::Running { end: now + self.break_time };
-                    }
+            self.void("interrupted");
+        }
+        if spent == Spent::Nothing {
+            return;
+        }
+        let work = spent == Spent::Work;
+        let entry = Entry {
+            started: away.since,
+            kind: if work { PhaseKind::Work } else { PhaseKind::ShortBreak },
+            cycle: self.engine.cycle(),
+            planned_secs: gone.as_secs(),
+            elapsed_secs: gone.as_secs(),
+            outcome: Outcome::Completed,
+            // Focus time, but not a pomodoro.
+            extension: work,
+            interruptions: Vec::new(),
+            pauses: 0,
+            note: None,
+            task: if work { self.current_task().map(str::to_string) } else { None },
+            over_budget: work && self.over_budget(),
+        };
+        self.save_entry(entry);
+    }
+
+    /// End the session early, the same way for `q`, signals and input
+    /// errors: keep it resumable, record the interrupted phase and stop.
+    fn shutdown(&mut self) {
+        // Nothing has happened yet while waiting to start, and nothing is
+        // left once the day is done; keep nothing.
+        if !matches!(self.screen, AppScreen::Waiting(_) | AppScreen::DayDone(_)) {
+            self.save_for_resume();
+            let events = self.engine.quit();
+            self.handle_events(events);
+        }
+        self.running.store(false, Ordering::Relaxed);
+        self.end_state = EndState::Quit;
+    }
+
+    fn update(&mut self) {
+        self.check_start();
+        let events = self.engine.tick();
+        self.handle_events(events);
+        self.repeat_alert();
+        self.check_warning();
+        self.sync_blocker();
+        self.sync_ambient();
+        self.check_phase_started();
+        // Too late to undo the skip; the phase is over for good.
+        if let Some(entry) = self.skipped.take_if(|_| self.engine.undo_left().is_none()) {
+            self.save_entry(entry);
+        }
+        let day_done = matches!(self.screen, AppScreen::DayDone(_));
+        if self.engine.is_finished() && self.end_state != EndState::Quit && !day_done {
+            if self.block + 1 < self.chain.len() {
+                self.start_next_block();
+            } else {
+                let announcement = self.messages.session_end.render(&self.template_vars(None, true));
+                self.alerts.say(&announcement);
+                if self.day_file.is_some() {
+                    self.close_day();
                 } else {
-                    if self.current_cycle == self.num_cycles {
-                        self.end_state = EndState::Quit;
-                    } else {
-                        self.end_state = EndState::None;
-                        self.phase = Phase { kind: "Work", duration: self.work_time };
-                        self.timer_state = TimerState::Running { end: now + self.work_time };
-                        self.current_cycle += 1;
-                    }
+                    self.end_state = EndState::Quit;
                 }
             }
-            _ => {},
         }
     }
+
+    /// Call the day done, and show so until the user leaves.
+    fn close_day(&mut self) {
+        if let Some(file) = &self.day_file
+            && let Err(e) = file.close(Local::now()) {
+            let message = format!("Could not call the day done in {}: {e}", file.path().display());
+            self.warn(message);
+        }
+        info!("day done");
+        self.screen = AppScreen::DayDone(self.engine.now());
+        self.redraw = true;
+    }
+
+    /// Go on with the next block of the chain once one has run its course.
+    fn start_next_block(&mut self) {
+        self.block += 1;
+        self.engine.start_block(&self.chain[self.block]);
+        self.remaining = self.engine.remaining();
+        self.phase_started = Local::now();
+        let message = self.messages.next_block.render(&self.template_vars(None, false));
+        info!(block = self.block + 1, of = self.chain.len(), "{message}");
+        self.banner = Some((self.engine.now(), message));
+    }
+
+    /// The block on now, counting from 1, if the session is part of a chain.
+    fn current_block(&self) -> Option<(usize, &Block)> {
+        self.chain.get(self.block).map(|block| (self.block + 1, block))
+    }
+
+    /// Wall-clock time the last block of the chain would end, if any come
+    /// after this one.
+    fn chain_ends_at(&self) -> Option<DateTime<Local>> {
+        let later = self.chain.get(self.block + 1..).filter(|later| !later.is_empty())?;
+        let late = self.engine.late();
+        Some(self.engine.session_ends_at()? + later.iter().map(|block| block.duration(late)).sum::<Duration>())
+    }
 }
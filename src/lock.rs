@@ -0,0 +1,45 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use crate::error::{self, OpomodoroError};
+
+/// Held by the instance running a session, so a second one does not
+/// clobber its state file and history. Released when dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+impl InstanceLock {
+    /// `<data dir>/opomodoro/instance.lock`, if the platform has a data dir.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("opomodoro").join("instance.lock"))
+    }
+
+    /// Take the lock at `path`, failing with
+    /// [`OpomodoroError::AlreadyRunning`] while another instance holds it.
+    pub fn acquire(path: &Path) -> error::Result<InstanceLock> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut pid = String::new();
+                let _ = file.read_to_string(&mut pid);
+                return Err(OpomodoroError::AlreadyRunning { pid: pid.trim().parse().ok() });
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", std::process::id())?;
+        Ok(InstanceLock { _file: file })
+    }
+}
@@ -1,70 +1,2444 @@
+#[cfg(feature = "terminal")]
+use std::cmp;
+use std::fs::OpenOptions;
 use std::io;
-use std::process;
+#[cfg(feature = "terminal")]
+use std::io::Write;
+#[cfg(unix)]
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
 use std::time::Duration;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use clap::Parser;
-use opomodoro::{App, Config};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap_mangen::roff::{Roff, bold, roman};
+#[cfg(feature = "terminal")]
+use ratatui::Terminal;
+#[cfg(feature = "terminal")]
+use ratatui::backend::CrosstermBackend;
+#[cfg(feature = "terminal")]
+use opomodoro::{App, terminal};
+use opomodoro::{Config, Countdown, FontChoice, HourFormat, OpomodoroError, ViewMode, format_time};
+use opomodoro::ambient::AmbientSound;
+use opomodoro::autostart::Schedule;
+use opomodoro::backup;
+use opomodoro::blocker;
+use opomodoro::chain::Block;
+use opomodoro::desktop;
+use opomodoro::engine::{BreakDebt, Engine, Phase, PhaseKind};
+#[cfg(feature = "terminal")]
+use opomodoro::engine::Timer;
+use opomodoro::error::Result;
+use opomodoro::history::{self, Focus, GroupBy, SortBy, Summary, Targets, format_focus};
+use opomodoro::intervals::Intervals;
+#[cfg(feature = "terminal")]
+use opomodoro::input::{ReplayInput, TerminalInput};
+use opomodoro::integrations::{Integrations, Outbox};
+#[cfg(any(unix, windows))]
+use opomodoro::ipc::{self, ControlServer, Link};
+#[cfg(all(any(unix, windows), feature = "terminal"))]
+use opomodoro::ipc::RemoteEngine;
+#[cfg(unix)]
+use opomodoro::net::TeamHost;
+use opomodoro::day::DayFile;
+use opomodoro::lock::InstanceLock;
+use opomodoro::schedule::SessionTemplate;
+use opomodoro::service::Manager;
+#[cfg(unix)]
+use opomodoro::systemd;
+use opomodoro::settings::{BlockSettings, ConfigFile, KEYS, MessageSettings, Settings};
+use opomodoro::simulate::{self, Step};
+use opomodoro::planner::TaskFile;
+use opomodoro::profile;
+#[cfg(feature = "terminal")]
+use opomodoro::record::Recorder;
+use opomodoro::todo::TodoFile;
+use opomodoro::score;
+use opomodoro::state::{SessionState, StateFile};
+use opomodoro::suggest::{self, Analysis};
+use opomodoro::template::{Messages, Template};
+use opomodoro::theme::{self, ThemeChoice};
 
 #[derive(Parser)]
 #[command(name = "Opomodoro")]
 #[command(version = "1.0")]
 #[command(about = "Pomodoro in the command line.", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(disable_help_subcommand = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Append a debug log to this file
+    #[arg(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+    /// Use this profile's config, history and stats, e.g. study
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+    /// Flags for `start`, so plain `opomodoro` starts a session
+    #[command(flatten)]
+    start: StartArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start a new session
+    Start(StartArgs),
+    /// Show the running or resumable session
+    Status,
+    /// Summarize focus time from the history
+    Stats(StatsArgs),
+    /// Look after the history of finished phases
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Bundle the config, history and session state into one file, or put them back
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommand,
+    },
+    /// Pause the running timer
+    Pause,
+    /// Unpause the running timer, or continue the last session that was quit early
+    Resume(ResumeArgs),
+    /// End the running timer's current phase and move on to the next
+    Skip,
+    /// Change how long the running timer's work phases and breaks take,
+    /// from the next one on
+    Lengths(LengthsArgs),
+    /// List the profiles set up so far
+    Profiles,
+    /// Show the settings and files in use, or change them
+    Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommand>,
+    },
+    /// Share a session's phases, blocks, sounds, hooks and messages as a template file
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommand,
+    },
+    /// Print the timeline a schedule would produce, without a TUI
+    Simulate(SimulateArgs),
+    /// Count down once, with no work/break cycles, e.g. for a meeting
+    Timer(TimerArgs),
+    /// Run a session without a TUI, controlled through `attach`
+    Daemon(DaemonArgs),
+    /// Show the TUI of a session running in the daemon
+    Attach(UiArgs),
+    /// Follow a timer shared with `--host`, without controlling it
+    Join(JoinArgs),
+    /// Write an HTML stream overlay that follows a session started with --serve
+    #[cfg(all(feature = "http", feature = "terminal"))]
+    Overlay(OverlayArgs),
+    /// Push the history recorded here to the sync_url, and pull in what
+    /// other machines pushed
+    #[cfg(feature = "webdav")]
+    Sync,
+    /// Print the long help of a command, with examples, or its man page
+    Help {
+        /// The command, e.g. `history prune`; left out, opomodoro itself
+        command: Vec<String>,
+        /// Write a roff man page instead, e.g. `opomodoro help --man > opomodoro.1`
+        #[arg(long)]
+        man: bool,
+    },
+}
+
+/// Example command lines, with what they do, for opomodoro itself (`""`)
+/// and its subcommands. `--help` and the man pages both show them.
+const EXAMPLES: &[(&str, &[(&str, &str)])] = &[
+    ("", &[
+        ("opomodoro", "Start a session with the settings from the config file"),
+        ("opomodoro --work 50m --break 10m --cycles 3", "Three cycles of 50 minutes' work"),
+        ("opomodoro stats --by tag", "Total the focus time for each #tag"),
+        ("opomodoro --profile study", "Study, with the settings and history of the study profile"),
+    ]),
+    ("start", &[
+        ("opomodoro start --task \"Write the report #writing\"", "Label each work phase with a task"),
+        ("opomodoro start --at 09:00 --day", "Run the config's day plan from 9 o'clock"),
+        ("opomodoro start --preset deep", "Run the config's [preset.deep], whatever the day's schedule"),
+        ("opomodoro start --warm-up 3m --cool-down 5m", "Plan first and review at the end"),
+        ("opomodoro start --then \"work=50m break=10m cycles=2\"", "Go on with a longer session"),
+        ("opomodoro start --intervals \"45s/15s x10\"", "Ten rounds of 45 seconds on and 15 off"),
+        ("opomodoro start --manual-advance", "Wait for a key before each phase, ringing until then"),
+        ("opomodoro start --visual-bell --volume 0", "Flash the screen on alerts instead of ringing"),
+        ("opomodoro start --theme deutan", "Colors told apart with red-green color blindness"),
+    ]),
+    ("stats", &[
+        ("opomodoro stats", "Today's and all-time pomodoros, focus time and scores"),
+        ("opomodoro stats --calendar", "A heatmap of the last 12 weeks"),
+        ("opomodoro stats --by day --sort name", "Focus time day by day"),
+        ("opomodoro stats --profile study", "The focus time of the study profile alone"),
+    ]),
+    ("history prune", &[
+        ("opomodoro history prune --older-than 1y --archive old.jsonl", "Move last year's entries away"),
+    ]),
+    ("history merge", &[
+        ("opomodoro history merge laptop.jsonl", "Add the entries the laptop recorded"),
+    ]),
+    ("backup create", &[
+        ("opomodoro backup create opomodoro.tar.gz", "Back everything up into one gzipped file"),
+    ]),
+    ("config", &[
+        ("opomodoro config", "Show the settings in effect, and the files in use"),
+        ("opomodoro config set work 50m", "Work 50 minutes from now on"),
+    ]),
+    ("schedule export", &[
+        (
+            "opomodoro schedule export morning.toml --name \"Deep work morning\"",
+            "Share the session set up here",
+        ),
+    ]),
+    ("schedule import", &[
+        ("opomodoro schedule import morning.toml", "Take up a shared session template"),
+    ]),
+    ("timer", &[
+        ("opomodoro timer 37m --label \"standup prep\"", "Count down 37 minutes, kept in the history as such"),
+        ("opomodoro timer 90s --plain", "A short countdown printed rather than drawn"),
+    ]),
+    ("simulate", &[
+        ("opomodoro simulate --schedule \"50m/10m x3\"", "List the phases three 50-minute cycles run"),
+    ]),
+    ("daemon", &[
+        ("opomodoro daemon --recurring", "Start sessions at the config's autostart times"),
+    ]),
+    ("daemon install", &[
+        ("opomodoro daemon install --recurring", "Start sessions at the autostart times from login on"),
+        ("opomodoro daemon install --print", "Show the service file without writing it"),
+    ]),
+    ("help", &[
+        ("opomodoro help history prune", "The long help of `history prune`"),
+        ("opomodoro help --man > opomodoro.1", "Write the man page"),
+    ]),
+];
+
+/// The command line, with the examples at the end of each command's long
+/// help.
+fn cli_command() -> clap::Command {
+    let mut command = Cli::command();
+    for (path, examples) in EXAMPLES {
+        let mut text = "Examples:\n".to_string();
+        for (line, what) in *examples {
+            text += &format!("  {line}\n      {what}\n");
+        }
+        command = with_examples(command, &path.split_whitespace().collect::<Vec<_>>(), text);
+    }
+    command
+}
+
+fn with_examples(command: clap::Command, path: &[&str], text: String) -> clap::Command {
+    match path {
+        [] => command.after_long_help(text),
+        [name, rest @ ..] => command.mut_subcommand(*name, |sub| with_examples(sub, rest, text)),
+    }
+}
+
+#[cfg(all(feature = "http", feature = "terminal"))]
+#[derive(Args)]
+struct OverlayArgs {
+    /// Directory to write index.html to
+    #[arg(long, value_name = "DIR")]
+    out: PathBuf,
+    /// Address of the session's HTTP API
+    #[arg(long, default_value = opomodoro::overlay::DEFAULT_URL)]
+    url: String,
+}
+
+#[derive(Args)]
+struct StatsArgs {
+    /// Show a heatmap of the focus time per day over the last 12 weeks
+    #[arg(long, conflicts_with = "by")]
+    calendar: bool,
+    /// Compare how often phases of each length get skipped, and suggest
+    /// work and break lengths to try
+    #[arg(long, conflicts_with_all = ["calendar", "by"])]
+    suggest: bool,
+    /// Break the focus time down by #tag on the task, by task, or by day
+    #[arg(long, value_enum)]
+    by: Option<GroupBy>,
+    /// Order of the breakdown
+    #[arg(long, value_enum, default_value = "focus", requires = "by")]
+    sort: SortBy,
+}
+
+#[derive(Args)]
+struct TimerArgs {
+    /// How long to count down, e.g. 37m or 1h30m
+    #[arg(value_parser = parse_length)]
+    length: Duration,
+    /// What the countdown is for, shown as its phase and kept in the
+    /// history [default: Timer]
+    #[arg(long)]
+    label: Option<String>,
+    /// Start even if another timer is already running
+    #[arg(long)]
+    force: bool,
+    /// Print a line as the countdown starts and ends instead of drawing it
+    #[arg(long)]
+    plain: bool,
+    #[command(flatten)]
+    ui: UiArgs,
+}
+
+#[derive(Args)]
+struct JoinArgs {
+    /// Address the host shares the timer on, e.g. 192.168.1.20:7879; left
+    /// out, pick one of the timers shared on the local network
+    addr: Option<String>,
+    #[command(flatten)]
+    ui: UiArgs,
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the value in effect for a setting
+    Get { key: String },
+    /// Change a setting in the config file
+    Set { key: String, value: String },
+    /// Open the config file in $VISUAL or $EDITOR
+    Edit,
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    /// Remove old entries, keeping them in an archive if asked to
+    Prune(PruneArgs),
+    /// Add the entries of other histories, e.g. another machine's or a
+    /// conflicted copy, that this one does not have
+    Merge {
+        /// History files, JSON Lines or SQLite, or synced folders
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleCommand {
+    /// Write the session the config file sets up to a template at PATH
+    Export {
+        path: PathBuf,
+        /// What to call the template, e.g. "Deep work morning"
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Set up sessions as the template at PATH has them, in the config file
+    Import { path: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum DaemonCommand {
+    /// Write a systemd user unit, launchd agent or Windows logon task that
+    /// runs the daemon when you log in
+    Install {
+        /// Have it run `daemon --recurring`, starting sessions at the
+        /// config's `autostart` times
+        #[arg(long)]
+        recurring: bool,
+        /// Print the file instead of writing it
+        #[arg(long)]
+        print: bool,
+        /// Replace a file that is already there
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupCommand {
+    /// Write a backup to PATH, a tar file gzipped if it ends in .gz
+    Create { path: PathBuf },
+    /// Put the files in the backup at PATH back in place
+    Restore {
+        path: PathBuf,
+        /// Replace files that are already there
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Args)]
+struct PruneArgs {
+    /// Remove entries started longer ago than this, e.g. 1y or 90d
+    #[arg(long, value_name = "DURATION")]
+    older_than: String,
+    /// Append the removed entries to this file as JSON lines, gzipped if it ends in .gz
+    #[arg(long, value_name = "PATH")]
+    archive: Option<PathBuf>,
+    /// Show what would be removed without changing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+struct StartArgs {
+    /// Work phase length [default: 25m]
     #[arg(long = "work")]
-    work_time: String,
+    work_time: Option<String>,
+    /// Break phase length [default: 5m]
     #[arg(long = "break")]
-    break_time: String,
+    break_time: Option<String>,
+    /// Number of work/break cycles [default: 4]
     #[arg(long = "cycles")]
-    num_cycles: u32,
+    num_cycles: Option<u32>,
+    /// Also take the break after the last work phase
+    #[arg(short, long)]
+    late: bool,
+    /// Plan for this long before the first work phase, e.g. 3m
+    #[arg(long, value_name = "DURATION")]
+    warm_up: Option<String>,
+    /// Review for this long after the last phase, e.g. 5m
+    #[arg(long, value_name = "DURATION")]
+    cool_down: Option<String>,
+    /// Label to record with each work phase
+    #[arg(long)]
+    task: Option<String>,
+    /// Run with this preset from the config file, rather than the one its
+    /// `schedule` has for the day
+    #[arg(long, value_name = "NAME")]
+    preset: Option<String>,
+    /// Run the day plan from the config file, its `[[day]]` blocks one
+    /// after another
+    #[arg(long)]
+    day: bool,
+    /// Run another session once this one is over, e.g. "work=50m break=10m
+    /// cycles=2", or a lone break like "name=lunch break=45m"; repeat to
+    /// chain more
+    #[arg(long, value_name = "BLOCK")]
+    then: Vec<String>,
+    /// Run rounds of labelled intervals in place of work and breaks, e.g.
+    /// "45s/15s x10" or "squats=40s/rest=20s x5", with a chime for each label
+    #[arg(
+        long,
+        value_name = "SET",
+        conflicts_with_all = [
+            "work_time", "break_time", "num_cycles", "late", "warm_up", "cool_down", "day", "then", "plan",
+        ]
+    )]
+    intervals: Option<Intervals>,
+    /// Wait until this time of day to start, e.g. 09:00; tomorrow if it
+    /// has passed
+    #[arg(long, value_name = "HH:MM", value_parser = parse_start_time, conflicts_with = "plan")]
+    at: Option<DateTime<Local>>,
+    /// List the session's tasks with estimates before the timer starts
+    #[arg(long)]
+    plan: bool,
+    /// Take the tasks from a todo.txt or markdown checklist file, and write
+    /// pomodoro counts and completions back to it
+    #[arg(long, value_name = "PATH")]
+    tasks_file: Option<PathBuf>,
+    /// Label the work with a GitHub issue's title, e.g. owner/repo#123
+    #[cfg(feature = "github")]
+    #[arg(long, value_name = "ISSUE")]
+    gh_issue: Option<String>,
+    /// Only show the timer shared on this address, with no controls, e.g. on
+    /// a projector
+    #[cfg(unix)]
+    #[arg(long, value_name = "ADDR")]
+    spectate: Option<String>,
+    /// Share the timer with `opomodoro join` on this address, e.g. 0.0.0.0:7879
+    #[cfg(unix)]
+    #[arg(long, value_name = "ADDR")]
+    host: Option<SocketAddr>,
+    /// Serve a REST API for the session on this address, e.g. 127.0.0.1:7878
+    #[cfg(all(unix, feature = "http"))]
+    #[arg(long, value_name = "ADDR")]
+    serve: Option<SocketAddr>,
+    /// Start even if another timer is already running
+    #[arg(long)]
+    force: bool,
+    /// Start even though end-of-day mode called the day done
+    #[arg(long = "override")]
+    override_day: bool,
+    /// Print a line as each phase starts and ends instead of drawing the
+    /// timer, for scripts; builds without the terminal frontend always do
+    #[arg(long)]
+    plain: bool,
+    /// Press the keys in this script at their times, e.g. "2s s enter", one
+    /// step a line; the keyboard takes over once it is done
+    #[cfg(feature = "terminal")]
+    #[arg(long, value_name = "PATH")]
+    replay: Option<PathBuf>,
+    /// Record the session as an asciinema cast, e.g. session.cast
+    #[cfg(feature = "terminal")]
+    #[arg(long, value_name = "PATH")]
+    record: Option<PathBuf>,
+    #[command(flatten)]
+    ui: UiArgs,
+}
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+struct DaemonArgs {
+    #[command(subcommand)]
+    command: Option<DaemonCommand>,
+    /// Keep running and start a session at each of the config's
+    /// `autostart` times, e.g. ["weekdays 09:00", "weekdays 13:30"]
+    #[arg(long, conflicts_with = "at")]
+    recurring: bool,
+    #[command(flatten)]
+    start: StartArgs,
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = true)]
+struct LengthsArgs {
+    /// Work phase length
+    #[arg(long, value_parser = parse_length)]
+    work: Option<Duration>,
+    /// Break phase length
+    #[arg(long = "break", value_parser = parse_length)]
+    break_time: Option<Duration>,
+}
+
+#[derive(Args)]
+struct ResumeArgs {
+    /// Resume even if another timer is already running
+    #[arg(long)]
+    force: bool,
+    #[command(flatten)]
+    ui: UiArgs,
+}
+
+impl StartArgs {
+    fn settings(&self) -> Settings {
+        Settings {
+            work: self.work_time.clone(),
+            break_time: self.break_time.clone(),
+            cycles: self.num_cycles,
+            late: self.late.then_some(true),
+            warm_up: self.warm_up.clone(),
+            cool_down: self.cool_down.clone(),
+            task: self.task.clone(),
+            ..self.ui.settings()
+        }
+    }
+}
+
+#[derive(Args)]
+struct UiArgs {
+    /// Big-digit font for the timer panel [default: auto]
+    #[arg(long, value_enum)]
+    font: Option<FontChoice>,
+    /// Initial timer view, cycle with `v` [default: digits]
+    #[arg(long, value_enum)]
+    view: Option<ViewMode>,
+    /// How the timer writes the time left, cycle with `f` [default: clock]
+    #[arg(long, value_enum)]
+    countdown: Option<Countdown>,
+    /// Colors to draw the timer in [default: tomato]
+    #[arg(long, value_enum)]
+    theme: Option<ThemeChoice>,
+    /// Alert volume from 0 (silent) to 100 [default: 100]
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    volume: Option<u8>,
+    /// Speak phase changes out loud, with say, espeak-ng or the Windows voices
+    #[arg(long)]
+    speak: bool,
+    /// Show a desktop notification as each phase runs out
+    #[arg(long)]
+    notify: bool,
+    /// Flash the screen on alerts; with --volume 0, in place of sound
+    #[arg(long)]
+    visual_bell: bool,
+    /// Ask for a one-line note after each completed work phase
+    #[arg(long)]
+    notes: bool,
+    /// Extra work time added by pressing `z` right after a work phase ends [default: 5m]
+    #[arg(long)]
+    snooze: Option<String>,
+    /// How many times a single break may be snoozed [default: 2]
+    #[arg(long)]
+    max_snoozes: Option<u32>,
+    /// Pause work phases after this long without keyboard or mouse input, e.g. 5m
+    #[arg(long, value_name = "DURATION")]
+    idle: Option<String>,
+    /// Warn this long before a work phase ends, e.g. 2m
+    #[arg(long, value_name = "DURATION")]
+    work_warning: Option<String>,
+    /// Warn this long before a break ends, e.g. 30s
+    #[arg(long, value_name = "DURATION")]
+    break_warning: Option<String>,
+    /// Fill the screen during breaks and ignore skips for their first
+    /// DURATION, e.g. 2m
+    #[arg(long, value_name = "DURATION")]
+    hard_break: Option<String>,
+    /// Count down this long before each work phase after a break, e.g. 5s
+    #[arg(long, value_name = "DURATION")]
+    get_ready: Option<String>,
+    /// Keep count of break time skipped, and show it or add it to the next break [default: off]
+    #[arg(long, value_enum, value_name = "POLICY")]
+    break_debt: Option<BreakDebt>,
+    /// Void work phases that are paused or interrupted, rather than count
+    /// them as pomodoros
+    #[arg(long)]
+    strict: bool,
+    /// After the last cycle, celebrate and start no more sessions until
+    /// tomorrow
+    #[arg(long)]
+    end_of_day: bool,
+    /// Hold each phase after one runs out until a key is pressed, sounding
+    /// the alert again until then
+    #[arg(long)]
+    manual_advance: bool,
+    /// How often the alert sounds again while a phase is held [default: 30s]
+    #[arg(long, value_name = "DURATION")]
+    alert_repeat: Option<String>,
+    /// Most times the alert sounds again for a held phase [default: 5]
+    #[arg(long)]
+    max_alert_repeats: Option<u32>,
+    /// Sound to play through work phases; needs a build with the ambient feature
+    #[arg(long, value_enum, value_name = "SOUND")]
+    ambient: Option<AmbientSound>,
+    /// Ambient sound volume from 0 to 100, change with `+` and `-` [default: 30]
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    ambient_volume: Option<u8>,
+    /// Focus time to aim for today, shown as a gauge, e.g. 4h
+    #[arg(long, value_name = "DURATION")]
+    daily_target: Option<String>,
+    /// Focus time to aim for this week, shown as a gauge, e.g. 20h
+    #[arg(long, value_name = "DURATION")]
+    weekly_target: Option<String>,
+    /// 12- or 24-hour clock for the times in the header [default: auto]
+    #[arg(long, value_enum)]
+    clock: Option<HourFormat>,
+    /// Show today's tasks next to the timer, toggle with `t`
+    #[arg(long)]
+    sidebar: bool,
+    /// Show a tomato whose face follows the timer, toggle on the settings
+    /// screen (`o`, then the Mascot row)
+    #[arg(long)]
+    mascot: bool,
+    /// Switch phases without the short splash in between
+    #[arg(long)]
+    no_animations: bool,
+    /// Mark a paused timer without the spinning dot
+    #[arg(long)]
+    no_spinner: bool,
+    /// Redraw about once a second and skip the splash, to save battery
+    #[arg(long)]
+    low_power: bool,
+}
+
+#[derive(Args)]
+struct SimulateArgs {
+    /// Schedule as WORK/BREAK with an optional cycle count, e.g. "50m/10m x3"
+    #[arg(long)]
+    schedule: Option<String>,
+    /// Take this preset from the config file rather than today's
+    #[arg(long, value_name = "NAME")]
+    preset: Option<String>,
+    /// Also take the break after the last work phase
     #[arg(short, long)]
     late: bool,
+    /// Replay at this many times real time, e.g. 100x [default: instant]
+    #[arg(long, value_parser = parse_speed)]
+    speed: Option<u32>,
+}
+
+impl UiArgs {
+    fn settings(&self) -> Settings {
+        Settings {
+            font: self.font,
+            view: self.view,
+            countdown: self.countdown,
+            theme: self.theme,
+            volume: self.volume,
+            speak: self.speak.then_some(true),
+            notify: self.notify.then_some(true),
+            visual_bell: self.visual_bell.then_some(true),
+            notes: self.notes.then_some(true),
+            snooze: self.snooze.clone(),
+            max_snoozes: self.max_snoozes,
+            idle: self.idle.clone(),
+            work_warning: self.work_warning.clone(),
+            break_warning: self.break_warning.clone(),
+            hard_break: self.hard_break.clone(),
+            get_ready: self.get_ready.clone(),
+            break_debt: self.break_debt,
+            strict: self.strict.then_some(true),
+            end_of_day: self.end_of_day.then_some(true),
+            manual_advance: self.manual_advance.then_some(true),
+            alert_repeat: self.alert_repeat.clone(),
+            max_alert_repeats: self.max_alert_repeats,
+            ambient: self.ambient,
+            ambient_volume: self.ambient_volume,
+            daily_target: self.daily_target.clone(),
+            weekly_target: self.weekly_target.clone(),
+            clock: self.clock,
+            sidebar: self.sidebar.then_some(true),
+            mascot: self.mascot.then_some(true),
+            animations: self.no_animations.then_some(false),
+            spinner: self.no_spinner.then_some(false),
+            low_power: self.low_power.then_some(true),
+            ..Settings::default()
+        }
+    }
+}
+
+fn parse_duration(value: &str, what: &str) -> Result<Duration> {
+    value
+        .parse::<humantime::Duration>()
+        .map(Duration::from)
+        .map_err(|err| OpomodoroError::parse(format!("{what} argument"), err))
+}
+
+/// Parse a `WORK/BREAK [xCYCLES]` schedule into settings.
+fn parse_schedule(schedule: &str) -> Result<Settings> {
+    let invalid = |message: &str| OpomodoroError::parse("schedule", message);
+    let (times, cycles) = match schedule.split_once('x') {
+        Some((times, cycles)) => {
+            let cycles = cycles
+                .trim()
+                .parse::<u32>()
+                .map_err(|err| OpomodoroError::parse("schedule cycles", err))?;
+            (times, Some(cycles))
+        }
+        None => (schedule, None),
+    };
+    let Some((work, break_time)) = times.split_once('/') else {
+        return Err(invalid("expected WORK/BREAK, e.g. 25m/5m x4"));
+    };
+    let (work, break_time) = (work.trim(), break_time.trim());
+    if work.is_empty() || break_time.is_empty() {
+        return Err(invalid("expected WORK/BREAK, e.g. 25m/5m x4"));
+    }
+    Ok(Settings {
+        work: Some(work.to_string()),
+        break_time: Some(break_time.to_string()),
+        cycles,
+        ..Settings::default()
+    })
 }
 
-fn main () -> io::Result<()> {
+/// How late a scheduled session may still start, e.g. when the machine
+/// woke up just after its time.
+const MISSED_SLOT: Duration = Duration::from_secs(10 * 60);
+
+/// The next time the clock shows `value`, e.g. `09:00`.
+fn parse_start_time(value: &str) -> std::result::Result<DateTime<Local>, String> {
+    let time = NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .map_err(|_| format!("expected a time like 09:00, got {value:?}"))?;
+    let now = Local::now();
+    let mut day = now.date_naive();
+    loop {
+        // Skip a day where the time falls in a DST gap.
+        if let Some(at) = day.and_time(time).and_local_timezone(Local).earliest()
+            && at > now {
+            return Ok(at);
+        }
+        day = day.succ_opt().ok_or("no such day")?;
+    }
+}
+
+fn parse_length(value: &str) -> std::result::Result<Duration, String> {
+    let length = humantime::parse_duration(value).map_err(|e| e.to_string())?;
+    opomodoro::check_length("length", length).map_err(|e| e.to_string())?;
+    Ok(length)
+}
+
+fn parse_speed(value: &str) -> std::result::Result<u32, String> {
+    match value.trim_end_matches('x').parse::<u32>() {
+        Ok(speed) if speed > 0 => Ok(speed),
+        _ => Err(format!("expected a speed like 100x, got {value:?}")),
+    }
+}
+
+/// Settings from the config file, over the shared one for a profile, with
+/// the project's file on top.
+fn file_settings() -> Result<Settings> {
+    let shared = match Settings::shared_path() {
+        Some(path) => Settings::load(&path)?,
+        None => Settings::default(),
+    };
+    let global = match Settings::default_path() {
+        Some(path) => shared.merge(Settings::load(&path)?),
+        None => shared,
+    };
+    match project_path() {
+        Some(path) => Ok(global.merge(Settings::load(&path)?)),
+        None => Ok(global),
+    }
+}
+
+/// The settings a session for `args` runs with: the config files, the
+/// preset for the day it starts on, then the flags.
+fn start_settings(args: &StartArgs) -> Result<Settings> {
+    let day = args.at.unwrap_or_else(Local::now).weekday();
+    Ok(file_settings()?.with_preset(day, args.preset.as_deref())?.merge(args.settings()))
+}
+
+/// The project config file that applies in the current directory.
+fn project_path() -> Option<PathBuf> {
+    Settings::find_project(&std::env::current_dir().ok()?)
+}
+
+fn build_config(settings: &Settings) -> Result<Config> {
+    let config = Config {
+        work_time: parse_duration(settings.work(), "work time")?,
+        break_time: parse_duration(settings.break_time(), "break time")?,
+        cycles: settings.cycles(),
+        long_break: None,
+        late: settings.late(),
+        warm_up: optional_duration(&settings.warm_up, "warm-up")?,
+        cool_down: optional_duration(&settings.cool_down, "cool-down")?,
+        font: settings.font(),
+        view: settings.view(),
+        countdown: settings.countdown(),
+        theme: settings.theme(),
+        no_color: theme::no_color(),
+        volume: settings.volume(),
+        speak: settings.speak(),
+        notify: settings.notify(),
+        visual_bell: settings.visual_bell(),
+        history: settings.history_path()?,
+        state: StateFile::default_path(),
+        tasks: TaskFile::default_path(),
+        notes: settings.notes(),
+        snooze: parse_duration(settings.snooze(), "snooze")?,
+        max_snoozes: settings.max_snoozes(),
+        idle: settings.idle.as_deref().map(|idle| parse_duration(idle, "idle")).transpose()?,
+        work_warning: optional_duration(&settings.work_warning, "work warning")?,
+        break_warning: optional_duration(&settings.break_warning, "break warning")?,
+        hard_break: optional_duration(&settings.hard_break, "hard break")?,
+        get_ready: optional_duration(&settings.get_ready, "get ready")?,
+        break_debt: settings.break_debt(),
+        pause_budget: optional_duration(&settings.pause_budget, "pause budget")?
+            .map(|budget| (budget, settings.pause_overrun())),
+        strict: settings.strict(),
+        end_of_day: settings.end_of_day().then(DayFile::default_path).flatten(),
+        manual_advance: if settings.manual_advance() {
+            let every = parse_duration(settings.alert_repeat(), "alert repeat")?;
+            Some((every, settings.max_alert_repeats()))
+        } else {
+            None
+        },
+        block_commands: match (&settings.block_command, &settings.unblock_command) {
+            (Some(block), Some(unblock)) => Some((block.clone(), unblock.clone())),
+            (None, None) => None,
+            _ => {
+                return Err(OpomodoroError::parse(
+                    "config",
+                    "block_command and unblock_command go together; set both or neither",
+                ));
+            }
+        },
+        ambient: settings.ambient.map(|sound| (sound, settings.ambient_volume())),
+        targets: targets(settings)?,
+        task: settings.task.clone(),
+        clock: settings.clock(),
+        sidebar: settings.sidebar(),
+        mascot: settings.mascot(),
+        animations: settings.animations(),
+        spinner: settings.spinner(),
+        low_power: settings.low_power(),
+        messages: messages(settings)?,
+        chain: Vec::new(),
+        single: None,
+        intervals: None,
+    };
+    // Only `daemon --recurring` uses these, but catch mistakes early.
+    autostart(settings)?;
+    check_config(config)
+}
+
+/// Parse a `--then` block of `key=value` pairs, e.g. `work=50m cycles=2`.
+fn parse_block(text: &str) -> Result<BlockSettings> {
+    let mut block = BlockSettings::default();
+    for pair in text.split_whitespace() {
+        let Some((key, value)) = pair.split_once('=') else {
+            return Err(OpomodoroError::parse("block", format!("expected key=value, got {pair:?}")));
+        };
+        let value = value.to_string();
+        match key {
+            "name" => block.name = Some(value),
+            "work" => block.work = Some(value),
+            "break" => block.break_time = Some(value),
+            "cycles" => {
+                let cycles = value.parse().map_err(|err| OpomodoroError::parse("block cycles", err))?;
+                block.cycles = Some(cycles);
+            }
+            _ => {
+                return Err(OpomodoroError::parse(
+                    "block",
+                    format!("unknown key {key:?}, expected name, work, break or cycles"),
+                ));
+            }
+        }
+    }
+    Ok(block)
+}
+
+/// A block as it will run, with what it leaves unset taken from `settings`.
+fn build_block(block: &BlockSettings, settings: &Settings) -> Result<Block> {
+    let break_time = block.break_time.as_deref().unwrap_or(settings.break_time());
+    // A break on its own is a block of just that break.
+    let work_time = match (&block.work, &block.break_time) {
+        (None, Some(_)) => None,
+        (work, _) => Some(parse_duration(work.as_deref().unwrap_or(settings.work()), "work time")?),
+    };
+    Ok(Block {
+        name: block.name.clone(),
+        work_time,
+        break_time: parse_duration(break_time, "break time")?,
+        cycles: block.cycles.unwrap_or(settings.cycles()),
+    })
+}
+
+/// The config file's day plan.
+fn day_plan(settings: &Settings) -> Result<Vec<Block>> {
+    let blocks = settings.day.as_deref().unwrap_or_default();
+    blocks.iter().map(|block| build_block(block, settings)).collect()
+}
+
+/// The blocks `args` chain together: the day plan or this session first,
+/// then each `--then`. Empty when there is nothing to chain.
+fn chain(args: &StartArgs, settings: &Settings) -> Result<Vec<Block>> {
+    let mut chain = if args.day {
+        let plan = day_plan(settings)?;
+        if plan.is_empty() {
+            return Err(OpomodoroError::parse(
+                "day plan",
+                "the config file has no [[day]] blocks; add some with `opomodoro config edit`",
+            ));
+        }
+        plan
+    } else if args.then.is_empty() {
+        return Ok(Vec::new());
+    } else {
+        vec![build_block(&BlockSettings::default(), settings)?]
+    };
+    for text in &args.then {
+        chain.push(build_block(&parse_block(text)?, settings)?);
+    }
+    Ok(chain)
+}
+
+fn targets(settings: &Settings) -> Result<Targets> {
+    Ok(Targets {
+        daily: optional_duration(&settings.daily_target, "daily target")?,
+        weekly: optional_duration(&settings.weekly_target, "weekly target")?,
+    })
+}
+
+/// The message templates, with the built-in wording for those not set.
+fn messages(settings: &Settings) -> Result<Messages> {
+    let mut messages = Messages::default();
+    let Some(set) = &settings.messages else {
+        return Ok(messages);
+    };
+    let template = |name: &str, text: &Option<String>| -> Result<Option<Template>> {
+        let template = text.as_deref().map(str::parse::<Template>).transpose();
+        template.map_err(|e| OpomodoroError::parse(format!("messages.{name}"), e))
+    };
+    if let Some(phase_start) = template("phase_start", &set.phase_start)? {
+        messages.phase_start = phase_start;
+    }
+    messages.phase_end = template("phase_end", &set.phase_end)?;
+    if let Some(work_warning) = template("work_warning", &set.work_warning)? {
+        messages.work_warning = work_warning;
+    }
+    if let Some(break_warning) = template("break_warning", &set.break_warning)? {
+        messages.break_warning = break_warning;
+    }
+    if let Some(next_block) = template("next_block", &set.next_block)? {
+        messages.next_block = next_block;
+    }
+    if let Some(session_end) = template("session_end", &set.session_end)? {
+        messages.session_end = session_end;
+    }
+    Ok(messages)
+}
+
+fn optional_duration(value: &Option<String>, what: &str) -> Result<Option<Duration>> {
+    value.as_deref().map(|value| parse_duration(value, what)).transpose()
+}
+
+fn check_config(config: Config) -> Result<Config> {
+    config.validate()?;
+    Ok(config)
+}
+
+fn main () -> ExitCode {
+    match try_main() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            if matches!(err, OpomodoroError::AlreadyRunning { .. }) {
+                eprintln!("Check on it with `opomodoro status`, or pass --force to run another.");
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Log to `path` only; stdout and stderr belong to the TUI.
+fn init_logging(path: &Path) -> Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| {
+            std::io::Error::new(e.kind(), format!("could not open log file {}: {e}", path.display()))
+        })?;
+    tracing_subscriber::fmt()
+        .with_writer(std::sync::Mutex::new(file))
+        .with_ansi(false)
+        .with_max_level(tracing::Level::DEBUG)
+        .init();
+    Ok(())
+}
+
+fn try_main() -> Result<()> {
     let running = Arc::new(AtomicBool::new(true));
     let r = running.clone();
 
     ctrlc::set_handler(move || {
-        r.store(false, Ordering::Relaxed);
-    }).expect("Error setting Ctrl-C handler");
+        // Ctrl-C, SIGTERM and SIGHUP all end the session like `q`. A second
+        // signal means it did not wind down; give the shell back instead.
+        if !r.swap(false, Ordering::Relaxed) {
+            #[cfg(feature = "terminal")]
+            terminal::restore();
+            blocker::release();
+            process::exit(130);
+        }
+    }).expect("Error setting signal handler");
 
-    let cli = Cli::parse();
+    let cli = Cli::from_arg_matches(&cli_command().get_matches()).unwrap_or_else(|e| e.exit());
+    if let Some(path) = &cli.log_file {
+        init_logging(path)?;
+    }
+    profile::select(cli.profile)?;
 
-    let work_time = cli.work_time
-        .parse::<humantime::Duration>()
-        .unwrap_or_else(|err| {
-            eprintln!("Issue parsing work time argument: {err}");
-            process::exit(1);
-            
-        })
-        .as_secs();
-    
-    let break_time = cli.break_time
-        .parse::<humantime::Duration>()
-        .unwrap_or_else(|err| {
-            eprintln!("Issue parsing break time argument: {err}");
-            process::exit(1);
-            
-        })
-        .as_secs();
-    
-    let cycles = cli.num_cycles;
+    match cli.command.unwrap_or(Command::Start(cli.start)) {
+        #[cfg(unix)]
+        Command::Start(StartArgs { spectate: Some(addr), ui, .. }) => {
+            join(&addr, &ui, true, running.as_ref())
+        }
+        Command::Start(args) => {
+            let _lock = match lock_instance(args.force) {
+                Err(err @ OpomodoroError::AlreadyRunning { .. }) => {
+                    return match ask_attach()? {
+                        Some(true) => attach(&args.ui, running.as_ref()),
+                        Some(false) => Ok(()),
+                        None => Err(err),
+                    };
+                }
+                lock => lock?,
+            };
+            check_day(args.override_day)?;
+            #[cfg(feature = "terminal")]
+            if args.replay.is_none() && !args.plain {
+                first_run()?;
+            }
+            let settings = start_settings(&args)?;
+            let mut config = check_config(start_config(&args, &settings)?)?;
+            let options = SessionOptions {
+                at: args.at,
+                plan: args.plan,
+                listen: _lock.is_some(),
+                plain: args.plain,
+                tasks: load_tasks(args.tasks_file.as_deref())?,
+                #[cfg(feature = "terminal")]
+                replay: load_replay(args.replay.as_deref())?,
+                #[cfg(feature = "terminal")]
+                record: args.record.clone(),
+                integrations: integrations(&args, &settings, &mut config)?,
+                #[cfg(unix)]
+                host: args.host,
+                #[cfg(unix)]
+                passphrase: settings.team_passphrase.clone(),
+                #[cfg(all(unix, feature = "http"))]
+                serve: args.serve,
+                ..SessionOptions::default()
+            };
+            run_session(config, running.as_ref(), options)
+        }
+        Command::Pause => control(Remote::Pause),
+        Command::Skip => control(Remote::Skip),
+        Command::Lengths(args) => control(Remote::Lengths(args.work, args.break_time)),
+        Command::Resume(args) => {
+            if send_control(Remote::Unpause)? {
+                return Ok(());
+            }
+            let _lock = lock_instance(args.force)?;
+            let state = match load_state()? {
+                Some(state) if !state.active => state,
+                _ => {
+                    println!("Nothing to resume.");
+                    return Ok(());
+                }
+            };
+            let mut config = build_config(&file_settings()?.merge(args.ui.settings()))?;
+            config.work_time = Duration::from_secs(state.work_secs);
+            config.break_time = Duration::from_secs(state.break_secs);
+            config.cycles = state.num_cycles;
+            config.late = state.late;
+            let config = check_config(config)?;
+            let options = SessionOptions {
+                resume: Some(state),
+                listen: _lock.is_some(),
+                ..SessionOptions::default()
+            };
+            run_session(config, running.as_ref(), options)
+        }
+        Command::Status => print_status(),
+        Command::Stats(args) => print_stats(&args),
+        Command::Config { command: None } => print_config(),
+        Command::Config { command: Some(ConfigCommand::Get { key }) } => {
+            let value = file_settings()?.get(&key).ok_or_else(|| {
+                OpomodoroError::parse("config key", format!("unknown key {key:?}"))
+            })?;
+            println!("{}", show_value(&value));
+            Ok(())
+        }
+        Command::Config { command: Some(ConfigCommand::Set { key, value }) } => {
+            let mut file = ConfigFile::open(&config_path()?)?;
+            file.set(&key, &value)?;
+            // Reject values that parse but would not run, like `work = "0s"`.
+            build_config(&file.settings()?)?;
+            file.save()?;
+            if let Some(value) = file.settings()?.get(&key) {
+                println!("{key} = {}", show_value(&value));
+            }
+            Ok(())
+        }
+        Command::Config { command: Some(ConfigCommand::Edit) } => edit_config(),
+        Command::History { command: HistoryCommand::Prune(args) } => prune_history(&args),
+        Command::History { command: HistoryCommand::Merge { paths } } => merge_history(&paths),
+        Command::Backup { command: BackupCommand::Create { path } } => create_backup(&path),
+        Command::Backup { command: BackupCommand::Restore { path, force } } => {
+            restore_backup(&path, force)
+        }
+        Command::Schedule { command: ScheduleCommand::Export { path, name } } => export_schedule(&path, name),
+        Command::Schedule { command: ScheduleCommand::Import { path } } => import_schedule(&path),
+        Command::Help { command, man } => print_help(&command, man),
+        Command::Daemon(DaemonArgs {
+            command: Some(DaemonCommand::Install { recurring, print, force }), ..
+        }) => install_daemon(recurring, print, force),
+        Command::Daemon(args) if args.recurring => run_recurring(&args.start, running.as_ref()),
+        Command::Daemon(args) => {
+            let _lock = lock_instance(args.start.force)?;
+            daemon_session(&args.start, running.as_ref())
+        }
+        Command::Attach(ui) => attach(&ui, running.as_ref()),
+        Command::Join(args) => {
+            let addr = match args.addr {
+                Some(addr) => addr,
+                None => pick_shared_timer()?,
+            };
+            join(&addr, &args.ui, false, running.as_ref())
+        }
+        #[cfg(all(feature = "http", feature = "terminal"))]
+        Command::Overlay(args) => {
+            let theme = file_settings()?.theme().theme();
+            let path = opomodoro::overlay::write(&args.out, &args.url, &theme)?;
+            println!("Wrote {}.", path.display());
+            println!("Add it to OBS as a local file browser source, and start sessions with");
+            let addr = args.url.split("://").last().unwrap_or(&args.url).trim_end_matches('/');
+            println!("`--serve {addr}`.");
+            Ok(())
+        }
+        #[cfg(feature = "webdav")]
+        Command::Sync => sync_history(),
+        Command::Profiles => {
+            let profiles = profile::list();
+            if profiles.is_empty() {
+                println!("No profiles yet; start one with e.g. `opomodoro --profile study`.");
+            }
+            for name in profiles {
+                let current = if profile::current() == Some(&name) { " (in use)" } else { "" };
+                println!("{name}{current}");
+            }
+            Ok(())
+        }
+        Command::Timer(args) => run_timer(&args, running.as_ref()),
+        Command::Simulate(args) => {
+            let mut settings = file_settings()?.with_preset(Local::now().weekday(), args.preset.as_deref())?;
+            if let Some(schedule) = &args.schedule {
+                settings = settings.merge(parse_schedule(schedule)?);
+            }
+            if args.late {
+                settings.late = Some(true);
+            }
+            let config = build_config(&settings)?;
+            print_simulation(&config, args.speed);
+            Ok(())
+        }
+    }
+}
 
-    let late: bool = cli.late;
+/// The config `start` runs with: `settings`, and the chain or the
+/// intervals `args` asks for.
+fn start_config(args: &StartArgs, settings: &Settings) -> Result<Config> {
+    let mut config = build_config(settings)?;
+    config.chain = chain(args, settings)?;
+    if let Some(intervals) = &args.intervals {
+        config.intervals = Some(intervals.clone());
+        // A workout, not the day's work: nothing to plan, review, or call
+        // a day over.
+        config.warm_up = None;
+        config.cool_down = None;
+        config.end_of_day = None;
+        config.notes = false;
+    }
+    Ok(config)
+}
 
-    let config = Config { 
-        work_time: Duration::from_secs(work_time), 
-        break_time: Duration::from_secs(break_time), 
-        cycles, 
-        late 
+/// Run a session for `args` without a TUI.
+fn daemon_session(args: &StartArgs, running: &AtomicBool) -> Result<()> {
+    check_day(args.override_day)?;
+    let settings = start_settings(args)?;
+    let mut config = check_config(start_config(args, &settings)?)?;
+    // Nobody is there to answer the note or idle prompts, or to advance.
+    config.notes = false;
+    config.idle = None;
+    config.manual_advance = None;
+    let options = SessionOptions {
+        at: args.at,
+        tasks: load_tasks(args.tasks_file.as_deref())?,
+        integrations: integrations(args, &settings, &mut config)?,
+        #[cfg(unix)]
+        host: args.host,
+        #[cfg(unix)]
+        passphrase: settings.team_passphrase.clone(),
+        #[cfg(all(unix, feature = "http"))]
+        serve: args.serve,
+        ..SessionOptions::default()
     };
+    run_daemon(config, running, options)
+}
 
-    let mut app = App::new(config, &running.as_ref());
-    ratatui::run(|terminal| 
-        App::run(&mut app, terminal))?;
+/// `opomodoro timer`: a session of the one phase, with the settings from
+/// the config file for how it looks and sounds.
+fn run_timer(args: &TimerArgs, running: &AtomicBool) -> Result<()> {
+    let _lock = lock_instance(args.force)?;
+    let mut config = build_config(&file_settings()?.merge(args.ui.settings()))?;
+    let label = args.label.as_deref().unwrap_or("Timer");
+    config.single = Some(Phase::build(PhaseKind::from(label), args.length));
+    // Not a day's session: no task to work on, nothing to plan, review or
+    // call a day.
+    config.task = None;
+    config.warm_up = None;
+    config.cool_down = None;
+    config.end_of_day = None;
+    config.notes = false;
+    let options = SessionOptions { listen: _lock.is_some(), plain: args.plain, ..SessionOptions::default() };
+    run_session(check_config(config)?, running, options)
+}
+
+/// Start a daemon session at every `autostart` time until stopped, saying
+/// so with a desktop notification. The config is read again for each
+/// session, so changes apply from the next one.
+fn run_recurring(args: &StartArgs, running: &AtomicBool) -> Result<()> {
+    #[cfg(unix)]
+    let mut supervisor = systemd::Notifier::from_env();
+    while running.load(Ordering::Relaxed) {
+        let schedule = autostart(&file_settings()?)?;
+        let Some(next) = schedule.next_after(Local::now()) else {
+            return Err(OpomodoroError::parse(
+                "autostart",
+                "no start times; set some with `opomodoro config set autostart '[\"weekdays 09:00\"]'`",
+            ));
+        };
+        let slots: Vec<_> = schedule.slots().iter().map(ToString::to_string).collect();
+        println!("Starting sessions {}; the next at {}.", slots.join(", "), next.format("%a %H:%M"));
+        #[cfg(unix)]
+        if let Some(supervisor) = &supervisor {
+            supervisor.ready(&format!("Next session at {}", next.format("%a %H:%M")));
+        }
+        while running.load(Ordering::Relaxed) && Local::now() < next {
+            #[cfg(unix)]
+            if let Some(supervisor) = &mut supervisor {
+                supervisor.keep_alive(std::time::Instant::now());
+            }
+            std::thread::sleep(Duration::from_secs(1));
+        }
+        if !running.load(Ordering::Relaxed) {
+            break;
+        }
+        let late = (Local::now() - next).to_std().unwrap_or_default();
+        if late > MISSED_SLOT {
+            // Most likely the machine slept through it.
+            tracing::info!(%next, "missed a scheduled session");
+            continue;
+        }
+        let _lock = match lock_instance(args.force) {
+            Err(OpomodoroError::AlreadyRunning { .. }) => {
+                println!("Another timer is running; skipping the session at {}.", next.format("%H:%M"));
+                continue;
+            }
+            lock => lock?,
+        };
+        if let Err(e) = desktop::notify("Pomodoro session started", "See it with `opomodoro attach`.") {
+            tracing::warn!("could not send a desktop notification: {e}");
+        }
+        match daemon_session(args, running) {
+            Err(OpomodoroError::DayDone { .. }) => {
+                println!("The day is done; skipping the session at {}.", next.format("%H:%M"));
+            }
+            done => done?,
+        }
+    }
+    #[cfg(unix)]
+    if let Some(supervisor) = &supervisor {
+        supervisor.stopping();
+    }
+    Ok(())
+}
+
+/// The config's `autostart` times.
+fn autostart(settings: &Settings) -> Result<Schedule> {
+    let entries = settings.autostart.as_deref().unwrap_or_default();
+    Schedule::parse(entries).map_err(|e| OpomodoroError::parse("autostart", e))
+}
+
+/// Refuse to start once end-of-day mode called today done, unless told to
+/// `override` it.
+fn check_day(override_day: bool) -> Result<()> {
+    let Some(path) = DayFile::default_path().filter(|_| !override_day) else {
+        return Ok(());
+    };
+    match DayFile::new(path).closed_today()? {
+        Some(at) => Err(OpomodoroError::DayDone { at }),
+        None => Ok(()),
+    }
+}
+
+/// Make sure this is the only instance running a session, unless `force`d.
+fn lock_instance(force: bool) -> Result<Option<InstanceLock>> {
+    let Some(path) = InstanceLock::default_path() else {
+        return Ok(None);
+    };
+    match InstanceLock::acquire(&path) {
+        Ok(lock) => Ok(Some(lock)),
+        Err(OpomodoroError::AlreadyRunning { .. }) if force => {
+            tracing::warn!("another timer is running, continuing because of --force");
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// How a session starts, besides its config.
+#[derive(Default)]
+struct SessionOptions {
+    resume: Option<SessionState>,
+    /// Count down to this time before starting.
+    at: Option<DateTime<Local>>,
+    /// Open the planning screen first.
+    plan: bool,
+    /// Serve the control socket.
+    listen: bool,
+    /// Print phase changes rather than run the TUI, which a build without
+    /// it always does.
+    #[cfg_attr(not(feature = "terminal"), allow(dead_code))]
+    plain: bool,
+    tasks: Option<TodoFile>,
+    /// Keys to press, instead of waiting for them.
+    #[cfg(feature = "terminal")]
+    replay: Option<ReplayInput>,
+    /// Where to record the session.
+    #[cfg(feature = "terminal")]
+    record: Option<PathBuf>,
+    integrations: Integrations,
+    /// Share the timer here, next to the control socket.
+    #[cfg(unix)]
+    host: Option<SocketAddr>,
+    /// What the team shares to join the timer.
+    #[cfg(unix)]
+    passphrase: Option<String>,
+    /// Serve the REST API here, next to the control socket.
+    #[cfg(all(unix, feature = "http"))]
+    serve: Option<SocketAddr>,
+}
+
+#[cfg(feature = "terminal")]
+fn run_session(config: Config, running: &AtomicBool, options: SessionOptions) -> Result<()> {
+    if options.plain {
+        return run_plain(config, running, options);
+    }
+    let SessionOptions {
+        resume, at, plan, listen, mut tasks, replay, record, integrations, ..
+    } = options;
+    tracing::info!(?config, resumed = resume.is_some(), "starting session");
+    #[cfg(all(unix, feature = "http"))]
+    let history = config.history.clone();
+    let mut app = App::new(config, running);
+    app.set_integrations(integrations);
+    if let Some(replay) = replay {
+        app.set_input(replay.then(TerminalInput));
+    }
+    if let Some(state) = &resume {
+        app.resume(state);
+    }
+    if let Some(file) = &tasks {
+        app.set_plan(file.plan());
+    }
+    if let Some(at) = at {
+        app.start_at(at);
+    }
+    if plan {
+        app.plan_session();
+    }
+    #[cfg(unix)]
+    let mut _team = None;
+    #[cfg(any(unix, windows))]
+    if listen && let Some(path) = ipc::default_path() {
+        match ControlServer::bind(&path) {
+            Ok(server) => {
+                #[cfg(unix)]
+                {
+                    _team = host_team(options.host, options.passphrase.as_deref(), &server)?;
+                }
+                #[cfg(all(unix, feature = "http"))]
+                serve_http(options.serve, &server, history)?;
+                app.listen(server)
+            }
+            Err(e) => tracing::warn!("could not open control socket {}: {e}", path.display()),
+        }
+    }
+    #[cfg(unix)]
+    if !listen && options.host.is_some() {
+        eprintln!("Warning: not sharing the timer without the control socket");
+    }
+    #[cfg(all(unix, feature = "http"))]
+    if !listen && options.serve.is_some() {
+        eprintln!("Warning: not serving the HTTP API without the control socket");
+    }
+    #[cfg(not(any(unix, windows)))]
+    let _ = listen;
+    run_tui(&mut app, record.as_deref())?;
+    print_session(&app);
+    save_tasks(tasks.as_mut(), &app)?;
     println!("Exiting...");
     std::thread::sleep(Duration::from_millis(500));
     println!("See you next time!");
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(not(feature = "terminal"))]
+fn run_session(config: Config, running: &AtomicBool, options: SessionOptions) -> Result<()> {
+    run_plain(config, running, options)
+}
+
+/// Run a session with the plain frontend, which prints each phase change
+/// and takes no keys; the control socket stands in for them.
+fn run_plain(config: Config, running: &AtomicBool, options: SessionOptions) -> Result<()> {
+    let SessionOptions { resume, at, plan, listen, tasks, integrations, .. } = options;
+    if plan || tasks.is_some() {
+        let message = if cfg!(feature = "terminal") {
+            "--plan and --tasks-file need the terminal frontend; leave out --plain"
+        } else {
+            "--plan and --tasks-file need the terminal frontend, which this build does not have"
+        };
+        return Err(io::Error::new(io::ErrorKind::Unsupported, message).into());
+    }
+    tracing::info!(?config, resumed = resume.is_some(), "starting plain session");
+    // With no keys, a held phase would never go on.
+    let config = Config { manual_advance: None, ..config };
+    let mut engine = Engine::new(&config);
+    if let Some(state) = &resume {
+        engine.restore(state.cycle, state.phase.clone(), state.remaining());
+    }
+    let mut plain = opomodoro::plain::Options { at, integrations, ..Default::default() };
+    #[cfg(unix)]
+    let mut _team = None;
+    #[cfg(any(unix, windows))]
+    if listen && let Some(path) = ipc::default_path() {
+        match ControlServer::bind(&path) {
+            Ok(server) => {
+                #[cfg(unix)]
+                {
+                    _team = host_team(options.host, options.passphrase.as_deref(), &server)?;
+                }
+                #[cfg(all(unix, feature = "http"))]
+                serve_http(options.serve, &server, config.history.clone())?;
+                plain.control = Some(server);
+            }
+            Err(e) => tracing::warn!("could not open control socket {}: {e}", path.display()),
+        }
+    }
+    #[cfg(unix)]
+    if !listen && options.host.is_some() {
+        eprintln!("Warning: not sharing the timer without the control socket");
+    }
+    #[cfg(all(unix, feature = "http"))]
+    if !listen && options.serve.is_some() {
+        eprintln!("Warning: not serving the HTTP API without the control socket");
+    }
+    #[cfg(not(any(unix, windows)))]
+    let _ = listen;
+    let summary = opomodoro::plain::run(engine, &config, plain, running, &mut io::stdout())?;
+    print_summary("Session", &summary);
+    Ok(())
+}
+
+/// Share the session behind `server` on `addr`, if given, for as long as
+/// the returned host is kept. With a `passphrase`, only those who know it
+/// can join.
+#[cfg(unix)]
+fn host_team(
+    addr: Option<SocketAddr>,
+    passphrase: Option<&str>,
+    server: &ControlServer,
+) -> Result<Option<TeamHost>> {
+    let Some(addr) = addr else {
+        return Ok(None);
+    };
+    let host = opomodoro::net::host(addr, server.handle(), passphrase)
+        .map_err(|e| io::Error::new(e.kind(), format!("could not share on {addr}: {e}")))?;
+    tracing::info!(%addr, "sharing the timer");
+    Ok(Some(host))
+}
+
+/// Let the user choose among the timers shared on the local network.
+#[cfg(all(unix, feature = "mdns"))]
+fn pick_shared_timer() -> Result<String> {
+    use std::io::{IsTerminal, Write};
+    println!("Looking for shared timers...");
+    let timers = opomodoro::discovery::browse(Duration::from_secs(3))?;
+    let timer = match timers.as_slice() {
+        [] => return Err(io::Error::other("no shared timers found on the network").into()),
+        [timer] => timer,
+        _ if !io::stdin().is_terminal() => {
+            return Err(io::Error::other("several shared timers found; give an address").into());
+        }
+        _ => {
+            for (n, timer) in timers.iter().enumerate() {
+                println!("  {}. {} ({})", n + 1, timer.name, timer.addr);
+            }
+            print!("Join which timer? [1-{}] ", timers.len());
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            answer
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| timers.get(n.wrapping_sub(1)))
+                .ok_or_else(|| OpomodoroError::parse("choice", format!("{:?}", answer.trim())))?
+        }
+    };
+    println!("Joining {} at {}.", timer.name, timer.addr);
+    Ok(timer.addr.to_string())
+}
+
+#[cfg(not(all(unix, feature = "mdns")))]
+fn pick_shared_timer() -> Result<String> {
+    let message = "give the address of the shared timer; this build cannot look for one";
+    Err(io::Error::other(message).into())
+}
+
+/// Serve the REST API on `addr`, if given, passing requests on to `server`.
+#[cfg(all(unix, feature = "http"))]
+fn serve_http(addr: Option<SocketAddr>, server: &ControlServer, history: Option<PathBuf>) -> Result<()> {
+    let Some(addr) = addr else {
+        return Ok(());
+    };
+    let history = history.map(history::open).transpose()?;
+    opomodoro::http::serve(addr, server.handle(), history)
+        .map_err(|e| io::Error::new(e.kind(), format!("could not serve on {addr}: {e}")))?;
+    tracing::info!(%addr, "serving the http api");
+    Ok(())
+}
+
+/// The integrations `args` and the settings ask for, with whatever earlier
+/// runs left in the outbox sent. The first one to come up with a task label
+/// supplies it, unless the config already has one.
+#[cfg_attr(not(all(feature = "github", feature = "jira")), allow(unused_variables, unused_mut))]
+fn integrations(
+    args: &StartArgs,
+    settings: &Settings,
+    config: &mut Config,
+) -> Result<Integrations> {
+    let mut integrations = Integrations::new(Outbox::default_path().map(Outbox::new));
+    #[cfg(feature = "github")]
+    if args.gh_issue.is_some() || settings.github_token.is_some() {
+        use opomodoro::integrations::github::{GitHub, IssueRef};
+        let issue = args.gh_issue.as_deref().map(IssueRef::parse).transpose()?;
+        integrations.push(Box::new(GitHub::new(
+            issue,
+            settings.github_api(),
+            settings.github_token.clone(),
+            settings.github_comment(),
+        )));
+    }
+    #[cfg(feature = "jira")]
+    if let (Some(url), Some(token)) = (&settings.jira_url, &settings.jira_token) {
+        use opomodoro::integrations::jira::Jira;
+        let email = settings.jira_email.as_deref();
+        integrations.push(Box::new(Jira::new(url, email, token)));
+    }
+    if settings.media_on_work.is_some() || settings.media_on_break.is_some() {
+        use opomodoro::integrations::media::{Media, MediaAction};
+        let action = |key: &'static str, value: &Option<String>| {
+            let action = value.as_deref().map(str::parse::<MediaAction>).transpose();
+            action.map_err(|e| OpomodoroError::parse(key, e))
+        };
+        integrations.push(Box::new(Media::new(
+            action("media_on_work", &settings.media_on_work)?,
+            action("media_on_break", &settings.media_on_break)?,
+        )));
+    }
+    for problem in integrations.flush() {
+        eprintln!("Warning: {problem}");
+    }
+    for integration in integrations.iter_mut() {
+        if config.task.is_some() {
+            break;
+        }
+        match integration.task_label() {
+            Ok(label) => config.task = label,
+            Err(e) => eprintln!("Warning: {e}"),
+        }
+    }
+    Ok(integrations)
+}
+
+/// Read the `--tasks-file`, if one was given.
+fn load_tasks(path: Option<&Path>) -> Result<Option<TodoFile>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let file = TodoFile::load(path).map_err(|e| {
+        io::Error::new(e.kind(), format!("could not read tasks from {}: {e}", path.display()))
+    })?;
+    Ok(Some(file))
+}
+
+/// Read the `--replay` script, if one was given.
+#[cfg(feature = "terminal")]
+fn load_replay(path: Option<&Path>) -> Result<Option<ReplayInput>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let script = std::fs::read_to_string(path).map_err(|e| {
+        io::Error::new(e.kind(), format!("could not read the replay {}: {e}", path.display()))
+    })?;
+    let replay = script.parse().map_err(|e| OpomodoroError::parse("the replay", e))?;
+    Ok(Some(replay))
+}
+
+/// Write the session's tallies back to the `--tasks-file`.
+#[cfg(feature = "terminal")]
+fn save_tasks<T: Timer>(file: Option<&mut TodoFile>, app: &App<T>) -> Result<()> {
+    let (Some(file), Some(plan)) = (file, app.plan()) else {
+        return Ok(());
+    };
+    file.save(plan).map_err(|e| {
+        io::Error::new(e.kind(), format!("could not write tasks to {}: {e}", file.path().display()))
+    })?;
+    Ok(())
+}
+
+/// Run `app` on the terminal, recording it to `record` if given.
+#[cfg(feature = "terminal")]
+fn run_tui<T: Timer>(app: &mut App<T>, record: Option<&Path>) -> Result<()> {
+    let Some(path) = record else {
+        return run_on(app, terminal::init());
+    };
+    let (width, height) = crossterm::terminal::size().map_err(OpomodoroError::Terminal)?;
+    let recorder = Recorder::create(io::stdout(), path, width, height).map_err(|e| {
+        io::Error::new(e.kind(), format!("could not record to {}: {e}", path.display()))
+    })?;
+    run_on(app, terminal::init_with(recorder))
+}
+
+#[cfg(feature = "terminal")]
+fn run_on<T: Timer, W: Write>(
+    app: &mut App<T>,
+    tui: io::Result<Terminal<CrosstermBackend<W>>>,
+) -> Result<()> {
+    let mut tui = tui.map_err(|e| {
+        terminal::restore();
+        OpomodoroError::Terminal(e)
+    })?;
+    let result = app.run(&mut tui);
+    terminal::restore();
+    result
+}
+
+#[cfg(feature = "terminal")]
+fn print_session<T: Timer>(app: &App<T>) {
+    for warning in app.warnings() {
+        eprintln!("{warning}");
+    }
+    let summary = app.summary();
+    println!(
+        "Session: {} pomodoro(s), {}m focused, {} interruption(s) ({} internal, {} external){}",
+        summary.pomodoros,
+        summary.focus.as_secs() / 60,
+        summary.interruptions(),
+        summary.internal_interruptions,
+        summary.external_interruptions,
+        extras(&summary),
+    );
+    let score = app.focus_score();
+    if score.score().is_some() {
+        println!("Focus score: {score}");
+    }
+    if let Some(plan) = app.plan() {
+        println!("Plan (actual/estimated pomodoros):");
+        for task in plan.tasks() {
+            let verdict = match task.actual.cmp(&task.estimate) {
+                cmp::Ordering::Less => "unfinished",
+                cmp::Ordering::Equal => "on estimate",
+                cmp::Ordering::Greater => "over estimate",
+            };
+            let verdict = match task.actual.checked_sub(task.estimate) {
+                Some(over) if over > 0 => format!("{verdict} by {over}"),
+                _ => verdict.to_string(),
+            };
+            println!("  {}/{}  {}  ({verdict})", task.actual, task.estimate, task.name);
+        }
+    }
+}
+
+#[cfg(all(any(unix, windows), feature = "terminal"))]
+fn run_daemon(config: Config, running: &AtomicBool, options: SessionOptions) -> Result<()> {
+    let SessionOptions { at, mut tasks, integrations, .. } = options;
+    let Some(path) = ipc::default_path() else {
+        return Err(unsupported().into());
+    };
+    let server = ControlServer::bind(&path)?;
+    #[cfg(unix)]
+    let _team = host_team(options.host, options.passphrase.as_deref(), &server)?;
+    #[cfg(all(unix, feature = "http"))]
+    serve_http(options.serve, &server, config.history.clone())?;
+    tracing::info!(?config, socket = %path.display(), "starting daemon session");
+    match at {
+        Some(at) => println!("Session starts at {}; see it with `opomodoro attach`.", at.format("%H:%M")),
+        None => println!("Session started; see it with `opomodoro attach`."),
+    }
+    let mut app = App::new(config, running);
+    app.set_integrations(integrations);
+    if let Some(file) = &tasks {
+        app.set_plan(file.plan());
+    }
+    if let Some(at) = at {
+        app.start_at(at);
+    }
+    #[cfg(unix)]
+    if let Some(supervisor) = systemd::Notifier::from_env() {
+        supervisor.ready("Session running");
+        app.supervise(supervisor);
+    }
+    app.serve(server);
+    print_session(&app);
+    save_tasks(tasks.as_mut(), &app)
+}
+
+#[cfg(all(any(unix, windows), feature = "terminal"))]
+fn attach(ui: &UiArgs, running: &AtomicBool) -> Result<()> {
+    let Some(path) = ipc::default_path() else {
+        return Err(unsupported().into());
+    };
+    let remote = RemoteEngine::connect(&path).map_err(|e| {
+        io::Error::new(e.kind(), format!("no session to attach to at {}: {e}", path.display()))
+    })?;
+    let mut config = build_config(&file_settings()?.merge(ui.settings()))?;
+    // The daemon keeps the history and state file.
+    config.history = None;
+    config.state = None;
+    config.tasks = None;
+    config.end_of_day = None;
+    config.notes = false;
+    config.idle = None;
+    let mut app = App::with_engine(config, remote, running);
+    run_tui(&mut app, None)?;
+    for warning in app.warnings() {
+        eprintln!("{warning}");
+    }
+    println!("Detached.");
+    Ok(())
+}
+
+/// Another instance holds the lock. If it is a daemon, ask whether to
+/// attach to it; `None` when there is nothing to attach to.
+#[cfg(any(unix, windows))]
+fn ask_attach() -> Result<Option<bool>> {
+    use std::io::{IsTerminal, Write};
+    let Some(path) = ipc::default_path() else {
+        return Ok(None);
+    };
+    if !io::stdin().is_terminal() || ipc::ControlClient::connect(&path).is_err() {
+        return Ok(None);
+    }
+    print!("Another timer is running: attach or quit? [a/q] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(Some(answer.trim().eq_ignore_ascii_case("a")))
+}
+
+#[cfg(not(all(any(unix, windows), feature = "terminal")))]
+fn run_daemon(_config: Config, _running: &AtomicBool, _options: SessionOptions) -> Result<()> {
+    Err(unsupported().into())
+}
+
+#[cfg(not(all(any(unix, windows), feature = "terminal")))]
+fn attach(_ui: &UiArgs, _running: &AtomicBool) -> Result<()> {
+    Err(unsupported().into())
+}
+
+/// Follow the timer shared at `addr`; a `spectator` is not offered any
+/// controls at all.
+#[cfg(all(unix, feature = "terminal"))]
+fn join(addr: &str, ui: &UiArgs, spectator: bool, running: &AtomicBool) -> Result<()> {
+    let settings = file_settings()?.merge(ui.settings());
+    let client = opomodoro::net::TeamClient::join(addr, settings.team_passphrase.as_deref())
+        .map_err(|e| io::Error::new(e.kind(), format!("no shared timer at {addr}: {e}")))?;
+    let remote = RemoteEngine::with_link(client)?;
+    let mut config = build_config(&settings)?;
+    // The host keeps the history and state file.
+    config.history = None;
+    config.state = None;
+    config.tasks = None;
+    config.end_of_day = None;
+    config.notes = false;
+    config.idle = None;
+    let mut app = App::with_engine(config, remote, running);
+    if spectator {
+        app.spectate();
+    }
+    run_tui(&mut app, None)?;
+    for warning in app.warnings() {
+        eprintln!("{warning}");
+    }
+    println!("Left the shared timer.");
+    Ok(())
+}
+
+#[cfg(not(all(unix, feature = "terminal")))]
+fn join(_addr: &str, _ui: &UiArgs, _spectator: bool, _running: &AtomicBool) -> Result<()> {
+    Err(unsupported().into())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn ask_attach() -> Result<Option<bool>> {
+    Ok(None)
+}
+
+/// What `pause`, `resume`, `skip` and `lengths` ask of a running session.
+#[derive(Clone, Copy)]
+enum Remote {
+    Pause,
+    Unpause,
+    Skip,
+    /// New work and break lengths, or the ones in use.
+    Lengths(Option<Duration>, Option<Duration>),
+}
+
+fn control(remote: Remote) -> Result<()> {
+    if !send_control(remote)? {
+        println!("No session running.");
+    }
+    Ok(())
+}
+
+/// Ask the running session to do `remote` and print how it is doing.
+/// Returns false when no session answers.
+#[cfg(any(unix, windows))]
+fn send_control(remote: Remote) -> Result<bool> {
+    let Some(path) = ipc::default_path() else {
+        return Ok(false);
+    };
+    let Ok(mut client) = ipc::ControlClient::connect(&path) else {
+        return Ok(false);
+    };
+    let request = match remote {
+        Remote::Pause => ipc::Request::Pause,
+        Remote::Unpause => ipc::Request::Unpause,
+        Remote::Skip => ipc::Request::Skip,
+        Remote::Lengths(work, break_time) => ipc::Request::SetLengths { work, break_time },
+    };
+    let snapshot = client.send(&request)?;
+    if snapshot.finished {
+        println!("Session finished.");
+    } else {
+        print_timer(
+            &snapshot.phase,
+            snapshot.cycle,
+            snapshot.num_cycles,
+            snapshot.remaining,
+            snapshot.paused,
+        );
+        if let Remote::Lengths(..) = remote {
+            println!(
+                "From the next phase on: {} work, {} breaks",
+                humantime::format_duration(snapshot.work_time),
+                humantime::format_duration(snapshot.break_time),
+            );
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn send_control(_remote: Remote) -> Result<bool> {
+    Ok(false)
+}
+
+fn unsupported() -> io::Error {
+    let message = if cfg!(feature = "terminal") {
+        "daemon mode is not available on this platform"
+    } else {
+        "this build has no terminal frontend; build opomodoro with the `terminal` feature for it"
+    };
+    io::Error::new(io::ErrorKind::Unsupported, message)
+}
+
+fn load_state() -> Result<Option<SessionState>> {
+    let Some(path) = StateFile::default_path() else {
+        return Ok(None);
+    };
+    StateFile::new(path).load().map_err(|err| match err.kind() {
+        std::io::ErrorKind::InvalidData => OpomodoroError::parse("session state", err),
+        _ => err.into(),
+    })
+}
+
+fn print_timer(phase: &PhaseKind, cycle: u32, num_cycles: u32, remaining: Duration, paused: bool) {
+    println!(
+        "{phase} · cycle {cycle}/{num_cycles} · {} left{}",
+        format_time(remaining.as_secs()),
+        if paused { " (paused)" } else { "" },
+    );
+}
+
+fn print_status() -> Result<()> {
+    match load_state()? {
+        Some(state) if state.active && !state.is_stale() => {
+            print_timer(&state.phase, state.cycle, state.num_cycles, state.remaining(), state.paused);
+        }
+        Some(state) if !state.active => {
+            println!("No session running.");
+            println!(
+                "A {} phase in cycle {}/{} was quit with {} left; continue it with `opomodoro resume`.",
+                state.phase,
+                state.cycle,
+                state.num_cycles,
+                format_time(state.remaining().as_secs()),
+            );
+        }
+        _ => println!("No session running."),
+    }
+    Ok(())
+}
+
+/// `H:MM:SS` offset into a session.
+fn format_offset(offset: Duration) -> String {
+    let secs = offset.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn print_simulation(config: &Config, speed: Option<u32>) {
+    let mut pomodoros = 0;
+    let mut focus = Duration::ZERO;
+    let total = simulate::simulate(config, speed, |step: Step| {
+        if step.phase.is_work() {
+            pomodoros += 1;
+            focus += step.phase.duration;
+        }
+        println!(
+            "{}  {:<9}  cycle {}/{}  {}",
+            format_offset(step.start),
+            step.phase.kind,
+            step.cycle,
+            config.cycles,
+            humantime::format_duration(step.phase.duration),
+        );
+    });
+    println!("{}  End", format_offset(total));
+    println!("{pomodoros} pomodoro(s), {}m focused", focus.as_secs() / 60);
+}
+
+fn print_summary(label: &str, summary: &Summary) {
+    println!(
+        "{label}: {} pomodoro(s), {}m focused, {} interruption(s){}",
+        summary.pomodoros,
+        summary.focus.as_secs() / 60,
+        summary.interruptions(),
+        extras(summary),
+    );
+}
+
+/// Today's focus score, and the scores of the days of the last week.
+fn print_scores(entries: &[history::Entry], today: NaiveDate) {
+    let week = today - chrono::Days::new(6);
+    let days: Vec<_> = score::by_day(entries).into_iter().filter(|(day, _)| *day >= week).collect();
+    if let Some((_, score)) = days.last().filter(|(day, _)| *day == today) {
+        println!("Focus score today: {score}");
+    }
+    let scores: Vec<_> = days
+        .iter()
+        .filter_map(|(day, score)| Some(format!("{} {}", day.format("%a"), score.score()?)))
+        .collect();
+    if scores.len() > 1 {
+        println!("Last 7 days: {}", scores.join(" · "));
+    }
+}
+
+/// How many work phases were voided and how many pomodoros went past
+/// their task's estimate, where any did.
+fn extras(summary: &Summary) -> String {
+    let mut extras = String::new();
+    if summary.voided > 0 {
+        extras += &format!(", {} voided", summary.voided);
+    }
+    if summary.over_budget > 0 {
+        extras += &format!(", {} over budget", summary.over_budget);
+    }
+    extras
+}
+
+fn print_stats(args: &StatsArgs) -> Result<()> {
+    let settings = file_settings()?;
+    let Some(path) = settings.history_path()? else {
+        println!("No history available on this platform.");
+        return Ok(());
+    };
+    let entries = history::open(path)?.load()?;
+    let today = Local::now().date_naive();
+    #[cfg(not(feature = "terminal"))]
+    if args.calendar {
+        return Err(unsupported().into());
+    }
+    #[cfg(feature = "terminal")]
+    if args.calendar {
+        let theme = settings.theme().theme();
+        print!("{}", opomodoro::calendar::render(&entries, today, &theme));
+        return Ok(());
+    }
+    if let Some(by) = args.by {
+        print_groups(&history::group(&entries, by, args.sort));
+        return Ok(());
+    }
+    if args.suggest {
+        print_suggestions(&suggest::analyze(&entries));
+        return Ok(());
+    }
+    let today_summary = Summary::from_entries(
+        entries.iter().filter(|entry| entry.started.date_naive() == today),
+    );
+    print_summary("Today", &today_summary);
+    print_summary("All time", &Summary::from_entries(&entries));
+    print_scores(&entries, today);
+    let targets = targets(&settings)?;
+    let focus = Focus::from_entries(&entries, Local::now());
+    let progress: Vec<_> = [("today", targets.daily, focus.today), ("this week", targets.weekly, focus.week)]
+        .into_iter()
+        .filter_map(|(label, target, done)| {
+            let target = target?;
+            let percent = 100 * done.as_secs() / target.as_secs().max(1);
+            Some(format!("{label} {} of {} ({percent}%)", format_focus(done), format_focus(target)))
+        })
+        .collect();
+    if !progress.is_empty() {
+        println!("Targets: {}", progress.join(", "));
+    }
+    Ok(())
+}
+
+fn prune_history(args: &PruneArgs) -> Result<()> {
+    let age = parse_duration(&args.older_than, "--older-than")?;
+    let age = chrono::Duration::from_std(age)
+        .map_err(|e| OpomodoroError::parse("--older-than argument", e))?;
+    let before = Local::now() - age;
+    let Some(path) = file_settings()?.history_path()? else {
+        println!("No history available on this platform.");
+        return Ok(());
+    };
+    let store = history::open(path)?;
+    let describe = |entries: &[history::Entry]| {
+        let (first, last) = (&entries[0], &entries[entries.len() - 1]);
+        format!(
+            "{} {} from {} to {} ({} focused)",
+            entries.len(),
+            if entries.len() == 1 { "entry" } else { "entries" },
+            first.started.format("%Y-%m-%d"),
+            last.started.format("%Y-%m-%d"),
+            format_focus(Summary::from_entries(entries).focus),
+        )
+    };
+    let old: Vec<_> = store.load()?.into_iter().filter(|entry| entry.started < before).collect();
+    if old.is_empty() {
+        println!("Nothing from before {} to remove.", before.format("%Y-%m-%d"));
+        return Ok(());
+    }
+    if args.dry_run {
+        match &args.archive {
+            Some(archive) => println!("Would move {} to {}.", describe(&old), archive.display()),
+            None => println!("Would remove {}.", describe(&old)),
+        }
+        return Ok(());
+    }
+    // A running session writes to the history; leave it alone meanwhile.
+    let _lock = lock_instance(false)?;
+    // Archive first, so a failure leaves the history as it was.
+    if let Some(archive) = &args.archive {
+        history::archive(archive, &old).map_err(|e| {
+            io::Error::new(e.kind(), format!("could not write {}: {e}", archive.display()))
+        })?;
+    }
+    store.remove_before(before)?;
+    match &args.archive {
+        Some(archive) => println!("Moved {} to {}.", describe(&old), archive.display()),
+        None => println!("Removed {}.", describe(&old)),
+    }
+    Ok(())
+}
+
+/// `n` of something, e.g. `1 entry` or `3 entries`.
+fn count(n: usize, one: &str, many: &str) -> String {
+    if n == 1 { format!("1 {one}") } else { format!("{n} {many}") }
+}
+
+/// `opomodoro history merge`.
+fn merge_history(paths: &[PathBuf]) -> Result<()> {
+    let Some(path) = file_settings()?.history_path()? else {
+        println!("No history available on this platform.");
+        return Ok(());
+    };
+    let store = history::open(path)?;
+    // A running session writes to the history; leave it alone meanwhile.
+    let _lock = lock_instance(false)?;
+    let mut entries = store.load()?;
+    for path in paths {
+        if !path.exists() {
+            let message = format!("no history at {}", path.display());
+            return Err(io::Error::new(io::ErrorKind::NotFound, message).into());
+        }
+        let new = history::missing(&entries, history::open(path.clone())?.load()?);
+        store.insert(&new)?;
+        println!("Added {} from {}.", count(new.len(), "entry", "entries"), path.display());
+        entries.extend(new);
+    }
+    Ok(())
+}
+
+/// `opomodoro sync`.
+#[cfg(feature = "webdav")]
+fn sync_history() -> Result<()> {
+    use opomodoro::cloud::{self, SyncBackend, SyncState, webdav::WebDav};
+    let settings = file_settings()?;
+    let Some(url) = &settings.sync_url else {
+        return Err(OpomodoroError::parse("sync_url", "not set; point it at a WebDAV folder"));
+    };
+    let (Some(path), Some(state_path)) = (settings.history_path()?, SyncState::default_path()) else {
+        println!("No history available on this platform.");
+        return Ok(());
+    };
+    let backend = WebDav::new(url, settings.sync_user.as_deref(), settings.sync_password.as_deref());
+    let store = history::open(path)?;
+    // A running session writes to the history; leave it alone meanwhile.
+    let _lock = lock_instance(false)?;
+    let mut state = SyncState::load(&state_path)?;
+    let synced = cloud::sync(store.as_ref(), &backend, &mut state)?;
+    state.save(&state_path)?;
+    println!(
+        "Pulled {} and pushed {} with {}.",
+        count(synced.pulled, "entry", "entries"),
+        count(synced.pushed, "entry", "entries"),
+        backend.location(),
+    );
+    Ok(())
+}
+
+/// `opomodoro schedule export`.
+fn export_schedule(path: &Path, name: Option<String>) -> Result<()> {
+    let settings = file_settings()?;
+    SessionTemplate::from_settings(&settings.resolved(), name).save(path)?;
+    println!("Wrote the session template to {}.", path.display());
+    Ok(())
+}
+
+/// `opomodoro schedule import`.
+fn import_schedule(path: &Path) -> Result<()> {
+    let template = SessionTemplate::load(path)?;
+    let mut file = ConfigFile::open(&config_path()?)?;
+    let keys = file.merge(&template.settings())?;
+    // Like `config set`, refuse what would not run.
+    let settings = file.settings()?;
+    build_config(&settings)?;
+    day_plan(&settings)?;
+    file.save()?;
+    let name = template.name.map_or_else(String::new, |name| format!(" {name:?}"));
+    if keys.is_empty() {
+        println!("The template{name} sets nothing; left the config as it was.");
+    } else {
+        println!("Imported the template{name}: set {}.", keys.join(", "));
+    }
+    Ok(())
+}
+
+/// `opomodoro daemon install`.
+fn install_daemon(recurring: bool, print: bool, force: bool) -> Result<()> {
+    let Some(manager) = Manager::native() else {
+        let message = "no service manager known on this platform";
+        return Err(io::Error::new(io::ErrorKind::Unsupported, message).into());
+    };
+    let mut args = vec!["daemon".to_string()];
+    if recurring {
+        args.push("--recurring".to_string());
+    }
+    if let Some(profile) = profile::current() {
+        args.extend(["--profile".to_string(), profile.to_string()]);
+    }
+    let program = std::env::current_exe()?;
+    let file = manager.render(&program, &args);
+    if print {
+        print!("{file}");
+        return Ok(());
+    }
+    let Some(path) = manager.path() else {
+        let message = "no home directory to install the service in";
+        return Err(io::Error::new(io::ErrorKind::NotFound, message).into());
+    };
+    if path.exists() && !force {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} is already there; pass --force to replace it", path.display()),
+        )
+        .into());
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, file)?;
+    println!("Wrote {}.", path.display());
+    println!("Start it now and at each login with: {}", manager.enable_command(&path));
+    Ok(())
+}
+
+/// `opomodoro backup create`.
+fn create_backup(path: &Path) -> Result<()> {
+    let manifest = backup::create(path, &backup::default_files())?;
+    if manifest.files.is_empty() {
+        println!("Nothing to back up yet; wrote an empty backup to {}.", path.display());
+    } else {
+        println!("Backed up {} to {}.", manifest.files.join(", "), path.display());
+    }
+    Ok(())
+}
+
+/// `opomodoro backup restore`.
+fn restore_backup(path: &Path, force: bool) -> Result<()> {
+    // A running session writes to the history and state; leave them alone
+    // meanwhile.
+    let _lock = lock_instance(false)?;
+    let manifest = backup::restore(path, &backup::default_files(), force)?;
+    println!(
+        "Restored {} from a backup made by opomodoro {} on {}.",
+        manifest.files.join(", "),
+        manifest.version,
+        manifest.created.format("%Y-%m-%d %H:%M"),
+    );
+    Ok(())
+}
+
+/// A line per group: its focus time, pomodoros and interruptions.
+fn print_groups(groups: &[(String, Summary)]) {
+    if groups.is_empty() {
+        println!("No focus time recorded yet.");
+        return;
+    }
+    let width = groups.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0).min(40);
+    for (name, summary) in groups {
+        println!(
+            "{name:<width$}  {:>6}  {} pomodoro(s), {} interruption(s){}",
+            format_focus(summary.focus),
+            summary.pomodoros,
+            summary.interruptions(),
+            extras(summary),
+        );
+    }
+}
+
+fn print_suggestions(analysis: &Analysis) {
+    if !analysis.is_enough() {
+        println!(
+            "Not enough history yet: phases on {} day(s), and suggestions start after {}.",
+            analysis.days,
+            suggest::MIN_DAYS,
+        );
+        return;
+    }
+    for (label, lengths) in [("Work", &analysis.work), ("Breaks", &analysis.breaks)] {
+        if lengths.is_empty() {
+            continue;
+        }
+        println!("{label:<8}  {:>6}  {:>12}", "phases", "skipped/quit");
+        for stats in lengths {
+            let judged = if stats.is_judged() { "" } else { "  (too few to go by)" };
+            println!(
+                "{:<8}  {:>6}  {:>11.0}%{judged}",
+                format_focus(stats.length),
+                stats.phases,
+                stats.cut_short_rate() * 100.0,
+            );
+        }
+        println!();
+    }
+    let suggestions = analysis.suggestions();
+    if suggestions.is_empty() {
+        println!("Your lengths hold up; nothing to suggest.");
+    }
+    for suggestion in suggestions {
+        println!("{suggestion}");
+    }
+}
+
+/// A setting as it would be typed on the command line.
+fn show_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn config_path() -> Result<PathBuf> {
+    Settings::default_path().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::Unsupported, "no config directory on this platform").into()
+    })
+}
+
+fn print_config() -> Result<()> {
+    let show_path = |path: Option<std::path::PathBuf>| {
+        path.map_or("(unavailable)".to_string(), |p| p.display().to_string())
+    };
+    let settings = file_settings()?;
+    for key in KEYS {
+        if let Some(value) = settings.get(key) {
+            // Keep secrets off the screen; `config get` still shows them.
+            let secret = ["_token", "_passphrase", "_password"].iter().any(|end| key.ends_with(end));
+            let value = if secret { "(set)".to_string() } else { show_value(&value) };
+            println!("{key:<15} = {value}");
+        }
+    }
+    let day: Vec<_> = day_plan(&settings)?.iter().map(Block::label).collect();
+    if !day.is_empty() {
+        println!("{:<15} = {}", "day", day.join(", "));
+    }
+    for (name, text) in settings.messages.iter().flat_map(MessageSettings::set) {
+        println!("{:<15} = {text:?}", format!("messages.{name}"));
+    }
+    if let Some(presets) = &settings.preset {
+        let names: Vec<_> = presets.keys().map(String::as_str).collect();
+        println!("{:<15} = {}", "presets", names.join(", "));
+    }
+    if let Some((name, _)) = settings.preset_for(Local::now().weekday(), None)? {
+        println!("{:<15} = {name}", "today's preset");
+    }
+    if let Some(name) = profile::current() {
+        println!("profile         = {name}");
+        println!("shared config   = {}", show_path(Settings::shared_path()));
+    }
+    println!("config          = {}", show_path(Settings::default_path()));
+    if let Some(path) = project_path() {
+        println!("project         = {}", path.display());
+    }
+    println!("history         = {}", show_path(settings.history_path()?));
+    println!("state           = {}", show_path(StateFile::default_path()));
+    println!("tasks           = {}", show_path(TaskFile::default_path()));
+    Ok(())
+}
+
+/// On the very first run, with no config file and someone at the terminal,
+/// ask how the timer should run and write the config file from that.
+#[cfg(feature = "terminal")]
+fn first_run() -> Result<()> {
+    use std::io::{IsTerminal, Write};
+    let Some(path) = Settings::default_path() else {
+        return Ok(());
+    };
+    let set_up = path.exists() || Settings::shared_path().is_some_and(|shared| shared.exists());
+    if set_up || !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Ok(());
+    }
+    let settings = opomodoro::onboarding::ask(&mut io::stdin().lock(), &mut io::stdout())?;
+    let mut file = ConfigFile::open(&path)?;
+    file.merge(&settings)?;
+    build_config(&file.settings()?)?;
+    file.save()?;
+    println!();
+    println!("Wrote {}; change it later with `opomodoro config set` or `config edit`.", path.display());
+    println!();
+    print!("{}", opomodoro::onboarding::first_keys());
+    print!("Press Enter to start. ");
+    io::stdout().flush()?;
+    io::stdin().read_line(&mut String::new())?;
+    Ok(())
+}
+
+/// `opomodoro help`: the long help of the command at `path`, or its man
+/// page.
+fn print_help(path: &[String], man: bool) -> Result<()> {
+    let mut command = if man { Cli::command() } else { cli_command() }.name("opomodoro");
+    command.build();
+    let mut found = &command;
+    for name in path {
+        found = found.find_subcommand(name).ok_or_else(|| {
+            OpomodoroError::parse("help", format!("unknown command {:?}", path.join(" ")))
+        })?;
+    }
+    if !man {
+        found.clone().print_long_help()?;
+        return Ok(());
+    }
+    let examples = EXAMPLES.iter().find(|(of, _)| of.split_whitespace().eq(path.iter().map(String::as_str)));
+    let source = format!("opomodoro {}", command.get_version().unwrap_or_default());
+    let page = clap_mangen::Man::new(found.clone()).source(source);
+    let out = &mut io::stdout().lock();
+    page.render_title(out)?;
+    page.render_name_section(out)?;
+    page.render_synopsis_section(out)?;
+    page.render_description_section(out)?;
+    page.render_options_section(out)?;
+    if found.has_subcommands() {
+        page.render_subcommands_section(out)?;
+    }
+    if let Some((_, examples)) = examples {
+        let mut roff = Roff::new();
+        roff.control("SH", ["EXAMPLES"]);
+        for (line, what) in *examples {
+            roff.control("TP", []).text([bold(*line)]).text([roman(*what)]);
+        }
+        roff.to_writer(out)?;
+    }
+    if found.get_version().is_some() {
+        page.render_version_section(out)?;
+    }
+    Ok(())
+}
+
+/// Open the config file in the user's editor, then check what they wrote.
+fn edit_config() -> Result<()> {
+    let path = config_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    // The variable may carry arguments, as in `code --wait`.
+    let mut words = editor.split_whitespace();
+    let failed = |message: String| OpomodoroError::Integration { name: "editor", message };
+    let Some(program) = words.next() else {
+        return Err(failed("$EDITOR is empty".to_string()));
+    };
+    let status = process::Command::new(program)
+        .args(words)
+        .arg(&path)
+        .status()
+        .map_err(|e| failed(format!("{program}: {e}")))?;
+    if !status.success() {
+        return Err(failed(format!("{editor} exited with {status}")));
+    }
+    build_config(&file_settings()?)?;
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::time::Duration;
+use crate::engine::Phase;
+
+/// How the tomato feels about the timer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mood {
+    /// Working, with time to spare.
+    Focused,
+    /// On a break.
+    Relaxed,
+    /// The work phase is about to run out.
+    Alarmed,
+    /// The timer is paused.
+    Napping,
+}
+
+impl Mood {
+    /// The mood for `phase` with `remaining` time left.
+    pub fn of(phase: Phase, remaining: Duration, paused: bool) -> Mood {
+        if paused {
+            Mood::Napping
+        } else if !phase.is_work() {
+            Mood::Relaxed
+        } else if remaining < phase.duration / 10 {
+            Mood::Alarmed
+        } else {
+            Mood::Focused
+        }
+    }
+
+    /// The tomato's face, stem first.
+    pub fn art(self) -> &'static [&'static str; 6] {
+        match self {
+            Mood::Focused => &FOCUSED,
+            Mood::Relaxed => &RELAXED,
+            Mood::Alarmed => &ALARMED,
+            Mood::Napping => &NAPPING,
+        }
+    }
+
+    pub fn caption(self) -> &'static str {
+        match self {
+            Mood::Focused => "Focused",
+            Mood::Relaxed => "Relaxed",
+            Mood::Alarmed => "Hurry!",
+            Mood::Napping => "Napping",
+        }
+    }
+}
+
+/// Columns the art takes.
+pub const WIDTH: u16 = 11;
+
+const FOCUSED: [&str; 6] = [
+    r"    \|/    ",
+    r"  .-'`'-.  ",
+    r" / o   o \ ",
+    r"|    -    |",
+    r" \       / ",
+    r"  '-...-'  ",
+];
+
+const RELAXED: [&str; 6] = [
+    r"    \|/    ",
+    r"  .-'`'-.  ",
+    r" / ^   ^ \ ",
+    r"|   \_/   |",
+    r" \       / ",
+    r"  '-...-'  ",
+];
+
+const ALARMED: [&str; 6] = [
+    r"    \|/  ! ",
+    r"  .-'`'-.  ",
+    r" / O   O \ ",
+    r"|    o    |",
+    r" \       / ",
+    r"  '-...-'  ",
+];
+
+const NAPPING: [&str; 6] = [
+    r"    \|/  z ",
+    r"  .-'`'-. Z",
+    r" / -   - \ ",
+    r"|    ~    |",
+    r" \       / ",
+    r"  '-...-'  ",
+];
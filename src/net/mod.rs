@@ -0,0 +1,243 @@
+//! Team mode: one session shared over TCP with others who follow along,
+//! e.g. for mob programming or study groups.
+//!
+//! The host sends a JSON [`Snapshot`] per line to everyone who joins, as
+//! soon as they connect and then every second and whenever the timer
+//! changes. Nothing is read back, so only the host can pause or skip.
+//!
+//! Teams with a passphrase get an encrypted channel instead, which only
+//! members who know it can open.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use crate::ipc::{Link, Request, SessionHandle, Snapshot};
+#[cfg(feature = "mdns")]
+use crate::discovery;
+
+#[cfg(feature = "secure")]
+mod noise;
+
+#[cfg(feature = "secure")]
+use noise::Key;
+
+/// Stands in for the key in builds that cannot encrypt; it has no values,
+/// so such builds never get past [`derive_key`] with a passphrase.
+#[cfg(not(feature = "secure"))]
+#[derive(Debug, Clone)]
+enum Key {}
+
+fn derive_key(passphrase: Option<&str>) -> io::Result<Option<Key>> {
+    match passphrase {
+        None => Ok(None),
+        #[cfg(feature = "secure")]
+        Some(passphrase) => Key::derive(passphrase).map(Some),
+        #[cfg(not(feature = "secure"))]
+        Some(_) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "this build cannot encrypt team sessions; drop team_passphrase or build with the secure feature",
+        )),
+    }
+}
+
+/// How snapshots travel between the host and a joiner.
+enum Channel {
+    /// A JSON line each.
+    Plain(BufReader<TcpStream>),
+    #[cfg(feature = "secure")]
+    Sealed(noise::Channel),
+}
+
+impl Channel {
+    fn accept(stream: TcpStream, key: Option<&Key>) -> io::Result<Channel> {
+        match key {
+            None => Ok(Channel::Plain(BufReader::new(stream))),
+            #[cfg(feature = "secure")]
+            Some(key) => {
+                // Do not let a silent client hold the thread forever.
+                stream.set_read_timeout(Some(JOIN_TIMEOUT))?;
+                noise::Channel::accept(stream, key).map(Channel::Sealed)
+            }
+            #[cfg(not(feature = "secure"))]
+            Some(key) => match *key {},
+        }
+    }
+
+    fn connect(stream: TcpStream, key: Option<&Key>) -> io::Result<Channel> {
+        match key {
+            None => Ok(Channel::Plain(BufReader::new(stream))),
+            #[cfg(feature = "secure")]
+            Some(key) => noise::Channel::connect(stream, key).map(Channel::Sealed),
+            #[cfg(not(feature = "secure"))]
+            Some(key) => match *key {},
+        }
+    }
+
+    fn stream(&self) -> &TcpStream {
+        match self {
+            Channel::Plain(reader) => reader.get_ref(),
+            #[cfg(feature = "secure")]
+            Channel::Sealed(channel) => channel.stream(),
+        }
+    }
+
+    fn send(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        let mut json = serde_json::to_string(snapshot)?;
+        match self {
+            Channel::Plain(reader) => {
+                json.push('\n');
+                reader.get_mut().write_all(json.as_bytes())
+            }
+            #[cfg(feature = "secure")]
+            Channel::Sealed(channel) => channel.send(json.as_bytes()),
+        }
+    }
+
+    fn recv(&mut self) -> io::Result<Snapshot> {
+        match self {
+            Channel::Plain(reader) => {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                Ok(serde_json::from_str(&line)?)
+            }
+            #[cfg(feature = "secure")]
+            Channel::Sealed(channel) => Ok(serde_json::from_slice(&channel.recv()?)?),
+        }
+    }
+}
+
+/// How long joining waits for the host's first snapshot.
+const JOIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A session being shared, announced on the local network until dropped
+/// in builds with mDNS.
+#[derive(Debug)]
+pub struct TeamHost {
+    #[cfg(feature = "mdns")]
+    _advertisement: Option<discovery::Advertisement>,
+}
+
+/// Share the session behind `session` with whoever connects to `addr`,
+/// or with a `passphrase`, only with those who know it.
+pub fn host(addr: SocketAddr, session: SessionHandle, passphrase: Option<&str>) -> io::Result<TeamHost> {
+    let key = derive_key(passphrase)?;
+    let listener = TcpListener::bind(addr)?;
+    #[cfg(feature = "mdns")]
+    let advertisement = discovery::advertise(addr)
+        .inspect_err(|e| warn!("could not announce the shared timer: {e}"))
+        .ok();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let session = session.clone();
+                    let key = key.clone();
+                    thread::spawn(move || share(stream, &session, key.as_ref()));
+                }
+                Err(e) => warn!("team accept failed: {e}"),
+            }
+        }
+    });
+    Ok(TeamHost {
+        #[cfg(feature = "mdns")]
+        _advertisement: advertisement,
+    })
+}
+
+fn share(stream: TcpStream, session: &SessionHandle, key: Option<&Key>) {
+    let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    let mut channel = match Channel::accept(stream, key) {
+        Ok(channel) => channel,
+        Err(e) => {
+            warn!(%peer, "turned away from the shared timer: {e}");
+            return;
+        }
+    };
+    info!(%peer, "joined the shared timer");
+    let updates = session.watch();
+    let first = session.ask(Request::Snapshot);
+    for snapshot in first.into_iter().chain(updates) {
+        if channel.send(&snapshot).is_err() {
+            info!(%peer, "left the shared timer");
+            return;
+        }
+    }
+}
+
+/// The latest snapshot from the host and when it arrived.
+#[derive(Debug, Default)]
+struct Received {
+    snapshot: Option<(Snapshot, Instant)>,
+    closed: bool,
+}
+
+/// A joiner's connection to a shared timer, read by a thread of its own.
+/// Requests other than [`Request::Snapshot`] are not passed on.
+#[derive(Debug)]
+pub struct TeamClient {
+    received: Arc<Mutex<Received>>,
+}
+
+impl TeamClient {
+    /// Join the timer shared at `addr`, e.g. `192.168.1.20:7879`, with the
+    /// team's `passphrase` if it has one.
+    pub fn join(addr: impl ToSocketAddrs, passphrase: Option<&str>) -> io::Result<TeamClient> {
+        let key = derive_key(passphrase)?;
+        let stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(JOIN_TIMEOUT))?;
+        let mut channel = Channel::connect(stream, key.as_ref())?;
+        let first = channel.recv().map_err(|e| match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::UnexpectedEof
+                if key.is_none() =>
+            {
+                io::Error::new(e.kind(), "no answer; does the team use a passphrase?")
+            }
+            _ => e,
+        })?;
+        channel.stream().set_read_timeout(None)?;
+        let received = Arc::new(Mutex::new(Received {
+            snapshot: Some((first, Instant::now())),
+            closed: false,
+        }));
+        let shared = received.clone();
+        thread::spawn(move || {
+            loop {
+                let snapshot = channel.recv();
+                let mut received = shared.lock().unwrap_or_else(|e| e.into_inner());
+                match snapshot {
+                    Ok(snapshot) => received.snapshot = Some((snapshot, Instant::now())),
+                    Err(e) => {
+                        debug!("shared timer closed: {e}");
+                        received.closed = true;
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(TeamClient { received })
+    }
+}
+
+impl Link for TeamClient {
+    fn send(&mut self, request: &Request) -> io::Result<Snapshot> {
+        if !matches!(request, Request::Snapshot) {
+            debug!(?request, "only the host controls a shared timer");
+        }
+        let received = self.received.lock().unwrap_or_else(|e| e.into_inner());
+        let Some((snapshot, at)) = received.snapshot.as_ref().filter(|_| !received.closed) else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        // Count the time since the host sent it, as if it were fresh.
+        let mut snapshot = snapshot.clone();
+        if !snapshot.paused {
+            snapshot.remaining = snapshot.remaining.saturating_sub(at.elapsed());
+        }
+        snapshot.grace_left = snapshot.grace_left.map(|left| left.saturating_sub(at.elapsed()));
+        Ok(snapshot)
+    }
+}
@@ -0,0 +1,128 @@
+//! Team sessions sealed with a key derived from the team's passphrase.
+//!
+//! Each joiner runs a Noise `NNpsk0` handshake with the host, which fails
+//! for anyone without the passphrase. Snapshots then travel encrypted, each
+//! in a frame prefixed with its length as a big-endian `u16`.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use argon2::Argon2;
+use snow::{Builder, HandshakeState, TransportState};
+
+const PATTERN: &str = "Noise_NNpsk0_25519_ChaChaPoly_BLAKE2s";
+
+/// Fixed so that the passphrase alone gives every member the same key.
+const SALT: &[u8] = b"opomodoro team passphrase";
+
+/// The pre-shared key for the handshake.
+#[derive(Clone)]
+pub struct Key([u8; 32]);
+
+impl Key {
+    /// Stretch `passphrase` into a key, slowly enough to make guessing it
+    /// from a recorded handshake expensive.
+    pub fn derive(passphrase: &str) -> io::Result<Key> {
+        let mut key = [0; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), SALT, &mut key)
+            .map_err(|e| io::Error::other(format!("could not derive the team key: {e}")))?;
+        Ok(Key(key))
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+/// An encrypted connection between the host and one joiner.
+pub struct Channel {
+    stream: TcpStream,
+    transport: TransportState,
+}
+
+fn rejected(e: snow::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, format!("handshake failed, check the passphrase ({e})"))
+}
+
+impl Channel {
+    /// The host's side: answer a joiner's handshake.
+    pub fn accept(mut stream: TcpStream, key: &Key) -> io::Result<Channel> {
+        let mut handshake = builder(key).build_responder().map_err(rejected)?;
+        let mut buf = [0; 1024];
+        handshake.read_message(&read_frame(&mut stream)?, &mut buf).map_err(rejected)?;
+        send_handshake(&mut stream, &mut handshake)?;
+        let transport = handshake.into_transport_mode().map_err(rejected)?;
+        Ok(Channel { stream, transport })
+    }
+
+    /// A joiner's side: start the handshake with the host.
+    pub fn connect(mut stream: TcpStream, key: &Key) -> io::Result<Channel> {
+        let mut handshake = builder(key).build_initiator().map_err(rejected)?;
+        send_handshake(&mut stream, &mut handshake)?;
+        // The host hangs up on a handshake it cannot read.
+        let reply = read_frame(&mut stream).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the host hung up, check the passphrase",
+            ),
+            _ => e,
+        })?;
+        let mut buf = [0; 1024];
+        handshake.read_message(&reply, &mut buf).map_err(rejected)?;
+        let transport = handshake.into_transport_mode().map_err(rejected)?;
+        Ok(Channel { stream, transport })
+    }
+
+    pub fn stream(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    pub fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        let mut sealed = vec![0; message.len() + 16];
+        let len = self
+            .transport
+            .write_message(message, &mut sealed)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        write_frame(&mut self.stream, &sealed[..len])
+    }
+
+    pub fn recv(&mut self) -> io::Result<Vec<u8>> {
+        let sealed = read_frame(&mut self.stream)?;
+        let mut message = vec![0; sealed.len()];
+        let len = self
+            .transport
+            .read_message(&sealed, &mut message)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        message.truncate(len);
+        Ok(message)
+    }
+}
+
+fn builder(key: &Key) -> Builder<'_> {
+    Builder::new(PATTERN.parse().expect("the Noise pattern is valid")).psk(0, &key.0)
+}
+
+fn send_handshake(stream: &mut TcpStream, handshake: &mut HandshakeState) -> io::Result<()> {
+    let mut message = [0; 1024];
+    let len = handshake.write_message(&[], &mut message).map_err(rejected)?;
+    write_frame(stream, &message[..len])
+}
+
+fn write_frame(stream: &mut TcpStream, frame: &[u8]) -> io::Result<()> {
+    let len = u16::try_from(frame.len()).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let mut out = Vec::with_capacity(frame.len() + 2);
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(frame);
+    stream.write_all(&out)
+}
+
+fn read_frame(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut len = [0; 2];
+    stream.read_exact(&mut len)?;
+    let mut frame = vec![0; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut frame)?;
+    Ok(frame)
+}
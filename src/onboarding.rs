@@ -0,0 +1,92 @@
+//! The first run: with no config file yet, a few questions on the terminal
+//! about how the timer should run, whose answers become the config file.
+//! Every question has a default, so Enter all the way through works too.
+
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+use clap::ValueEnum;
+use crate::KEY_HELP;
+use crate::settings::{DEFAULT_BREAK, DEFAULT_WORK, Settings};
+use crate::theme::ThemeChoice;
+
+/// Ask the questions on `out`, reading the answers from `input`, and
+/// return the settings they make. The end of the input takes the
+/// defaults for the questions left.
+pub fn ask(input: &mut dyn BufRead, out: &mut dyn Write) -> io::Result<Settings> {
+    writeln!(out, "Welcome to opomodoro! A few questions to set it up; Enter takes the default.")?;
+    writeln!(out)?;
+    let work = ask_until(input, out, "How long should work phases be?", DEFAULT_WORK, duration)?;
+    let break_time = ask_until(input, out, "And breaks?", DEFAULT_BREAK, duration)?;
+    let notify = ask_until(input, out, "Show a desktop notification as each phase ends? (y/n)", "n", yes)?;
+    let themes: Vec<_> = ThemeChoice::value_variants()
+        .iter()
+        .filter_map(|theme| Some(theme.to_possible_value()?.get_name().to_string()))
+        .collect();
+    let question = format!("Which colors: {}?", themes.join(", "));
+    let theme = ask_until(input, out, &question, "tomato", |answer| {
+        ThemeChoice::from_str(answer, true).map_err(|_| format!("pick one of {}", themes.join(", ")))
+    })?;
+    Ok(Settings {
+        work: Some(work),
+        break_time: Some(break_time),
+        notify: Some(notify),
+        theme: Some(theme),
+        ..Settings::default()
+    })
+}
+
+/// Ask `question` until `parse` takes the answer, or the default.
+fn ask_until<T>(
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+    question: &str,
+    default: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> io::Result<T> {
+    loop {
+        write!(out, "{question} [{default}] ")?;
+        out.flush()?;
+        let mut answer = String::new();
+        let answer = match input.read_line(&mut answer)? {
+            0 => {
+                writeln!(out)?;
+                default
+            }
+            _ => Some(answer.trim()).filter(|answer| !answer.is_empty()).unwrap_or(default),
+        };
+        match parse(answer) {
+            Ok(value) => return Ok(value),
+            Err(e) => writeln!(out, "  {e}")?,
+        }
+    }
+}
+
+/// A phase length, kept as written.
+fn duration(answer: &str) -> Result<String, String> {
+    match humantime::parse_duration(answer) {
+        Ok(duration) if duration > Duration::ZERO => Ok(answer.to_string()),
+        Ok(_) => Err("that is no time at all; try e.g. 25m".to_string()),
+        Err(e) => Err(format!("{e}; try e.g. 25m")),
+    }
+}
+
+fn yes(answer: &str) -> Result<bool, String> {
+    match answer.to_ascii_lowercase().as_str() {
+        "y" | "yes" => Ok(true),
+        "n" | "no" => Ok(false),
+        _ => Err("answer y or n".to_string()),
+    }
+}
+
+/// The keys to know for a first session: the timer's, and where to find
+/// the others.
+pub fn first_keys() -> String {
+    let (_, keys) = KEY_HELP[0];
+    let width = keys.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+    let mut text = String::from("The timer's keys:\n");
+    for (key, what) in keys {
+        text += &format!("  {key:<width$}  {what}\n");
+    }
+    text += "Press ? in the timer for the rest, and q to quit.\n";
+    text
+}
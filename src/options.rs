@@ -0,0 +1,266 @@
+//! The settings screen: the session's lengths, sounds and display, changed
+//! while it runs and written back to the config file on request.
+
+use std::path::PathBuf;
+use std::time::Duration;
+use clap::ValueEnum;
+use crate::ambient::VOLUME_STEP;
+use crate::engine::Timer;
+use crate::settings::{ConfigFile, Settings};
+use crate::{App, AppScreen, ConfigError, MAX_CYCLES, check_length};
+
+/// A line of the settings screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Field {
+    Work,
+    Break,
+    Cycles,
+    Volume,
+    AmbientVolume,
+    View,
+    Countdown,
+    Mascot,
+    Sidebar,
+    Animations,
+    Notes,
+}
+
+/// The settings screen's lines, top to bottom.
+pub(crate) const FIELDS: [Field; 11] = [
+    Field::Work,
+    Field::Break,
+    Field::Cycles,
+    Field::Volume,
+    Field::AmbientVolume,
+    Field::View,
+    Field::Countdown,
+    Field::Mascot,
+    Field::Sidebar,
+    Field::Animations,
+    Field::Notes,
+];
+
+/// How much ←/→ change a phase length by.
+const LENGTH_STEP: Duration = Duration::from_secs(60);
+
+impl Field {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Field::Work => "Work",
+            Field::Break => "Break",
+            Field::Cycles => "Cycles",
+            Field::Volume => "Alert volume",
+            Field::AmbientVolume => "Ambient volume",
+            Field::View => "View",
+            Field::Countdown => "Countdown",
+            Field::Mascot => "Mascot",
+            Field::Sidebar => "Task list",
+            Field::Animations => "Animations",
+            Field::Notes => "Notes",
+        }
+    }
+
+    /// Its key in the config file.
+    fn key(self) -> &'static str {
+        match self {
+            Field::Work => "work",
+            Field::Break => "break",
+            Field::Cycles => "cycles",
+            Field::Volume => "volume",
+            Field::AmbientVolume => "ambient_volume",
+            Field::View => "view",
+            Field::Countdown => "countdown",
+            Field::Mascot => "mascot",
+            Field::Sidebar => "sidebar",
+            Field::Animations => "animations",
+            Field::Notes => "notes",
+        }
+    }
+
+    /// Whether Enter types in a new value, rather than changing it in place.
+    pub(crate) fn is_typed(self) -> bool {
+        matches!(self, Field::Work | Field::Break | Field::Cycles)
+    }
+}
+
+fn name(value: impl ValueEnum) -> String {
+    value.to_possible_value().map_or_else(String::new, |value| value.get_name().to_string())
+}
+
+impl<T: Timer> App<'_, T> {
+    /// `field` as it stands, the way the config file writes it; `None` for
+    /// an ambient volume with no ambient sound.
+    pub(crate) fn option(&self, field: Field) -> Option<String> {
+        let engine = &self.engine;
+        let value = match field {
+            Field::Work => humantime::format_duration(engine.work_time()).to_string(),
+            Field::Break => humantime::format_duration(engine.break_time()).to_string(),
+            Field::Cycles => engine.num_cycles().to_string(),
+            Field::Volume => self.alerts.volume().to_string(),
+            Field::AmbientVolume => self.ambient_volume()?.to_string(),
+            Field::View => name(self.view),
+            Field::Countdown => name(self.countdown),
+            Field::Mascot => self.mascot.to_string(),
+            Field::Sidebar => self.sidebar.to_string(),
+            Field::Animations => self.animations.to_string(),
+            Field::Notes => self.notes.to_string(),
+        };
+        Some(value)
+    }
+
+    /// A minute more or less for the work phases, or the breaks, still to
+    /// start.
+    pub(crate) fn step_length(&mut self, work: bool, longer: bool) -> Result<(), String> {
+        let step = |length: Duration| {
+            Some(if longer { length + LENGTH_STEP } else { length.saturating_sub(LENGTH_STEP) })
+        };
+        if work {
+            self.set_lengths(step(self.engine.work_time()), None)
+        } else {
+            self.set_lengths(None, step(self.engine.break_time()))
+        }
+    }
+
+    /// Step `field` up or down: a minute, a cycle or a notch of volume, or
+    /// on to the next choice or the other side of a toggle.
+    pub(crate) fn adjust_option(&mut self, field: Field, up: bool) -> Result<(), String> {
+        let volume = |volume: u8| {
+            if up { volume.saturating_add(VOLUME_STEP) } else { volume.saturating_sub(VOLUME_STEP) }
+        };
+        match field {
+            Field::Work => self.step_length(true, up),
+            Field::Break => self.step_length(false, up),
+            Field::Cycles => {
+                let cycles = self.engine.num_cycles();
+                self.set_cycles(if up { cycles + 1 } else { cycles.saturating_sub(1) })
+            }
+            Field::Volume => {
+                self.alerts.set_volume(volume(self.alerts.volume()));
+                Ok(())
+            }
+            Field::AmbientVolume => {
+                let ambient = self.ambient.as_mut().ok_or("No ambient sound this session")?;
+                ambient.set_volume(volume(ambient.volume()));
+                Ok(())
+            }
+            Field::View => {
+                self.view = self.view.next();
+                Ok(())
+            }
+            Field::Countdown => {
+                self.countdown = self.countdown.next();
+                Ok(())
+            }
+            Field::Mascot => {
+                self.mascot = !self.mascot;
+                Ok(())
+            }
+            Field::Sidebar => {
+                self.sidebar = !self.sidebar;
+                Ok(())
+            }
+            Field::Animations if self.low_power => Err("Low-power mode keeps them off".to_string()),
+            Field::Animations => {
+                self.animations = !self.animations;
+                Ok(())
+            }
+            Field::Notes => {
+                self.notes = !self.notes;
+                Ok(())
+            }
+        }
+    }
+
+    /// Set a typed `field` to `text`, e.g. `50m` or `6`.
+    pub(crate) fn set_option(&mut self, field: Field, text: &str) -> Result<(), String> {
+        let length = |what| {
+            humantime::parse_duration(text).map_err(|e| format!("Bad {what} {text:?}: {e}"))
+        };
+        match field {
+            Field::Work => self.set_lengths(Some(length("work time")?), None),
+            Field::Break => self.set_lengths(None, Some(length("break time")?)),
+            Field::Cycles => {
+                let cycles = text.parse().map_err(|_| format!("Bad number of cycles {text:?}"))?;
+                self.set_cycles(cycles)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Change the work or break length, or both, from the next phase on.
+    pub(crate) fn set_lengths(
+        &mut self,
+        work: Option<Duration>,
+        break_time: Option<Duration>,
+    ) -> Result<(), String> {
+        let work = work.unwrap_or(self.engine.work_time());
+        let break_time = break_time.unwrap_or(self.engine.break_time());
+        check_length("work time", work).map_err(|e| e.to_string())?;
+        check_length("break time", break_time).map_err(|e| e.to_string())?;
+        if !self.engine.set_lengths(work, break_time) {
+            return Err("The session runs in another process; change it there".to_string());
+        }
+        Ok(())
+    }
+
+    fn set_cycles(&mut self, cycles: u32) -> Result<(), String> {
+        let cycle = self.engine.cycle();
+        match cycles {
+            0 => return Err(ConfigError::NoCycles.to_string()),
+            n if n > MAX_CYCLES => return Err(ConfigError::TooManyCycles(n).to_string()),
+            n if n < cycle => return Err(format!("Already in cycle {cycle}")),
+            _ => {}
+        }
+        if !self.engine.set_cycles(cycles) {
+            return Err("The session runs in another process; change it there".to_string());
+        }
+        Ok(())
+    }
+
+    /// Write every setting on the screen to the config file, returning
+    /// where that is.
+    pub(crate) fn save_options(&self) -> crate::error::Result<PathBuf> {
+        let path = Settings::default_path().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::Unsupported, "no config directory on this platform")
+        })?;
+        let mut file = ConfigFile::open(&path)?;
+        for field in FIELDS {
+            if let Some(value) = self.option(field) {
+                file.set(field.key(), &value)?;
+            }
+        }
+        file.save()?;
+        Ok(path)
+    }
+
+    /// Flash how changing the lengths from the timer screen went.
+    pub(crate) fn lengths_changed(&mut self, result: Result<(), String>) {
+        let message = match result {
+            Ok(()) => format!(
+                "From the next phase on: {} work, {} breaks",
+                humantime::format_duration(self.engine.work_time()),
+                humantime::format_duration(self.engine.break_time()),
+            ),
+            Err(e) => e,
+        };
+        self.banner = Some((self.engine.now(), message));
+    }
+
+    /// Say how changing `field` went, and when a new length starts to count.
+    pub(crate) fn option_changed(&mut self, field: Field, result: Result<(), String>) {
+        let message = match (result, field) {
+            (Err(e), _) => e,
+            (Ok(()), Field::Work) => "Work phases take this long from the next one on".to_string(),
+            (Ok(()), Field::Break) => "Breaks take this long from the next one on".to_string(),
+            (Ok(()), _) => return,
+        };
+        self.settings_message(message);
+    }
+
+    /// Show `message` under the settings, if they are still open.
+    pub(crate) fn settings_message(&mut self, message: String) {
+        if let AppScreen::Settings { message: shown, .. } = &mut self.screen {
+            *shown = Some(message);
+        }
+    }
+}
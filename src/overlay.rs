@@ -0,0 +1,29 @@
+//! A stream overlay: an HTML page that follows a session over the HTTP
+//! API's event stream, for OBS and other tools with a browser source.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::theme::{self, Theme};
+
+const PAGE: &str = include_str!("overlay.html");
+
+/// Where the overlay looks for the session unless told otherwise.
+pub const DEFAULT_URL: &str = "http://127.0.0.1:7878";
+
+/// The page, following the API at `url` and drawn in `theme`'s colors.
+pub fn page(url: &str, theme: &Theme) -> String {
+    // A JSON string is a JS string; keep `</script>` from closing the tag.
+    let url = serde_json::Value::from(url.trim_end_matches('/')).to_string().replace('<', "\\u003c");
+    PAGE.replace("{{url}}", &url)
+        .replace("{{progress}}", &theme::css(theme.progress))
+        .replace("{{track}}", &theme::css(theme.track))
+}
+
+/// Write the page to `dir/index.html`, creating `dir` if need be.
+pub fn write(dir: &Path, url: &str, theme: &Theme) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join("index.html");
+    fs::write(&path, page(url, theme))?;
+    Ok(path)
+}
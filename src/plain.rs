@@ -0,0 +1,436 @@
+//! The plain frontend: a session with no screen to draw, just a line on
+//! stdout as each phase starts and ends, for scripts and logs to follow.
+//! It keeps the history and state file like the TUI does, but has no keys:
+//! the session runs as configured, block after block of a chain, taking
+//! `opomodoro pause`, `skip` and friends over the control socket, and
+//! Ctrl-C ends it, resumable.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+use chrono::{DateTime, Local};
+use tracing::{debug, info, warn};
+use crate::clock::Instant;
+use crate::engine::{Engine, Event, PhaseKind};
+use crate::error;
+use crate::history::{self, Entry, HistoryStore, Interruption, Outcome, Summary};
+use crate::integrations::Integrations;
+#[cfg(any(unix, windows))]
+use crate::ipc::{ControlServer, Pending, Request, Snapshot};
+use crate::state::{SessionState, StateFile};
+use crate::{Config, check_length};
+
+/// Longest the loop sleeps, so Ctrl-C is not kept waiting.
+const TICK: Duration = Duration::from_millis(250);
+
+/// How a plain session starts, besides its config.
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Hold the timer until this time, then start. Until then, requests
+    /// over the control socket only report it.
+    pub at: Option<DateTime<Local>>,
+    /// Where requests from `opomodoro pause` and the like come in.
+    #[cfg(any(unix, windows))]
+    pub control: Option<ControlServer>,
+    /// Told about each phase as it starts and ends, and about the session.
+    pub integrations: Integrations,
+}
+
+/// Run the session of `engine`, set up from `config`, to its end or until
+/// `running` goes false, writing each phase change to `out`.
+pub fn run(
+    engine: Engine,
+    config: &Config,
+    options: Options,
+    running: &AtomicBool,
+    out: &mut dyn Write,
+) -> error::Result<Summary> {
+    let history = match &config.history {
+        Some(path) => Some(history::open(path.clone()).map_err(|e| {
+            io::Error::new(e.kind(), format!("could not open history at {}: {e}", path.display()))
+        })?),
+        None => None,
+    };
+    let mut session = Session {
+        engine,
+        config,
+        history,
+        state: config.state.clone().map(StateFile::new),
+        block: 0,
+        entries: Vec::new(),
+        skipped: None,
+        started: Local::now(),
+        interruptions: Vec::new(),
+        pauses: 0,
+        integrations: options.integrations,
+        reported: None,
+        out,
+    };
+    let mut waiting = options.at;
+    match waiting {
+        Some(at) => {
+            if !session.engine.is_paused() {
+                session.engine.toggle_pause();
+            }
+            writeln!(session.out, "{} waiting to start at {}", now(), at.format("%H:%M"))?;
+        }
+        None => session.announce()?,
+    }
+    let mut saved: Option<Instant> = None;
+    let mut quit = false;
+    loop {
+        if !running.load(Ordering::SeqCst) {
+            quit = waiting.is_none();
+            if quit {
+                // Keep it resumable, as the TUI does on `q`.
+                session.save_state(false);
+                let events = session.engine.quit();
+                session.handle(events)?;
+            }
+            break;
+        }
+        if let Some(at) = waiting.filter(|at| Local::now() >= *at) {
+            info!(%at, "scheduled start");
+            waiting = None;
+            session.engine.toggle_pause();
+            session.started = Local::now();
+            session.announce()?;
+        }
+        if waiting.is_none() {
+            let events = session.engine.tick();
+            session.handle(events)?;
+        }
+        session.start_next_block()?;
+        if session.engine.is_finished() {
+            break;
+        }
+        if session.engine.undo_left().is_none() {
+            session.keep_skipped()?;
+        }
+        let now = session.engine.now();
+        if saved.is_none_or(|at| now.duration_since(at) >= Duration::from_secs(1)) {
+            session.save_state(true);
+            saved = Some(now);
+        }
+        session.out.flush()?;
+        let engine = &session.engine;
+        let wait = if waiting.is_some() || engine.is_paused() {
+            TICK
+        } else {
+            (engine.remaining() + Duration::from_millis(5)).min(TICK)
+        };
+        #[cfg(any(unix, windows))]
+        if let Some(control) = &options.control {
+            if control.is_watched() {
+                control.publish(&session.snapshot());
+            }
+            if let Some(pending) = control.next(wait) {
+                session.answer(pending, waiting.is_some())?;
+                // Write the change to the state file right away.
+                saved = None;
+            }
+            continue;
+        }
+        thread::sleep(wait);
+    }
+    session.keep_skipped()?;
+    session.out.flush()?;
+    if !quit
+        && let Some(file) = &session.state
+        && let Err(e) = file.clear()
+    {
+        warn!("could not remove session state {}: {e}", file.path().display());
+    }
+    #[cfg(any(unix, windows))]
+    if let Some(control) = &options.control {
+        control.publish(&session.snapshot());
+    }
+    let summary = Summary::from_entries(&session.entries);
+    let problems = session.integrations.session_ended(&summary);
+    report(problems);
+    Ok(summary)
+}
+
+/// A plain session as it runs, and what it keeps of the phases so far.
+struct Session<'a> {
+    engine: Engine,
+    config: &'a Config,
+    history: Option<Box<dyn HistoryStore>>,
+    state: Option<StateFile>,
+    /// The block of the chain on now.
+    block: usize,
+    entries: Vec<Entry>,
+    /// A skipped phase, kept out of the history while the skip can still
+    /// be undone.
+    skipped: Option<Entry>,
+    /// When the phase on now started.
+    started: DateTime<Local>,
+    interruptions: Vec<Interruption>,
+    pauses: u32,
+    integrations: Integrations,
+    /// The block, cycle and phase last reported to the integrations as
+    /// started, so a snoozed or unskipped one is not reported again.
+    reported: Option<(usize, u32, PhaseKind)>,
+    out: &'a mut dyn Write,
+}
+
+impl Session<'_> {
+    fn handle(&mut self, events: Vec<Event>) -> io::Result<()> {
+        for event in events {
+            debug!(?event, "engine event");
+            match event {
+                Event::PhaseEnded { phase, cycle, elapsed, outcome, extension } => {
+                    writeln!(
+                        self.out,
+                        "{} {} {cycle}/{} {}",
+                        now(),
+                        phase.kind,
+                        self.engine.num_cycles(),
+                        outcome_name(outcome),
+                    )?;
+                    let entry = Entry {
+                        started: self.started,
+                        kind: phase.kind.clone(),
+                        cycle,
+                        planned_secs: phase.duration.as_secs(),
+                        elapsed_secs: elapsed.as_secs(),
+                        outcome,
+                        extension,
+                        interruptions: std::mem::take(&mut self.interruptions),
+                        pauses: std::mem::take(&mut self.pauses),
+                        note: None,
+                        task: if phase.is_work() { self.config.task.clone() } else { None },
+                        over_budget: false,
+                    };
+                    // Only one skip can be undone; an older one is final.
+                    self.keep_skipped()?;
+                    if outcome == Outcome::Skipped {
+                        self.skipped = Some(entry);
+                    } else {
+                        self.keep(entry)?;
+                    }
+                    self.started = Local::now();
+                    if !self.engine.is_finished() {
+                        self.announce()?;
+                    }
+                }
+                Event::Snoozed => {
+                    self.started = Local::now();
+                    writeln!(self.out, "{} snoozed", now())?;
+                    self.announce()?;
+                }
+                Event::SkipUndone => {
+                    // Pick the skipped phase up where it was, interruptions
+                    // and all.
+                    if let Some(entry) = self.skipped.take() {
+                        self.started = entry.started;
+                        let since = std::mem::take(&mut self.interruptions);
+                        self.interruptions = entry.interruptions;
+                        self.interruptions.extend(since);
+                        self.pauses += entry.pauses;
+                    }
+                    writeln!(self.out, "{} skip undone", now())?;
+                    self.announce()?;
+                }
+                Event::PauseBudgetSpent { budget, failed } => {
+                    let budget = humantime::format_duration(budget);
+                    let what = if failed { "pomodoro failed" } else { "pomodoro over budget" };
+                    writeln!(self.out, "{} paused over the {budget} budget; {what}", now())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Put `entry` in the history for good.
+    fn keep(&mut self, entry: Entry) -> io::Result<()> {
+        if let Some(history) = &self.history {
+            history.append(&entry)?;
+        }
+        let problems = self.integrations.phase_ended(&entry);
+        report(problems);
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Put a skipped phase in the history, now that it stays skipped.
+    fn keep_skipped(&mut self) -> io::Result<()> {
+        match self.skipped.take() {
+            Some(entry) => self.keep(entry),
+            None => Ok(()),
+        }
+    }
+
+    /// Go on with the next block of the chain once one has run its course,
+    /// if there is one.
+    fn start_next_block(&mut self) -> io::Result<()> {
+        let chain = &self.config.chain;
+        if !self.engine.is_finished() || self.block + 1 >= chain.len() {
+            return Ok(());
+        }
+        self.block += 1;
+        self.engine.start_block(&chain[self.block]);
+        self.started = Local::now();
+        info!(block = self.block + 1, of = chain.len(), "next block");
+        writeln!(self.out, "{} block {}/{}", now(), self.block + 1, chain.len())?;
+        self.announce()
+    }
+
+    /// Write the phase just started, and how long it runs, and tell the
+    /// integrations if it is new.
+    fn announce(&mut self) -> io::Result<()> {
+        let engine = &self.engine;
+        let phase = engine.phase();
+        let started = Some((self.block, engine.cycle(), phase.kind.clone()));
+        if std::mem::replace(&mut self.reported, started.clone()) != started {
+            let problems = self.integrations.phase_started(&phase);
+            report(problems);
+        }
+        let engine = &self.engine;
+        writeln!(
+            self.out,
+            "{} {} {}/{} {}",
+            now(),
+            phase.kind,
+            engine.cycle(),
+            engine.num_cycles(),
+            humantime::format_duration(phase.duration),
+        )
+    }
+
+    /// Write the session's state for `status`, and for `resume` once it is
+    /// no longer `active`.
+    fn save_state(&mut self, active: bool) {
+        let Some(file) = &self.state else {
+            return;
+        };
+        let engine = &self.engine;
+        let state = SessionState {
+            updated: Local::now(),
+            active,
+            phase: engine.phase().kind,
+            cycle: engine.cycle(),
+            num_cycles: engine.num_cycles(),
+            remaining_secs: engine.remaining().as_secs(),
+            paused: engine.is_paused(),
+            work_secs: engine.work_time().as_secs(),
+            break_secs: engine.break_time().as_secs(),
+            late: engine.late(),
+        };
+        if let Err(e) = file.save(&state) {
+            // Give up on the state file rather than failing every tick.
+            warn!("could not write session state to {}: {e}", file.path().display());
+            self.state = None;
+        }
+    }
+
+    /// Do what a control socket client asks, unless the session is still
+    /// `waiting` to start, and tell it how the session stands.
+    #[cfg(any(unix, windows))]
+    fn answer(&mut self, pending: Pending, waiting: bool) -> io::Result<()> {
+        debug!(request = ?pending.request, "control request");
+        let engine = &mut self.engine;
+        let paused = engine.is_paused();
+        let events = match pending.request.clone() {
+            _ if waiting => Vec::new(),
+            Request::Snapshot | Request::Pause | Request::Unpause => {
+                let toggle = matches!(
+                    (&pending.request, paused),
+                    (Request::Pause, false) | (Request::Unpause, true)
+                );
+                if toggle {
+                    self.toggle_pause();
+                }
+                Vec::new()
+            }
+            Request::Toggle => {
+                self.toggle_pause();
+                Vec::new()
+            }
+            Request::Skip => engine.skip(),
+            Request::SkipTo { target } => engine.skip_to(target),
+            Request::SetLengths { work, break_time } => {
+                let work = work.unwrap_or(engine.work_time());
+                let break_time = break_time.unwrap_or(engine.break_time());
+                match check_length("work time", work).and(check_length("break time", break_time)) {
+                    Ok(()) => {
+                        engine.set_lengths(work, break_time);
+                    }
+                    Err(e) => warn!("not changing the lengths: {e}"),
+                }
+                Vec::new()
+            }
+            Request::GotoCycle { cycle } => engine.goto_cycle(cycle),
+            Request::Snooze => engine.snooze(),
+            Request::UndoSkip => engine.undo_skip(),
+            // Interruptions only make sense against a work phase.
+            Request::Interrupt { source, note } => {
+                if engine.phase().is_work() {
+                    self.interruptions.push(Interruption { at: Local::now(), source, note });
+                }
+                Vec::new()
+            }
+        };
+        self.handle(events)?;
+        self.start_next_block()?;
+        pending.reply(self.snapshot());
+        Ok(())
+    }
+
+    #[cfg(any(unix, windows))]
+    fn toggle_pause(&mut self) {
+        self.engine.toggle_pause();
+        if self.engine.is_paused() {
+            self.pauses += 1;
+        }
+    }
+
+    /// The session as reported to control socket clients.
+    #[cfg(any(unix, windows))]
+    fn snapshot(&self) -> Snapshot {
+        let engine = &self.engine;
+        let phase = engine.phase();
+        Snapshot {
+            phase: phase.kind,
+            phase_duration: phase.duration,
+            cycle: engine.cycle(),
+            num_cycles: engine.num_cycles(),
+            remaining: engine.remaining(),
+            paused: engine.is_paused(),
+            finished: engine.is_finished(),
+            late: engine.late(),
+            work_time: engine.work_time(),
+            break_time: engine.break_time(),
+            grace_left: engine.grace_left(),
+            undo_left: engine.undo_left(),
+            get_ready_left: engine.get_ready_left(),
+            break_debt: engine.break_debt(),
+            cool_down: engine.cool_down(),
+            long_break: engine.long_break(),
+            completed: self.entries.iter().filter(|entry| entry.outcome == Outcome::Completed).count(),
+        }
+    }
+}
+
+/// Log the integrations' problems; there is no screen to show them on.
+fn report(problems: Vec<error::OpomodoroError>) {
+    for problem in problems {
+        warn!("{problem}");
+    }
+}
+
+/// The wall-clock time each line starts with.
+fn now() -> impl std::fmt::Display {
+    Local::now().format("%H:%M:%S")
+}
+
+fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Completed => "completed",
+        Outcome::Skipped => "skipped",
+        Outcome::Quit => "quit",
+        Outcome::Incomplete => "incomplete",
+        Outcome::Voided => "voided",
+    }
+}
@@ -0,0 +1,151 @@
+//! Tasks planned ahead of a session, each with an estimate in pomodoros.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use crate::profile;
+
+/// A task and how many pomodoros it was expected to take and did take.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Task {
+    pub name: String,
+    pub estimate: u32,
+    pub actual: u32,
+}
+
+impl Task {
+    pub fn new(name: impl Into<String>, estimate: u32) -> Task {
+        Task { name: name.into(), estimate: estimate.max(1), actual: 0 }
+    }
+
+    /// Read a task as typed on the planning screen: its name, optionally
+    /// followed by the estimate (`Write docs 3`). The estimate defaults to 1.
+    pub fn parse(line: &str) -> Option<Task> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        if let Some((name, count)) = line.rsplit_once(char::is_whitespace)
+            && let Ok(estimate) = count.parse() {
+            return Some(Task::new(name.trim_end(), estimate));
+        }
+        Some(Task::new(line, 1))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.actual >= self.estimate
+    }
+
+    /// More pomodoros went into the task than it was budgeted.
+    pub fn is_over_budget(&self) -> bool {
+        self.actual > self.estimate
+    }
+}
+
+/// The session's task list and which task the work phases go to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    tasks: Vec<Task>,
+    current: usize,
+}
+
+impl Plan {
+    pub fn push(&mut self, task: Task) {
+        self.tasks.push(task);
+    }
+
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub fn current(&self) -> Option<&Task> {
+        self.tasks.get(self.current)
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// Count a finished pomodoro against the current task, and move on to
+    /// the next one once its estimate is used up. The last task keeps
+    /// counting, so overruns show up in the report.
+    pub fn record_pomodoro(&mut self) {
+        let Some(task) = self.tasks.get_mut(self.current) else {
+            return;
+        };
+        task.actual += 1;
+        if task.is_done() {
+            self.advance();
+        }
+    }
+
+    /// Move on to the next task, even if the current one has estimate left.
+    pub fn advance(&mut self) {
+        if self.current + 1 < self.tasks.len() {
+            self.current += 1;
+        }
+    }
+
+    /// Go back to the previous task.
+    pub fn back(&mut self) {
+        self.current = self.current.saturating_sub(1);
+    }
+}
+
+/// The plan as saved, tied to the day it was made for.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedPlan {
+    day: NaiveDate,
+    #[serde(flatten)]
+    plan: Plan,
+}
+
+/// Today's tasks and their tallies, kept between sessions.
+#[derive(Debug)]
+pub struct TaskFile {
+    path: PathBuf,
+}
+
+impl TaskFile {
+    pub fn new(path: PathBuf) -> TaskFile {
+        TaskFile { path }
+    }
+
+    /// `tasks.json` in the [profile's](crate::profile) data folder, if the
+    /// platform has a data dir.
+    pub fn default_path() -> Option<PathBuf> {
+        profile::data_dir().map(|dir| dir.join("tasks.json"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn save(&self, plan: &Plan) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let saved = SavedPlan { day: Local::now().date_naive(), plan: plan.clone() };
+        // Write then rename so readers never see a half-written file.
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&saved)?)?;
+        fs::rename(tmp, &self.path)
+    }
+
+    /// The plan saved today, if any; one from an earlier day is stale.
+    pub fn load_today(&self) -> io::Result<Option<Plan>> {
+        let saved: SavedPlan = match fs::read(&self.path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let today = saved.day == Local::now().date_naive();
+        Ok(today.then_some(saved.plan).filter(|plan| !plan.is_empty()))
+    }
+}
@@ -0,0 +1,72 @@
+//! Profiles: one setup per context, like work, study or writing, so their
+//! stats never mix. A profile keeps its own config file, layered over the
+//! shared one, and its own data: history, saved session, tasks and the
+//! rest. There is still one timer at a time, whichever profile it runs.
+//!
+//! ```text
+//! ~/.config/opomodoro/config.toml                   shared by every profile
+//! ~/.config/opomodoro/profiles/study/config.toml
+//! ~/.local/share/opomodoro/profiles/study/history.jsonl
+//! ```
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use crate::error::{self, OpomodoroError};
+
+/// The profile chosen for this run, once chosen.
+static CURRENT: OnceLock<Option<String>> = OnceLock::new();
+
+/// Use the profile `name` for the rest of the run, or none. Only the first
+/// choice counts; choose before looking up any file.
+pub fn select(name: Option<String>) -> error::Result<()> {
+    if let Some(name) = &name {
+        let valid = |ch: char| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_';
+        if name.is_empty() || !name.chars().all(valid) {
+            return Err(OpomodoroError::parse(
+                "profile",
+                format!("{name:?} is not a profile name; use letters, digits, - and _"),
+            ));
+        }
+    }
+    let _ = CURRENT.set(name);
+    Ok(())
+}
+
+/// The profile in use, if any.
+pub fn current() -> Option<&'static str> {
+    CURRENT.get().and_then(Option::as_deref)
+}
+
+/// `<config dir>/opomodoro`, or the profile's folder in there.
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| within(dir.join("opomodoro")))
+}
+
+/// `<data dir>/opomodoro`, or the profile's folder in there.
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| within(dir.join("opomodoro")))
+}
+
+fn within(dir: PathBuf) -> PathBuf {
+    match current() {
+        Some(name) => dir.join("profiles").join(name),
+        None => dir,
+    }
+}
+
+/// The profiles with a config file or data, by name.
+pub fn list() -> Vec<String> {
+    let mut names: Vec<String> = [dirs::config_dir(), dirs::data_dir()]
+        .into_iter()
+        .flatten()
+        .filter_map(|dir| fs::read_dir(dir.join("opomodoro").join("profiles")).ok())
+        .flatten()
+        .filter_map(|item| item.ok())
+        .filter(|item| item.path().is_dir())
+        .filter_map(|item| item.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
@@ -0,0 +1,44 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// What a key press did to a [`TextInput`].
+#[derive(Debug, PartialEq)]
+pub enum InputResult {
+    Editing,
+    Submit,
+    Cancel,
+}
+
+/// Single-line text entry used by the overlay prompts.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TextInput {
+    value: String,
+}
+
+impl TextInput {
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> InputResult {
+        match key.code {
+            KeyCode::Enter => InputResult::Submit,
+            KeyCode::Esc => InputResult::Cancel,
+            KeyCode::Backspace => {
+                self.value.pop();
+                InputResult::Editing
+            }
+            KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.value.push(ch);
+                InputResult::Editing
+            }
+            _ => InputResult::Editing,
+        }
+    }
+
+    /// The trimmed value, or `None` if nothing was typed.
+    pub fn take(&mut self) -> Option<String> {
+        let value = std::mem::take(&mut self.value);
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+}
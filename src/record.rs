@@ -0,0 +1,90 @@
+//! `--record`: a copy of everything the TUI writes to the terminal, with
+//! its timing, as an [asciicast v2] file that `asciinema play` or the web
+//! player can show.
+//!
+//! [asciicast v2]: https://docs.asciinema.org/manual/asciicast/v2/
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use serde_json::json;
+use tracing::warn;
+
+/// Passes output through to `inner`, and writes each flushed frame to the
+/// recording too.
+#[derive(Debug)]
+pub struct Recorder<W: Write> {
+    inner: W,
+    /// Where frames go, until writing one fails.
+    cast: Option<BufWriter<File>>,
+    path: PathBuf,
+    started: Instant,
+    /// Output since the last flush.
+    pending: Vec<u8>,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start a recording of a `width` by `height` terminal at `path`.
+    pub fn create(inner: W, path: &Path, width: u16, height: u16) -> io::Result<Recorder<W>> {
+        let mut cast = BufWriter::new(File::create(path)?);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let header = json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": timestamp,
+            "title": "opomodoro",
+        });
+        writeln!(cast, "{header}")?;
+        Ok(Recorder {
+            inner,
+            cast: Some(cast),
+            path: path.to_path_buf(),
+            started: Instant::now(),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Write the output since the last flush as one event, keeping back the
+    /// start of a character cut off at the end.
+    fn record(&mut self) -> io::Result<()> {
+        let Some(cast) = &mut self.cast else {
+            return Ok(());
+        };
+        let complete = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            Err(e) => e.valid_up_to(),
+        };
+        if complete == 0 {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&self.pending[..complete]);
+        let event = json!([self.started.elapsed().as_secs_f64(), "o", text]);
+        writeln!(cast, "{event}")?;
+        cast.flush()?;
+        self.pending.drain(..complete);
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Recorder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if self.cast.is_some() {
+            self.pending.extend_from_slice(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()?;
+        // A full disk shouldn't end the session, only the recording.
+        if let Err(e) = self.record() {
+            warn!(path = %self.path.display(), "recording stopped: {e}");
+            self.cast = None;
+            self.pending.clear();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+//! `opomodoro schedule`: session templates, the whole plan for a session
+//! in a TOML file of its own, for a team to share a standard "deep work
+//! morning": its phases, the blocks of the day with their labels, and the
+//! sounds, hooks and messages that go with them. What belongs to one
+//! machine, like file paths, accounts and secrets, stays out.
+//!
+//! ```toml
+//! format = 1
+//! name = "Deep work morning"
+//!
+//! [phases]
+//! work = "50m"
+//! break = "10m"
+//!
+//! [sounds]
+//! ambient = "rain"
+//!
+//! [[block]]
+//! name = "Writing"
+//! cycles = 2
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::ambient::AmbientSound;
+use crate::engine::{BreakDebt, OverPauseBudget};
+use crate::error::{self, OpomodoroError};
+use crate::schema::Schema;
+use crate::settings::{BlockSettings, MessageSettings, Settings};
+
+pub const SCHEMA: Schema = Schema::new("session template", &[]);
+
+/// A session template, as written in its file.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionTemplate {
+    /// The version of the layout, from [`SCHEMA`].
+    pub format: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub phases: Phases,
+    #[serde(skip_serializing_if = "is_default")]
+    pub sounds: Sounds,
+    /// Commands run as work starts and stops.
+    #[serde(skip_serializing_if = "is_default")]
+    pub hooks: Hooks,
+    #[serde(skip_serializing_if = "is_default")]
+    pub messages: MessageSettings,
+    /// The sessions of the day, run one after another by `start --day`.
+    #[serde(rename = "block", skip_serializing_if = "Vec::is_empty")]
+    pub blocks: Vec<BlockSettings>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Phases {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work: Option<String>,
+    #[serde(rename = "break", skip_serializing_if = "Option::is_none")]
+    pub break_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub late: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warm_up: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cool_down: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snooze: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_snoozes: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work_warning: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_warning: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hard_break: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub get_ready: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_debt: Option<BreakDebt>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_budget: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_overrun: Option<OverPauseBudget>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sounds {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speak: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ambient: Option<AmbientSound>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ambient_volume: Option<u8>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unblock_command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_on_work: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_on_break: Option<String>,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+impl SessionTemplate {
+    /// The template of the session `settings` would run, called `name`.
+    pub fn from_settings(settings: &Settings, name: Option<String>) -> SessionTemplate {
+        let settings = settings.clone();
+        SessionTemplate {
+            format: SCHEMA.version(),
+            name,
+            phases: Phases {
+                work: settings.work,
+                break_time: settings.break_time,
+                cycles: settings.cycles,
+                late: settings.late,
+                warm_up: settings.warm_up,
+                cool_down: settings.cool_down,
+                snooze: settings.snooze,
+                max_snoozes: settings.max_snoozes,
+                work_warning: settings.work_warning,
+                break_warning: settings.break_warning,
+                hard_break: settings.hard_break,
+                get_ready: settings.get_ready,
+                break_debt: settings.break_debt,
+                pause_budget: settings.pause_budget,
+                pause_overrun: settings.pause_overrun,
+                strict: settings.strict,
+            },
+            sounds: Sounds {
+                volume: settings.volume,
+                speak: settings.speak,
+                ambient: settings.ambient,
+                ambient_volume: settings.ambient_volume,
+            },
+            hooks: Hooks {
+                block_command: settings.block_command,
+                unblock_command: settings.unblock_command,
+                media_on_work: settings.media_on_work,
+                media_on_break: settings.media_on_break,
+            },
+            messages: settings.messages.unwrap_or_default(),
+            blocks: settings.day.unwrap_or_default(),
+        }
+    }
+
+    /// The settings the template sets, and only those.
+    pub fn settings(&self) -> Settings {
+        let SessionTemplate { phases, sounds, hooks, .. } = self.clone();
+        Settings {
+            work: phases.work,
+            break_time: phases.break_time,
+            cycles: phases.cycles,
+            late: phases.late,
+            warm_up: phases.warm_up,
+            cool_down: phases.cool_down,
+            snooze: phases.snooze,
+            max_snoozes: phases.max_snoozes,
+            work_warning: phases.work_warning,
+            break_warning: phases.break_warning,
+            hard_break: phases.hard_break,
+            get_ready: phases.get_ready,
+            break_debt: phases.break_debt,
+            pause_budget: phases.pause_budget,
+            pause_overrun: phases.pause_overrun,
+            strict: phases.strict,
+            volume: sounds.volume,
+            speak: sounds.speak,
+            ambient: sounds.ambient,
+            ambient_volume: sounds.ambient_volume,
+            block_command: hooks.block_command,
+            unblock_command: hooks.unblock_command,
+            media_on_work: hooks.media_on_work,
+            media_on_break: hooks.media_on_break,
+            messages: (!is_default(&self.messages)).then(|| self.messages.clone()),
+            day: (!self.blocks.is_empty()).then(|| self.blocks.clone()),
+            ..Settings::default()
+        }
+    }
+
+    /// Read the template at `path`, upgrading it from an older format.
+    pub fn load(path: &Path) -> error::Result<SessionTemplate> {
+        let what = || format!("template {}", path.display());
+        let text = fs::read_to_string(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("could not read {}: {e}", path.display())))?;
+        let mut value: Value = toml::from_str(&text).map_err(|e| OpomodoroError::parse(what(), e))?;
+        let format = value.get("format").and_then(Value::as_u64).unwrap_or(1);
+        let format = u32::try_from(format).unwrap_or(u32::MAX);
+        SCHEMA.upgrade(&mut value, format).map_err(|e| OpomodoroError::parse(what(), e))?;
+        value["format"] = SCHEMA.version().into();
+        serde_json::from_value(value).map_err(|e| OpomodoroError::parse(what(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> error::Result<()> {
+        let text = toml::to_string_pretty(self).map_err(|e| OpomodoroError::parse("template", e))?;
+        fs::write(path, text)
+            .map_err(|e| io::Error::new(e.kind(), format!("could not write {}: {e}", path.display())))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+//! Versions of the formats opomodoro keeps its data in, and the migrations
+//! between them. Each file records the version it was written in; reading
+//! an older one runs the migrations from there on, one version at a time,
+//! while a newer one is refused rather than read and written back without
+//! what the newer version added.
+//!
+//! A change to a format appends a migration, which makes it the next
+//! version:
+//!
+//! ```
+//! use opomodoro::schema::Schema;
+//! use serde_json::{Value, json};
+//!
+//! // Format 2 counts breaks in minutes rather than seconds.
+//! fn minutes(value: &mut Value) -> Result<(), String> {
+//!     let secs = value["break_secs"].as_u64().ok_or("no break_secs")?;
+//!     value["break_mins"] = json!(secs / 60);
+//!     Ok(())
+//! }
+//!
+//! const BREAKS: Schema = Schema::new("breaks", &[minutes]);
+//! assert_eq!(BREAKS.version(), 2);
+//!
+//! let mut value = json!({ "break_secs": 300 });
+//! BREAKS.upgrade(&mut value, 1).unwrap();
+//! assert_eq!(value["break_mins"], 5);
+//! assert!(BREAKS.upgrade(&mut value, 3).is_err());
+//! ```
+
+use serde_json::Value;
+use crate::error::SchemaError;
+
+/// A step from one version of a format to the next, on the data as JSON.
+pub type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// A format and the migrations that bring its older versions up to date.
+/// Data from before formats had versions is version 1.
+#[derive(Debug, Clone, Copy)]
+pub struct Schema {
+    /// What is in the format, for messages.
+    what: &'static str,
+    /// The first upgrades version 1 to 2, the next 2 to 3, and so on.
+    migrations: &'static [Migration],
+}
+
+impl Schema {
+    pub const fn new(what: &'static str, migrations: &'static [Migration]) -> Schema {
+        Schema { what, migrations }
+    }
+
+    /// The version written now.
+    pub const fn version(&self) -> u32 {
+        self.migrations.len() as u32 + 1
+    }
+
+    /// Whether this version can read data written in `version`.
+    pub fn check(&self, version: u32) -> Result<(), SchemaError> {
+        if version > self.version() {
+            return Err(SchemaError::Newer { what: self.what.to_string(), found: version, current: self.version() });
+        }
+        Ok(())
+    }
+
+    /// Bring `value`, written in version `from`, up to the current one.
+    pub fn upgrade(&self, value: &mut Value, from: u32) -> Result<(), SchemaError> {
+        self.check(from)?;
+        for (migration, version) in self.migrations.iter().zip(1..).skip(from.saturating_sub(1) as usize) {
+            migration(value).map_err(|message| SchemaError::Migration {
+                what: self.what.to_string(),
+                from: version,
+                message,
+            })?;
+        }
+        Ok(())
+    }
+}
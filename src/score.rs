@@ -0,0 +1,95 @@
+//! A focus score out of 100 for a day or a session, and the distractions
+//! that go into it. The score is
+//!
+//! ```text
+//! score = 100 × C / (1 + D + O)
+//!
+//! C = work phases seen through / work phases
+//! D = (½ × interruptions + ¼ × pauses) / work phases
+//! O = overtime / planned work time
+//! ```
+//!
+//! Work phases are the pomodoros started, whether they were completed,
+//! skipped or quit; snoozes and time away are not among them. Pauses are
+//! the ones made by hand. Overtime is the work done past the pomodoros by
+//! snoozing breaks or counting time away as work. A day of pomodoros all
+//! seen through, with no interruptions, pauses or snoozes, scores 100.
+//! An interruption costs as much as two pauses.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+use chrono::NaiveDate;
+use crate::history::{Entry, Outcome, format_focus};
+
+/// What a focus score is worked out from.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FocusScore {
+    pub phases: u32,
+    pub completed: u32,
+    pub interruptions: u32,
+    pub pauses: u32,
+    pub overtime: Duration,
+    /// The work time the phases were planned for.
+    pub planned: Duration,
+}
+
+impl FocusScore {
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> FocusScore {
+        let mut score = FocusScore::default();
+        for entry in entries.into_iter().filter(|entry| entry.is_work()) {
+            score.interruptions += entry.interruptions.len() as u32;
+            score.pauses += entry.pauses;
+            if entry.extension {
+                score.overtime += Duration::from_secs(entry.elapsed_secs);
+                continue;
+            }
+            score.phases += 1;
+            score.planned += Duration::from_secs(entry.planned_secs);
+            if entry.outcome == Outcome::Completed {
+                score.completed += 1;
+            }
+        }
+        score
+    }
+
+    /// The score out of 100, or `None` without any work phases to go by.
+    pub fn score(&self) -> Option<u32> {
+        if self.phases == 0 {
+            return None;
+        }
+        let phases = f64::from(self.phases);
+        let completion = f64::from(self.completed) / phases;
+        let distraction = (0.5 * f64::from(self.interruptions) + 0.25 * f64::from(self.pauses)) / phases;
+        let overtime = self.overtime.as_secs_f64() / self.planned.as_secs_f64().max(1.0);
+        Some((100.0 * completion / (1.0 + distraction + overtime)).round() as u32)
+    }
+}
+
+/// The score and what went into it, e.g. `72 (7/8 seen through, 2
+/// interruption(s), 3 pause(s), 10m overtime)`.
+impl fmt::Display for FocusScore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(score) = self.score() else {
+            return write!(f, "no work phases yet");
+        };
+        write!(
+            f,
+            "{score} ({}/{} seen through, {} interruption(s), {} pause(s), {} overtime)",
+            self.completed,
+            self.phases,
+            self.interruptions,
+            self.pauses,
+            format_focus(self.overtime),
+        )
+    }
+}
+
+/// The focus score of each day in `entries` with any work, oldest first.
+pub fn by_day(entries: &[Entry]) -> Vec<(NaiveDate, FocusScore)> {
+    let mut days: BTreeMap<NaiveDate, Vec<&Entry>> = BTreeMap::new();
+    for entry in entries.iter().filter(|entry| entry.is_work()) {
+        days.entry(entry.started.date_naive()).or_default().push(entry);
+    }
+    days.into_iter().map(|(day, entries)| (day, FocusScore::from_entries(entries))).collect()
+}
@@ -0,0 +1,206 @@
+//! Files that have the system start `opomodoro daemon` at login, written by
+//! `opomodoro daemon install`: a systemd user unit, a launchd agent, or on
+//! Windows a Task Scheduler task. A Windows service proper runs in session
+//! 0, away from the user's files and notifications, so a task that starts
+//! at logon stands in for one.
+
+use std::path::{Path, PathBuf};
+
+/// What the service is called by the system.
+const NAME: &str = "opomodoro";
+/// The launchd label, reverse-DNS like Apple's own.
+const LABEL: &str = "io.github.hgfjh.opomodoro";
+
+/// A service manager that can run the daemon for the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Manager {
+    Systemd,
+    Launchd,
+    TaskScheduler,
+}
+
+impl Manager {
+    /// The service manager of this platform, if it has one opomodoro knows.
+    pub fn native() -> Option<Manager> {
+        if cfg!(target_os = "macos") {
+            Some(Manager::Launchd)
+        } else if cfg!(windows) {
+            Some(Manager::TaskScheduler)
+        } else if cfg!(target_os = "linux") {
+            Some(Manager::Systemd)
+        } else {
+            None
+        }
+    }
+
+    /// Where the file goes for the current user. Task Scheduler keeps its
+    /// tasks itself; the file is only handed to `schtasks`.
+    pub fn path(self) -> Option<PathBuf> {
+        match self {
+            Manager::Systemd => {
+                Some(dirs::config_dir()?.join("systemd").join("user").join(format!("{NAME}.service")))
+            }
+            Manager::Launchd => {
+                Some(dirs::home_dir()?.join("Library").join("LaunchAgents").join(format!("{LABEL}.plist")))
+            }
+            Manager::TaskScheduler => Some(dirs::data_dir()?.join(NAME).join(format!("{NAME}-task.xml"))),
+        }
+    }
+
+    /// The file that runs `program` with `args`.
+    pub fn render(self, program: &Path, args: &[String]) -> String {
+        match self {
+            Manager::Systemd => systemd_unit(program, args),
+            Manager::Launchd => launchd_plist(program, args),
+            Manager::TaskScheduler => scheduler_task(program, args),
+        }
+    }
+
+    /// The command that starts the service from the file at `path`, now
+    /// and at each login.
+    pub fn enable_command(self, path: &Path) -> String {
+        match self {
+            Manager::Systemd => format!("systemctl --user enable --now {NAME}.service"),
+            Manager::Launchd => format!("launchctl load -w {}", path.display()),
+            Manager::TaskScheduler => format!("schtasks /Create /TN {NAME} /XML \"{}\"", path.display()),
+        }
+    }
+}
+
+fn systemd_unit(program: &Path, args: &[String]) -> String {
+    let command: Vec<String> = std::iter::once(program.display().to_string())
+        .chain(args.iter().cloned())
+        .map(|arg| systemd_quote(&arg))
+        .collect();
+    let command = command.join(" ");
+    format!(
+        "[Unit]
+Description=opomodoro pomodoro timer daemon
+
+[Service]
+Type=notify
+ExecStart={command}
+Restart=on-failure
+WatchdogSec=30
+
+[Install]
+WantedBy=default.target
+"
+    )
+}
+
+/// `arg` as one word of an `ExecStart=` line.
+fn systemd_quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || "\"'\\%$;".contains(c)) {
+        return arg.to_string();
+    }
+    // Inside double quotes systemd still expands `%` specifiers and `$`
+    // variables, so those are doubled.
+    let escaped = arg.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%").replace('$', "$$");
+    format!("\"{escaped}\"")
+}
+
+fn launchd_plist(program: &Path, args: &[String]) -> String {
+    let arguments: String = std::iter::once(program.display().to_string())
+        .chain(args.iter().cloned())
+        .map(|arg| format!("\n        <string>{}</string>", xml_escape(&arg)))
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>{arguments}
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+</dict>
+</plist>
+"#
+    )
+}
+
+fn scheduler_task(program: &Path, args: &[String]) -> String {
+    let arguments: Vec<String> = args.iter().map(|arg| windows_quote(arg)).collect();
+    let command = xml_escape(&program.display().to_string());
+    let arguments = xml_escape(&arguments.join(" "));
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Description>opomodoro pomodoro timer daemon</Description>
+  </RegistrationInfo>
+  <Triggers>
+    <LogonTrigger>
+      <Enabled>true</Enabled>
+    </LogonTrigger>
+  </Triggers>
+  <Principals>
+    <Principal id="Author">
+      <LogonType>InteractiveToken</LogonType>
+      <RunLevel>LeastPrivilege</RunLevel>
+    </Principal>
+  </Principals>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>
+    <RestartOnFailure>
+      <Interval>PT1M</Interval>
+      <Count>3</Count>
+    </RestartOnFailure>
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>{command}</Command>
+      <Arguments>{arguments}</Arguments>
+    </Exec>
+  </Actions>
+</Task>
+"#
+    )
+}
+
+/// `arg` as one argument of a Windows command line, which the program
+/// splits up again itself.
+fn windows_quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '"') {
+        return arg.to_string();
+    }
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Backslashes before a quote are escapes, so double them,
+                // then escape the quote itself.
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,792 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::Weekday;
+use serde::{Deserialize, Serialize};
+use toml_edit::DocumentMut;
+use crate::ambient::AmbientSound;
+use crate::engine::{BreakDebt, OverPauseBudget};
+use crate::error::{self, OpomodoroError};
+use crate::history::HistoryBackend;
+use crate::profile;
+use crate::theme::ThemeChoice;
+use crate::{Countdown, FontChoice, HourFormat, ViewMode};
+
+pub const DEFAULT_WORK: &str = "25m";
+pub const DEFAULT_BREAK: &str = "5m";
+pub const DEFAULT_CYCLES: u32 = 4;
+pub const DEFAULT_VOLUME: u8 = 100;
+pub const DEFAULT_SNOOZE: &str = "5m";
+pub const DEFAULT_MAX_SNOOZES: u32 = 2;
+pub const DEFAULT_ALERT_REPEAT: &str = "30s";
+pub const DEFAULT_MAX_ALERT_REPEATS: u32 = 5;
+pub const DEFAULT_AMBIENT_VOLUME: u8 = 30;
+pub const DEFAULT_GITHUB_API: &str = "https://api.github.com";
+
+/// Keys the config file accepts, in display order.
+pub const KEYS: [&str; 58] = [
+    "work", "break", "cycles", "late", "warm_up", "cool_down", "font", "view", "countdown", "theme", "volume",
+    "speak", "notify", "visual_bell",
+    "notes",
+    "snooze", "max_snoozes", "idle", "work_warning", "break_warning", "hard_break", "get_ready", "break_debt",
+    "pause_budget",
+    "pause_overrun", "strict", "end_of_day", "manual_advance", "alert_repeat", "max_alert_repeats",
+    "block_command", "unblock_command", "ambient", "ambient_volume", "daily_target", "weekly_target", "task",
+    "clock", "sidebar", "mascot", "animations", "spinner", "low_power", "autostart", "history_backend",
+    "sync_dir", "sync_url", "sync_user", "sync_password",
+    "github_api", "github_token", "github_comment", "jira_url", "jira_email", "jira_token",
+    "media_on_work", "media_on_break", "team_passphrase",
+];
+
+/// Name of a project's config file, looked up from the current directory
+/// towards the root.
+pub const PROJECT_FILE: &str = ".opomodoro.toml";
+
+/// User settings as found in the config file or on the command line.
+/// Every field is optional so layers can be merged; unset fields fall back
+/// to the `DEFAULT_*` constants.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work: Option<String>,
+    #[serde(rename = "break", skip_serializing_if = "Option::is_none")]
+    pub break_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub late: Option<bool>,
+    /// Plan for this long before the first work phase, e.g. `3m`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warm_up: Option<String>,
+    /// Review for this long after the last phase, e.g. `5m`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cool_down: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font: Option<FontChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view: Option<ViewMode>,
+    /// How the timer writes the time left: `clock`, `minutes` or `percent`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub countdown: Option<Countdown>,
+    /// Colors to draw in: `tomato`, `ocean`, `forest`, `deutan`, `tritan`
+    /// or `mono`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<ThemeChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<u8>,
+    /// Speak phase changes out loud.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speak: Option<bool>,
+    /// Show a desktop notification as each phase runs out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify: Option<bool>,
+    /// Flash the screen on alerts; with `volume = 0`, in place of sound.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visual_bell: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snooze: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_snoozes: Option<u32>,
+    /// Pause work phases after this long without keyboard or mouse input.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle: Option<String>,
+    /// How long before a work phase ends to warn that it is about to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work_warning: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_warning: Option<String>,
+    /// Fill the screen during breaks, and hold off skipping them for this long.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hard_break: Option<String>,
+    /// Count down this long before each work phase after a break, e.g. `5s`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub get_ready: Option<String>,
+    /// What to do about skipped break time: `off`, `show` it, or `repay` it
+    /// in the next break.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_debt: Option<BreakDebt>,
+    /// Most time a work phase may spend paused, e.g. `2m`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_budget: Option<String>,
+    /// What pausing for longer does: `warn`, or `fail` the pomodoro.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_overrun: Option<OverPauseBudget>,
+    /// Void work phases that are paused or interrupted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+    /// Celebrate once the last cycle ends, and start no more sessions
+    /// until tomorrow short of `start --override`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_of_day: Option<bool>,
+    /// Hold each phase until a key is pressed, sounding the alert again
+    /// until then.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manual_advance: Option<bool>,
+    /// How often the alert sounds again while a phase is held, e.g. `30s`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert_repeat: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_alert_repeats: Option<u32>,
+    /// Shell command run as work starts, e.g. to block distracting sites.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_command: Option<String>,
+    /// Shell command run as work stops, and always before exiting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unblock_command: Option<String>,
+    /// Sound to play through work phases, `white` or `rain`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ambient: Option<AmbientSound>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ambient_volume: Option<u8>,
+    /// Focus time to aim for each day, e.g. `4h`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily_target: Option<String>,
+    /// Focus time to aim for each week, e.g. `20h`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weekly_target: Option<String>,
+    /// Label recorded with each work phase, usually set per project.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock: Option<HourFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sidebar: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mascot: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animations: Option<bool>,
+    /// Spin a braille dot by the phase while paused, and hold it still
+    /// while running.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spinner: Option<bool>,
+    /// Redraw about once a second and skip the splash, to save battery.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub low_power: Option<bool>,
+    /// When `daemon --recurring` starts sessions, e.g. `["weekdays 09:00"]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autostart: Option<Vec<String>>,
+    /// `sqlite` keeps the history in `history.db`, imported from
+    /// `history.jsonl` the first time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_backend: Option<HistoryBackend>,
+    /// A folder synced between machines, e.g. by Dropbox or Syncthing, to
+    /// keep the history in instead; each machine writes a file of its own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_dir: Option<PathBuf>,
+    /// A WebDAV folder for `opomodoro sync` to keep the history in step
+    /// through, e.g. `https://cloud.example.com/remote.php/dav/files/me/opomodoro`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub github_api: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub github_token: Option<String>,
+    /// Comment the session's focus time on the `--gh-issue`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub github_comment: Option<bool>,
+    /// Base URL of the Jira to log work in, e.g. `https://acme.atlassian.net`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jira_url: Option<String>,
+    /// Account for a Jira Cloud API token; leave unset for a personal access token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jira_email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jira_token: Option<String>,
+    /// What to do with the media player as work starts: `play`, `pause`,
+    /// or a URI to play, like a Spotify playlist.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_on_work: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_on_break: Option<String>,
+    /// Shared by a team to encrypt its timer and keep others from joining.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team_passphrase: Option<String>,
+    /// Sessions to run one after another with `start --day`, written as
+    /// `[[day]]` tables.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day: Option<Vec<BlockSettings>>,
+    /// What banners and announcements say, in a `[messages]` table.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub messages: Option<MessageSettings>,
+    /// Named sets of settings, like `[preset.deep]`, for `--preset` or the
+    /// `schedule` to pick.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<BTreeMap<String, Settings>>,
+    /// The preset to run each day of the week, like `[schedule.monday]
+    /// preset = "deep"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<WeekSchedule>,
+}
+
+/// A session in a chain, as written in a `[[day]]` table or given to
+/// `--then`. Unset values fall back to the settings around it, except that
+/// a block with a break and no work is just that break.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlockSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work: Option<String>,
+    #[serde(rename = "break", skip_serializing_if = "Option::is_none")]
+    pub break_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycles: Option<u32>,
+}
+
+/// Templates for the session's messages, like `phase_end = "{phase} over,
+/// next: {next_phase}"`. Unset ones keep the built-in wording.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MessageSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase_start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase_end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work_warning: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_warning: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_block: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_end: Option<String>,
+}
+
+/// The `[schedule]` table: what each day of the week runs.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeekSchedule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monday: Option<DaySchedule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tuesday: Option<DaySchedule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wednesday: Option<DaySchedule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thursday: Option<DaySchedule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub friday: Option<DaySchedule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saturday: Option<DaySchedule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sunday: Option<DaySchedule>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaySchedule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+}
+
+impl WeekSchedule {
+    pub fn day(&self, day: Weekday) -> Option<&DaySchedule> {
+        match day {
+            Weekday::Mon => self.monday.as_ref(),
+            Weekday::Tue => self.tuesday.as_ref(),
+            Weekday::Wed => self.wednesday.as_ref(),
+            Weekday::Thu => self.thursday.as_ref(),
+            Weekday::Fri => self.friday.as_ref(),
+            Weekday::Sat => self.saturday.as_ref(),
+            Weekday::Sun => self.sunday.as_ref(),
+        }
+    }
+
+    /// Layer `over` on top of `self`, day by day.
+    fn merge(self, over: WeekSchedule) -> WeekSchedule {
+        WeekSchedule {
+            monday: over.monday.or(self.monday),
+            tuesday: over.tuesday.or(self.tuesday),
+            wednesday: over.wednesday.or(self.wednesday),
+            thursday: over.thursday.or(self.thursday),
+            friday: over.friday.or(self.friday),
+            saturday: over.saturday.or(self.saturday),
+            sunday: over.sunday.or(self.sunday),
+        }
+    }
+}
+
+impl MessageSettings {
+    /// Every template set, by name, in display order.
+    pub fn set(&self) -> Vec<(&'static str, &str)> {
+        let all = [
+            ("phase_start", &self.phase_start),
+            ("phase_end", &self.phase_end),
+            ("work_warning", &self.work_warning),
+            ("break_warning", &self.break_warning),
+            ("next_block", &self.next_block),
+            ("session_end", &self.session_end),
+        ];
+        all.into_iter().filter_map(|(name, text)| Some((name, text.as_deref()?))).collect()
+    }
+
+    /// Layer `over` on top of `self`, template by template.
+    fn merge(self, over: MessageSettings) -> MessageSettings {
+        MessageSettings {
+            phase_start: over.phase_start.or(self.phase_start),
+            phase_end: over.phase_end.or(self.phase_end),
+            work_warning: over.work_warning.or(self.work_warning),
+            break_warning: over.break_warning.or(self.break_warning),
+            next_block: over.next_block.or(self.next_block),
+            session_end: over.session_end.or(self.session_end),
+        }
+    }
+}
+
+impl Settings {
+    /// `config.toml` in the [profile's](crate::profile) config folder, if
+    /// the platform has a config dir. It is the one `config set` changes.
+    pub fn default_path() -> Option<PathBuf> {
+        profile::config_dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// `<config dir>/opomodoro/config.toml`, which a profile's config file
+    /// is layered over; `None` with no profile in use, when it is the
+    /// [`default_path`](Settings::default_path) itself.
+    pub fn shared_path() -> Option<PathBuf> {
+        profile::current()?;
+        dirs::config_dir().map(|dir| dir.join("opomodoro").join("config.toml"))
+    }
+
+    /// The nearest [`PROJECT_FILE`] in `dir` or one of its parents.
+    pub fn find_project(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|dir| dir.join(PROJECT_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Read settings from a TOML file; a missing file means no settings.
+    pub fn load(path: &Path) -> error::Result<Settings> {
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| {
+                OpomodoroError::parse(format!("config file {}", path.display()), e)
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Layer `over` on top of `self`: any value set in `over` wins.
+    pub fn merge(self, over: Settings) -> Settings {
+        Settings {
+            work: over.work.or(self.work),
+            break_time: over.break_time.or(self.break_time),
+            cycles: over.cycles.or(self.cycles),
+            late: over.late.or(self.late),
+            warm_up: over.warm_up.or(self.warm_up),
+            cool_down: over.cool_down.or(self.cool_down),
+            font: over.font.or(self.font),
+            view: over.view.or(self.view),
+            countdown: over.countdown.or(self.countdown),
+            theme: over.theme.or(self.theme),
+            volume: over.volume.or(self.volume),
+            speak: over.speak.or(self.speak),
+            notify: over.notify.or(self.notify),
+            visual_bell: over.visual_bell.or(self.visual_bell),
+            notes: over.notes.or(self.notes),
+            snooze: over.snooze.or(self.snooze),
+            max_snoozes: over.max_snoozes.or(self.max_snoozes),
+            idle: over.idle.or(self.idle),
+            work_warning: over.work_warning.or(self.work_warning),
+            break_warning: over.break_warning.or(self.break_warning),
+            hard_break: over.hard_break.or(self.hard_break),
+            get_ready: over.get_ready.or(self.get_ready),
+            break_debt: over.break_debt.or(self.break_debt),
+            pause_budget: over.pause_budget.or(self.pause_budget),
+            pause_overrun: over.pause_overrun.or(self.pause_overrun),
+            strict: over.strict.or(self.strict),
+            end_of_day: over.end_of_day.or(self.end_of_day),
+            manual_advance: over.manual_advance.or(self.manual_advance),
+            alert_repeat: over.alert_repeat.or(self.alert_repeat),
+            max_alert_repeats: over.max_alert_repeats.or(self.max_alert_repeats),
+            block_command: over.block_command.or(self.block_command),
+            unblock_command: over.unblock_command.or(self.unblock_command),
+            ambient: over.ambient.or(self.ambient),
+            ambient_volume: over.ambient_volume.or(self.ambient_volume),
+            daily_target: over.daily_target.or(self.daily_target),
+            weekly_target: over.weekly_target.or(self.weekly_target),
+            task: over.task.or(self.task),
+            clock: over.clock.or(self.clock),
+            sidebar: over.sidebar.or(self.sidebar),
+            mascot: over.mascot.or(self.mascot),
+            animations: over.animations.or(self.animations),
+            spinner: over.spinner.or(self.spinner),
+            low_power: over.low_power.or(self.low_power),
+            autostart: over.autostart.or(self.autostart),
+            history_backend: over.history_backend.or(self.history_backend),
+            sync_dir: over.sync_dir.or(self.sync_dir),
+            sync_url: over.sync_url.or(self.sync_url),
+            sync_user: over.sync_user.or(self.sync_user),
+            sync_password: over.sync_password.or(self.sync_password),
+            github_api: over.github_api.or(self.github_api),
+            github_token: over.github_token.or(self.github_token),
+            github_comment: over.github_comment.or(self.github_comment),
+            jira_url: over.jira_url.or(self.jira_url),
+            jira_email: over.jira_email.or(self.jira_email),
+            jira_token: over.jira_token.or(self.jira_token),
+            media_on_work: over.media_on_work.or(self.media_on_work),
+            media_on_break: over.media_on_break.or(self.media_on_break),
+            team_passphrase: over.team_passphrase.or(self.team_passphrase),
+            day: over.day.or(self.day),
+            // Presets of the same name give way; the others all stay.
+            preset: match (self.preset, over.preset) {
+                (Some(mut base), Some(over)) => {
+                    base.extend(over);
+                    Some(base)
+                }
+                (base, over) => over.or(base),
+            },
+            schedule: match (self.schedule, over.schedule) {
+                (Some(base), Some(over)) => Some(base.merge(over)),
+                (base, over) => over.or(base),
+            },
+            messages: match (self.messages, over.messages) {
+                (Some(base), Some(over)) => Some(base.merge(over)),
+                (base, over) => over.or(base),
+            },
+        }
+    }
+
+    /// Every value filled in, falling back to the defaults.
+    pub fn resolved(&self) -> Settings {
+        Settings {
+            work: Some(self.work().to_string()),
+            break_time: Some(self.break_time().to_string()),
+            cycles: Some(self.cycles()),
+            late: Some(self.late()),
+            // Off unless asked for.
+            warm_up: self.warm_up.clone(),
+            cool_down: self.cool_down.clone(),
+            font: Some(self.font()),
+            view: Some(self.view()),
+            countdown: Some(self.countdown()),
+            theme: Some(self.theme()),
+            volume: Some(self.volume()),
+            speak: Some(self.speak()),
+            notify: Some(self.notify()),
+            visual_bell: Some(self.visual_bell()),
+            notes: Some(self.notes()),
+            snooze: Some(self.snooze().to_string()),
+            max_snoozes: Some(self.max_snoozes()),
+            // Off unless asked for.
+            idle: self.idle.clone(),
+            work_warning: self.work_warning.clone(),
+            break_warning: self.break_warning.clone(),
+            hard_break: self.hard_break.clone(),
+            get_ready: self.get_ready.clone(),
+            break_debt: Some(self.break_debt()),
+            pause_budget: self.pause_budget.clone(),
+            pause_overrun: Some(self.pause_overrun()),
+            strict: Some(self.strict()),
+            end_of_day: Some(self.end_of_day()),
+            manual_advance: Some(self.manual_advance()),
+            alert_repeat: Some(self.alert_repeat().to_string()),
+            max_alert_repeats: Some(self.max_alert_repeats()),
+            block_command: self.block_command.clone(),
+            unblock_command: self.unblock_command.clone(),
+            ambient: self.ambient,
+            ambient_volume: Some(self.ambient_volume()),
+            daily_target: self.daily_target.clone(),
+            weekly_target: self.weekly_target.clone(),
+            // No default task; an unset one stays unset.
+            task: self.task.clone(),
+            clock: Some(self.clock()),
+            sidebar: Some(self.sidebar()),
+            mascot: Some(self.mascot()),
+            animations: Some(self.animations()),
+            spinner: Some(self.spinner()),
+            low_power: Some(self.low_power()),
+            autostart: self.autostart.clone(),
+            history_backend: Some(self.history_backend()),
+            sync_dir: self.sync_dir.clone(),
+            sync_url: self.sync_url.clone(),
+            sync_user: self.sync_user.clone(),
+            sync_password: self.sync_password.clone(),
+            github_api: Some(self.github_api().to_string()),
+            github_token: self.github_token.clone(),
+            github_comment: Some(self.github_comment()),
+            jira_url: self.jira_url.clone(),
+            jira_email: self.jira_email.clone(),
+            jira_token: self.jira_token.clone(),
+            media_on_work: self.media_on_work.clone(),
+            media_on_break: self.media_on_break.clone(),
+            team_passphrase: self.team_passphrase.clone(),
+            day: self.day.clone(),
+            preset: self.preset.clone(),
+            schedule: self.schedule.clone(),
+            messages: self.messages.clone(),
+        }
+    }
+
+    /// The preset to run on `day`: `name` if given, or else the one the
+    /// `schedule` has for the day, if any.
+    pub fn preset_for(&self, day: Weekday, name: Option<&str>) -> error::Result<Option<(&str, &Settings)>> {
+        let scheduled = self.schedule.as_ref().and_then(|schedule| schedule.day(day)?.preset.as_deref());
+        let Some(name) = name.or(scheduled) else {
+            return Ok(None);
+        };
+        let presets = self.preset.as_ref();
+        let Some((name, preset)) = presets.and_then(|presets| presets.get_key_value(name)) else {
+            let known: Vec<_> = presets.into_iter().flat_map(BTreeMap::keys).map(String::as_str).collect();
+            let known = if known.is_empty() { "none".to_string() } else { known.join(", ") };
+            return Err(OpomodoroError::parse("preset", format!("no preset {name:?}; the config has {known}")));
+        };
+        if preset.preset.is_some() || preset.schedule.is_some() {
+            return Err(OpomodoroError::parse("preset", format!("{name:?} holds presets of its own")));
+        }
+        Ok(Some((name, preset)))
+    }
+
+    /// These settings with the preset for `day`, as
+    /// [`preset_for`](Settings::preset_for) picks it, on top.
+    pub fn with_preset(self, day: Weekday, name: Option<&str>) -> error::Result<Settings> {
+        match self.preset_for(day, name)? {
+            Some((_, preset)) => {
+                let preset = preset.clone();
+                Ok(self.merge(preset))
+            }
+            None => Ok(self),
+        }
+    }
+
+    /// The value in effect for config file `key`.
+    pub fn get(&self, key: &str) -> Option<toml::Value> {
+        let table = toml::Table::try_from(self.resolved()).ok()?;
+        table.get(key).cloned()
+    }
+
+    pub fn work(&self) -> &str {
+        self.work.as_deref().unwrap_or(DEFAULT_WORK)
+    }
+
+    pub fn break_time(&self) -> &str {
+        self.break_time.as_deref().unwrap_or(DEFAULT_BREAK)
+    }
+
+    pub fn cycles(&self) -> u32 {
+        self.cycles.unwrap_or(DEFAULT_CYCLES)
+    }
+
+    pub fn late(&self) -> bool {
+        self.late.unwrap_or(false)
+    }
+
+    pub fn font(&self) -> FontChoice {
+        self.font.unwrap_or(FontChoice::Auto)
+    }
+
+    pub fn view(&self) -> ViewMode {
+        self.view.unwrap_or(ViewMode::Digits)
+    }
+
+    pub fn countdown(&self) -> Countdown {
+        self.countdown.unwrap_or(Countdown::Clock)
+    }
+
+    pub fn theme(&self) -> ThemeChoice {
+        self.theme.unwrap_or_default()
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume.unwrap_or(DEFAULT_VOLUME)
+    }
+
+    pub fn speak(&self) -> bool {
+        self.speak.unwrap_or(false)
+    }
+
+    pub fn notify(&self) -> bool {
+        self.notify.unwrap_or(false)
+    }
+
+    pub fn visual_bell(&self) -> bool {
+        self.visual_bell.unwrap_or(false)
+    }
+
+    pub fn break_debt(&self) -> BreakDebt {
+        self.break_debt.unwrap_or_default()
+    }
+
+    pub fn pause_overrun(&self) -> OverPauseBudget {
+        self.pause_overrun.unwrap_or_default()
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict.unwrap_or(false)
+    }
+
+    pub fn end_of_day(&self) -> bool {
+        self.end_of_day.unwrap_or(false)
+    }
+
+    pub fn manual_advance(&self) -> bool {
+        self.manual_advance.unwrap_or(false)
+    }
+
+    pub fn alert_repeat(&self) -> &str {
+        self.alert_repeat.as_deref().unwrap_or(DEFAULT_ALERT_REPEAT)
+    }
+
+    pub fn max_alert_repeats(&self) -> u32 {
+        self.max_alert_repeats.unwrap_or(DEFAULT_MAX_ALERT_REPEATS)
+    }
+
+    pub fn ambient_volume(&self) -> u8 {
+        self.ambient_volume.unwrap_or(DEFAULT_AMBIENT_VOLUME)
+    }
+
+    pub fn notes(&self) -> bool {
+        self.notes.unwrap_or(false)
+    }
+
+    pub fn snooze(&self) -> &str {
+        self.snooze.as_deref().unwrap_or(DEFAULT_SNOOZE)
+    }
+
+    pub fn max_snoozes(&self) -> u32 {
+        self.max_snoozes.unwrap_or(DEFAULT_MAX_SNOOZES)
+    }
+
+    pub fn clock(&self) -> HourFormat {
+        self.clock.unwrap_or(HourFormat::Auto)
+    }
+
+    pub fn sidebar(&self) -> bool {
+        self.sidebar.unwrap_or(false)
+    }
+
+    pub fn mascot(&self) -> bool {
+        self.mascot.unwrap_or(false)
+    }
+
+    pub fn animations(&self) -> bool {
+        self.animations.unwrap_or(true)
+    }
+
+    pub fn spinner(&self) -> bool {
+        self.spinner.unwrap_or(true)
+    }
+
+    pub fn low_power(&self) -> bool {
+        self.low_power.unwrap_or(false)
+    }
+
+    pub fn history_backend(&self) -> HistoryBackend {
+        self.history_backend.unwrap_or_default()
+    }
+
+    /// Where the history is: the `sync_dir`, which has to be there already
+    /// for a folder the sync looks after, or else the backend's file. A
+    /// profile keeps to a folder of its own in the `sync_dir`.
+    pub fn history_path(&self) -> error::Result<Option<PathBuf>> {
+        match &self.sync_dir {
+            Some(dir) if !dir.is_dir() => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("sync_dir {} is not a folder", dir.display()),
+            )
+            .into()),
+            Some(dir) => match profile::current() {
+                Some(name) => {
+                    let dir = dir.join("profiles").join(name);
+                    fs::create_dir_all(&dir)?;
+                    Ok(Some(dir))
+                }
+                None => Ok(Some(dir.clone())),
+            },
+            None => Ok(self.history_backend().default_path()),
+        }
+    }
+
+    pub fn github_api(&self) -> &str {
+        self.github_api.as_deref().unwrap_or(DEFAULT_GITHUB_API)
+    }
+
+    pub fn github_comment(&self) -> bool {
+        self.github_comment.unwrap_or(false)
+    }
+}
+
+/// The config file as a TOML document, so edits keep the comments and
+/// layout the user gave it.
+#[derive(Debug)]
+pub struct ConfigFile {
+    path: PathBuf,
+    doc: DocumentMut,
+}
+
+impl ConfigFile {
+    /// Open the config file at `path`; a missing file is an empty one.
+    pub fn open(path: &Path) -> error::Result<ConfigFile> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let doc = text.parse::<DocumentMut>().map_err(|e| {
+            OpomodoroError::parse(format!("config file {}", path.display()), e)
+        })?;
+        Ok(ConfigFile { path: path.to_path_buf(), doc })
+    }
+
+    /// The settings the document holds.
+    pub fn settings(&self) -> error::Result<Settings> {
+        toml::from_str(&self.doc.to_string()).map_err(|e| {
+            OpomodoroError::parse(format!("config file {}", self.path.display()), e)
+        })
+    }
+
+    /// Set `key` to `value`, read as a TOML value where it is one (`4`,
+    /// `true`) and as a string otherwise (`50m`).
+    pub fn set(&mut self, key: &str, value: &str) -> error::Result<()> {
+        if !KEYS.contains(&key) {
+            return Err(OpomodoroError::parse(
+                "config key",
+                format!("unknown key {key:?}, expected one of {}", KEYS.join(", ")),
+            ));
+        }
+        let mut value = value
+            .parse::<toml_edit::Value>()
+            .unwrap_or_else(|_| toml_edit::Value::from(value));
+        if let Some(old) = self.doc.get(key).and_then(|item| item.as_value()) {
+            *value.decor_mut() = old.decor().clone();
+        }
+        self.doc[key] = toml_edit::Item::Value(value);
+        self.settings().map(|_| ())
+    }
+
+    /// Write every setting `settings` has into the document, tables and
+    /// all, over what was there. Returns the keys written.
+    pub fn merge(&mut self, settings: &Settings) -> error::Result<Vec<String>> {
+        let text = toml::to_string(settings).map_err(|e| OpomodoroError::parse("settings", e))?;
+        let doc = text.parse::<DocumentMut>().map_err(|e| OpomodoroError::parse("settings", e))?;
+        let mut keys = Vec::new();
+        for (key, item) in doc.iter() {
+            let mut item = item.clone();
+            // Keep the comments around a value that is only replaced, as `set` does.
+            let old = self.doc.get(key).and_then(|old| old.as_value());
+            if let (Some(value), Some(old)) = (item.as_value_mut(), old) {
+                *value.decor_mut() = old.decor().clone();
+            }
+            self.doc[key] = item;
+            keys.push(key.to_string());
+        }
+        self.settings()?;
+        Ok(keys)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, self.doc.to_string())
+    }
+}
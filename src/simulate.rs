@@ -0,0 +1,32 @@
+use std::thread;
+use std::time::Duration;
+use crate::Config;
+use crate::clock::MockClock;
+use crate::engine::{Engine, Phase};
+
+/// A phase of a simulated session, reported as it starts.
+#[derive(Debug, Clone)]
+pub struct Step {
+    /// Offset from the start of the session.
+    pub start: Duration,
+    pub phase: Phase,
+    pub cycle: u32,
+}
+
+/// Run a session for `config` on a [`MockClock`], calling `report` as each
+/// phase starts. With a `speed`, phases take `1/speed` of their real length;
+/// without one the whole run is instant. Returns the length of the session.
+pub fn simulate(config: &Config, speed: Option<u32>, mut report: impl FnMut(Step)) -> Duration {
+    let clock = MockClock::new();
+    let mut engine = Engine::with_clock(config, clock.clone());
+    while !engine.is_finished() {
+        report(Step { start: clock.elapsed(), phase: engine.phase(), cycle: engine.cycle() });
+        let left = engine.remaining();
+        if let Some(speed) = speed.filter(|speed| *speed > 0) {
+            thread::sleep(left / speed);
+        }
+        clock.advance(left);
+        engine.tick();
+    }
+    clock.elapsed()
+}
@@ -0,0 +1,98 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::engine::PhaseKind;
+use crate::profile;
+use crate::schema::Schema;
+
+/// The format of the state file, recorded in it as `format`. See
+/// [`crate::schema`] for how to change it.
+pub const SCHEMA: Schema = Schema::new("session state", &[]);
+
+/// Snapshot of a session, written by the running instance for `status`
+/// and kept after an early quit so the session can be resumed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionState {
+    pub updated: DateTime<Local>,
+    /// False once the session was quit and is waiting to be resumed.
+    pub active: bool,
+    pub phase: PhaseKind,
+    pub cycle: u32,
+    pub num_cycles: u32,
+    pub remaining_secs: u64,
+    pub paused: bool,
+    pub work_secs: u64,
+    pub break_secs: u64,
+    pub late: bool,
+}
+
+impl SessionState {
+    pub fn remaining(&self) -> Duration {
+        Duration::from_secs(self.remaining_secs)
+    }
+
+    /// An active state that has not been refreshed recently was left behind
+    /// by an instance that died without cleaning up.
+    pub fn is_stale(&self) -> bool {
+        self.active && (Local::now() - self.updated).num_seconds() > 10
+    }
+}
+
+#[derive(Debug)]
+pub struct StateFile {
+    path: PathBuf,
+}
+
+impl StateFile {
+    pub fn new(path: PathBuf) -> StateFile {
+        StateFile { path }
+    }
+
+    /// `state.json` in the [profile's](crate::profile) data folder, if the
+    /// platform has a data dir.
+    pub fn default_path() -> Option<PathBuf> {
+        profile::data_dir().map(|dir| dir.join("state.json"))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn save(&self, state: &SessionState) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write then rename so readers never see a half-written file.
+        let tmp = self.path.with_extension("json.tmp");
+        let mut value = serde_json::to_value(state)?;
+        value["format"] = SCHEMA.version().into();
+        fs::write(&tmp, serde_json::to_vec(&value)?)?;
+        fs::rename(tmp, &self.path)
+    }
+
+    pub fn load(&self) -> io::Result<Option<SessionState>> {
+        match fs::read(&self.path) {
+            Ok(bytes) => {
+                let mut value: Value = serde_json::from_slice(&bytes)?;
+                // States from before there were formats have none.
+                let version = value.get("format").and_then(Value::as_u64).unwrap_or(1);
+                let version = version.try_into().unwrap_or(u32::MAX);
+                SCHEMA.upgrade(&mut value, version)?;
+                Ok(Some(serde_json::from_value(value)?))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! `opomodoro stats --suggest`: how often phases of each length are seen
+//! through, and a length to try when one is often cut short.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::time::Duration;
+use crate::history::{Entry, Outcome, format_focus};
+
+/// Days with phases in the history before anything is suggested.
+pub const MIN_DAYS: usize = 3;
+
+/// Phases of one length it takes to judge that length.
+pub const MIN_PHASES: usize = 5;
+
+/// Share of phases cut short from which a length is too long.
+const TOO_LONG: f64 = 0.25;
+
+/// Share of work phases seen through from which work could go longer.
+const EASY: f64 = 0.95;
+
+/// How much longer to try work that is seen through that often.
+const LONGER: Duration = Duration::from_secs(5 * 60);
+
+/// How the phases of one planned length went.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LengthStats {
+    pub length: Duration,
+    pub phases: usize,
+    /// How far into the phase each one skipped or quit got.
+    pub cut_short: Vec<Duration>,
+}
+
+impl LengthStats {
+    /// The share of phases skipped or quit before their time was up.
+    pub fn cut_short_rate(&self) -> f64 {
+        self.cut_short.len() as f64 / self.phases.max(1) as f64
+    }
+
+    /// Whether there are enough phases to go by.
+    pub fn is_judged(&self) -> bool {
+        self.phases >= MIN_PHASES
+    }
+
+    /// The median time into a phase that was cut short.
+    fn given_up_after(&self) -> Option<Duration> {
+        let mut times = self.cut_short.clone();
+        times.sort();
+        times.get(times.len() / 2).copied()
+    }
+}
+
+/// Phases of each length in a history, shortest first. Snoozes and time
+/// away, which were never planned as phases, are left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    /// Days the history has phases on.
+    pub days: usize,
+    pub work: Vec<LengthStats>,
+    pub breaks: Vec<LengthStats>,
+}
+
+pub fn analyze(entries: &[Entry]) -> Analysis {
+    let mut days = BTreeSet::new();
+    let mut work: BTreeMap<u64, LengthStats> = BTreeMap::new();
+    let mut breaks: BTreeMap<u64, LengthStats> = BTreeMap::new();
+    for entry in entries.iter().filter(|entry| !entry.extension) {
+        days.insert(entry.started.date_naive());
+        let groups = if entry.is_work() {
+            &mut work
+        } else if entry.is_break() {
+            &mut breaks
+        } else {
+            // Warm-ups and cool-downs have lengths of their own.
+            continue;
+        };
+        let stats = groups.entry(entry.planned_secs).or_insert_with(|| LengthStats {
+            length: Duration::from_secs(entry.planned_secs),
+            phases: 0,
+            cut_short: Vec::new(),
+        });
+        stats.phases += 1;
+        if entry.outcome != Outcome::Completed {
+            stats.cut_short.push(Duration::from_secs(entry.elapsed_secs));
+        }
+    }
+    Analysis {
+        days: days.len(),
+        work: work.into_values().collect(),
+        breaks: breaks.into_values().collect(),
+    }
+}
+
+/// A length to try in place of the one used most.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub work: bool,
+    pub from: Duration,
+    pub to: Duration,
+    /// The share of phases of `from` cut short.
+    pub cut_short: f64,
+}
+
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (from, to) = (format_focus(self.from), format_focus(self.to));
+        let percent = (self.cut_short * 100.0).round();
+        let phases = if self.work { "work phases" } else { "breaks" };
+        if self.to > self.from {
+            let seen_through = 100.0 - percent;
+            write!(f, "You see {seen_through}% of your {from} {phases} through; you could try {to}.")
+        } else {
+            write!(f, "Your {from} {phases} are skipped or quit {percent}% of the time; try {to}.")
+        }
+    }
+}
+
+impl Analysis {
+    /// Whether there is enough history to suggest anything from.
+    pub fn is_enough(&self) -> bool {
+        self.days >= MIN_DAYS
+    }
+
+    /// New lengths to try for work and for breaks, if the ones used most
+    /// are often cut short, or work is nearly always seen through.
+    pub fn suggestions(&self) -> Vec<Suggestion> {
+        if !self.is_enough() {
+            return Vec::new();
+        }
+        let work = most_used(&self.work).and_then(|main| {
+            let rate = main.cut_short_rate();
+            let to = if rate >= TOO_LONG {
+                // About when the phases get given up on, or a shorter
+                // length that holds up if that is longer still.
+                let given_up = round_down(main.given_up_after()?, 5 * 60).max(Duration::from_secs(5 * 60));
+                let held = self.work.iter().rev().find(|stats| {
+                    stats.length < main.length && stats.is_judged() && stats.cut_short_rate() < TOO_LONG
+                });
+                let to = held.map_or(given_up, |stats| stats.length.max(given_up));
+                if to >= main.length {
+                    return None;
+                }
+                to
+            } else if 1.0 - rate >= EASY {
+                let tried = self.work.iter().any(|stats| {
+                    stats.length > main.length && stats.is_judged() && stats.cut_short_rate() >= TOO_LONG
+                });
+                if tried {
+                    return None;
+                }
+                main.length + LONGER
+            } else {
+                return None;
+            };
+            Some(Suggestion { work: true, from: main.length, to, cut_short: rate })
+        });
+        let breaks = most_used(&self.breaks).and_then(|main| {
+            let rate = main.cut_short_rate();
+            if rate < TOO_LONG {
+                return None;
+            }
+            let to = round_down(main.given_up_after()?, 60).max(Duration::from_secs(60));
+            (to < main.length).then_some(Suggestion { work: false, from: main.length, to, cut_short: rate })
+        });
+        work.into_iter().chain(breaks).collect()
+    }
+}
+
+/// The length with the most phases, if there are enough of them.
+fn most_used(lengths: &[LengthStats]) -> Option<&LengthStats> {
+    lengths.iter().filter(|stats| stats.is_judged()).max_by_key(|stats| stats.phases)
+}
+
+fn round_down(time: Duration, secs: u64) -> Duration {
+    Duration::from_secs(time.as_secs() / secs * secs)
+}
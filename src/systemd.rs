@@ -0,0 +1,82 @@
+//! The `sd_notify` protocol, for a daemon run by a systemd unit with
+//! `Type=notify`: it says when it is ready and when it is stopping, and
+//! pings the watchdog so systemd restarts it if the loop hangs. Outside
+//! systemd there is no `NOTIFY_SOCKET`, and nothing is sent.
+
+use std::env;
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// A connection to the service manager that started the daemon.
+#[derive(Debug)]
+pub struct Notifier {
+    socket: UnixDatagram,
+    path: PathBuf,
+    /// How often the watchdog wants a ping, if it is on for this process.
+    watchdog: Option<Duration>,
+    last_ping: Option<Instant>,
+}
+
+impl Notifier {
+    /// The service manager named by `NOTIFY_SOCKET`, if there is one.
+    pub fn from_env() -> Option<Notifier> {
+        let path = PathBuf::from(env::var_os("NOTIFY_SOCKET")?);
+        let socket = UnixDatagram::unbound().ok()?;
+        Some(Notifier { socket, path, watchdog: watchdog_interval(), last_ping: None })
+    }
+
+    /// The daemon is up and taking requests.
+    pub fn ready(&self, status: &str) {
+        self.send(&format!("READY=1\nSTATUS={status}"));
+    }
+
+    /// The daemon is shutting down, and saving what it needs to first.
+    pub fn stopping(&self) {
+        self.send("STOPPING=1");
+    }
+
+    /// Ping the watchdog if half its interval has gone by since the last
+    /// ping, so one late turn of the loop is not taken for a hang.
+    pub fn keep_alive(&mut self, now: Instant) {
+        let Some(interval) = self.watchdog else {
+            return;
+        };
+        if self.last_ping.is_none_or(|at| now.duration_since(at) >= interval / 2) {
+            self.send("WATCHDOG=1");
+            self.last_ping = Some(now);
+        }
+    }
+
+    fn send(&self, message: &str) {
+        if let Err(e) = self.send_to(message.as_bytes()) {
+            debug!("could not notify systemd at {}: {e}", self.path.display());
+        }
+    }
+
+    /// A name starting with `@` is in Linux's abstract namespace, with no
+    /// file behind it.
+    fn send_to(&self, message: &[u8]) -> io::Result<usize> {
+        #[cfg(target_os = "linux")]
+        if let Some(name) = self.path.to_str().and_then(|path| path.strip_prefix('@')) {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            return self.socket.send_to_addr(message, &addr);
+        }
+        self.socket.send_to(message, &self.path)
+    }
+}
+
+/// `WATCHDOG_USEC`, when `WATCHDOG_PID` is this process or not given.
+fn watchdog_interval() -> Option<Duration> {
+    if let Some(pid) = env::var_os("WATCHDOG_PID")
+        && pid.to_str().and_then(|pid| pid.parse::<u32>().ok()) != Some(process::id())
+    {
+        return None;
+    }
+    let usec = env::var("WATCHDOG_USEC").ok()?.parse().ok().filter(|&usec| usec > 0)?;
+    Some(Duration::from_micros(usec))
+}
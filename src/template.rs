@@ -0,0 +1,138 @@
+//! Message templates like `{phase} over, next: {next_phase}
+//! ({next_duration})`, filled in from the session's state as it runs.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Names a template may use between braces.
+pub const VARIABLES: [&str; 9] = [
+    "phase",
+    "duration",
+    "remaining",
+    "cycle",
+    "cycles",
+    "next_phase",
+    "next_duration",
+    "task",
+    "block",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Variable(&'static str),
+}
+
+/// A message with `{variable}`s to fill in; `{{` and `}}` stand for
+/// literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Template, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let Some(end) = rest.find('}') else {
+                        return Err(format!("unclosed {{ in {text:?}"));
+                    };
+                    let name = rest[..end].trim();
+                    let Some(&name) = VARIABLES.iter().find(|known| **known == name) else {
+                        return Err(format!(
+                            "unknown variable {{{name}}}; use one of {}",
+                            VARIABLES.join(", "),
+                        ));
+                    };
+                    parts.push(Part::Text(std::mem::take(&mut literal)));
+                    parts.push(Part::Variable(name));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err(format!("stray }} in {text:?}; write }}}} for a brace")),
+                ch => literal.push(ch),
+            }
+        }
+        parts.push(Part::Text(literal));
+        parts.retain(|part| *part != Part::Text(String::new()));
+        Ok(Template { parts })
+    }
+}
+
+impl Template {
+    /// Shorthand for templates known to be good, like the defaults.
+    fn fixed(text: &str) -> Template {
+        text.parse().expect("built-in template")
+    }
+
+    /// The message with `vars` filled in; variables without a value, like
+    /// `next_phase` in the last phase, come out empty.
+    pub fn render(&self, vars: &Vars) -> String {
+        let mut message = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => message.push_str(text),
+                Part::Variable(name) => {
+                    message.push_str(vars.0.get(name).map_or("", String::as_str));
+                }
+            }
+        }
+        message
+    }
+}
+
+/// Values for a template's variables.
+#[derive(Debug, Default)]
+pub struct Vars(BTreeMap<&'static str, String>);
+
+impl Vars {
+    pub fn set(&mut self, name: &'static str, value: impl ToString) -> &mut Vars {
+        debug_assert!(VARIABLES.contains(&name), "unknown template variable {name}");
+        self.0.insert(name, value.to_string());
+        self
+    }
+}
+
+/// The messages a session sends, as banners across the timer or spoken.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Messages {
+    /// Spoken as a phase starts, with `--speak`.
+    pub phase_start: Template,
+    /// Flashed as a phase runs its course; nothing unless set.
+    pub phase_end: Option<Template>,
+    /// Flashed shortly before a work phase ends, with a work warning set.
+    pub work_warning: Template,
+    /// Flashed shortly before a break ends, with a break warning set.
+    pub break_warning: Template,
+    /// Flashed as the next block of a chain starts.
+    pub next_block: Template,
+    /// Spoken once the whole session is done, with `--speak`.
+    pub session_end: Template,
+}
+
+impl Default for Messages {
+    fn default() -> Messages {
+        Messages {
+            phase_start: Template::fixed("{phase} time, {remaining}"),
+            phase_end: None,
+            work_warning: Template::fixed("{remaining} left, time to wrap up"),
+            break_warning: Template::fixed("{remaining} left of the break"),
+            next_block: Template::fixed("Up next: {block}"),
+            session_end: Template::fixed("Session complete"),
+        }
+    }
+}
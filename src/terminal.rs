@@ -0,0 +1,52 @@
+use std::io::{self, Write, stdout};
+use std::panic;
+use std::sync::Once;
+use crossterm::cursor::Show;
+use crossterm::execute;
+use crossterm::terminal::{
+    EnterAlternateScreen,
+    LeaveAlternateScreen,
+    disable_raw_mode,
+    enable_raw_mode,
+};
+use ratatui::DefaultTerminal;
+use ratatui::backend::CrosstermBackend;
+use ratatui::Terminal;
+
+static PANIC_HOOK: Once = Once::new();
+
+/// Switch to raw mode and the alternate screen, with a panic hook that
+/// puts the terminal back first.
+pub fn init() -> io::Result<DefaultTerminal> {
+    init_with(stdout())
+}
+
+/// Like [`init`], but drawing through `writer`, which is to pass the
+/// output on to stdout, e.g. a [`Recorder`](crate::record::Recorder).
+pub fn init_with<W: Write>(writer: W) -> io::Result<Terminal<CrosstermBackend<W>>> {
+    install_panic_hook();
+    enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(writer))
+}
+
+/// Undo [`init`]: leave the alternate screen, disable raw mode and show the
+/// cursor. Best effort and safe to call more than once, since it also runs
+/// from the panic hook and signal handlers.
+pub fn restore() {
+    // Raw mode first, it has more side effects than the alternate screen.
+    let _ = disable_raw_mode();
+    let _ = execute!(stdout(), LeaveAlternateScreen, Show);
+}
+
+/// Restore the terminal before the default hook prints the panic, so the
+/// message lands on the normal screen and the shell stays usable.
+fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            hook(info);
+        }));
+    });
+}
@@ -0,0 +1,144 @@
+//! Colors the TUI draws with, shared with the stream overlay so both look
+//! the same.
+
+use clap::ValueEnum;
+#[cfg(feature = "terminal")]
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "terminal")]
+use crate::engine::PhaseKind;
+
+#[cfg(feature = "terminal")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// The part of the phase that is done.
+    pub progress: Color,
+    /// The part still to go.
+    pub track: Color,
+    /// Things that need attention, like a task over its budget.
+    pub warning: Color,
+    /// The warm-up and cool-down phases, in place of `progress`.
+    pub warm_up: Color,
+    pub cool_down: Color,
+}
+
+#[cfg(feature = "terminal")]
+impl Theme {
+    /// The theme to draw a phase of `kind` in.
+    pub fn for_phase(self, kind: &PhaseKind) -> Theme {
+        match kind {
+            PhaseKind::Work | PhaseKind::ShortBreak | PhaseKind::LongBreak | PhaseKind::Custom(_) => self,
+            PhaseKind::WarmUp => Theme { progress: self.warm_up, ..self },
+            PhaseKind::CoolDown => Theme { progress: self.cool_down, ..self },
+        }
+    }
+}
+
+#[cfg(feature = "terminal")]
+impl Default for Theme {
+    fn default() -> Theme {
+        ThemeChoice::default().theme()
+    }
+}
+
+/// The themes to pick from, by name.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeChoice {
+    /// Red on gray, like the tomato.
+    #[default]
+    Tomato,
+    Ocean,
+    Forest,
+    /// Sky blue and orange, apart for red-green color blindness, be it
+    /// deuteranopia or protanopia.
+    Deutan,
+    /// Vermilion and teal, apart for blue-yellow color blindness.
+    Tritan,
+    /// No color, for terminals that have little.
+    Mono,
+}
+
+#[cfg(feature = "terminal")]
+impl ThemeChoice {
+    pub fn theme(self) -> Theme {
+        let (progress, warning, warm_up, cool_down) = match self {
+            ThemeChoice::Tomato => (Color::LightRed, Color::Yellow, Color::LightMagenta, Color::LightCyan),
+            ThemeChoice::Ocean => (Color::LightBlue, Color::LightYellow, Color::LightCyan, Color::LightMagenta),
+            ThemeChoice::Forest => (Color::LightGreen, Color::Yellow, Color::LightCyan, Color::LightMagenta),
+            // From the Okabe-Ito palette, as near as the 256 xterm colors
+            // come, which more terminals have than true color.
+            ThemeChoice::Deutan => {
+                (Color::Indexed(74), Color::Indexed(214), Color::Indexed(227), Color::Indexed(175))
+            }
+            ThemeChoice::Tritan => {
+                (Color::Indexed(166), Color::Indexed(37), Color::Indexed(175), Color::Indexed(250))
+            }
+            ThemeChoice::Mono => (Color::White, Color::Gray, Color::Gray, Color::Gray),
+        };
+        Theme { progress, track: Color::DarkGray, warning, warm_up, cool_down }
+    }
+}
+
+/// Whether `NO_COLOR` is set, and not empty, asking for no color at all;
+/// see <https://no-color.org>.
+pub fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// `color` as CSS, taking the xterm palette for the named colors.
+#[cfg(feature = "terminal")]
+pub fn css(color: Color) -> String {
+    let (r, g, b) = match color {
+        Color::Reset => return "inherit".to_string(),
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(n) => indexed(n),
+        named => indexed(ansi_index(named)),
+    };
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+#[cfg(feature = "terminal")]
+fn ansi_index(color: Color) -> u8 {
+    match color {
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+        _ => 0,
+    }
+}
+
+/// RGB of an entry in the 256-color xterm palette.
+#[cfg(feature = "terminal")]
+fn indexed(n: u8) -> (u8, u8, u8) {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0x00, 0x00, 0x00), (0xcd, 0x00, 0x00), (0x00, 0xcd, 0x00), (0xcd, 0xcd, 0x00),
+        (0x00, 0x00, 0xee), (0xcd, 0x00, 0xcd), (0x00, 0xcd, 0xcd), (0xe5, 0xe5, 0xe5),
+        (0x7f, 0x7f, 0x7f), (0xff, 0x00, 0x00), (0x00, 0xff, 0x00), (0xff, 0xff, 0x00),
+        (0x5c, 0x5c, 0xff), (0xff, 0x00, 0xff), (0x00, 0xff, 0xff), (0xff, 0xff, 0xff),
+    ];
+    let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+    match n {
+        0..16 => BASIC[n as usize],
+        16..232 => {
+            let n = n - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        _ => {
+            let gray = 8 + (n - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
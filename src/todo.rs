@@ -0,0 +1,179 @@
+//! Task lists kept in plain text files, in todo.txt or markdown checklist
+//! format. Estimates and pomodoro counts live in `est:N` and `pom:N` tags,
+//! which todo.txt tools already leave alone:
+//!
+//! ```text
+//! (A) Write docs +opomodoro est:3 pom:1
+//! - [ ] Write docs est:3 pom:1
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use chrono::{Local, NaiveDate};
+use crate::planner::{Plan, Task};
+
+/// How the file lays out its tasks, going by the file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    TodoTxt,
+    Markdown,
+}
+
+impl Format {
+    pub fn of(path: &Path) -> Format {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("md" | "markdown") => Format::Markdown,
+            _ => Format::TodoTxt,
+        }
+    }
+}
+
+/// A task file as read, so it can be written back with every other line
+/// left as it was.
+#[derive(Debug)]
+pub struct TodoFile {
+    path: PathBuf,
+    format: Format,
+    lines: Vec<String>,
+    /// Line of each open task, in plan order.
+    tasks: Vec<usize>,
+}
+
+impl TodoFile {
+    pub fn load(path: &Path) -> io::Result<TodoFile> {
+        let text = fs::read_to_string(path)?;
+        let format = Format::of(path);
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        let tasks = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| parse_line(line, format).is_some())
+            .map(|(i, _)| i)
+            .collect();
+        Ok(TodoFile { path: path.to_path_buf(), format, lines, tasks })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The open tasks, in file order.
+    pub fn plan(&self) -> Plan {
+        let mut plan = Plan::default();
+        for &i in &self.tasks {
+            if let Some(task) = parse_line(&self.lines[i], self.format) {
+                plan.push(task);
+            }
+        }
+        plan
+    }
+
+    /// Write `plan` back: pomodoro counts on every task, finished ones
+    /// marked done, and tasks added during the session appended.
+    pub fn save(&mut self, plan: &Plan) -> io::Result<()> {
+        for (n, task) in plan.tasks().iter().enumerate() {
+            match self.tasks.get(n) {
+                Some(&i) => self.lines[i] = update_line(&self.lines[i], task, self.format),
+                None => {
+                    let line = match self.format {
+                        Format::TodoTxt => String::new(),
+                        Format::Markdown => "- [ ] ".to_string(),
+                    };
+                    self.tasks.push(self.lines.len());
+                    self.lines.push(update_line(&(line + &task.name), task, self.format));
+                }
+            }
+        }
+        let mut text = self.lines.join("\n");
+        text.push('\n');
+        fs::write(&self.path, text)
+    }
+}
+
+/// The task on an open line, or `None` for anything else.
+fn parse_line(line: &str, format: Format) -> Option<Task> {
+    let text = match format {
+        Format::TodoTxt => {
+            if line.trim().is_empty() || line.starts_with("x ") {
+                return None;
+            }
+            strip_todo_prefix(line)
+        }
+        Format::Markdown => checklist_item(line).filter(|(done, _)| !done)?.1,
+    };
+    let mut estimate = 1;
+    let mut actual = 0;
+    let mut words = Vec::new();
+    for word in text.split_whitespace() {
+        let tag = |name| word.strip_prefix(name).and_then(|n| n.parse::<u32>().ok());
+        if let Some(n) = tag("est:") {
+            estimate = n;
+        } else if let Some(n) = tag("pom:") {
+            actual = n;
+        } else {
+            words.push(word);
+        }
+    }
+    if words.is_empty() {
+        return None;
+    }
+    let mut task = Task::new(words.join(" "), estimate);
+    task.actual = actual;
+    Some(task)
+}
+
+/// Drop the `(A) ` priority and creation date in front of a todo.txt task.
+fn strip_todo_prefix(line: &str) -> &str {
+    let rest = strip_priority(line.trim_start());
+    match rest.split_once(' ') {
+        Some((date, text)) if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() => text,
+        _ => rest,
+    }
+}
+
+/// `(done, text)` of a `- [ ] text` or `* [x] text` line.
+fn checklist_item(line: &str) -> Option<(bool, &str)> {
+    let rest = line.trim_start().strip_prefix(['-', '*', '+'])?.strip_prefix(' ')?;
+    let done = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    Some((done, rest[3..].trim_start()))
+}
+
+/// `line` with its `pom:` tag set to the task's count, marked done once the
+/// estimate is used up.
+fn update_line(line: &str, task: &Task, format: Format) -> String {
+    let mut line: String = line
+        .split(' ')
+        .filter(|word| !word.starts_with("pom:"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if task.estimate != 1 && !line.split_whitespace().any(|word| word.starts_with("est:")) {
+        line.push_str(&format!(" est:{}", task.estimate));
+    }
+    if task.actual > 0 {
+        line.push_str(&format!(" pom:{}", task.actual));
+    }
+    if !task.is_done() {
+        return line;
+    }
+    match format {
+        Format::TodoTxt => {
+            let today = Local::now().format("%Y-%m-%d");
+            format!("x {today} {}", strip_priority(&line))
+        }
+        Format::Markdown => line.replacen("[ ]", "[x]", 1),
+    }
+}
+
+/// Drop a todo.txt `(A) ` priority, which completed tasks do not keep.
+fn strip_priority(line: &str) -> &str {
+    let mut chars = line.chars();
+    match (chars.next(), chars.next(), chars.next(), chars.next()) {
+        (Some('('), Some(p), Some(')'), Some(' ')) if p.is_ascii_uppercase() => &line[4..],
+        _ => line,
+    }
+}
@@ -1,171 +1,868 @@
+use std::f64::consts::PI;
+use chrono::{DateTime, Local};
+use crate::clock::Instant;
 use ratatui::{
     Frame,
-    layout::{Alignment, Constraint, Layout},
-    style::Stylize,
+    layout::{Alignment, Constraint, Flex, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    symbols::Marker,
     text::{Line, Span},
-    widgets::{Block, Borders, Gauge, Paragraph},
+    widgets::{Block, Borders, Clear, Gauge, LineGauge, Paragraph, canvas::{Canvas, Points}},
 };
-use crate::{App, TimerState};
+use crate::{App, AppScreen, Countdown, HourFormat, KEY_HELP, KeyGroup, SKIP_CHOICES, ViewMode, format_time};
+use crate::history::{Focus, InterruptionSource, Targets, format_focus};
+use crate::planner::{Plan, Task};
+use crate::profile;
+use crate::engine::{Phase, PhaseKind, Timer};
+use crate::font::{self, Font};
+use crate::mascot::{self, Mood};
+use crate::options::{FIELDS, Field};
+use crate::theme::Theme;
 
-pub fn render(frame: &mut Frame, app: &App) {
+/// Columns taken by the task sidebar, borders included.
+const SIDEBAR_WIDTH: u16 = 30;
+
+/// Columns the timer keeps before the mascot makes way for it.
+const MASCOT_MIN_TIMER: u16 = 40;
+
+/// Rows the mascot panel needs: the art, a caption and the borders.
+const MASCOT_HEIGHT: u16 = 10;
+
+pub fn render<T: Timer>(frame: &mut Frame, app: &App<T>) {
+    render_screen(frame, app);
+    if app.no_color {
+        for cell in &mut frame.buffer_mut().content {
+            // The track stays apart from what is done on it.
+            if cell.fg == app.theme.track {
+                cell.modifier.insert(Modifier::DIM);
+            }
+            cell.fg = Color::Reset;
+            cell.bg = Color::Reset;
+        }
+    }
+    // The visual bell: everything inverted, on and off for a few frames.
+    if app.flashing() == Some(true) {
+        for cell in &mut frame.buffer_mut().content {
+            cell.modifier.toggle(Modifier::REVERSED);
+        }
+    }
+}
+
+fn render_screen<T: Timer>(frame: &mut Frame, app: &App<T>) {
     let area = frame.area();
 
-    // 4 vertical bands: header / timer / gauge / footer
-    let chunks = Layout::vertical([
+    // So does the end of the day, until the user leaves.
+    if let AppScreen::DayDone(since) = app.screen {
+        render_day_done(frame, area, app, since);
+        return;
+    }
+
+    // A hard break takes the whole screen until it is over.
+    if app.in_hard_break() {
+        render_hard_break(frame, area, app);
+        render_overlays(frame, area, app);
+        return;
+    }
+
+    // 5 vertical bands: header / timer / gauge / targets / footer
+    let targets = app.focus_targets();
+    let mut chunks = Layout::vertical([
         Constraint::Length(3),  // header
         Constraint::Min(7),     // big timer panel
         Constraint::Length(3),  // gauge
+        Constraint::Length(u16::from(targets.is_some())), // focus targets
         Constraint::Length(2),  // footer
     ])
-    .split(area);
+    .split(area)
+    .to_vec();
+
+    // The task sidebar takes the right of the timer panel and gauge.
+    if app.sidebar && area.width >= SIDEBAR_WIDTH * 2 {
+        let body = chunks[1].union(chunks[2]);
+        let [_, side] = Layout::horizontal([Constraint::Min(0), Constraint::Length(SIDEBAR_WIDTH)])
+            .areas(body);
+        chunks[1].width -= side.width;
+        chunks[2].width -= side.width;
+        render_sidebar(frame, side, app.plan.as_ref(), app.theme);
+    }
+
+    // The mascot takes the left of the timer panel, if that leaves room.
+    let mascot_width = mascot::WIDTH + 4;
+    let roomy = chunks[1].width >= mascot_width + MASCOT_MIN_TIMER && chunks[1].height >= MASCOT_HEIGHT;
+    if app.mascot && roomy && app.view != ViewMode::Minimal {
+        let [side, timer] = Layout::horizontal([Constraint::Length(mascot_width), Constraint::Min(0)])
+            .areas(chunks[1]);
+        chunks[1] = timer;
+        let engine = &app.engine;
+        let mood = Mood::of(engine.phase(), app.remaining, engine.is_paused());
+        render_mascot(frame, side, mood, app.theme);
+    }
 
     // ---------- Header ----------
-    let paused = matches!(app.timer_state, TimerState::Paused { .. });
+    let engine = &app.engine;
+    let phase = engine.phase();
+    let paused = engine.is_paused();
 
     let header_line = Line::from(vec![
         Span::from(" Opomodoro ").bold(),
-        Span::from(format!(" Cycle {}/{} ", app.current_cycle, app.num_cycles)).bold(),
-        Span::from(" ").into(),
-        Span::from(app.phase.kind).bold(),
+        Span::from(profile::current().map_or(String::new(), |name| format!("({name}) "))),
+        Span::from(format!(" Cycle {}/{} ", engine.cycle(), engine.num_cycles())).bold(),
+        Span::from(app.spinner().map_or(" ".to_string(), |spinner| format!(" {spinner} "))),
+        Span::from(format!("{} {}", phase_icon(&phase.kind), phase.kind.name())).bold(),
         if paused { Span::from(" (Paused)").bold() } else { Span::from("") },
-        if app.late { Span::from("  w/ last break").bold() } else { Span::from("") },
+        if app.voided { Span::from(" (Voided)").bold().fg(app.theme.warning) } else { Span::from("") },
+        match app.plan.as_ref().and_then(Plan::current) {
+            Some(task) if task.is_over_budget() => {
+                Span::from(format!("  {} {}/{} over budget", task.name, task.actual, task.estimate))
+                    .fg(app.theme.warning)
+            }
+            Some(task) => Span::from(format!("  {} {}/{}", task.name, task.actual, task.estimate)),
+            None => Span::from(app.task.as_ref().map_or(String::new(), |task| format!("  {task}"))),
+        },
+        if engine.late() { Span::from("  w/ last break").bold() } else { Span::from("") },
+        match engine.break_debt().as_secs() {
+            0 => Span::from(""),
+            secs => Span::from(format!("  break debt {}m", secs.div_ceil(60))).fg(app.theme.warning),
+        },
+        Span::from(format!("  {}", app.clock.format(Local::now()))),
+        match engine.phase_ends_at() {
+            Some(end) => Span::from(format!(" · ends at {}", app.clock.format(end))),
+            None => Span::from(""),
+        },
+        if app.alerts.is_silent() {
+            Span::from("  🔕")
+        } else {
+            Span::from(format!("  🔔 {}%", app.alerts.volume()))
+        },
     ]);
 
+    // In a chain, the title says which block this is.
+    let header_title = match app.current_block() {
+        Some((n, block)) => format!("Status · {} ({n}/{})", block.label(), app.chain.len()),
+        None => "Status".to_string(),
+    };
     let header = Paragraph::new(header_line)
-        .block(Block::default().borders(Borders::ALL).title("Status"))
+        .block(Block::default().borders(Borders::ALL).title(header_title))
         .alignment(Alignment::Center);
 
     frame.render_widget(header, chunks[0]);
 
     // ---------- Timer panel ----------
-    let secs = app.remaining.as_secs();
-    let mm = secs / 60;
-    let ss = secs % 60;
-    let time_str = format!("{:02}:{:02}", mm, ss);
-
-    let timer_block = Block::default().borders(Borders::ALL).title("Timer");
-    let inner = timer_block.inner(chunks[1]);
+    // Before work starts, the panel counts the seconds to get ready instead.
+    let get_ready = engine.get_ready_left().map(|left| left.as_secs_f64().ceil() as u64);
+    let time_str = get_ready.map_or_else(|| countdown_text(app), |secs| secs.to_string());
 
-    // Only use big digits if we have enough vertical space inside the block.
-    let timer = if inner.height >= BIG_HEIGHT {
-        let lines = big_time_lines(mm as u8, ss as u8, inner.height);
-        Paragraph::new(lines)
-            .block(timer_block)
-            .alignment(Alignment::Center)
-    } else {
-        Paragraph::new(Line::from(time_str).bold())
-            .block(timer_block)
-            .alignment(Alignment::Center)
+    let kind = &phase.kind;
+    let theme = app.theme.for_phase(kind);
+    // Warm-ups and cool-downs stand apart from the work and breaks.
+    let own_colors = matches!(kind, PhaseKind::WarmUp | PhaseKind::CoolDown);
+    let timer_block = match get_ready {
+        Some(_) => Block::default().borders(Borders::ALL).title("Get ready").fg(app.theme.warning),
+        None if own_colors => Block::default().borders(Borders::ALL).title(kind.name()).fg(theme.progress),
+        None => Block::default().borders(Borders::ALL).title("Timer"),
     };
+    let inner = timer_block.inner(chunks[1]);
 
-    frame.render_widget(timer, chunks[1]);
-
-    // ---------- Gauge ----------
-    let total = app.phase.duration;
+    // Progress through the phase, shared by the ring and the gauge.
+    let total = engine.phase().duration;
     let total_s = total.as_secs_f64();
     let rem_s = app.remaining.as_secs_f64();
     let elapsed_s = (total_s - rem_s).max(0.0);
     let ratio = if total_s > 0.0 { elapsed_s / total_s } else { 0.0 };
 
-    let gauge_label = format!(
+    // The minimal view drops the timer panel and centers the gauge instead.
+    let mut gauge_area = chunks[2];
+    if app.view == ViewMode::Minimal {
+        let body = chunks[1].union(chunks[2]);
+        [gauge_area] = Layout::vertical([Constraint::Length(3)])
+            .flex(Flex::Center)
+            .areas(body);
+    } else if app.view == ViewMode::Ring {
+        render_ring(frame, chunks[1], timer_block, ratio, time_str.clone(), theme);
+    } else {
+        // Only use big digits if some font fits inside the block.
+        let timer = if let Some(font) = font::pick(app.font, &time_str, inner.width, inner.height) {
+            let lines = big_time_lines(font, &time_str, inner.height);
+            Paragraph::new(lines)
+                .block(timer_block)
+                .alignment(Alignment::Center)
+        } else {
+            Paragraph::new(Line::from(time_str.clone()).bold())
+                .block(timer_block)
+                .alignment(Alignment::Center)
+        };
+
+        frame.render_widget(timer, chunks[1]);
+    }
+
+    // ---------- Gauge ----------
+    let mut gauge_label = format!(
         "{} / {}",
-        format_mmss(elapsed_s as u64),
-        format_mmss(total.as_secs())
+        format_time(elapsed_s as u64),
+        format_time(total.as_secs())
     );
+    if app.view == ViewMode::Minimal {
+        gauge_label = match get_ready {
+            Some(secs) => format!("Get ready… {secs}  ({gauge_label})"),
+            None => format!("{time_str} left  ({gauge_label})"),
+        };
+    }
 
-    let gauge = Gauge::default()
-        .block(Block::default().borders(Borders::ALL).title("Progress"))
+    let mut gauge_block = Block::default().borders(Borders::ALL).title("Progress");
+    if let Some(end) = engine.session_ends_at() {
+        let title = match app.chain_ends_at() {
+            Some(last) => format!(" block ends ~{} · all done ~{} ", app.clock.format(end), app.clock.format(last)),
+            None => format!(" session ends ~{} ", app.clock.format(end)),
+        };
+        gauge_block = gauge_block.title_top(Line::from(title).right_aligned());
+    }
+    let mut gauge = Gauge::default()
+        .block(gauge_block)
         .ratio(ratio)
         .label(gauge_label);
+    if own_colors {
+        gauge = gauge.gauge_style(Style::new().fg(theme.progress));
+    }
+
+    frame.render_widget(gauge, gauge_area);
+
+    // The phase just changed: a tomato rises over the timer and fades.
+    if let Some(progress) = app.transition().filter(|_| app.view != ViewMode::Minimal) {
+        render_transition(frame, inner, progress, engine.phase(), theme);
+    }
+
+    // Something needs noticing: flash a banner across the timer.
+    if let Some(message) = app.banner() {
+        let banner_area = Rect { height: 1, ..inner };
+        let mut banner = Line::from(format!(" {message} ")).bold().fg(app.theme.warning);
+        // Blinking would need more wakeups than low-power mode allows.
+        if !app.low_power && Local::now().timestamp_subsec_millis() < 500 {
+            banner = banner.reversed();
+        }
+        frame.render_widget(Clear, banner_area);
+        frame.render_widget(Paragraph::new(banner).alignment(Alignment::Center), banner_area);
+    }
 
-    frame.render_widget(gauge, chunks[2]);
+    // ---------- Targets ----------
+    if let Some((targets, focus)) = targets {
+        render_targets(frame, chunks[3], targets, focus, app.theme);
+    }
 
     // ---------- Footer ----------
-    let footer_line = Line::from(vec![
-        Span::from(" p ").bold(),
-        Span::from("pause/resume   "),
-        Span::from(" s ").bold(),
-        Span::from("skip   "),
-        Span::from(" q ").bold(),
-        Span::from("quit"),
+    let mut footer_spans = Vec::new();
+    if let Some(left) = engine.grace_left().filter(|_| !app.spectator) {
+        footer_spans.push(Span::from(" z ").bold());
+        footer_spans.push(Span::from(format!("snooze ({}s)   ", left.as_secs())));
+    }
+    if let Some(left) = engine.undo_left().filter(|_| !app.spectator) {
+        footer_spans.push(Span::from(" u ").bold());
+        footer_spans.push(Span::from(format!("undo skip ({}s)   ", left.as_secs())));
+    }
+    if app.spectator {
+        footer_spans.push(Span::from("Spectating   ").dim());
+    } else {
+        footer_spans.extend([
+            Span::from(" p ").bold(),
+            Span::from("pause/resume   "),
+            Span::from(" s ").bold(),
+            Span::from("skip   "),
+        ]);
+    }
+    footer_spans.extend([
+        Span::from(" v ").bold(),
+        Span::from("view   "),
+        Span::from(" m ").bold(),
+        Span::from("mute   "),
     ]);
+    if let Some(volume) = app.ambient_volume() {
+        footer_spans.extend([Span::from(" +/- ").bold(), Span::from(format!("sound {volume}%   "))]);
+    }
+    if !app.spectator {
+        footer_spans.extend([Span::from(" i ").bold(), Span::from("interrupt   ")]);
+    }
+    footer_spans.extend([Span::from(" q ").bold(), Span::from("quit")]);
+    let footer_line = Line::from(footer_spans);
 
     let footer = Paragraph::new(footer_line)
         .alignment(Alignment::Center);
 
-    frame.render_widget(footer, chunks[3]);
-}
-
-// small helper: render seconds as MM:SS
-fn format_mmss(total_secs: u64) -> String {
-    let mm = total_secs / 60;
-    let ss = total_secs % 60;
-    format!("{:02}:{:02}", mm, ss)
-}
-
-const BIG_HEIGHT: u16 = 5;
-
-const DIGITS: [[&str; 5]; 10] = [
-    // 0
-    ["█████", "█   █", "█   █", "█   █", "█████"],
-    // 1
-    ["  █  ", " ██  ", "  █  ", "  █  ", "█████"],
-    // 2
-    ["█████", "    █", "█████", "█    ", "█████"],
-    // 3
-    ["█████", "    █", "█████", "    █", "█████"],
-    // 4
-    ["█   █", "█   █", "█████", "    █", "    █"],
-    // 5
-    ["█████", "█    ", "█████", "    █", "█████"],
-    // 6
-    ["█████", "█    ", "█████", "█   █", "█████"],
-    // 7
-    ["█████", "    █", "   █ ", "  █  ", "  █  "],
-    // 8
-    ["█████", "█   █", "█████", "█   █", "█████"],
-    // 9
-    ["█████", "█   █", "█████", "    █", "█████"],
-];
+    frame.render_widget(footer, chunks[4]);
+
+    render_overlays(frame, area, app);
+}
+
+fn render_overlays<T: Timer>(frame: &mut Frame, area: Rect, app: &App<T>) {
+    match &app.screen {
+        AppScreen::Timer => {
+            if let Some(held) = &app.held {
+                render_held(frame, area, &held.ended, &app.engine.phase());
+            }
+        }
+        AppScreen::Interruption { source, note } => {
+            render_interruption(frame, area, *source, note.value());
+        }
+        AppScreen::Note(note) => render_note(frame, area, note.value()),
+        AppScreen::Away => {
+            if let Some(away) = &app.away {
+                render_away(frame, area, away.since, app.clock);
+            }
+        }
+        AppScreen::Plan(input) => {
+            let tasks = app.plan.as_ref().map_or(&[][..], Plan::tasks);
+            render_plan(frame, area, tasks, input.value());
+        }
+        AppScreen::SkipMenu { selected } => render_skip_menu(frame, area, *selected),
+        AppScreen::GotoCycle(input) => {
+            render_goto(frame, area, input.value(), app.engine.num_cycles());
+        }
+        AppScreen::Waiting(at) => render_waiting(frame, area, *at, app.clock),
+        AppScreen::Help => render_help(frame, area),
+        // Drawn in place of the timer, not over it.
+        AppScreen::DayDone(_) => {}
+        AppScreen::Settings { selected, editing, message } => {
+            let editing = editing.as_ref().map(|input| input.value());
+            render_settings(frame, area, app, *selected, editing, message.as_deref());
+        }
+    }
+}
+
+/// The break, filling `area`: the time left, a nudge to step away, and
+/// when skipping it unlocks.
+fn render_hard_break<T: Timer>(frame: &mut Frame, area: Rect, app: &App<T>) {
+    let time_str = countdown_text(app);
+    let phase = app.engine.phase();
+    let block = Block::default().borders(Borders::ALL).title(phase.kind.name()).fg(app.theme.progress);
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let [timer, message, keys] = Layout::vertical([Constraint::Min(1), Constraint::Length(2), Constraint::Length(1)])
+        .areas(inner);
+    let digits = match font::pick(app.font, &time_str, timer.width, timer.height) {
+        Some(font) => big_time_lines(font, &time_str, timer.height),
+        None => vec![Line::from(time_str).bold()],
+    };
+    frame.render_widget(Paragraph::new(digits).alignment(Alignment::Center), timer);
+    frame.render_widget(
+        Paragraph::new(Line::from("Step away from the screen. Stretch, drink some water.").italic())
+            .alignment(Alignment::Center),
+        message,
+    );
+    let keys_line = match app.hard_break_left() {
+        Some(left) => Line::from(format!("Skipping unlocks in {}", format_time(left.as_secs_f64().ceil() as u64))).dim(),
+        None => Line::from(vec![
+            Span::from(" s ").bold(),
+            Span::from("skip   "),
+            Span::from(" q ").bold(),
+            Span::from("quit"),
+        ]),
+    };
+    frame.render_widget(Paragraph::new(keys_line).alignment(Alignment::Center), keys);
+}
 
-const COLON: [&str; 5] = [
-    "  ",
-    "██",
-    "  ",
-    "██",
-    "  ",
+/// Confetti pieces, drawn a few to a column.
+const CONFETTI: [char; 5] = ['*', '+', 'o', '~', '.'];
+
+/// The end of the day, filling `area`: confetti falling since `since`
+/// around what the session came to.
+fn render_day_done<T: Timer>(frame: &mut Frame, area: Rect, app: &App<T>, since: Instant) {
+    frame.render_widget(Clear, area);
+    if app.animations && area.width > 0 && area.height > 0 {
+        let elapsed = app.engine.now().saturating_duration_since(since).as_secs_f64();
+        let colors = [app.theme.progress, app.theme.warning, app.theme.warm_up, app.theme.cool_down];
+        let pieces = (u32::from(area.width) * u32::from(area.height) / 30).min(300);
+        let buf = frame.buffer_mut();
+        for piece in 0..pieces {
+            let seed = scatter(piece);
+            let x = area.x + (seed % u32::from(area.width)) as u16;
+            // Each piece falls at its own speed, from its own height, and
+            // comes round again from the top.
+            let speed = 3.0 + f64::from((seed >> 12) % 6);
+            let start = f64::from((seed >> 16) % u32::from(area.height));
+            let y = area.y + ((start + elapsed * speed) as u64 % u64::from(area.height)) as u16;
+            let glyph = CONFETTI[(seed >> 20) as usize % CONFETTI.len()];
+            let color = colors[(seed >> 24) as usize % colors.len()];
+            buf[(x, y)].set_char(glyph).set_fg(color);
+        }
+    }
+
+    let summary = app.summary();
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(format!("{} pomodoro(s), {} focused", summary.pomodoros, format_focus(summary.focus)))
+            .bold(),
+    ];
+    if let Some(score) = app.focus_score().score() {
+        lines.push(Line::from(format!("Focus score {score}")));
+    }
+    lines.extend([
+        Line::from(""),
+        Line::from("Well done. New sessions wait until tomorrow."),
+        Line::from(""),
+        Line::from("q leave").italic(),
+    ]);
+    let popup = popup_area(area, 50, lines.len() as u16 + 2);
+    let card = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title(" Done for today ").fg(app.theme.progress));
+    frame.render_widget(Clear, popup);
+    frame.render_widget(card, popup);
+}
+
+/// Spread `n` over the bits of a `u32`, so neighbouring pieces of confetti
+/// land far apart.
+fn scatter(n: u32) -> u32 {
+    let mut x = n.wrapping_mul(0x9E37_79B9) ^ 0x85EB_CA6B;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846C_A68B);
+    x ^ (x >> 16)
+}
+
+/// What the header shows by the phase's name, so phases are told apart by
+/// more than the theme's colors.
+fn phase_icon(kind: &PhaseKind) -> &'static str {
+    match kind {
+        PhaseKind::Work => "🍅",
+        PhaseKind::ShortBreak => "☕",
+        PhaseKind::LongBreak => "🌴",
+        PhaseKind::WarmUp => "📝",
+        PhaseKind::CoolDown => "🔍",
+        PhaseKind::Custom(_) => "⏳",
+    }
+}
+
+/// Splash art for a phase change.
+const TOMATO: [&str; 6] = [
+    r"    \|/    ",
+    r"  .-'`'-.  ",
+    r" /       \ ",
+    r"|         |",
+    r" \       / ",
+    r"  '-...-'  ",
 ];
 
-fn glyph(ch: char) -> [&'static str; 5] {
-    match ch {
-        '0'..='9' => DIGITS[(ch as u8 - b'0') as usize],
-        ':' => COLON,
-        _ => ["", "", "", "", ""],
+/// The splash between phases, `progress` of the way through: the tomato
+/// rises from the bottom of `area`, holds, and fades out.
+fn render_transition(frame: &mut Frame, area: Rect, progress: f64, phase: Phase, theme: Theme) {
+    let label = match phase.kind {
+        PhaseKind::Work => "Time to focus",
+        PhaseKind::ShortBreak | PhaseKind::LongBreak => "Time for a break",
+        PhaseKind::WarmUp => "Time to plan",
+        PhaseKind::CoolDown => "Time to look back",
+        PhaseKind::Custom(_) => "Next up",
+    };
+    let mut lines = Vec::new();
+    // Just the words where the art does not fit.
+    if usize::from(area.height) > TOMATO.len() + 1 {
+        lines.push(Line::from(TOMATO[0]).fg(Color::Green));
+        lines.extend(TOMATO[1..].iter().map(|row| Line::from(*row).fg(theme.progress)));
+        lines.push(Line::from(""));
     }
+    lines.push(Line::from(label).bold());
+    let height = lines.len() as u16;
+    let rest = area.height.saturating_sub(height) / 2;
+    let rise = (1.0 - (progress * 3.0).min(1.0)) * f64::from(area.height - rest);
+    let top = area.y + rest + rise as u16;
+    let mut splash = Paragraph::new(lines).alignment(Alignment::Center);
+    if progress > 0.75 {
+        splash = splash.dim();
+    }
+    frame.render_widget(Clear, area);
+    frame.render_widget(splash, Rect { y: top, height: area.bottom().saturating_sub(top).min(height), ..area });
+}
+
+fn render_mascot(frame: &mut Frame, area: Rect, mood: Mood, theme: Theme) {
+    let block = Block::default().borders(Borders::ALL);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let body = if mood == Mood::Alarmed { theme.warning } else { theme.progress };
+    let art = mood.art();
+    let mut lines = vec![Line::from(art[0]).fg(Color::Green)];
+    lines.extend(art[1..].iter().map(|row| Line::from(*row).fg(body)));
+    lines.push(Line::from(""));
+    lines.push(Line::from(mood.caption()).bold());
+    let [face] = Layout::vertical([Constraint::Length(lines.len() as u16)])
+        .flex(Flex::Center)
+        .areas(inner);
+    frame.render_widget(Paragraph::new(lines).alignment(Alignment::Center), face);
+}
+
+fn popup_area(area: Rect, width: u16, height: u16) -> Rect {
+    let [row] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(row);
+    popup
 }
 
-/// Build big-digit lines for `MM:SS`, vertically centered within `inner_height`.
-fn big_time_lines(mm: u8, ss: u8, inner_height: u16) -> Vec<Line<'static>> {
-    let text = format!("{:02}:{:02}", mm, ss);
-    let chars: Vec<char> = text.chars().collect();
+fn render_skip_menu(frame: &mut Frame, area: Rect, selected: usize) {
+    let popup = popup_area(area, 40, SKIP_CHOICES.len() as u16 + 4);
+    let mut lines: Vec<Line> = SKIP_CHOICES
+        .iter()
+        .enumerate()
+        .map(|(i, (_, label))| {
+            let text = format!("{} {}. {label}", if i == selected { ">" } else { " " }, i + 1);
+            if i == selected { Line::from(text).bold() } else { Line::from(text) }
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from("↑/↓ choose · Enter skip · Esc back").italic());
+    let menu = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Skip"));
 
-    // First build the 5 content lines (strings).
-    let mut content: Vec<String> = Vec::with_capacity(5);
-    for row in 0..5 {
-        let mut line = String::new();
-        for (i, ch) in chars.iter().enumerate() {
-            let g = glyph(*ch);
-            line.push_str(g[row]);
-            if i + 1 < chars.len() {
-                line.push(' '); // spacing between glyphs
+    frame.render_widget(Clear, popup);
+    frame.render_widget(menu, popup);
+}
+
+fn render_help(frame: &mut Frame, area: Rect) {
+    let key_width = KEY_HELP
+        .iter()
+        .flat_map(|(_, keys)| keys.iter().map(|(key, _)| key.chars().count()))
+        .max()
+        .unwrap_or(0);
+    let lines = |groups: &[KeyGroup]| {
+        let mut lines = Vec::new();
+        for (group, keys) in groups {
+            if !lines.is_empty() {
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::from(*group).bold());
+            for (key, what) in *keys {
+                lines.push(Line::from(vec![
+                    Span::from(format!("  {key:<key_width$}  ")).bold(),
+                    Span::from(*what),
+                ]));
             }
         }
-        content.push(line);
+        lines
+    };
+    // Two columns, split about evenly, to fit on a 24-row terminal.
+    let total: usize = KEY_HELP.iter().map(|(_, keys)| keys.len() + 2).sum();
+    let (mut split, mut left) = (0, 0);
+    while left * 2 < total {
+        left += KEY_HELP[split].1.len() + 2;
+        split += 1;
+    }
+    let columns = [lines(&KEY_HELP[..split]), lines(&KEY_HELP[split..])];
+    let height = columns.iter().map(Vec::len).max().unwrap_or(0) as u16;
+
+    let popup = popup_area(area, 64, height + 4);
+    let block = Block::default().borders(Borders::ALL).title("Keys");
+    let [body, keys] = Layout::vertical([Constraint::Min(0), Constraint::Length(2)])
+        .areas(block.inner(popup));
+    let [left, right] = Layout::horizontal([Constraint::Ratio(1, 2); 2]).spacing(2).areas(body);
+    frame.render_widget(Clear, popup);
+    frame.render_widget(block, popup);
+    for (column, area) in columns.into_iter().zip([left, right]) {
+        frame.render_widget(Paragraph::new(column), area);
+    }
+    let keys_line = vec![Line::from(""), Line::from("Esc back").italic()];
+    frame.render_widget(Paragraph::new(keys_line), keys);
+}
+
+/// The settings and their values, with `editing` typed in place of the
+/// selected one's, and how the last change went.
+fn render_settings<T: Timer>(
+    frame: &mut Frame,
+    area: Rect,
+    app: &App<T>,
+    selected: usize,
+    editing: Option<&str>,
+    message: Option<&str>,
+) {
+    let label_width = FIELDS.iter().map(|field| field.label().len()).max().unwrap_or(0);
+    let mut lines: Vec<Line> = FIELDS
+        .iter()
+        .enumerate()
+        .map(|(i, &field)| {
+            let marker = if i == selected { ">" } else { " " };
+            let value = match editing.filter(|_| i == selected) {
+                Some(input) => Span::from(format!("{input}_")).bold(),
+                None => Span::from(shown(field, app.option(field))),
+            };
+            let line = Line::from(vec![Span::from(format!("{marker} {:<label_width$}  ", field.label())), value]);
+            if i == selected { line.bold() } else { line }
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(message.unwrap_or_default()).fg(app.theme.warning));
+    let keys = if editing.is_some() {
+        "Enter set · Esc cancel"
+    } else {
+        "↑/↓ choose · ←/→ change · Enter edit · w save · Esc back"
+    };
+    lines.push(Line::from(keys).italic());
+    let popup = popup_area(area, 62, lines.len() as u16 + 2);
+    let form = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Settings"));
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(form, popup);
+}
+
+/// A setting's value the way the settings screen shows it.
+fn shown(field: Field, value: Option<String>) -> String {
+    match value.as_deref() {
+        None | Some("false") => "off".to_string(),
+        Some("true") => "on".to_string(),
+        Some(volume) if matches!(field, Field::Volume | Field::AmbientVolume) => format!("{volume}%"),
+        Some(value) => value.to_string(),
     }
+}
+
+fn render_goto(frame: &mut Frame, area: Rect, input: &str, num_cycles: u32) {
+    let popup = popup_area(area, 40, 6);
+    let lines = vec![
+        Line::from(format!("Jump to cycle (1-{num_cycles}):")),
+        Line::from(Span::from(format!("{input}_")).bold()),
+        Line::from(""),
+        Line::from("Enter jump · Esc cancel").italic(),
+    ];
+    let form = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Go to cycle"));
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(form, popup);
+}
+
+fn render_targets(frame: &mut Frame, area: Rect, targets: Targets, focus: Focus, theme: Theme) {
+    let gauges: Vec<_> = [("Today", targets.daily, focus.today), ("Week", targets.weekly, focus.week)]
+        .into_iter()
+        .filter_map(|(label, target, done)| Some((label, target?, done)))
+        .collect();
+    let areas = Layout::horizontal(vec![Constraint::Fill(1); gauges.len()])
+        .spacing(2)
+        .split(area);
+    for ((label, target, done), area) in gauges.into_iter().zip(areas.iter()) {
+        let ratio = (done.as_secs_f64() / target.as_secs_f64()).min(1.0);
+        let gauge = LineGauge::default()
+            .ratio(ratio)
+            .label(format!(" {label} {} / {}", format_focus(done), format_focus(target)))
+            .filled_style(Style::new().fg(theme.progress))
+            .unfilled_style(Style::new().fg(theme.track));
+        frame.render_widget(gauge, *area);
+    }
+}
+
+fn render_sidebar(frame: &mut Frame, area: Rect, plan: Option<&Plan>, theme: Theme) {
+    let tasks = plan.map_or(&[][..], Plan::tasks);
+    let current = plan.map(Plan::current_index);
+    let mut lines: Vec<Line> = tasks
+        .iter()
+        .enumerate()
+        .map(|(i, task)| {
+            let mark = if Some(i) == current {
+                "▶"
+            } else if task.is_over_budget() {
+                "!"
+            } else if task.is_done() {
+                "✓"
+            } else {
+                " "
+            };
+            let tally = format!("{}/{}", task.actual, task.estimate);
+            let width = usize::from(area.width).saturating_sub(tally.len() + 5);
+            let name: String = task.name.chars().take(width).collect();
+            let mut line = Line::from(format!("{mark} {name:<width$} {tally}"));
+            if task.is_over_budget() {
+                line = line.fg(theme.warning);
+            }
+            match (Some(i) == current, task.is_done()) {
+                (true, _) => line.bold(),
+                (false, true) => line.dim(),
+                (false, false) => line,
+            }
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(Line::from("No tasks yet; plan some with").italic());
+        lines.push(Line::from("`opomodoro start --plan`").italic());
+    }
+    let list = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Today · ↑/↓ switch · t hide"));
+    frame.render_widget(list, area);
+}
+
+fn render_plan(frame: &mut Frame, area: Rect, tasks: &[Task], input: &str) {
+    let popup = popup_area(area, 50, tasks.len() as u16 + 8);
+    let mut lines: Vec<Line> = tasks
+        .iter()
+        .enumerate()
+        .map(|(i, task)| Line::from(format!("{}. {} ({})", i + 1, task.name, task.estimate)))
+        .collect();
+    lines.extend([
+        Line::from("Task and pomodoro estimate, e.g. Write docs 3"),
+        Line::from(Span::from(format!("{input}_")).bold()),
+        Line::from(""),
+        Line::from("Enter add · empty Enter or Esc start").italic(),
+        Line::from("n during the session moves to the next task").italic(),
+    ]);
+    let form = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Plan the session"));
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(form, popup);
+}
+
+fn render_note(frame: &mut Frame, area: Rect, note: &str) {
+    let popup = popup_area(area, 50, 6);
+    let lines = vec![
+        Line::from("What did you accomplish?"),
+        Line::from(Span::from(format!("{note}_")).bold()),
+        Line::from(""),
+        Line::from("Enter save · Esc skip").italic(),
+    ];
+    let form = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Work phase done"));
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(form, popup);
+}
+
+fn render_away(frame: &mut Frame, area: Rect, since: DateTime<Local>, clock: HourFormat) {
+    let popup = popup_area(area, 50, 6);
+    let minutes = (Local::now() - since).num_seconds().max(0).unsigned_abs().div_ceil(60);
+    let lines = vec![
+        Line::from(format!("You were away for {minutes} min, since {}.", clock.format(since))),
+        Line::from("What was that time?"),
+        Line::from(""),
+        Line::from("w work · b break · d neither").italic(),
+    ];
+    let form = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Welcome back"));
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(form, popup);
+}
+
+fn render_waiting(frame: &mut Frame, area: Rect, at: DateTime<Local>, clock: HourFormat) {
+    let popup = popup_area(area, 40, 6);
+    let secs = (at - Local::now()).num_seconds().max(0).unsigned_abs();
+    let countdown = format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+    let lines = vec![
+        Line::from(format!("Starting at {}", clock.format(at))),
+        Line::from(format!("in {countdown}")).bold(),
+        Line::from(""),
+        Line::from("Enter start now · q quit").italic(),
+    ];
+    let form = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title("Waiting"));
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(form, popup);
+}
+
+/// The phase held in manual-advance mode, waiting for a key.
+fn render_held(frame: &mut Frame, area: Rect, ended: &Phase, next: &Phase) {
+    let popup = popup_area(area, 40, 6);
+    let lines = vec![
+        Line::from(format!("{} is over", ended.kind)),
+        Line::from(format!("{} is next", next.kind)).bold(),
+        Line::from(""),
+        Line::from("Press any key to start").italic(),
+    ];
+    let form = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title("Time's up"));
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(form, popup);
+}
+
+fn render_interruption(frame: &mut Frame, area: Rect, source: InterruptionSource, note: &str) {
+    let popup = popup_area(area, 50, 6);
+    let choice = |label: &'static str, selected: bool| {
+        if selected { Span::from(format!("[{label}]")).bold() } else { Span::from(format!(" {label} ")) }
+    };
+    let lines = vec![
+        Line::from(vec![
+            Span::from("Source: "),
+            choice("internal", source == InterruptionSource::Internal),
+            Span::from(" "),
+            choice("external", source == InterruptionSource::External),
+        ]),
+        Line::from(vec![Span::from("Note: "), Span::from(format!("{note}_")).bold()]),
+        Line::from(""),
+        Line::from("Tab source · Enter save · Esc cancel").italic(),
+    ];
+    let form = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Log interruption"));
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(form, popup);
+}
+
+// Progress ring drawn clockwise from 12 o'clock, with the time in the middle.
+fn render_ring(
+    frame: &mut Frame,
+    area: Rect,
+    block: Block,
+    ratio: f64,
+    time_str: String,
+    theme: Theme,
+) {
+    let inner = block.inner(area);
+    if inner.width == 0 || inner.height == 0 {
+        return;
+    }
+
+    // Terminal cells are roughly twice as tall as they are wide, so widen the
+    // x bounds to keep the ring circular.
+    let y_half = 1.1;
+    let x_half = y_half * inner.width as f64 / (inner.height as f64 * 2.0);
+    let cell_w = 2.0 * x_half / inner.width as f64;
+
+    let steps = 240;
+    let mut done: Vec<(f64, f64)> = Vec::new();
+    let mut todo: Vec<(f64, f64)> = Vec::new();
+    for i in 0..steps {
+        let t = i as f64 / steps as f64;
+        let angle = PI / 2.0 - t * 2.0 * PI;
+        for r in [0.8, 0.84, 0.88, 0.92, 0.96, 1.0] {
+            let point = (r * angle.cos(), r * angle.sin());
+            if t < ratio { done.push(point) } else { todo.push(point) }
+        }
+    }
+
+    let label_x = -(time_str.len() as f64 * cell_w) / 2.0;
+    let canvas = Canvas::default()
+        .block(block)
+        .marker(Marker::Braille)
+        .x_bounds([-x_half, x_half])
+        .y_bounds([-y_half, y_half])
+        .paint(move |ctx| {
+            ctx.draw(&Points { coords: &todo, color: theme.track });
+            ctx.draw(&Points { coords: &done, color: theme.progress });
+            ctx.print(label_x, 0.0, Line::from(time_str.clone()).bold());
+        });
+
+    frame.render_widget(canvas, area);
+}
+
+/// The time left on the phase, written the way the user picked.
+fn countdown_text<T: Timer>(app: &App<T>) -> String {
+    match app.countdown {
+        Countdown::Clock => format_time(app.remaining.as_secs()),
+        Countdown::Minutes => format!("{}m", app.remaining.as_secs().div_ceil(60)),
+        Countdown::Percent => {
+            let total = app.engine.phase().duration.as_secs_f64();
+            let left = if total > 0.0 { app.remaining.as_secs_f64() / total } else { 0.0 };
+            format!("{}%", (left * 100.0).ceil())
+        }
+    }
+}
+
+/// Build big-digit lines for `text`, vertically centered within `inner_height`.
+fn big_time_lines(font: &Font, text: &str, inner_height: u16) -> Vec<Line<'static>> {
+    let content = font.render(text);
 
     // Vertically center within the block's inner rect.
-    let content_h = BIG_HEIGHT;
-    let pad_top = inner_height.saturating_sub(content_h) / 2;
+    let pad_top = inner_height.saturating_sub(font.height) / 2;
 
     let mut lines: Vec<Line<'static>> = Vec::new();
     for _ in 0..pad_top {
@@ -179,3 +876,24 @@ fn big_time_lines(mm: u8, ss: u8, inner_height: u16) -> Vec<Line<'static>> {
 
     lines
 }
+
+/// The drawing code as the benchmarks in `benches/` need it.
+#[cfg(feature = "bench")]
+pub mod bench {
+    use ratatui::text::Line;
+    use crate::{FontChoice, font};
+
+    pub use super::render;
+
+    /// The big digits for `text` in the font `choice` picks for a `width`
+    /// by `height` panel, as the timer lays them out.
+    pub fn big_time_lines(
+        choice: FontChoice,
+        text: &str,
+        width: u16,
+        height: u16,
+    ) -> Option<Vec<Line<'static>>> {
+        let font = font::pick(choice, text, width, height)?;
+        Some(super::big_time_lines(font, text, height))
+    }
+}
@@ -0,0 +1,57 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Which big-digit font the timer panel should use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FontChoice {
+    /// Pick the largest font that fits the timer panel.
+    Auto,
+    Large,
+    Medium,
+    Small,
+}
+
+/// How the timer panel visualizes the remaining time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewMode {
+    /// Big block digits.
+    Digits,
+    /// Circular progress ring around the remaining time.
+    Ring,
+    /// Just the progress gauge, labelled with the remaining time.
+    Minimal,
+}
+
+impl ViewMode {
+    pub fn next(self) -> ViewMode {
+        match self {
+            ViewMode::Digits => ViewMode::Ring,
+            ViewMode::Ring => ViewMode::Minimal,
+            ViewMode::Minimal => ViewMode::Digits,
+        }
+    }
+}
+
+/// How the timer writes the time left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Countdown {
+    /// Minutes and seconds, with the hours in front from an hour up.
+    Clock,
+    /// Whole minutes, rounded up.
+    Minutes,
+    /// Share of the phase still to go.
+    Percent,
+}
+
+impl Countdown {
+    pub fn next(self) -> Countdown {
+        match self {
+            Countdown::Clock => Countdown::Minutes,
+            Countdown::Minutes => Countdown::Percent,
+            Countdown::Percent => Countdown::Clock,
+        }
+    }
+}
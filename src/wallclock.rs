@@ -0,0 +1,55 @@
+use std::env;
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Whether wall-clock times are shown on a 12- or 24-hour clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum HourFormat {
+    /// Follow the locale in `LC_ALL`, `LC_TIME` or `LANG`.
+    #[serde(rename = "auto")]
+    Auto,
+    #[value(name = "12h")]
+    #[serde(rename = "12h")]
+    H12,
+    #[value(name = "24h")]
+    #[serde(rename = "24h")]
+    H24,
+}
+
+/// Regions whose locales read the time on a 12-hour clock.
+const TWELVE_HOUR_REGIONS: [&str; 9] = ["US", "AU", "NZ", "PH", "IN", "PK", "BD", "EG", "SA"];
+
+impl HourFormat {
+    /// Settle `Auto` by looking at the locale, falling back to 24 hours.
+    pub fn resolve(self) -> HourFormat {
+        match self {
+            HourFormat::Auto if locale_uses_12h() => HourFormat::H12,
+            HourFormat::Auto => HourFormat::H24,
+            format => format,
+        }
+    }
+
+    pub fn format(self, time: DateTime<Local>) -> String {
+        match self.resolve() {
+            HourFormat::H12 => time.format("%-I:%M %p").to_string(),
+            _ => time.format("%H:%M").to_string(),
+        }
+    }
+}
+
+/// The locale in effect for times, as POSIX looks it up.
+fn locale_uses_12h() -> bool {
+    let locale = ["LC_ALL", "LC_TIME", "LANG"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    // language[_TERRITORY][.codeset][@modifier]
+    let name = locale.split(['.', '@']).next().unwrap_or_default();
+    match name.split_once('_') {
+        Some(("en", "CA")) => true,
+        Some((_, region)) => TWELVE_HOUR_REGIONS.contains(&region),
+        None => false,
+    }
+}
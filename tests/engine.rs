@@ -0,0 +1,600 @@
+use std::time::Duration;
+use opomodoro::chain::Block;
+use opomodoro::clock::MockClock;
+use opomodoro::engine::{
+    BreakDebt, Engine, Event, GRACE_WINDOW, OverPauseBudget, Phase, PhaseKind, SkipTarget, Timer, TimerState,
+    UNDO_WINDOW,
+};
+use opomodoro::history::{Outcome, Targets};
+use opomodoro::intervals::Intervals;
+use opomodoro::template::Messages;
+use opomodoro::theme::ThemeChoice;
+use opomodoro::{Config, ConfigError, Countdown, FontChoice, HourFormat, ViewMode};
+
+const WORK: Duration = Duration::from_secs(25 * 60);
+const BREAK: Duration = Duration::from_secs(5 * 60);
+const SNOOZE: Duration = Duration::from_secs(5 * 60);
+
+fn config(cycles: u32, late: bool) -> Config {
+    Config {
+        work_time: WORK,
+        break_time: BREAK,
+        cycles,
+        long_break: None,
+        late,
+        warm_up: None,
+        cool_down: None,
+        font: FontChoice::Auto,
+        view: ViewMode::Digits,
+        countdown: Countdown::Clock,
+        theme: ThemeChoice::Tomato,
+        no_color: false,
+        volume: 100,
+        speak: false,
+        notify: false,
+        visual_bell: false,
+        history: None,
+        state: None,
+        tasks: None,
+        notes: false,
+        snooze: SNOOZE,
+        max_snoozes: 2,
+        idle: None,
+        work_warning: None,
+        break_warning: None,
+        hard_break: None,
+        get_ready: None,
+        break_debt: BreakDebt::Off,
+        pause_budget: None,
+        strict: false,
+        block_commands: None,
+        ambient: None,
+        targets: Targets::default(),
+        task: None,
+        clock: HourFormat::Auto,
+        sidebar: false,
+        mascot: false,
+        animations: false,
+        spinner: false,
+        low_power: false,
+        end_of_day: None,
+        manual_advance: None,
+        messages: Messages::default(),
+        chain: Vec::new(),
+        single: None,
+        intervals: None,
+    }
+}
+
+fn engine(cycles: u32, late: bool) -> (Engine<MockClock>, MockClock) {
+    let clock = MockClock::new();
+    (Engine::with_clock(&config(cycles, late), clock.clone()), clock)
+}
+
+fn ended(events: &[Event]) -> Vec<(PhaseKind, u32, Outcome)> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::PhaseEnded { phase, cycle, outcome, .. } => Some((phase.kind.clone(), *cycle, *outcome)),
+            Event::Snoozed | Event::SkipUndone | Event::PauseBudgetSpent { .. } => None,
+        })
+        .collect()
+}
+
+#[test]
+fn phase_runs_until_its_time_is_up() {
+    let (mut engine, clock) = engine(2, false);
+    clock.advance(WORK - Duration::from_secs(1));
+    assert!(engine.tick().is_empty());
+    assert_eq!(engine.remaining(), Duration::from_secs(1));
+
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(ended(&engine.tick()), [(PhaseKind::Work, 1, Outcome::Completed)]);
+    assert_eq!(engine.phase().kind, PhaseKind::ShortBreak);
+    assert_eq!(engine.remaining(), BREAK);
+}
+
+#[test]
+fn cycles_alternate_work_and_break() {
+    let (mut engine, clock) = engine(2, false);
+    let mut log = Vec::new();
+    for step in [WORK, BREAK, WORK] {
+        clock.advance(step);
+        log.extend(ended(&engine.tick()));
+    }
+    assert_eq!(
+        log,
+        [
+            (PhaseKind::Work, 1, Outcome::Completed),
+            (PhaseKind::ShortBreak, 1, Outcome::Completed),
+            (PhaseKind::Work, 2, Outcome::Completed),
+        ]
+    );
+    assert!(engine.is_finished());
+}
+
+#[test]
+fn late_takes_the_last_break() {
+    let (mut engine, clock) = engine(1, true);
+    clock.advance(WORK);
+    engine.tick();
+    assert!(!engine.is_finished());
+    assert_eq!(engine.phase().kind, PhaseKind::ShortBreak);
+
+    clock.advance(BREAK);
+    assert_eq!(ended(&engine.tick()), [(PhaseKind::ShortBreak, 1, Outcome::Completed)]);
+    assert!(engine.is_finished());
+}
+
+#[test]
+fn a_single_phase_runs_alone() {
+    let standup = PhaseKind::from("Standup prep");
+    let length = Duration::from_secs(37 * 60);
+    let config = Config {
+        single: Some(Phase::build(standup.clone(), length)),
+        warm_up: Some(Duration::from_secs(60)),
+        ..config(4, true)
+    };
+    let clock = MockClock::new();
+    let mut engine = Engine::with_clock(&config, clock.clone());
+    assert_eq!(engine.phase(), Phase::build(standup.clone(), length));
+    assert_eq!(engine.num_cycles(), 1);
+
+    clock.advance(length);
+    assert_eq!(ended(&engine.tick()), [(standup, 1, Outcome::Completed)]);
+    assert!(engine.is_finished());
+}
+
+#[test]
+fn intervals_run_round_after_round() {
+    let intervals: Intervals = "45s/rest=15s x2".parse().unwrap();
+    let (on, rest) = (PhaseKind::from("On"), PhaseKind::from("rest"));
+    let config = Config { intervals: Some(intervals), ..config(4, true) };
+    let clock = MockClock::new();
+    let mut engine = Engine::with_clock(&config, clock.clone());
+    assert_eq!(engine.phase(), Phase::build(on.clone(), Duration::from_secs(45)));
+    assert_eq!(engine.num_cycles(), 2);
+    assert_eq!(engine.session_remaining(), Duration::from_secs(120));
+    assert_eq!(engine.next_phase(), Some(Phase::build(rest.clone(), Duration::from_secs(15))));
+
+    clock.advance(Duration::from_secs(45));
+    assert_eq!(ended(&engine.tick()), [(on.clone(), 1, Outcome::Completed)]);
+    clock.advance(Duration::from_secs(15));
+    assert_eq!(ended(&engine.tick()), [(rest.clone(), 1, Outcome::Completed)]);
+    assert_eq!((engine.phase().kind, engine.cycle()), (on.clone(), 2));
+
+    engine.skip();
+    assert_eq!(engine.next_phase(), None);
+    clock.advance(Duration::from_secs(15));
+    assert_eq!(ended(&engine.tick()), [(rest, 2, Outcome::Completed)]);
+    assert!(engine.is_finished());
+}
+
+#[test]
+fn manual_advance_holds_each_phase_until_started() {
+    let config = Config { manual_advance: Some((Duration::from_secs(30), 5)), ..config(2, false) };
+    let clock = MockClock::new();
+    let mut engine = Engine::with_clock(&config, clock.clone());
+    clock.advance(WORK);
+    assert_eq!(ended(&engine.tick()), [(PhaseKind::Work, 1, Outcome::Completed)]);
+    assert!(engine.is_paused());
+
+    clock.advance(Duration::from_secs(600));
+    assert!(engine.tick().is_empty());
+    assert_eq!(engine.remaining(), BREAK);
+    // Nor does the wait count once a skip past it is undone.
+    engine.skip();
+    assert_eq!(engine.undo_skip(), [Event::SkipUndone]);
+    clock.advance(Duration::from_secs(600));
+    assert_eq!(engine.paused_time(), Duration::ZERO);
+    engine.toggle_pause();
+    clock.advance(BREAK);
+    assert_eq!(ended(&engine.tick()), [(PhaseKind::ShortBreak, 1, Outcome::Completed)]);
+    assert_eq!(engine.paused_time(), Duration::ZERO);
+
+    // A phase failed over the pause budget is held all the same.
+    let budget = Some((Duration::from_secs(60), OverPauseBudget::Fail));
+    let config = Config { pause_budget: budget, ..config };
+    let mut engine = Engine::with_clock(&config, clock.clone());
+    clock.advance(Duration::from_secs(1));
+    engine.toggle_pause();
+    clock.advance(Duration::from_secs(61));
+    let events = engine.tick();
+    assert_eq!(ended(&events), [(PhaseKind::Work, 1, Outcome::Incomplete)]);
+    assert_eq!(engine.phase().kind, PhaseKind::ShortBreak);
+    clock.advance(Duration::from_secs(600));
+    assert!(engine.is_paused());
+    assert_eq!(engine.remaining(), BREAK);
+}
+
+#[test]
+fn pause_freezes_the_remaining_time() {
+    let (mut engine, clock) = engine(1, false);
+    clock.advance(Duration::from_secs(60));
+    engine.toggle_pause();
+    clock.advance(Duration::from_secs(3600));
+    assert!(engine.tick().is_empty());
+    assert_eq!(engine.remaining(), WORK - Duration::from_secs(60));
+
+    engine.toggle_pause();
+    clock.advance(Duration::from_secs(30));
+    assert_eq!(engine.remaining(), WORK - Duration::from_secs(90));
+}
+
+#[test]
+fn pausing_past_the_budget_warns_or_fails_the_pomodoro() {
+    let budget = Duration::from_secs(2 * 60);
+    for over in [OverPauseBudget::Warn, OverPauseBudget::Fail] {
+        let clock = MockClock::new();
+        let config = Config { pause_budget: Some((budget, over)), ..config(2, false) };
+        let mut engine = Engine::with_clock(&config, clock.clone());
+        // Two pauses in the phase add up.
+        for _ in 0..2 {
+            clock.advance(Duration::from_secs(60));
+            engine.toggle_pause();
+            clock.advance(Duration::from_secs(60));
+            assert!(engine.tick().is_empty());
+            engine.toggle_pause();
+        }
+        assert_eq!(engine.paused_time(), budget);
+        engine.toggle_pause();
+        clock.advance(Duration::from_secs(1));
+        let events = engine.tick();
+        let failed = over == OverPauseBudget::Fail;
+        assert!(matches!(events[0], Event::PauseBudgetSpent { failed: f, .. } if f == failed));
+        if failed {
+            assert_eq!(ended(&events), [(PhaseKind::Work, 1, Outcome::Incomplete)]);
+            assert_eq!(engine.phase().kind, PhaseKind::ShortBreak);
+            assert_eq!(engine.paused_time(), Duration::ZERO);
+        } else {
+            assert_eq!(events.len(), 1);
+            // Only said once.
+            clock.advance(Duration::from_secs(60));
+            assert!(engine.tick().is_empty());
+            assert_eq!(engine.phase().kind, PhaseKind::Work);
+        }
+    }
+}
+
+#[test]
+fn work_after_a_break_waits_for_the_get_ready_countdown() {
+    let clock = MockClock::new();
+    let config = Config { get_ready: Some(Duration::from_secs(5)), ..config(2, false) };
+    let mut engine = Engine::with_clock(&config, clock.clone());
+    // The first work phase starts right away.
+    assert_eq!(engine.get_ready_left(), None);
+    clock.advance(WORK);
+    engine.tick();
+    clock.advance(BREAK);
+    engine.tick();
+    assert_eq!(engine.phase().kind, PhaseKind::Work);
+    assert_eq!(engine.get_ready_left(), Some(Duration::from_secs(5)));
+
+    clock.advance(Duration::from_secs(3));
+    assert_eq!(engine.get_ready_left(), Some(Duration::from_secs(2)));
+    assert_eq!(engine.remaining(), WORK);
+    clock.advance(Duration::from_secs(3));
+    assert_eq!(engine.get_ready_left(), None);
+    assert_eq!(engine.remaining(), WORK - Duration::from_secs(1));
+}
+
+#[test]
+fn skipped_break_time_is_repaid_in_the_next_break() {
+    let clock = MockClock::new();
+    let config = Config { break_debt: BreakDebt::Repay, ..config(3, false) };
+    let mut engine = Engine::with_clock(&config, clock.clone());
+    // Straight on to the next work phase: a whole break owed.
+    engine.skip_to(SkipTarget::NextWork);
+    assert_eq!(engine.break_debt(), BREAK);
+    clock.advance(WORK);
+    engine.tick();
+    assert_eq!(engine.phase().duration, BREAK * 2);
+    assert_eq!(engine.break_debt(), Duration::ZERO);
+
+    // Skipping most of that break owes what was left of it.
+    clock.advance(BREAK / 2);
+    engine.skip();
+    assert_eq!(engine.break_debt(), BREAK * 3 / 2);
+    engine.undo_skip();
+    assert_eq!(engine.break_debt(), Duration::ZERO);
+}
+
+#[test]
+fn late_tick_reports_the_full_phase() {
+    let (mut engine, clock) = engine(2, false);
+    clock.advance(WORK + Duration::from_secs(120));
+    let events = engine.tick();
+    assert!(matches!(
+        events[..],
+        [Event::PhaseEnded { elapsed, .. }] if elapsed == WORK
+    ));
+}
+
+#[test]
+fn skip_to_break_and_next_work() {
+    let (mut engine, clock) = engine(3, false);
+    clock.advance(Duration::from_secs(600));
+    let events = engine.skip_to(SkipTarget::Break);
+    assert!(matches!(
+        events[..],
+        [Event::PhaseEnded { outcome: Outcome::Skipped, elapsed, .. }]
+            if elapsed == Duration::from_secs(600)
+    ));
+    assert_eq!(engine.phase().kind, PhaseKind::ShortBreak);
+
+    engine.skip_to(SkipTarget::NextWork);
+    assert_eq!((engine.phase().kind, engine.cycle()), (PhaseKind::Work, 2));
+
+    engine.skip_to(SkipTarget::RestOfCycle);
+    assert!(engine.is_finished());
+}
+
+#[test]
+fn snooze_only_within_the_grace_window() {
+    let (mut engine, clock) = engine(2, false);
+    clock.advance(WORK);
+    engine.tick();
+    clock.advance(GRACE_WINDOW - Duration::from_secs(1));
+    assert_eq!(engine.snooze(), [Event::Snoozed]);
+    assert_eq!(engine.phase().kind, PhaseKind::Work);
+    assert_eq!(engine.remaining(), SNOOZE);
+
+    clock.advance(SNOOZE);
+    let events = engine.tick();
+    assert!(matches!(events[..], [Event::PhaseEnded { extension: true, .. }]));
+
+    clock.advance(GRACE_WINDOW);
+    assert!(engine.snooze().is_empty());
+}
+
+#[test]
+fn undo_skip_only_within_the_undo_window() {
+    let (mut engine, clock) = engine(2, false);
+    clock.advance(Duration::from_secs(10 * 60));
+    engine.skip();
+    assert_eq!(engine.phase().kind, PhaseKind::ShortBreak);
+    clock.advance(UNDO_WINDOW - Duration::from_secs(1));
+    assert_eq!(engine.undo_skip(), [Event::SkipUndone]);
+    assert_eq!((engine.phase().kind, engine.cycle()), (PhaseKind::Work, 1));
+    assert_eq!(engine.remaining(), Duration::from_secs(15 * 60));
+    assert!(engine.undo_skip().is_empty());
+
+    engine.skip_to(SkipTarget::NextWork);
+    clock.advance(UNDO_WINDOW);
+    assert!(engine.undo_skip().is_empty());
+    assert_eq!(engine.cycle(), 2);
+}
+
+#[test]
+fn goto_and_quit() {
+    let (mut engine, _clock) = engine(4, false);
+    assert!(engine.goto_cycle(5).is_empty());
+    assert_eq!(ended(&engine.goto_cycle(3)), [(PhaseKind::Work, 1, Outcome::Skipped)]);
+    assert_eq!(engine.cycle(), 3);
+
+    assert_eq!(ended(&engine.quit()), [(PhaseKind::Work, 3, Outcome::Quit)]);
+    assert!(engine.is_finished());
+    assert!(engine.quit().is_empty());
+}
+
+#[test]
+fn start_block_runs_the_next_session_from_its_first_cycle() {
+    let (mut engine, clock) = engine(1, false);
+    clock.advance(WORK);
+    assert_eq!(ended(&engine.tick()), [(PhaseKind::Work, 1, Outcome::Completed)]);
+    assert!(engine.is_finished());
+
+    let lunch = Block { name: Some("Lunch".into()), work_time: None, break_time: WORK, cycles: 3 };
+    engine.start_block(&lunch);
+    assert!(!engine.is_finished());
+    assert_eq!((engine.phase().kind, engine.cycle(), engine.num_cycles()), (PhaseKind::ShortBreak, 1, 1));
+    clock.advance(WORK);
+    assert_eq!(ended(&engine.tick()), [(PhaseKind::ShortBreak, 1, Outcome::Completed)]);
+    assert!(engine.is_finished());
+
+    let work = Duration::from_secs(50 * 60);
+    engine.start_block(&Block { name: None, work_time: Some(work), break_time: BREAK, cycles: 2 });
+    assert_eq!((engine.phase().kind, engine.cycle(), engine.num_cycles()), (PhaseKind::Work, 1, 2));
+    assert_eq!(engine.remaining(), work);
+    assert_eq!(engine.session_remaining(), work * 2 + BREAK);
+}
+
+#[cfg(feature = "plain")]
+#[test]
+fn plain_sessions_run_the_whole_chain() {
+    use std::sync::atomic::AtomicBool;
+    use opomodoro::plain;
+
+    let tick = Duration::from_millis(40);
+    let first = Block { name: None, work_time: Some(tick), break_time: tick, cycles: 1 };
+    let lunch = Block { name: Some("Lunch".into()), work_time: None, break_time: tick, cycles: 1 };
+    let config = Config {
+        work_time: tick,
+        break_time: tick,
+        chain: vec![first, lunch],
+        ..config(1, false)
+    };
+    let running = AtomicBool::new(true);
+    let mut out = Vec::new();
+    let options = plain::Options::default();
+    let summary = plain::run(Engine::new(&config), &config, options, &running, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<_> = out.lines().map(|line| line.split_once(' ').unwrap().1).collect();
+    assert_eq!(
+        lines,
+        ["Work 1/1 40ms", "Work 1/1 completed", "block 2/2", "Break 1/1 40ms", "Break 1/1 completed"],
+    );
+    assert_eq!(summary.pomodoros, 1);
+}
+
+#[test]
+fn warm_up_comes_first_and_cool_down_last() {
+    let clock = MockClock::new();
+    let (warm_up, cool_down) = (Duration::from_secs(3 * 60), Duration::from_secs(5 * 60));
+    let config = Config { warm_up: Some(warm_up), cool_down: Some(cool_down), ..config(2, false) };
+    let mut engine = Engine::with_clock(&config, clock.clone());
+    assert_eq!((engine.phase().kind, engine.cycle()), (PhaseKind::WarmUp, 1));
+    assert_eq!(engine.next_phase().map(|phase| phase.kind), Some(PhaseKind::Work));
+    assert_eq!(engine.session_remaining(), warm_up + WORK * 2 + BREAK + cool_down);
+
+    clock.advance(warm_up);
+    assert_eq!(ended(&engine.tick()), [(PhaseKind::WarmUp, 1, Outcome::Completed)]);
+    assert_eq!((engine.phase().kind, engine.cycle()), (PhaseKind::Work, 1));
+    for phase in [WORK, BREAK, WORK] {
+        clock.advance(phase);
+        engine.tick();
+    }
+    assert_eq!((engine.phase().kind, engine.cycle()), (PhaseKind::CoolDown, 2));
+    assert_eq!(engine.next_phase(), None);
+    assert_eq!(engine.session_remaining(), cool_down);
+    clock.advance(cool_down);
+    assert_eq!(ended(&engine.tick()), [(PhaseKind::CoolDown, 2, Outcome::Completed)]);
+    assert!(engine.is_finished());
+
+    // Skipping the rest of the cycles still leaves time to look back.
+    let mut skipped = Engine::with_clock(&config, clock.clone());
+    skipped.skip_to(SkipTarget::RestOfCycle);
+    assert_eq!(skipped.phase().kind, PhaseKind::CoolDown);
+    skipped.skip();
+    assert!(skipped.is_finished());
+}
+
+#[test]
+fn session_remaining_counts_the_phases_to_come() {
+    let (late, _) = engine(3, true);
+    assert_eq!(late.session_remaining(), (WORK + BREAK) * 3);
+
+    let (mut engine, clock) = engine(3, false);
+    assert_eq!(engine.session_remaining(), WORK * 3 + BREAK * 2);
+
+    clock.advance(WORK / 5);
+    assert_eq!(engine.session_remaining(), WORK * 3 + BREAK * 2 - WORK / 5);
+
+    engine.toggle_pause();
+    clock.advance(WORK);
+    assert_eq!(engine.session_remaining(), WORK * 3 + BREAK * 2 - WORK / 5);
+    engine.toggle_pause();
+
+    engine.skip();
+    assert_eq!(engine.session_remaining(), WORK * 2 + BREAK * 2);
+}
+
+#[test]
+fn set_remaining_keeps_the_phase_running_or_paused() {
+    let (mut engine, clock) = engine(2, false);
+    clock.advance(Duration::from_secs(10 * 60));
+    engine.set_remaining(Duration::from_secs(20 * 60));
+    assert_eq!(engine.remaining(), Duration::from_secs(20 * 60));
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(engine.remaining(), Duration::from_secs(19 * 60));
+
+    // Never more than the whole phase.
+    engine.toggle_pause();
+    engine.set_remaining(WORK * 2);
+    clock.advance(Duration::from_secs(60));
+    assert!(engine.is_paused());
+    assert_eq!(engine.remaining(), WORK);
+}
+
+#[test]
+fn new_lengths_and_cycles_start_with_the_next_phase() {
+    let (mut engine, clock) = engine(2, false);
+    clock.advance(Duration::from_secs(60));
+    engine.set_lengths(Duration::from_secs(50 * 60), Duration::from_secs(10 * 60));
+    assert_eq!(engine.phase().duration, WORK);
+    assert_eq!(engine.remaining(), WORK - Duration::from_secs(60));
+
+    clock.advance(WORK - Duration::from_secs(60));
+    engine.tick();
+    assert_eq!(engine.remaining(), Duration::from_secs(10 * 60));
+
+    // Never fewer cycles than the one on now.
+    engine.set_cycles(0);
+    assert_eq!(engine.num_cycles(), 1);
+    clock.advance(Duration::from_secs(10 * 60));
+    engine.tick();
+    assert!(engine.is_finished());
+}
+
+#[test]
+fn phases_serialize_with_their_kinds_by_name() {
+    let phase = Phase::build(PhaseKind::WarmUp, Duration::from_secs(180));
+    let json = serde_json::to_value(&phase).unwrap();
+    assert_eq!(json["kind"], "Warm-up");
+    assert_eq!(serde_json::from_value::<Phase>(json).unwrap(), phase);
+
+    // Histories from before kept breaks as plain "Break".
+    let kinds: Vec<PhaseKind> = serde_json::from_str(r#"["Work", "Break", "Standup"]"#).unwrap();
+    assert_eq!(kinds, [PhaseKind::Work, PhaseKind::ShortBreak, PhaseKind::Custom("Standup".into())]);
+    assert_eq!(serde_json::to_string(&kinds).unwrap(), r#"["Work","Break","Standup"]"#);
+
+    let paused = TimerState::Paused { remaining: Duration::from_secs(90) };
+    let json = serde_json::to_string(&paused).unwrap();
+    assert_eq!(serde_json::from_str::<TimerState>(&json).unwrap(), paused);
+}
+
+#[test]
+fn builder_checks_the_config_and_long_breaks_come_every_few_cycles() {
+    assert!(matches!(Config::builder().cycles(0).build(), Err(ConfigError::NoCycles)));
+    assert!(matches!(Config::builder().long_break(15, 0).build(), Err(ConfigError::NoLongBreakCycles)));
+
+    let config = Config::builder().work_mins(25).break_mins(5).cycles(4).long_break(15, 2).build().unwrap();
+    let clock = MockClock::new();
+    let mut engine = Engine::with_clock(&config, clock.clone());
+    let long = Duration::from_secs(15 * 60);
+    assert_eq!(engine.session_remaining(), WORK * 4 + BREAK * 2 + long);
+    let mut breaks = Vec::new();
+    while !engine.is_finished() {
+        clock.advance(engine.remaining());
+        engine.tick();
+        let phase = engine.phase();
+        if phase.is_break() && !engine.is_finished() {
+            breaks.push((phase.kind, phase.duration));
+        }
+    }
+    let short = (PhaseKind::ShortBreak, BREAK);
+    assert_eq!(breaks, [short.clone(), (PhaseKind::LongBreak, long), short]);
+}
+
+#[test]
+fn a_restored_long_break_keeps_its_length() {
+    let config = Config::builder().work_mins(25).break_mins(5).cycles(4).long_break(15, 2).build().unwrap();
+    let clock = MockClock::new();
+    let mut engine = Engine::with_clock(&config, clock.clone());
+    engine.restore(2, PhaseKind::LongBreak, Duration::from_secs(12 * 60));
+    assert_eq!(engine.phase(), Phase::build(PhaseKind::LongBreak, Duration::from_secs(15 * 60)));
+    assert_eq!(engine.remaining(), Duration::from_secs(12 * 60));
+    clock.advance(Duration::from_secs(12 * 60));
+    assert_eq!(ended(&engine.tick()), [(PhaseKind::LongBreak, 2, Outcome::Completed)]);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn the_async_engine_follows_commands_and_broadcasts_what_happens() {
+    use opomodoro::embed::Command;
+    use tokio::sync::{broadcast, mpsc};
+
+    let clock = MockClock::new();
+    let engine = Engine::with_clock(&config(2, false), clock.clone());
+    let (commands, inbox) = mpsc::channel(8);
+    let (events, mut listener) = broadcast::channel(8);
+    for command in [Command::Toggle, Command::Skip, Command::Skip, Command::Skip] {
+        commands.send(command).await.unwrap();
+    }
+    let engine = engine.run_async(inbox, events).await;
+    assert!(engine.is_finished());
+    let mut ended = Vec::new();
+    while let Ok(event) = listener.try_recv() {
+        if let Event::PhaseEnded { phase, outcome, .. } = event {
+            ended.push((phase.kind, outcome));
+        }
+    }
+    let skipped = |kind| (kind, Outcome::Skipped);
+    assert_eq!(ended, [skipped(PhaseKind::Work), skipped(PhaseKind::ShortBreak), skipped(PhaseKind::Work)]);
+
+    // With the commands gone, the session is over.
+    let (commands, inbox) = mpsc::channel(8);
+    drop(commands);
+    let engine = Engine::with_clock(&config(2, false), clock).run_async(inbox, broadcast::channel(8).0).await;
+    assert!(engine.is_finished());
+}
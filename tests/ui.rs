@@ -0,0 +1,310 @@
+//! Snapshots of the screen in each view and state, drawn on a ratatui
+//! `TestBackend`. After a deliberate change to the UI, review and accept the
+//! new ones with `cargo insta review`, or rerun with `INSTA_UPDATE=always`.
+
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+use chrono::Local;
+use crossterm::event::{KeyCode, KeyEvent};
+use insta::assert_snapshot;
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use ratatui::style::{Color, Modifier};
+use opomodoro::clock::MockClock;
+use opomodoro::day::DayFile;
+use opomodoro::engine::{BreakDebt, Engine};
+use opomodoro::history::Targets;
+use opomodoro::input::ReplayInput;
+use opomodoro::template::Messages;
+use opomodoro::theme::ThemeChoice;
+use opomodoro::{App, Config, Countdown, FontChoice, HourFormat, ViewMode};
+
+static RUNNING: AtomicBool = AtomicBool::new(true);
+
+type TestApp = App<'static, Engine<MockClock>>;
+
+fn config(view: ViewMode) -> Config {
+    Config {
+        work_time: Duration::from_secs(25 * 60),
+        break_time: Duration::from_secs(5 * 60),
+        cycles: 4,
+        long_break: None,
+        late: false,
+        warm_up: None,
+        cool_down: None,
+        font: FontChoice::Auto,
+        view,
+        countdown: Countdown::Clock,
+        theme: ThemeChoice::Tomato,
+        no_color: false,
+        volume: 0,
+        speak: false,
+        notify: false,
+        visual_bell: false,
+        history: None,
+        state: None,
+        tasks: None,
+        notes: false,
+        snooze: Duration::from_secs(5 * 60),
+        max_snoozes: 2,
+        idle: None,
+        work_warning: None,
+        break_warning: None,
+        hard_break: None,
+        get_ready: None,
+        break_debt: BreakDebt::Off,
+        pause_budget: None,
+        strict: false,
+        block_commands: None,
+        ambient: None,
+        targets: Targets::default(),
+        task: Some("Write the report".to_string()),
+        clock: HourFormat::H24,
+        sidebar: false,
+        mascot: false,
+        animations: true,
+        spinner: true,
+        low_power: false,
+        end_of_day: None,
+        manual_advance: None,
+        messages: Messages::default(),
+        chain: Vec::new(),
+        single: None,
+        intervals: None,
+    }
+}
+
+fn app(config: Config) -> (TestApp, MockClock) {
+    let clock = MockClock::new();
+    let engine = Engine::with_clock(&config, clock.clone());
+    (App::with_engine(config, engine, &RUNNING), clock)
+}
+
+fn press(app: &mut TestApp, keys: &str) {
+    for ch in keys.chars() {
+        let code = if ch == '\r' { KeyCode::Enter } else { KeyCode::Char(ch) };
+        app.press(KeyEvent::from(code));
+    }
+}
+
+/// The screen as `app` draws it in a `width` by `height` terminal.
+fn screen(app: &mut TestApp, width: u16, height: u16) -> String {
+    let before = Local::now();
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    let clocks = [before, Local::now()].map(|now| HourFormat::H24.format(now));
+    blank_wall_clock(&terminal.backend().to_string(), &clocks)
+}
+
+/// Blank out the times of day, which change from run to run: the clock in
+/// the status bar, and when the phase and the session end.
+fn blank_wall_clock(screen: &str, clocks: &[String]) -> String {
+    let mut lines = Vec::new();
+    for (row, line) in screen.lines().enumerate() {
+        let mut line = line.to_string();
+        if row < 3 {
+            for clock in clocks {
+                line = line.replace(clock.as_str(), "HH:MM");
+            }
+        }
+        // "ends at 17:25", "session ends ~18:45", even with a popup over the
+        // "session ends".
+        for marker in ["at ", "~"] {
+            let times: Vec<_> = line
+                .match_indices(marker)
+                .map(|(at, _)| at + marker.len())
+                .filter(|&at| line.get(at..at + 5).is_some_and(is_time))
+                .collect();
+            for at in times {
+                line.replace_range(at..at + 5, "HH:MM");
+            }
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+fn is_time(text: &str) -> bool {
+    text.char_indices().all(|(i, ch)| if i == 2 { ch == ':' } else { ch.is_ascii_digit() })
+}
+
+#[test]
+fn digits_view() {
+    let (mut app, _) = app(config(ViewMode::Digits));
+    assert_snapshot!(screen(&mut app, 80, 24));
+}
+
+#[test]
+fn ring_view() {
+    let (mut app, _) = app(config(ViewMode::Ring));
+    assert_snapshot!(screen(&mut app, 80, 24));
+}
+
+#[test]
+fn minimal_view() {
+    let (mut app, _) = app(config(ViewMode::Minimal));
+    assert_snapshot!(screen(&mut app, 80, 24));
+}
+
+#[test]
+fn small_terminals() {
+    for view in [ViewMode::Digits, ViewMode::Ring, ViewMode::Minimal] {
+        let (mut app, _) = app(config(view));
+        for (width, height) in [(40, 12), (24, 6)] {
+            assert_snapshot!(format!("{view:?}_{width}x{height}"), screen(&mut app, width, height));
+        }
+    }
+}
+
+#[test]
+fn paused() {
+    let (mut app, clock) = app(config(ViewMode::Digits));
+    clock.advance(Duration::from_secs(61));
+    press(&mut app, "p");
+    assert_snapshot!(screen(&mut app, 80, 24));
+}
+
+#[test]
+fn skip_menu() {
+    let (mut app, _) = app(config(ViewMode::Digits));
+    press(&mut app, "s");
+    assert_snapshot!(screen(&mut app, 80, 24));
+}
+
+#[test]
+fn help() {
+    let (mut app, _) = app(config(ViewMode::Digits));
+    press(&mut app, "?");
+    assert_snapshot!(screen(&mut app, 80, 24));
+}
+
+#[test]
+fn settings() {
+    let (mut app, _) = app(config(ViewMode::Digits));
+    // A minute more work, from the next work phase on.
+    press(&mut app, "ol");
+    assert_snapshot!(screen(&mut app, 80, 24));
+}
+
+#[test]
+fn longer_work_from_the_next_phase() {
+    let (mut app, _) = app(config(ViewMode::Digits));
+    press(&mut app, "]]{");
+    assert_snapshot!(screen(&mut app, 80, 24));
+}
+
+#[test]
+fn transition_to_a_break() {
+    let (mut app, clock) = app(config(ViewMode::Digits));
+    screen(&mut app, 80, 24);
+    press(&mut app, "s\r");
+    screen(&mut app, 80, 24);
+    clock.advance(Duration::from_millis(600));
+    assert_snapshot!("splash", screen(&mut app, 80, 24));
+    clock.advance(Duration::from_secs(1));
+    assert_snapshot!("break", screen(&mut app, 80, 24));
+}
+
+#[test]
+fn replayed_keys_drive_the_app() {
+    let (mut app, _) = app(config(ViewMode::Digits));
+    let replay: ReplayInput = "# skip to the break\n0s s\n10ms enter\n20ms q\n".parse().unwrap();
+    app.set_input(replay);
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    app.run(&mut terminal).unwrap();
+    assert!(terminal.backend().to_string().contains("Cycle 1/4  ☕ Break"));
+}
+
+#[test]
+fn strict_mode_voids_a_paused_pomodoro() {
+    let (mut app, clock) = app(Config { strict: true, ..config(ViewMode::Digits) });
+    clock.advance(Duration::from_secs(61));
+    press(&mut app, "pp");
+    let screen = screen(&mut app, 80, 24);
+    assert!(screen.contains("Work (Voided)"));
+    assert!(screen.contains("Pomodoro voided: paused"));
+}
+
+#[test]
+fn the_visual_bell_flashes_the_screen() {
+    let (mut app, clock) = app(Config { strict: true, visual_bell: true, ..config(ViewMode::Digits) });
+    clock.advance(Duration::from_secs(61));
+    // Voiding the pomodoro sounds the alert.
+    press(&mut app, "p");
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    let mut inverted = |app: &mut TestApp| {
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        terminal.backend().buffer()[(0, 0)].modifier.contains(Modifier::REVERSED)
+    };
+    assert!(inverted(&mut app));
+    clock.advance(Duration::from_millis(200));
+    assert!(!inverted(&mut app));
+    clock.advance(Duration::from_millis(150));
+    assert!(inverted(&mut app));
+    clock.advance(Duration::from_secs(1));
+    assert!(!inverted(&mut app));
+}
+
+#[test]
+fn the_spinner_turns_only_while_paused() {
+    let (mut live, clock) = app(config(ViewMode::Digits));
+    let header = |app: &mut TestApp| screen(app, 80, 24).lines().nth(1).unwrap().to_string();
+    let running = header(&mut live);
+    clock.advance(Duration::from_millis(250));
+    assert_eq!(header(&mut live), running);
+    press(&mut live, "p");
+    let paused = header(&mut live);
+    assert_ne!(paused, running);
+    clock.advance(Duration::from_millis(100));
+    assert_ne!(header(&mut live), paused);
+    let (mut still, _) = app(Config { spinner: false, ..config(ViewMode::Digits) });
+    press(&mut still, "p");
+    assert!(!header(&mut still).contains(['⠿', '⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏']));
+}
+
+#[test]
+fn no_color_dims_the_track_instead() {
+    let (mut app, clock) = app(Config { no_color: true, ..config(ViewMode::Ring) });
+    clock.advance(Duration::from_secs(300));
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    let cells = &terminal.backend().buffer().content;
+    assert!(cells.iter().all(|cell| cell.fg == Color::Reset && cell.bg == Color::Reset));
+    assert!(cells.iter().any(|cell| cell.modifier.contains(Modifier::DIM)));
+}
+
+#[test]
+fn quitting_leaves_a_held_phase_held() {
+    static HELD_RUNNING: AtomicBool = AtomicBool::new(true);
+    let config = Config {
+        work_time: Duration::from_millis(50),
+        manual_advance: Some((Duration::from_secs(30), 5)),
+        ..config(ViewMode::Digits)
+    };
+    let engine = Engine::new(&config);
+    let mut app = App::with_engine(config, engine, &HELD_RUNNING);
+    // The second `q` only in case the first starts the break instead.
+    let replay: ReplayInput = "# wait out the work, then quit\n300ms q\n400ms q\n".parse().unwrap();
+    app.set_input(replay);
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    app.run(&mut terminal).unwrap();
+    assert!(terminal.backend().to_string().contains("Time's up"));
+}
+
+#[test]
+fn the_last_cycle_ends_the_day() {
+    let path = std::env::temp_dir().join(format!("opomodoro-day-done-{}", std::process::id()));
+    // Quitting stops the flag it runs by, so not the one other tests share.
+    static DAY_RUNNING: AtomicBool = AtomicBool::new(true);
+    let config = Config { cycles: 1, end_of_day: Some(path.clone()), ..config(ViewMode::Digits) };
+    let engine = Engine::with_clock(&config, MockClock::new());
+    let mut app = App::with_engine(config, engine, &DAY_RUNNING);
+    let replay: ReplayInput = "# skip the rest of the only cycle\n0s s\n10ms 3\n20ms q\n".parse().unwrap();
+    app.set_input(replay);
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    app.run(&mut terminal).unwrap();
+    assert!(terminal.backend().to_string().contains("Done for today"));
+    let closed = DayFile::new(path.clone()).closed_today();
+    std::fs::remove_file(&path).unwrap();
+    assert!(closed.unwrap().is_some());
+}